@@ -0,0 +1,40 @@
+//! 将本地目录与 relay 同步一次
+//!
+//! `cargo run -p relayfetch-client --example mirror -- http://mirror.lab:8080/ ./mirror [--prune]`
+//! 清单需要 token 时通过环境变量 `RELAYFETCH_TOKEN` 传入。
+
+use relayfetch_client::Client;
+
+#[tokio::main]
+async fn main() -> relayfetch_client::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(base), Some(dir)) = (args.first(), args.get(1)) else {
+        eprintln!("usage: mirror <relay url> <dir> [--prune]");
+        std::process::exit(2);
+    };
+    let prune = args.iter().any(|a| a == "--prune");
+
+    let mut client = Client::new(base)?;
+    if let Ok(token) = std::env::var("RELAYFETCH_TOKEN") {
+        client = client.with_token(token);
+    }
+
+    let report = client.sync_dir(dir.as_ref(), prune).await?;
+    for path in &report.downloaded {
+        println!("downloaded {}", path);
+    }
+    for path in &report.removed {
+        println!("removed {}", path);
+    }
+    for (path, e) in &report.failed {
+        eprintln!("failed {}: {}", path, e);
+    }
+    println!(
+        "{} downloaded, {} unchanged, {} removed, {} failed",
+        report.downloaded.len(),
+        report.unchanged.len(),
+        report.removed.len(),
+        report.failed.len()
+    );
+    Ok(())
+}
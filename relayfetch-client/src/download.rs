@@ -0,0 +1,111 @@
+//! 单文件下载：写入 `<文件>.part`，支持断点续传，完成后校验大小与 sha256 再替换
+
+use std::path::{Path, PathBuf};
+
+use futures_util::StreamExt;
+use reqwest::{StatusCode, header};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::error::{Error, Result};
+use crate::manifest::ManifestFile;
+
+pub(crate) fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+pub(crate) async fn fetch(
+    http: &reqwest::Client,
+    url: url::Url,
+    file: &ManifestFile,
+    dest: &Path,
+) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let part = part_path(dest);
+    let existing = tokio::fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0);
+
+    let mut req = http.get(url.clone());
+    if existing > 0 {
+        req = req.header(header::RANGE, format!("bytes={}-", existing));
+        // 文件在 relay 上已变化时服务端返回完整内容而不是续传
+        if let Some(etag) = &file.etag {
+            req = req.header(header::IF_RANGE, etag);
+        }
+    }
+    let resp = req.send().await?;
+    let status = resp.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // 本地 .part 已不小于远端文件，丢弃重下
+        tokio::fs::remove_file(&part).await?;
+        return Box::pin(fetch(http, url, file, dest)).await;
+    }
+    if !status.is_success() {
+        return Err(Error::Status {
+            url: url.to_string(),
+            status: status.as_u16(),
+        });
+    }
+
+    let resumed = status == StatusCode::PARTIAL_CONTENT;
+    let mut out = if resumed {
+        tokio::fs::OpenOptions::new().append(true).open(&part).await?
+    } else {
+        tokio::fs::File::create(&part).await?
+    };
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        out.write_all(&chunk?).await?;
+    }
+    out.flush().await?;
+    drop(out);
+
+    if let Err(e) = verify(file, &part).await {
+        let _ = tokio::fs::remove_file(&part).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&part, dest).await?;
+    Ok(())
+}
+
+/// 校验大小与 sha256（清单中有的话）
+async fn verify(file: &ManifestFile, path: &Path) -> Result<()> {
+    let len = tokio::fs::metadata(path).await?.len();
+    if let Some(size) = file.size
+        && size != len
+    {
+        return Err(Error::Checksum {
+            path: file.path.clone(),
+            expected: format!("{} bytes", size),
+            actual: format!("{} bytes", len),
+        });
+    }
+    if let Some(expected) = &file.sha256 {
+        let actual = sha256(path).await?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::Checksum {
+                path: file.path.clone(),
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn sha256(path: &Path) -> Result<String> {
+    let mut f = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = f.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,32 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid url: {0}")]
+    Url(#[from] url::ParseError),
+
+    #[error("invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+
+    #[error("{url} returned {status}")]
+    Status { url: String, status: u16 },
+
+    #[error("unsafe path in manifest: {0}")]
+    UnsafePath(String),
+
+    #[error("invalid patch: {0}")]
+    Patch(String),
+
+    #[error("checksum mismatch for {path}: expected {expected}, got {actual}")]
+    Checksum {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
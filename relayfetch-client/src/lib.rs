@@ -0,0 +1,90 @@
+//! relayfetch 客户端
+//!
+//! 读取 relay 在下载端口发布的 `/.well-known/relayfetch.json`（需在 relay 上配置
+//! `[client_manifest]`），断点续传下载文件并校验 sha256，或将本地目录与 relay 保持同步；
+//! relay 启用了 `[delta_patches]` 时，本地为上一版本的文件只下载增量补丁。
+//!
+//! ```no_run
+//! # async fn run() -> relayfetch_client::Result<()> {
+//! let client = relayfetch_client::Client::new("http://mirror.lab:8080/")?.with_token("s3cret");
+//! let report = client.sync_dir("/var/lib/mirror".as_ref(), true).await?;
+//! println!("{} downloaded, {} failed", report.downloaded.len(), report.failed.len());
+//! # Ok(())
+//! # }
+//! ```
+
+mod download;
+mod error;
+mod manifest;
+mod patch;
+mod sync;
+
+use std::path::Path;
+
+use url::Url;
+
+pub use error::{Error, Result};
+pub use manifest::{Manifest, ManifestFile};
+pub use patch::apply as apply_patch;
+pub use sync::SyncReport;
+
+/// 清单在 relay 上的位置
+pub const MANIFEST_PATH: &str = "/.well-known/relayfetch.json";
+
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base: Url,
+    token: Option<String>,
+}
+
+impl Client {
+    /// `base` 为 relay 下载服务地址，如 `http://mirror.lab:8080/`
+    pub fn new(base: &str) -> Result<Self> {
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base: Url::parse(base)?,
+            token: None,
+        })
+    }
+
+    /// 清单的访问 token（relay 配置了 `client_manifest.tokens` 时需要）
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// 使用自定义的 HTTP 客户端（代理、超时等）
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// 拉取 relay 的文件清单
+    pub async fn manifest(&self) -> Result<Manifest> {
+        let url = self.base.join(MANIFEST_PATH)?;
+        let mut req = self.http.get(url.clone());
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+        let resp = req.send().await?;
+        if !resp.status().is_success() {
+            return Err(Error::Status {
+                url: url.to_string(),
+                status: resp.status().as_u16(),
+            });
+        }
+        Ok(serde_json::from_slice(&resp.bytes().await?)?)
+    }
+
+    /// 文件的下载地址
+    pub fn file_url(&self, file: &ManifestFile) -> Result<Url> {
+        Ok(self.base.join(MANIFEST_PATH)?.join(&file.url)?)
+    }
+
+    /// 下载单个文件到 `dest`，中断后再次调用会从 `<dest>.part` 续传；
+    /// 大小或 sha256 与清单不符时返回 [`Error::Checksum`]，不会覆盖 `dest`
+    pub async fn download(&self, file: &ManifestFile, dest: &Path) -> Result<()> {
+        download::fetch(&self.http, self.file_url(file)?, file, dest).await
+    }
+}
@@ -0,0 +1,51 @@
+//! `/.well-known/relayfetch.json` 的结构（与 relay 端保持一致）
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    /// relay 版本
+    pub version: String,
+    pub generated_at: String,
+    /// relay 最近一次全部成功的同步
+    pub last_ok_sync: Option<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestFile {
+    /// 相对路径（`/` 分隔）
+    pub path: String,
+    /// 相对清单地址解析的下载路径（已编码）
+    pub url: String,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+    pub etag: Option<String>,
+    /// 上游给出的修改时间
+    pub last_modified: Option<String>,
+    /// relay 最近一次与上游确认的时间
+    pub fetched_at: Option<String>,
+    /// 只镜像了上游对象的一段时为上游的 Content-Range（如 `bytes 0-1023/4096`）
+    #[serde(default)]
+    pub content_range: Option<String>,
+}
+
+impl ManifestFile {
+    /// 本地目录下的保存位置
+    pub fn local_path(&self, dir: &Path) -> Result<PathBuf> {
+        local_path(dir, &self.path)
+    }
+}
+
+/// 拒绝 `..`、绝对路径等越出目录的路径
+pub(crate) fn local_path(dir: &Path, path: &str) -> Result<PathBuf> {
+    let rel = Path::new(path);
+    if path.is_empty() || rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(Error::UnsafePath(path.to_string()));
+    }
+    Ok(dir.join(rel))
+}
@@ -0,0 +1,106 @@
+//! 增量补丁：本地是上一版本时只下载 `/<文件>.patch?from=<本地 sha256>`
+//!
+//! 格式见 relay 的 `sync::delta`：头部（`RFDELTA1`、旧长度、新长度、新 sha256）之后为
+//! 复制（`0x01` 偏移 长度）与插入（`0x02` 长度 数据）指令，整数均为小端 u64。
+
+use std::path::Path;
+
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+
+use crate::Client;
+use crate::error::{Error, Result};
+use crate::manifest::ManifestFile;
+
+const MAGIC: &[u8; 8] = b"RFDELTA1";
+const HEADER_LEN: usize = 8 + 8 + 8 + 32;
+
+impl Client {
+    /// 用 relay 上的补丁把 `dest`（sha256 为 `from`）更新到清单中的版本；
+    /// relay 没有对应补丁时返回 false，由调用方完整下载
+    pub async fn patch(&self, file: &ManifestFile, from: &str, dest: &Path) -> Result<bool> {
+        let mut url = self.file_url(file)?;
+        url.set_path(&format!("{}.patch", url.path()));
+        url.set_query(Some(&format!("from={}", from)));
+        let resp = self.http.get(url.clone()).send().await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        if !resp.status().is_success() {
+            return Err(Error::Status {
+                url: url.to_string(),
+                status: resp.status().as_u16(),
+            });
+        }
+        let patch = resp.bytes().await?;
+
+        let old = tokio::fs::read(dest).await?;
+        let new = apply(&old, &patch)?;
+        let actual = hex::encode(Sha256::digest(&new));
+        let expected = file.sha256.clone().unwrap_or_else(|| hex::encode(&patch[24..HEADER_LEN]));
+        if !actual.eq_ignore_ascii_case(&expected) {
+            return Err(Error::Checksum {
+                path: file.path.clone(),
+                expected,
+                actual,
+            });
+        }
+
+        let part = crate::download::part_path(dest);
+        tokio::fs::write(&part, &new).await?;
+        tokio::fs::rename(&part, dest).await?;
+        Ok(true)
+    }
+}
+
+/// 对旧内容应用补丁，返回新内容
+pub fn apply(old: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let invalid = |msg: &str| Error::Patch(msg.to_string());
+    if patch.len() < HEADER_LEN || &patch[..8] != MAGIC {
+        return Err(invalid("not a relayfetch delta"));
+    }
+    if read_u64(patch, 8) != Some(old.len() as u64) {
+        return Err(invalid("patch was made for a different base file"));
+    }
+    let new_len = read_u64(patch, 16).ok_or_else(|| invalid("truncated header"))?;
+
+    let mut out = Vec::with_capacity(new_len.min(1 << 30) as usize);
+    let mut pos = HEADER_LEN;
+    while pos < patch.len() {
+        let op = patch[pos];
+        pos += 1;
+        match op {
+            0x01 => {
+                let (offset, len) = read_u64(patch, pos)
+                    .zip(read_u64(patch, pos + 8))
+                    .ok_or_else(|| invalid("truncated copy"))?;
+                pos += 16;
+                let range = usize::try_from(offset)
+                    .ok()
+                    .zip(usize::try_from(len).ok())
+                    .and_then(|(o, l)| old.get(o..o.checked_add(l)?))
+                    .ok_or_else(|| invalid("copy outside base file"))?;
+                out.extend_from_slice(range);
+            }
+            0x02 => {
+                let len = read_u64(patch, pos).ok_or_else(|| invalid("truncated insert"))?;
+                pos += 8;
+                let data = usize::try_from(len)
+                    .ok()
+                    .and_then(|l| patch.get(pos..pos.checked_add(l)?))
+                    .ok_or_else(|| invalid("truncated insert"))?;
+                pos += data.len();
+                out.extend_from_slice(data);
+            }
+            _ => return Err(invalid("unknown instruction")),
+        }
+    }
+    if out.len() as u64 != new_len {
+        return Err(invalid("output length mismatch"));
+    }
+    Ok(out)
+}
+
+fn read_u64(buf: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(buf.get(pos..pos + 8)?.try_into().ok()?))
+}
@@ -0,0 +1,158 @@
+//! 将本地目录与 relay 保持同步
+//!
+//! 已校验过的文件记录在 `<目录>/.relayfetch-client.json`（sha256、大小、mtime），
+//! 大小与 mtime 未变时不再重新计算摘要。`prune` 只删除由本客户端下载、
+//! 且已不在清单中的文件，不会动目录中的其他内容。
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+use crate::error::{Error, Result};
+use crate::manifest::{self, ManifestFile};
+
+const STATE_FILE: &str = ".relayfetch-client.json";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct State {
+    files: BTreeMap<String, LocalFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct LocalFile {
+    sha256: Option<String>,
+    size: u64,
+    mtime: u64,
+}
+
+/// 一次同步的结果；单个文件失败不影响其他文件
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub downloaded: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub removed: Vec<String>,
+    pub failed: Vec<(String, Error)>,
+}
+
+impl Client {
+    /// 下载清单中新增或变化的文件；`prune` 为 true 时删除已不在清单中的文件
+    pub async fn sync_dir(&self, dir: &Path, prune: bool) -> Result<SyncReport> {
+        let manifest = self.manifest().await?;
+        tokio::fs::create_dir_all(dir).await?;
+        let state_path = dir.join(STATE_FILE);
+        let mut state: State = tokio::fs::read(&state_path)
+            .await
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .unwrap_or_default();
+
+        let mut report = SyncReport::default();
+        for file in &manifest.files {
+            match self.sync_file(dir, file, state.files.get(&file.path)).await {
+                Ok((local, downloaded)) => {
+                    state.files.insert(file.path.clone(), local);
+                    if downloaded {
+                        report.downloaded.push(file.path.clone());
+                    } else {
+                        report.unchanged.push(file.path.clone());
+                    }
+                }
+                Err(e) => report.failed.push((file.path.clone(), e)),
+            }
+        }
+
+        if prune {
+            let listed: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+            let stale: Vec<String> = state
+                .files
+                .keys()
+                .filter(|p| !listed.contains(&p.as_str()))
+                .cloned()
+                .collect();
+            for path in stale {
+                state.files.remove(&path);
+                let Ok(local) = manifest::local_path(dir, &path) else {
+                    continue;
+                };
+                match tokio::fs::remove_file(&local).await {
+                    Ok(()) => report.removed.push(path),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => report.failed.push((path, e.into())),
+                }
+            }
+        }
+
+        tokio::fs::write(&state_path, serde_json::to_vec_pretty(&state)?).await?;
+        Ok(report)
+    }
+
+    /// 返回文件的本地记录，以及本次是否重新下载
+    async fn sync_file(
+        &self,
+        dir: &Path,
+        file: &ManifestFile,
+        known: Option<&LocalFile>,
+    ) -> Result<(LocalFile, bool)> {
+        let dest = file.local_path(dir)?;
+        if let Some(local) = inspect(&dest).await {
+            // 本地未被改动过：直接比较记录的摘要，否则重新计算
+            let sha256 = match known {
+                Some(k) if k.size == local.size && k.mtime == local.mtime => k.sha256.clone(),
+                _ => Some(crate::download::sha256(&dest).await?),
+            };
+            let same = match (&file.sha256, &sha256) {
+                (Some(expected), Some(actual)) => expected.eq_ignore_ascii_case(actual),
+                // relay 没有摘要时只能比较大小
+                (None, _) => file.size == Some(local.size),
+                (Some(_), None) => false,
+            };
+            if same {
+                return Ok((LocalFile { sha256, ..local }, false));
+            }
+            // 本地是旧版本：优先只下载补丁，失败时回退到完整下载
+            if let (Some(from), Some(_)) = (&sha256, &file.sha256)
+                && self.patch(file, from, &dest).await.unwrap_or(false)
+            {
+                return Ok((record(file, &dest).await?, true));
+            }
+        }
+
+        self.download(file, &dest).await?;
+        Ok((record(file, &dest).await?, true))
+    }
+
+}
+
+/// 刚下载并校验过的文件的本地记录
+async fn record(file: &ManifestFile, dest: &Path) -> Result<LocalFile> {
+    let md = tokio::fs::metadata(dest).await?;
+    Ok(LocalFile {
+        sha256: file.sha256.clone(),
+        size: md.len(),
+        mtime: mtime(&md),
+    })
+}
+
+/// 本地文件的大小与 mtime；不存在时为 None
+async fn inspect(path: &Path) -> Option<LocalFile> {
+    let md = tokio::fs::metadata(path).await.ok()?;
+    if !md.is_file() {
+        return None;
+    }
+    Some(LocalFile {
+        sha256: None,
+        size: md.len(),
+        mtime: mtime(&md),
+    })
+}
+
+fn mtime(md: &std::fs::Metadata) -> u64 {
+    md.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,58 @@
+//! 端到端性能基准：同步吞吐、下载服务请求速率、status 延迟
+//!
+//! 运行：`cargo bench --bench perf`
+//! 规模：RELAYFETCH_BENCH_FILES（默认 50）、RELAYFETCH_BENCH_FILE_SIZE（字节，默认 256 KiB）
+
+mod support;
+
+use std::time::Duration;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use support::{Harness, PREFIX, Params};
+
+fn benches(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let harness = rt.block_on(Harness::start(Params::from_env()));
+
+    let mut group = c.benchmark_group("sync");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(harness.params.total_bytes()));
+    group.bench_function(format!("{}x{}B", harness.params.files, harness.params.file_size), |b| {
+        b.to_async(&rt).iter_custom(|iters| {
+            let harness = &harness;
+            async move {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    total += harness.full_sync().await;
+                }
+                total
+            }
+        })
+    });
+    group.finish();
+
+    // 上面的同步保证文件已在本地
+    let mut group = c.benchmark_group("serve");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("get_file", |b| {
+        let url = format!("http://{}/{}/f0", harness.download, PREFIX);
+        b.to_async(&rt).iter(|| harness.get(url.clone()))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("management");
+    group.bench_function("status", |b| {
+        let url = format!("http://{}/status", harness.admin);
+        b.to_async(&rt).iter(|| harness.get(url.clone()))
+    });
+    group.bench_function("status_detail", |b| {
+        let url = format!("http://{}/status?detail=true", harness.admin);
+        b.to_async(&rt).iter(|| harness.get(url.clone()))
+    });
+    group.finish();
+
+    drop(harness);
+}
+
+criterion_group!(perf, benches);
+criterion_main!(perf);
@@ -0,0 +1,188 @@
+//! 性能测试环境：本地合成上游 + 独立进程运行的 relayfetch
+//!
+//! 上游提供 `/f/<n>`（n < files），每个文件 `file_size` 字节；relayfetch 使用临时目录
+//! 作为 storage_dir，端口随机分配，结束时进程随 `Harness` 一起退出。
+
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::routing::get;
+
+/// 本地存储中的镜像前缀
+pub const PREFIX: &str = "bench";
+
+pub struct Params {
+    pub files: usize,
+    pub file_size: usize,
+}
+
+impl Params {
+    /// 通过环境变量调整规模：RELAYFETCH_BENCH_FILES / RELAYFETCH_BENCH_FILE_SIZE
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            files: var("RELAYFETCH_BENCH_FILES", 50),
+            file_size: var("RELAYFETCH_BENCH_FILE_SIZE", 256 * 1024),
+        }
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        (self.files * self.file_size) as u64
+    }
+}
+
+pub struct Harness {
+    pub params: Params,
+    pub download: SocketAddr,
+    pub admin: SocketAddr,
+    pub client: reqwest::Client,
+    storage_dir: PathBuf,
+    child: Child,
+    _dir: tempfile::TempDir,
+}
+
+impl Harness {
+    /// 启动上游与 relayfetch，并等待启动时的首次同步完成
+    pub async fn start(params: Params) -> Self {
+        let origin = spawn_origin(params.file_size).await;
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let storage_dir = dir.path().join("data");
+        let download = free_addr();
+        let admin = free_addr();
+        let grpc = free_addr();
+
+        let config = format!(
+            "interval_secs = 86400\n\
+             storage_dir = {:?}\n\
+             bind = \"{}\"\n\
+             grpc_admin = \"{}\"\n\
+             http_admin = \"{}\"\n\
+             download_concurrency = 16\n",
+            storage_dir, download, grpc, admin
+        );
+        let mut files = String::from("[files]\n");
+        for n in 0..params.files {
+            files.push_str(&format!("\"{}/f{}\" = \"http://{}/f/{}\"\n", PREFIX, n, origin, n));
+        }
+        std::fs::write(dir.path().join("config.toml"), config).unwrap();
+        std::fs::write(dir.path().join("files.toml"), files).unwrap();
+
+        let child = Command::new(env!("CARGO_BIN_EXE_relayfetch"))
+            .arg("--config")
+            .arg(dir.path().join("config.toml"))
+            .arg("--files")
+            .arg(dir.path().join("files.toml"))
+            .env("RUST_LOG", "error")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start relayfetch");
+
+        let harness = Self {
+            params,
+            download,
+            admin,
+            client: reqwest::Client::new(),
+            storage_dir,
+            child,
+            _dir: dir,
+        };
+        harness.wait_idle().await;
+        harness
+    }
+
+    /// 清空已同步的文件并完整同步一次，等同步任务结束后返回耗时
+    pub async fn full_sync(&self) -> Duration {
+        let _ = std::fs::remove_dir_all(self.storage_dir.join(PREFIX));
+        let start = Instant::now();
+        let job: serde_json::Value = self
+            .client
+            .post(format!("http://{}/trigger_sync", self.admin))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .expect("trigger_sync failed")
+            .json()
+            .await
+            .expect("trigger_sync response");
+        let job_id = job["job_id"].as_str().expect("job_id").to_string();
+
+        let deadline = Instant::now() + Duration::from_secs(600);
+        while Instant::now() < deadline {
+            let job: serde_json::Value = self
+                .client
+                .get(format!("http://{}/sync_job?job_id={}", self.admin, job_id))
+                .send()
+                .await
+                .expect("sync_job failed")
+                .json()
+                .await
+                .expect("sync_job response");
+            match job["state"].as_str() {
+                Some("finished") => return start.elapsed(),
+                Some("failed") => panic!("sync job {} failed: {}", job_id, job),
+                _ => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        panic!("sync job {} did not finish", job_id);
+    }
+
+    pub async fn get(&self, url: String) {
+        let resp = self.client.get(url).send().await.expect("request failed");
+        assert!(resp.status().is_success(), "unexpected status {}", resp.status());
+        resp.bytes().await.expect("body");
+    }
+
+    /// 等待管理接口可用且没有进行中的同步
+    async fn wait_idle(&self) {
+        let deadline = Instant::now() + Duration::from_secs(120);
+        while Instant::now() < deadline {
+            if let Ok(resp) = self.client.get(format!("http://{}/status", self.admin)).send().await
+                && let Ok(status) = resp.json::<serde_json::Value>().await
+                && status["is_running"] == false
+                && !status["last_sync"].is_null()
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("relayfetch did not become ready");
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn free_addr() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+}
+
+/// 合成上游：所有文件内容相同，不带缓存校验头
+async fn spawn_origin(file_size: usize) -> SocketAddr {
+    let body = Bytes::from(vec![b'x'; file_size]);
+    let app = Router::new()
+        .route("/f/{n}", get(|State(body): State<Bytes>, Path(_n): Path<usize>| async move { body }))
+        .with_state(body);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
@@ -4,7 +4,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         tonic_prost_build::configure()
             .build_server(true) // 生成 server stub
             .build_client(false) // 不生成 client stub
-            .compile_protos(&["proto/management.proto"], &["proto"])?;
+            .compile_protos(&["proto/management.proto", "proto/management/v2/management.proto"], &["proto"])?;
     }
     Ok(())
 }
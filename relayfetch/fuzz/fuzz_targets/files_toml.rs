@@ -0,0 +1,23 @@
+//! files.toml 解析：任意输入只能返回错误；解析成功的配置可以原样写回
+//! （管理接口修改文件列表时会重新序列化）
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/config/file.rs"]
+mod file;
+
+fuzz_target!(|input: &str| {
+    let Ok(cfg) = file::FilesConfig::parse(input) else {
+        return;
+    };
+    for source in cfg.files.values() {
+        let _ = (source.urls(), source.content_type(), source.attachment(), source.decompress());
+    }
+
+    let written = toml::to_string(&cfg).expect("serialize files.toml");
+    let reparsed = file::FilesConfig::parse(&written).expect("reparse files.toml");
+    assert_eq!(cfg.files, reparsed.files);
+});
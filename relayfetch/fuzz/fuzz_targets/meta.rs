@@ -0,0 +1,16 @@
+//! `.meta` 解析：磁盘上损坏的 meta 只能返回错误
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/sync/meta/mod.rs"]
+mod meta;
+
+fuzz_target!(|input: &str| {
+    if let Ok(m) = meta::Meta::parse(input) {
+        let written = toml::to_string(&m).expect("serialize meta");
+        meta::Meta::parse(&written).expect("reparse meta");
+    }
+});
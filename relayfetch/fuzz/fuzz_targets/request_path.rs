@@ -0,0 +1,22 @@
+//! 请求路径规范化：任意输入都不能 panic，也不能越出存储目录
+
+#![no_main]
+
+use std::path::{Component, Path};
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/server/path.rs"]
+mod path;
+
+fuzz_target!(|input: &str| {
+    if let Ok(rel) = path::normalize(input) {
+        assert!(rel.components().all(|c| matches!(c, Component::Normal(_))));
+        assert!(Path::new("/srv").join(&rel).starts_with("/srv"));
+    }
+
+    // 重新编码后必须是合法的响应头值
+    let encoded = path::encode(input);
+    assert!(encoded.bytes().all(|b| b.is_ascii_graphic()));
+});
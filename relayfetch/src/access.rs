@@ -0,0 +1,196 @@
+//! 下载服务的文件访问记录，用作 LRU 淘汰的依据
+//!
+//! - 记录每个文件最近一次被下载的时间，以及因配额淘汰、等待按需重新下载的文件
+//! - 统计每个文件的下载次数、返回的字节数与续传次数（Range 起点大于 0 的请求），用于了解哪些文件
+//!   实际被使用、发现下游连接经常中断的文件
+//! - 持久化到 `storage_dir/.relayfetch/access.toml`，定期落盘
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use chrono::Utc;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::bandwidth::STATE_DIR;
+use crate::config::ConfigCenter;
+
+/// 落盘间隔
+const FLUSH_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Records {
+    /// 相对路径 -> 最近一次被下载的时间（unix 秒）
+    #[serde(default)]
+    last_served: BTreeMap<String, i64>,
+    /// 已淘汰的文件，被请求时从记录的上游重新下载
+    #[serde(default)]
+    evicted: BTreeMap<String, Evicted>,
+    /// 相对路径 -> 下载次数、字节数与续传次数
+    #[serde(default)]
+    transfers: BTreeMap<String, TransferStats>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TransferStats {
+    /// 从磁盘提供的请求数（含完整下载与 Range 请求）
+    pub requests: u64,
+    /// 返回的字节数（Range 请求按范围计）
+    #[serde(default)]
+    pub bytes: u64,
+    /// 其中从文件中间开始的续传请求数
+    pub resumes: u64,
+    /// 最近一次续传的时间（unix 秒）
+    pub last_resume: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Evicted {
+    /// 淘汰前成功下载所用的上游
+    pub source: String,
+    /// 淘汰时间（unix 秒）
+    pub at: i64,
+}
+
+pub struct AccessLog {
+    path: PathBuf,
+    records: Mutex<Records>,
+    dirty: AtomicBool,
+}
+
+impl AccessLog {
+    /// 读取已有记录，文件缺失或损坏时从零开始
+    pub fn load(storage_dir: &Path) -> Self {
+        let path = storage_dir.join(STATE_DIR).join("access.toml");
+        let records = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| match toml::from_str(&s) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    warn!("[access] failed to parse {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            records: Mutex::new(records),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// 记录一次下载
+    pub fn touch(&self, file: &str) {
+        let mut records = self.records.lock().unwrap();
+        records.last_served.insert(file.to_string(), Utc::now().timestamp());
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// 记录一次返回 `bytes` 字节的请求，`resumed` 表示客户端从中断处续传
+    pub fn record_transfer(&self, file: &str, resumed: bool, bytes: u64) {
+        let mut records = self.records.lock().unwrap();
+        let stats = records.transfers.entry(file.to_string()).or_default();
+        stats.requests += 1;
+        stats.bytes += bytes;
+        if resumed {
+            stats.resumes += 1;
+            stats.last_resume = Some(Utc::now().timestamp());
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// 所有文件的下载与续传次数
+    pub fn transfers(&self) -> Vec<(String, TransferStats)> {
+        let records = self.records.lock().unwrap();
+        records.transfers.iter().map(|(f, s)| (f.clone(), s.clone())).collect()
+    }
+
+    /// 所有被下载过的文件的统计与最近一次被下载的时间
+    pub fn file_stats(&self) -> Vec<(String, TransferStats, Option<i64>)> {
+        let records = self.records.lock().unwrap();
+        let mut files: BTreeMap<&str, (TransferStats, Option<i64>)> = records
+            .transfers
+            .iter()
+            .map(|(f, s)| (f.as_str(), (s.clone(), None)))
+            .collect();
+        // 早于下载统计的记录只有访问时间
+        for (f, t) in &records.last_served {
+            files.entry(f.as_str()).or_default().1 = Some(*t);
+        }
+        files.into_iter().map(|(f, (s, t))| (f.to_string(), s, t)).collect()
+    }
+
+    /// 最近一次被下载的时间，从未被下载过时为 None
+    pub fn last_served(&self, file: &str) -> Option<i64> {
+        self.records.lock().unwrap().last_served.get(file).copied()
+    }
+
+    pub fn mark_evicted(&self, file: &str, source: String) {
+        let mut records = self.records.lock().unwrap();
+        records.evicted.insert(
+            file.to_string(),
+            Evicted {
+                source,
+                at: Utc::now().timestamp(),
+            },
+        );
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    pub fn evicted(&self, file: &str) -> Option<Evicted> {
+        self.records.lock().unwrap().evicted.get(file).cloned()
+    }
+
+    pub fn is_evicted(&self, file: &str) -> bool {
+        self.records.lock().unwrap().evicted.contains_key(file)
+    }
+
+    /// 文件重新下载完成，不再视为已淘汰
+    pub fn restored(&self, file: &str) {
+        let mut records = self.records.lock().unwrap();
+        if records.evicted.remove(file).is_some() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 有变更时写盘（tmp + rename）
+    pub fn flush(&self) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let toml = {
+            let records = self.records.lock().unwrap();
+            toml::to_string_pretty(&*records)?
+        };
+
+        let result = (|| {
+            crate::sync::meta::ensure_parent_dir(&self.path)?;
+            let tmp = self.path.with_extension("toml.tmp");
+            std::fs::write(&tmp, toml)?;
+            std::fs::rename(&tmp, &self.path)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+/// 定期把访问记录落盘
+pub fn spawn_flusher(cc: Arc<ConfigCenter>) {
+    tokio::spawn(async move {
+        let mut ticker =
+            tokio::time::interval(std::time::Duration::from_secs(FLUSH_INTERVAL_SECS));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = cc.access().flush() {
+                warn!("[access] failed to persist access records: {:?}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,121 @@
+//! 按客户端地址的访问控制（`[access]`）
+//!
+//! 客户端地址：直连地址是受信任的代理（`trusted_proxies`，或经 unix socket 连接）时，
+//! 从右向左取 X-Forwarded-For 中第一个不受信任的地址，否则就是直连地址。
+//! 访问日志使用同一个地址。
+//!
+//! 规则先匹配 deny，再匹配 allow（非空时）。无法确定客户端 IP 时（如经 unix socket 连接且没有
+//! X-Forwarded-For），配置了 allow 则拒绝。
+
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use log::debug;
+
+use crate::config::ConfigCenter;
+use crate::config::config::{AccessRules, IpRange};
+
+/// 连接的对端，由 accept 循环放入请求扩展
+#[derive(Debug, Clone, Copy)]
+pub enum Peer {
+    Tcp(IpAddr),
+    /// unix socket，对端是同机的反向代理
+    Unix,
+}
+
+/// 识别出的客户端
+#[derive(Debug, Clone)]
+pub enum Client {
+    Ip(IpAddr),
+    /// 没有对端信息、经 unix socket 且没有 X-Forwarded-For，或 X-Forwarded-For 中不是地址的值
+    Unknown(String),
+}
+
+impl fmt::Display for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => ip.fmt(f),
+            Self::Unknown(s) => f.write_str(s),
+        }
+    }
+}
+
+/// 见模块注释
+pub fn client(peer: Option<Peer>, headers: &HeaderMap, trusted: &[IpRange]) -> Client {
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|r| r.contains(ip));
+    let direct = match peer {
+        Some(Peer::Tcp(ip)) => ip.to_canonical(),
+        Some(Peer::Unix) => {
+            return forwarded_for(headers, is_trusted).unwrap_or_else(|| Client::Unknown("unix".to_string()));
+        }
+        None => return Client::Unknown("-".to_string()),
+    };
+    if !is_trusted(&direct) {
+        return Client::Ip(direct);
+    }
+    forwarded_for(headers, is_trusted).unwrap_or(Client::Ip(direct))
+}
+
+/// X-Forwarded-For 中从右向左第一个不受信任的地址；全部受信任时取最左边的
+fn forwarded_for(headers: &HeaderMap, is_trusted: impl Fn(&IpAddr) -> bool) -> Option<Client> {
+    let hops: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    let parse = |hop: &str| match hop.parse::<IpAddr>() {
+        Ok(ip) => Client::Ip(ip.to_canonical()),
+        Err(_) => Client::Unknown(hop.to_string()),
+    };
+    for hop in hops.iter().rev() {
+        match parse(hop) {
+            Client::Ip(ip) if is_trusted(&ip) => continue,
+            // 不是地址（如 unknown、带端口）时无法判断是否受信任，到此为止
+            c => return Some(c),
+        }
+    }
+    hops.first().map(|hop| parse(hop))
+}
+
+/// 客户端能否访问
+pub fn allowed(rules: &AccessRules, client: &Client) -> bool {
+    match client {
+        Client::Ip(ip) => {
+            !rules.deny.iter().any(|r| r.contains(ip))
+                && (rules.allow.is_empty() || rules.allow.iter().any(|r| r.contains(ip)))
+        }
+        Client::Unknown(_) => rules.allow.is_empty(),
+    }
+}
+
+/// 下载服务的访问控制中间件，拒绝时返回 403；ACME 验证请求来自 CA，不受限制
+pub async fn download(State(cc): State<Arc<ConfigCenter>>, req: Request, next: Next) -> Response {
+    if req.uri().path().starts_with("/.well-known/acme-challenge/") {
+        return next.run(req).await;
+    }
+    let denied = {
+        let cfg = cc.config().await;
+        let access = &cfg.access;
+        if access.download.is_empty() {
+            None
+        } else {
+            let client = client(req.extensions().get::<Peer>().copied(), req.headers(), &access.trusted_proxies);
+            (!allowed(&access.download, &client)).then_some(client)
+        }
+    };
+    if let Some(client) = denied {
+        debug!("[access] denied {} {}", client, req.uri().path());
+        return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+    }
+    next.run(req).await
+}
@@ -0,0 +1,332 @@
+//! 最小 ACME 客户端：账户注册、下单、HTTP-01 验证、CSR 提交、下载证书链
+//!
+//! 账户与证书密钥均为 ECDSA P-256，JWS 使用 ES256。
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use log::{debug, info};
+use openssl::bn::BigNumContext;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::stack::Stack;
+use openssl::x509::X509ReqBuilder;
+use openssl::x509::extension::SubjectAlternativeName;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+/// 轮询订单 / 授权状态的次数与间隔
+const POLL_ATTEMPTS: usize = 30;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Deserialize)]
+struct Order {
+    status: String,
+    #[serde(default)]
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Authorization {
+    status: String,
+    identifier: Identifier,
+    #[serde(default)]
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Deserialize)]
+struct Identifier {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    token: String,
+}
+
+/// 签发结果（PEM）
+pub struct Issued {
+    pub key_pem: Vec<u8>,
+    pub chain_pem: Vec<u8>,
+}
+
+pub struct Client {
+    http: reqwest::Client,
+    directory: Directory,
+    key: EcKey<Private>,
+    nonce: Option<String>,
+    kid: Option<String>,
+}
+
+impl Client {
+    /// 读取目录并加载（或生成）账户私钥
+    pub async fn new(http: reqwest::Client, directory_url: &str, account_key: &Path) -> Result<Self> {
+        let directory = http
+            .get(directory_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Directory>()
+            .await
+            .context("invalid ACME directory")?;
+
+        let key = match std::fs::read(account_key) {
+            Ok(pem) => EcKey::private_key_from_pem(&pem).context("invalid ACME account key")?,
+            Err(_) => {
+                let key = new_key()?;
+                std::fs::write(account_key, key.private_key_to_pem()?)?;
+                key
+            }
+        };
+
+        Ok(Self {
+            http,
+            directory,
+            key,
+            nonce: None,
+            kid: None,
+        })
+    }
+
+    /// 完整签发流程
+    pub async fn issue(mut self, contact: &[String], domains: &[String], challenge_dir: &Path) -> Result<Issued> {
+        self.register(contact).await?;
+
+        let identifiers: Vec<Value> = domains
+            .iter()
+            .map(|d| json!({ "type": "dns", "value": d }))
+            .collect();
+        let resp = self
+            .post(&self.directory.new_order.clone(), Some(json!({ "identifiers": identifiers })))
+            .await?;
+        let order_url = location(&resp)?;
+        let order: Order = resp.json().await?;
+
+        for authz_url in &order.authorizations {
+            self.authorize(authz_url, challenge_dir).await?;
+        }
+
+        // 提交 CSR
+        let cert_key = new_key()?;
+        let csr = csr(&cert_key, domains)?;
+        self.post(&order.finalize, Some(json!({ "csr": URL_SAFE_NO_PAD.encode(csr) })))
+            .await?;
+
+        let mut order = order;
+        for _ in 0..POLL_ATTEMPTS {
+            if order.status == "valid" && order.certificate.is_some() {
+                break;
+            }
+            if order.status == "invalid" {
+                bail!("order became invalid");
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+            order = self.post(&order_url, None).await?.json().await?;
+        }
+        let cert_url = order
+            .certificate
+            .ok_or_else(|| anyhow!("order not finalized in time (status: {})", order.status))?;
+
+        let chain_pem = self.post(&cert_url, None).await?.bytes().await?.to_vec();
+        Ok(Issued {
+            key_pem: cert_key.private_key_to_pem()?,
+            chain_pem,
+        })
+    }
+
+    /// 注册账户（已存在时返回同一账户）
+    async fn register(&mut self, contact: &[String]) -> Result<()> {
+        let contact: Vec<String> = contact
+            .iter()
+            .map(|c| if c.contains(':') { c.clone() } else { format!("mailto:{}", c) })
+            .collect();
+        let payload = json!({ "termsOfServiceAgreed": true, "contact": contact });
+        let resp = self.post(&self.directory.new_account.clone(), Some(payload)).await?;
+        self.kid = Some(location(&resp)?);
+        debug!("[acme] account {}", self.kid.as_deref().unwrap_or_default());
+        Ok(())
+    }
+
+    /// 完成单个授权的 HTTP-01 验证
+    async fn authorize(&mut self, authz_url: &str, challenge_dir: &Path) -> Result<()> {
+        let authz: Authorization = self.post(authz_url, None).await?.json().await?;
+        if authz.status == "valid" {
+            return Ok(());
+        }
+
+        let challenge = authz
+            .challenges
+            .iter()
+            .find(|c| c.kind == "http-01")
+            .ok_or_else(|| anyhow!("no http-01 challenge for {}", authz.identifier.value))?;
+        anyhow::ensure!(
+            challenge
+                .token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "unexpected challenge token"
+        );
+
+        let token_path = challenge_dir.join(&challenge.token);
+        std::fs::write(&token_path, format!("{}.{}", challenge.token, self.thumbprint()?))?;
+        info!("[acme] answering http-01 challenge for {}", authz.identifier.value);
+
+        let result = async {
+            self.post(&challenge.url, Some(json!({}))).await?;
+            for _ in 0..POLL_ATTEMPTS {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                let authz: Authorization = self.post(authz_url, None).await?.json().await?;
+                match authz.status.as_str() {
+                    "valid" => return Ok(()),
+                    "pending" | "processing" => continue,
+                    status => bail!("authorization for {} is {}", authz.identifier.value, status),
+                }
+            }
+            bail!("authorization for {} timed out", authz.identifier.value)
+        }
+        .await;
+
+        let _ = std::fs::remove_file(&token_path);
+        result
+    }
+
+    /// 发送 JWS 请求；payload 为 None 时为 POST-as-GET
+    async fn post(&mut self, url: &str, payload: Option<Value>) -> Result<reqwest::Response> {
+        // badNonce 时重试一次
+        for attempt in 0..2 {
+            let nonce = match self.nonce.take() {
+                Some(n) => n,
+                None => self.fresh_nonce().await?,
+            };
+
+            let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+            match &self.kid {
+                Some(kid) => protected["kid"] = json!(kid),
+                None => protected["jwk"] = self.jwk()?,
+            }
+            let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+            let payload = match &payload {
+                Some(p) => URL_SAFE_NO_PAD.encode(p.to_string()),
+                None => String::new(),
+            };
+            let signature = self.sign(format!("{}.{}", protected, payload).as_bytes())?;
+            let body = json!({ "protected": protected, "payload": payload, "signature": signature });
+
+            let resp = self
+                .http
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/jose+json")
+                .body(body.to_string())
+                .send()
+                .await?;
+            self.nonce = resp
+                .headers()
+                .get("replay-nonce")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+
+            if resp.status().is_success() {
+                return Ok(resp);
+            }
+            let status = resp.status();
+            let problem: Value = resp.json().await.unwrap_or_default();
+            if attempt == 0 && problem["type"] == "urn:ietf:params:acme:error:badNonce" {
+                continue;
+            }
+            bail!("ACME request to {} failed ({}): {}", url, status, problem);
+        }
+        unreachable!()
+    }
+
+    async fn fresh_nonce(&self) -> Result<String> {
+        let resp = self.http.head(&self.directory.new_nonce).send().await?;
+        resp.headers()
+            .get("replay-nonce")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("ACME server returned no nonce"))
+    }
+
+    fn jwk(&self) -> Result<Value> {
+        let (x, y) = public_coordinates(&self.key)?;
+        Ok(json!({ "crv": "P-256", "kty": "EC", "x": x, "y": y }))
+    }
+
+    /// RFC 7638 JWK 指纹（字段按字典序）
+    fn thumbprint(&self) -> Result<String> {
+        let (x, y) = public_coordinates(&self.key)?;
+        let canonical = format!(r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#, x, y);
+        Ok(URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes())))
+    }
+
+    /// ES256：签名为 r || s 各 32 字节
+    fn sign(&self, data: &[u8]) -> Result<String> {
+        let sig = EcdsaSig::sign(&Sha256::digest(data), &self.key)?;
+        let mut raw = sig.r().to_vec_padded(32)?;
+        raw.extend(sig.s().to_vec_padded(32)?);
+        Ok(URL_SAFE_NO_PAD.encode(raw))
+    }
+}
+
+fn new_key() -> Result<EcKey<Private>> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    Ok(EcKey::generate(&group)?)
+}
+
+fn public_coordinates(key: &EcKey<Private>) -> Result<(String, String)> {
+    let mut ctx = BigNumContext::new()?;
+    let mut x = openssl::bn::BigNum::new()?;
+    let mut y = openssl::bn::BigNum::new()?;
+    key.public_key()
+        .affine_coordinates(key.group(), &mut x, &mut y, &mut ctx)?;
+    Ok((
+        URL_SAFE_NO_PAD.encode(x.to_vec_padded(32)?),
+        URL_SAFE_NO_PAD.encode(y.to_vec_padded(32)?),
+    ))
+}
+
+/// 生成包含全部域名 SAN 的 CSR（DER）
+fn csr(key: &EcKey<Private>, domains: &[String]) -> Result<Vec<u8>> {
+    let pkey = PKey::from_ec_key(key.clone())?;
+    let mut builder = X509ReqBuilder::new()?;
+    builder.set_pubkey(&pkey)?;
+
+    let mut san = SubjectAlternativeName::new();
+    for d in domains {
+        san.dns(d);
+    }
+    let mut extensions = Stack::new()?;
+    extensions.push(san.build(&builder.x509v3_context(None))?)?;
+    builder.add_extensions(&extensions)?;
+
+    builder.sign(&pkey, MessageDigest::sha256())?;
+    Ok(builder.build().to_der()?)
+}
+
+fn location(resp: &reqwest::Response) -> Result<String> {
+    resp.headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("ACME response without Location header"))
+}
@@ -0,0 +1,138 @@
+//! ACME（RFC 8555）证书自动签发与续期，使用 HTTP-01 验证
+//!
+//! - 状态保存在 `storage_dir/.relayfetch/acme/`：账户私钥、证书链、证书私钥
+//! - 验证文件写入 `challenges/<token>`，由下载服务在
+//!   `/.well-known/acme-challenge/<token>` 提供，因此下载服务需能从 80 端口访问
+//! - 证书到期前 `renew_before_days` 天自动续期，TLS 服务热替换证书
+
+mod client;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use openssl::asn1::Asn1Time;
+use openssl::x509::X509;
+
+use crate::bandwidth::STATE_DIR;
+use crate::config::{ConfigCenter, config::AcmeConfig};
+use crate::server::tls::CertResolver;
+
+/// 续期检查间隔
+const CHECK_INTERVAL_SECS: u64 = 12 * 3600;
+
+/// 签发失败后的重试间隔
+const RETRY_INTERVAL_SECS: u64 = 3600;
+
+/// ACME 状态目录
+pub fn state_dir(storage_dir: &Path) -> PathBuf {
+    storage_dir.join(STATE_DIR).join("acme")
+}
+
+/// HTTP-01 验证文件目录
+pub fn challenge_dir(storage_dir: &Path) -> PathBuf {
+    state_dir(storage_dir).join("challenges")
+}
+
+pub fn cert_path(storage_dir: &Path) -> PathBuf {
+    state_dir(storage_dir).join("cert.pem")
+}
+
+pub fn key_path(storage_dir: &Path) -> PathBuf {
+    state_dir(storage_dir).join("key.pem")
+}
+
+/// 需要签发的域名：未显式配置时取 `url` 中的主机名
+pub fn domains(acme: &AcmeConfig, url: &str) -> Vec<String> {
+    if !acme.domains.is_empty() {
+        return acme.domains.clone();
+    }
+    let with_scheme = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    };
+    reqwest::Url::parse(&with_scheme)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .into_iter()
+        .collect()
+}
+
+/// 证书不存在或即将过期时需要（重新）签发
+fn needs_renewal(cert_path: &Path, renew_before_days: u32) -> bool {
+    let Ok(pem) = std::fs::read(cert_path) else {
+        return true;
+    };
+    let Ok(cert) = X509::from_pem(&pem) else {
+        return true;
+    };
+    match Asn1Time::days_from_now(renew_before_days) {
+        Ok(threshold) => cert.not_after() < threshold,
+        Err(_) => true,
+    }
+}
+
+/// 签发证书并写入状态目录（tmp + rename）
+async fn issue(cc: &ConfigCenter) -> Result<()> {
+    let (acme, domains, storage_dir, client) = {
+        let cfg = cc.config().await;
+        let acme = cfg.acme.clone().context("acme is not configured")?;
+        let domains = domains(&acme, &cfg.url);
+        (acme, domains, cfg.storage_dir.clone(), crate::sync::build_client(&cfg)?)
+    };
+    anyhow::ensure!(!domains.is_empty(), "no domain to request a certificate for");
+
+    let dir = state_dir(&storage_dir);
+    std::fs::create_dir_all(challenge_dir(&storage_dir))?;
+
+    info!("[acme] requesting certificate for {:?} from {}", domains, acme.directory);
+    let issued = client::Client::new(client, &acme.directory, &dir.join("account.key"))
+        .await?
+        .issue(&acme.contact, &domains, &challenge_dir(&storage_dir))
+        .await?;
+
+    for (path, data) in [
+        (key_path(&storage_dir), issued.key_pem),
+        (cert_path(&storage_dir), issued.chain_pem),
+    ] {
+        let tmp = path.with_extension("pem.tmp");
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, &path)?;
+    }
+    info!("[acme] certificate for {:?} stored in {}", domains, dir.display());
+    Ok(())
+}
+
+/// 启动证书维护任务：首次签发 + 周期续期，成功后刷新 TLS 证书
+pub fn spawn_renewal(cc: Arc<ConfigCenter>, resolver: Arc<CertResolver>) {
+    tokio::spawn(async move {
+        loop {
+            let (storage_dir, renew_before_days) = {
+                let cfg = cc.config().await;
+                let days = cfg.acme.as_ref().map(|a| a.renew_before_days).unwrap_or(30);
+                (cfg.storage_dir.clone(), days)
+            };
+            let cert = cert_path(&storage_dir);
+
+            let mut next = CHECK_INTERVAL_SECS;
+            if needs_renewal(&cert, renew_before_days) {
+                match issue(&cc).await {
+                    Ok(()) => {}
+                    Err(e) => {
+                        error!("[acme] certificate issuance failed: {:?}", e);
+                        next = RETRY_INTERVAL_SECS;
+                    }
+                }
+            }
+
+            if let Err(e) = resolver.load(&cert, &key_path(&storage_dir)) {
+                warn!("[acme] no usable certificate yet: {:?}", e);
+            }
+
+            tokio::time::sleep(Duration::from_secs(next)).await;
+        }
+    });
+}
@@ -0,0 +1,136 @@
+//! 同步告警（`[alert]`）
+//!
+//! 最近一次成功同步早于 `stale_after_secs`，或连续 `consecutive_failures` 次同步以失败结束时，
+//! 通过 SMTP 发送告警邮件；告警持续期间按 `repeat_after_secs` 重复发送，解除后可发送恢复通知。
+//! 每轮同步结束时与每分钟检查一次。告警状态不持久化，重启后条件仍满足时会再发送一次。
+
+mod smtp;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::ConfigCenter;
+use crate::config::config::AlertConfig;
+use crate::sync::{SyncEvent, SyncResult, SyncStatus};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn spawn(cc: Arc<ConfigCenter>) {
+    tokio::spawn(async move {
+        let started = SystemTime::now();
+        let mut events = cc.subscribe_events();
+        let mut tick = tokio::time::interval(CHECK_INTERVAL);
+        // 告警中时为上次发送的时间
+        let mut active: Option<Instant> = None;
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {}
+                ev = events.recv() => match ev {
+                    Ok(SyncEvent::SyncFinished { .. }) | Err(RecvError::Lagged(_)) => {}
+                    Ok(_) => continue,
+                    Err(RecvError::Closed) => return,
+                },
+            }
+            let Some(alert) = cc.config().await.alert.clone() else {
+                active = None;
+                continue;
+            };
+            let reasons = {
+                let status = cc.sync_status().await;
+                check(&alert, &status, started)
+            };
+            active = step(&cc, &alert, active, reasons).await;
+        }
+    });
+}
+
+/// 满足的告警条件，没有时为空
+fn check(alert: &AlertConfig, status: &SyncStatus, started: SystemTime) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if let Some(secs) = alert.stale_after_secs
+        && let Ok(age) = SystemTime::now().duration_since(status.last_ok_sync.unwrap_or(started))
+        && age >= Duration::from_secs(secs)
+    {
+        reasons.push(match status.last_ok_sync {
+            Some(t) => format!(
+                "last successful sync was {} ago ({})",
+                duration(age),
+                DateTime::<Utc>::from(t).to_rfc3339()
+            ),
+            None => format!("no successful sync since startup {} ago", duration(age)),
+        });
+    }
+    if let Some(n) = alert.consecutive_failures
+        && n > 0
+        && status.consecutive_failures >= n
+    {
+        let last = match &status.last_result {
+            SyncResult::Failed(e) => format!(", last error: {}", e),
+            _ => String::new(),
+        };
+        reasons.push(format!("{} consecutive syncs failed{}", status.consecutive_failures, last));
+    }
+    reasons
+}
+
+/// 按检查结果发送告警、重复告警或恢复通知，返回新的告警状态
+async fn step(cc: &ConfigCenter, alert: &AlertConfig, active: Option<Instant>, reasons: Vec<String>) -> Option<Instant> {
+    match active {
+        None if reasons.is_empty() => None,
+        None => {
+            warn!("[alert] {}", reasons.join("; "));
+            notify(cc, alert, "sync alert", &reasons).await;
+            Some(Instant::now())
+        }
+        Some(_) if reasons.is_empty() => {
+            info!("[alert] sync recovered");
+            if alert.send_recovery {
+                notify(cc, alert, "sync recovered", &["a sync completed successfully".to_string()]).await;
+            }
+            None
+        }
+        Some(last_sent) => {
+            if alert.repeat_after_secs > 0 && last_sent.elapsed() >= Duration::from_secs(alert.repeat_after_secs) {
+                notify(cc, alert, "sync alert (repeated)", &reasons).await;
+                return Some(Instant::now());
+            }
+            Some(last_sent)
+        }
+    }
+}
+
+async fn notify(cc: &ConfigCenter, alert: &AlertConfig, what: &str, reasons: &[String]) {
+    let url = cc.config().await.url.clone();
+    let subject = format!("{} {}: {}", alert.smtp.subject_prefix, url, what);
+    let mut body = format!("relayfetch at {}\n\n", url);
+    for r in reasons {
+        body.push_str(&format!("- {}\n", r));
+    }
+
+    // EHLO 报告的本机名取 url 中的主机名（url 可以不带 scheme）
+    let helo = reqwest::Url::parse(&url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .or_else(|| url.split([':', '/']).next().filter(|h| !h.is_empty()).map(str::to_string))
+        .unwrap_or_else(|| "localhost".to_string());
+    match smtp::send(&alert.smtp, &helo, &subject, &body).await {
+        Ok(()) => info!("[alert] mail sent to {}", alert.smtp.to.join(", ")),
+        Err(e) => warn!("[alert] failed to send mail: {:#}", e),
+    }
+}
+
+/// 如 `2d 3h`、`5h 12m`、`40m`、`15s`
+fn duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (days, hours, mins) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    match (days, hours) {
+        (0, 0) if mins == 0 => format!("{}s", secs),
+        (0, 0) => format!("{}m", mins),
+        (0, _) => format!("{}h {}m", hours, mins),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
@@ -0,0 +1,152 @@
+//! 最小 SMTP 客户端：EHLO、STARTTLS / SMTPS、AUTH PLAIN，发送一封纯文本邮件
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::config::config::{SmtpConfig, SmtpTls};
+
+/// 连接与每条命令的超时
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 发送一封邮件；`helo` 为 EHLO 中报告的本机名
+pub async fn send(cfg: &SmtpConfig, helo: &str, subject: &str, body: &str) -> Result<()> {
+    tokio::time::timeout(TIMEOUT * 4, deliver(cfg, helo, subject, body))
+        .await
+        .context("smtp session timed out")?
+}
+
+async fn deliver(cfg: &SmtpConfig, helo: &str, subject: &str, body: &str) -> Result<()> {
+    let addr = (cfg.host.as_str(), cfg.port());
+    let tcp = tokio::time::timeout(TIMEOUT, TcpStream::connect(addr))
+        .await
+        .context("connect timed out")?
+        .with_context(|| format!("connect to {}:{}", cfg.host, cfg.port()))?;
+
+    match cfg.tls {
+        SmtpTls::None => {
+            let mut s = BufReader::new(tcp);
+            expect(&mut s, 220).await?;
+            command(&mut s, &format!("EHLO {}", helo), 250).await?;
+            session(&mut s, cfg, subject, body).await
+        }
+        SmtpTls::Starttls => {
+            let mut s = BufReader::new(tcp);
+            expect(&mut s, 220).await?;
+            let ehlo = command(&mut s, &format!("EHLO {}", helo), 250).await?;
+            if !ehlo.lines().any(|l| l.trim().eq_ignore_ascii_case("STARTTLS")) {
+                bail!("server does not support STARTTLS");
+            }
+            command(&mut s, "STARTTLS", 220).await?;
+            let mut s = BufReader::new(tls(cfg, s.into_inner()).await?);
+            command(&mut s, &format!("EHLO {}", helo), 250).await?;
+            session(&mut s, cfg, subject, body).await
+        }
+        SmtpTls::Tls => {
+            let mut s = BufReader::new(tls(cfg, tcp).await?);
+            expect(&mut s, 220).await?;
+            command(&mut s, &format!("EHLO {}", helo), 250).await?;
+            session(&mut s, cfg, subject, body).await
+        }
+    }
+}
+
+async fn tls(cfg: &SmtpConfig, tcp: TcpStream) -> Result<tokio_native_tls::TlsStream<TcpStream>> {
+    let connector = tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+    connector
+        .connect(&cfg.host, tcp)
+        .await
+        .with_context(|| format!("tls handshake with {}", cfg.host))
+}
+
+/// EHLO 之后：认证、信封与正文
+async fn session<S: AsyncRead + AsyncWrite + Unpin>(
+    s: &mut BufReader<S>,
+    cfg: &SmtpConfig,
+    subject: &str,
+    body: &str,
+) -> Result<()> {
+    if let Some(user) = &cfg.username {
+        let password = cfg.password().map_err(anyhow::Error::msg)?.unwrap_or_default();
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("\0{}\0{}", user, password));
+        command(s, &format!("AUTH PLAIN {}", token), 235)
+            .await
+            .context("authentication failed")?;
+    }
+    command(s, &format!("MAIL FROM:<{}>", cfg.from), 250).await?;
+    for to in &cfg.to {
+        command(s, &format!("RCPT TO:<{}>", to), 250)
+            .await
+            .with_context(|| format!("recipient {} rejected", to))?;
+    }
+    command(s, "DATA", 354).await?;
+    s.write_all(message(cfg, subject, body).as_bytes()).await?;
+    command(s, ".", 250).await?;
+    let _ = command(s, "QUIT", 221).await;
+    Ok(())
+}
+
+/// 邮件头与正文；行尾统一为 CRLF，以 `.` 开头的行加一个 `.`
+fn message(cfg: &SmtpConfig, subject: &str, body: &str) -> String {
+    let mut out = format!(
+        "From: <{}>\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        cfg.from,
+        cfg.to.iter().map(|t| format!("<{}>", t)).collect::<Vec<_>>().join(", "),
+        encode_header(subject),
+        chrono::Utc::now().to_rfc2822(),
+    );
+    for line in body.lines() {
+        if line.starts_with('.') {
+            out.push('.');
+        }
+        out.push_str(line);
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// 非 ASCII 的标题按 RFC 2047 编码
+fn encode_header(s: &str) -> String {
+    if s.is_ascii() {
+        return s.to_string();
+    }
+    format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(s))
+}
+
+/// 发送一条命令并检查回复码，返回回复文本（多行回复去掉状态码后拼接）
+async fn command<S: AsyncRead + AsyncWrite + Unpin>(s: &mut BufReader<S>, line: &str, code: u16) -> Result<String> {
+    s.write_all(format!("{}\r\n", line).as_bytes()).await?;
+    s.flush().await?;
+    let verb = line.split(' ').next().unwrap_or(line);
+    expect(s, code).await.with_context(|| format!("{} failed", verb))
+}
+
+async fn expect<S: AsyncRead + Unpin>(s: &mut BufReader<S>, code: u16) -> Result<String> {
+    let mut text = String::new();
+    loop {
+        let mut line = String::new();
+        let n = tokio::time::timeout(TIMEOUT, s.read_line(&mut line))
+            .await
+            .context("smtp server timed out")??;
+        if n == 0 {
+            bail!("connection closed by smtp server");
+        }
+        let line = line.trim_end();
+        let got: u16 = line.get(..3).and_then(|c| c.parse().ok()).context("malformed smtp reply")?;
+        text.push_str(line.get(4..).unwrap_or(""));
+        text.push('\n');
+        // `250-...` 表示还有后续行
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        // 只比较回复类别（如 RCPT 的 251 也表示接受）
+        if got / 100 != code / 100 {
+            bail!("smtp server replied {}: {}", got, text.trim_end());
+        }
+        return Ok(text);
+    }
+}
@@ -0,0 +1,222 @@
+//! 按上游主机统计每日下载流量，并支持每日字节预算
+//!
+//! - 统计按 UTC 自然日划分，持久化到 `storage_dir/.relayfetch/bandwidth.toml`
+//! - 超出预算后，同步任务会推迟该主机的文件，反向代理缓存只返回已有副本
+//! - 全局月度预算用尽后暂停同步（下载服务照常），可通过管理接口临时放行当月
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::Utc;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigCenter;
+
+/// 内部状态目录（位于 storage_dir 下）
+pub const STATE_DIR: &str = ".relayfetch";
+
+/// 保留的历史天数
+const RETAIN_DAYS: usize = 31;
+
+/// 落盘间隔
+const FLUSH_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Ledger {
+    /// 日期（YYYY-MM-DD） -> 主机 -> 字节数
+    #[serde(default)]
+    days: BTreeMap<String, BTreeMap<String, u64>>,
+    /// 管理端放行的月份（YYYY-MM），该月忽略月度预算
+    #[serde(default)]
+    override_month: Option<String>,
+}
+
+/// 单个主机某一天的用量
+#[derive(Debug, Clone)]
+pub struct OriginUsage {
+    pub day: String,
+    pub host: String,
+    pub bytes: u64,
+}
+
+pub struct BandwidthLedger {
+    path: PathBuf,
+    ledger: Mutex<Ledger>,
+    dirty: AtomicBool,
+}
+
+impl BandwidthLedger {
+    /// 读取已有统计，文件缺失或损坏时从零开始
+    pub fn load(storage_dir: &Path) -> Self {
+        let path = storage_dir.join(STATE_DIR).join("bandwidth.toml");
+        let ledger = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| match toml::from_str(&s) {
+                Ok(l) => Some(l),
+                Err(e) => {
+                    warn!("[bandwidth] failed to parse {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            ledger: Mutex::new(ledger),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// 累加某主机今日下载量
+    pub fn record(&self, host: &str, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+        let mut ledger = self.ledger.lock().unwrap();
+        *ledger
+            .days
+            .entry(today())
+            .or_default()
+            .entry(host.to_string())
+            .or_default() += bytes;
+
+        // 只保留最近 RETAIN_DAYS 天
+        while ledger.days.len() > RETAIN_DAYS {
+            ledger.days.pop_first();
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// 某主机今日已下载字节数
+    pub fn used_today(&self, host: &str) -> u64 {
+        let ledger = self.ledger.lock().unwrap();
+        ledger
+            .days
+            .get(&today())
+            .and_then(|d| d.get(host))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// 今日用量是否已达到预算（未配置预算的主机不受限）
+    pub fn over_budget(&self, host: &str, budgets: &BTreeMap<String, u64>) -> bool {
+        budgets
+            .get(host)
+            .is_some_and(|budget| self.used_today(host) >= *budget)
+    }
+
+    /// 本月（UTC）所有主机累计下载量
+    pub fn month_total(&self) -> u64 {
+        let month = this_month();
+        let ledger = self.ledger.lock().unwrap();
+        ledger
+            .days
+            .iter()
+            .filter(|(day, _)| day.starts_with(&month))
+            .flat_map(|(_, hosts)| hosts.values())
+            .sum()
+    }
+
+    /// 月度预算是否已用尽（当月已放行则视为未用尽）
+    pub fn monthly_exhausted(&self, budget: Option<u64>) -> bool {
+        let Some(budget) = budget else {
+            return false;
+        };
+        !self.override_active() && self.month_total() >= budget
+    }
+
+    /// 当月是否已被管理端放行
+    pub fn override_active(&self) -> bool {
+        let ledger = self.ledger.lock().unwrap();
+        ledger.override_month.as_deref() == Some(this_month().as_str())
+    }
+
+    /// 放行 / 取消放行本月的月度预算
+    pub fn set_override(&self, enabled: bool) {
+        let mut ledger = self.ledger.lock().unwrap();
+        ledger.override_month = enabled.then(this_month);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// 今日各主机用量
+    pub fn today(&self) -> BTreeMap<String, u64> {
+        let ledger = self.ledger.lock().unwrap();
+        ledger.days.get(&today()).cloned().unwrap_or_default()
+    }
+
+    /// 全部保留的历史用量，按日期、主机排序
+    pub fn history(&self) -> Vec<OriginUsage> {
+        let ledger = self.ledger.lock().unwrap();
+        ledger
+            .days
+            .iter()
+            .flat_map(|(day, hosts)| {
+                hosts.iter().map(move |(host, bytes)| OriginUsage {
+                    day: day.clone(),
+                    host: host.clone(),
+                    bytes: *bytes,
+                })
+            })
+            .collect()
+    }
+
+    /// 有变更时写盘（tmp + rename）
+    pub fn flush(&self) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let toml = {
+            let ledger = self.ledger.lock().unwrap();
+            toml::to_string_pretty(&*ledger)?
+        };
+
+        let result = (|| {
+            crate::sync::meta::ensure_parent_dir(&self.path)?;
+            let tmp = self.path.with_extension("toml.tmp");
+            std::fs::write(&tmp, toml)?;
+            std::fs::rename(&tmp, &self.path)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+/// 从 URL 中取出主机名（含非默认端口），用于统计与预算
+pub fn host_of(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+fn this_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+/// 定期把流量统计落盘
+pub fn spawn_flusher(cc: Arc<ConfigCenter>) {
+    tokio::spawn(async move {
+        let mut ticker =
+            tokio::time::interval(std::time::Duration::from_secs(FLUSH_INTERVAL_SECS));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = cc.bandwidth().flush() {
+                warn!("[bandwidth] failed to persist usage: {:?}", e);
+            }
+        }
+    });
+}
@@ -1,7 +1,11 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, net::IpAddr, path::PathBuf};
 
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 
+use super::file::{CheckMethod, HttpOverrides, UpdateHook, UpstreamAuth};
+
 // ================= config.toml =================
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
@@ -10,7 +14,7 @@ pub struct Config {
     #[serde(default = "default_storage_dir")]
     pub storage_dir: PathBuf,
     #[serde(default = "default_bind")]
-    pub bind: String,
+    pub bind: Bind,
     #[serde(skip)] // 不从 toml 解析，运行时生成
     pub bind_addr: String,
     #[serde(skip)]
@@ -22,24 +26,1090 @@ pub struct Config {
     #[serde(default = "default_url")]
     pub url: String,
     pub proxy: Option<String>,
+    #[serde(default)] // 不经过代理的域名（含子域名），同 NO_PROXY 环境变量的写法，对所有代理生效
+    pub no_proxy: Vec<String>,
+    #[serde(default)] // 代理认证：basic_auth / token_env 作为 Proxy-Authorization，headers 附加到发往代理的请求；对 proxy 与 proxy_pool 生效
+    pub proxy_auth: UpstreamAuth,
+    #[serde(default)] // 下载 files 条目时轮换使用的代理，配置时代替 proxy；清单、目录列举等其他请求仍使用 proxy
+    pub proxy_pool: Vec<String>,
+    #[serde(default)] // 请求上游的连接 / 读取超时、重定向次数与 User-Agent，files.toml 中的条目可逐项覆盖
+    pub upstream_http: UpstreamHttp,
+    #[serde(default)] // proxy_pool 的轮换方式：per_file（每个文件换一个）/ per_attempt（每次重试换一个）
+    pub proxy_rotation: ProxyRotation,
     #[serde(default = "default_download_concurrency")]
     pub download_concurrency: usize,
+    #[serde(default)] // 从 download_concurrency 中为 priority = "high" 的文件保留的并发数，其他文件不能占用
+    pub high_priority_slots: usize,
     #[serde(default = "default_download_retry")]
     pub download_retry: usize,
     #[serde(default = "default_retry_base_delay")]
     pub retry_base_delay_ms: u64,
+    #[serde(default)] // 续传前按写入时记录的检查点校验已下载部分的摘要，不一致或上游只给出弱 ETag 时重新下载
+    pub verify_resume: bool,
+    #[serde(default)] // 判断本地文件是否过期的方式：条件 GET，或先 HEAD 比较校验头与大小，files.toml 中的条目可单独配置
+    pub check_method: CheckMethod,
+    #[serde(default)] // 下载服务器是否提供目录索引
+    pub enable_listing: bool,
+    #[serde(default)] // 下载端口 GET / 的响应：目录索引 / 跳转控制台 / JSON 服务描述 / 404
+    pub root: RootConfig,
+    #[serde(default)] // 日志格式：text / json
+    pub log_format: LogFormat,
+    #[serde(default)] // 监听 config.toml / files.toml 变更并自动重载（重启生效）
+    pub watch_config: bool,
+    #[serde(default = "default_shutdown_timeout")] // 退出时等待进行中的下载请求与同步结束的最长时间
+    pub shutdown_timeout_secs: u64,
+    #[serde(default = "default_config_revisions")] // 保留的配置修订数（启动、重载、管理接口修改与回滚时记录），0 表示不记录
+    pub config_revisions: usize,
+    #[serde(default)] // 存储扫描（stat / meta / 哈希）线程数，0 表示按 CPU 核数（重启生效）
+    pub scan_threads: usize,
+    #[serde(default = "default_storage_index_ttl")] // 存储目录索引的最长有效期，0 表示不缓存
+    pub storage_index_ttl_secs: u64,
+    #[serde(default = "default_coalesce_max_waiters")] // 单个回源上允许等待的最大请求数，超出返回 503
+    pub coalesce_max_waiters: usize,
+    #[serde(default)] // 每个上游主机每日下载字节预算，超出后推迟下载（UTC 日界）
+    pub origin_daily_budget_bytes: BTreeMap<String, u64>,
+    #[serde(default)] // 全局每月下载字节预算（UTC 自然月），用尽后暂停同步
+    pub monthly_budget_bytes: Option<u64>,
+    #[serde(default)] // 存储目录占用上限（含内部数据），下载前按 Content-Length 检查
+    pub max_storage_bytes: Option<u64>,
+    #[serde(default)] // 超出 max_storage_bytes 时淘汰最久未被下载的文件，被请求时再按需下载
+    pub evict_lru: bool,
+    #[serde(default)] // 按时段限速（本地时间），第一个命中的时段生效
+    pub bandwidth_schedule: Vec<BandwidthWindow>,
+    #[serde(default)] // 连续多轮失败的文件按轮次指数退避，避免每轮都重试失效的上游
+    pub failure_backoff: FailureBackoffConfig,
+    #[serde(default = "default_sync_history")] // 保留最近几次同步的流量统计（接收 / 节省的字节数、耗时），0 表示不保留
+    pub sync_history: usize,
+    #[serde(default)] // 禁止周期同步的时段（变更冻结、维护窗口等，本地时间）
+    pub sync_blackout: Option<BlackoutConfig>,
+    #[serde(default)] // 管理接口访问 token，为空时不鉴权
+    pub management_tokens: Vec<ApiToken>,
+    #[serde(default)] // ACME 自动证书（HTTP-01），配置后额外启动 HTTPS 下载服务
+    pub acme: Option<AcmeConfig>,
+    #[serde(default)] // 多镜像文件的上游选择顺序：ordered（按配置顺序）/ latency（按探测延迟）
+    pub source_selection: SourceSelection,
+    #[serde(default)] // 远端 files 清单，每次同步前拉取并校验后与本地 files.toml 合并
+    pub files_manifest: Option<FilesManifest>,
+    #[serde(default)] // 反向代理缓存规则（pull-through）
+    pub proxy_cache: Vec<ProxyCacheRule>,
+    #[serde(default)] // 按需回源：请求存储中不存在的路径时从上游拉取，边转发边落盘
+    pub on_demand: Option<OnDemandConfig>,
+    #[serde(default)] // 在下载端口提供 /.well-known/relayfetch.json 客户端清单
+    pub client_manifest: Option<ClientManifestConfig>,
+    #[serde(default)] // 在下载端口提供 /__manifest，供其他节点以 [upstream_peer] 镜像本节点的全部文件
+    pub peer_manifest: Option<ClientManifestConfig>,
+    #[serde(default)] // 镜像另一个 relayfetch 节点（hub）已同步的全部文件，无需重复配置 files.toml
+    pub upstream_peer: Option<UpstreamPeer>,
+    #[serde(default)] // 文件更新时生成上一版本到新版本的增量补丁，以 `/<文件>.patch?from=<sha256>` 提供
+    pub delta_patches: Option<DeltaPatchConfig>,
+    #[serde(default)] // 通过 mDNS 在局域网内广播下载服务（重启生效）
+    pub mdns: Option<MdnsConfig>,
+    #[serde(default)] // 文件下载完成 / 同步失败 / 被删除时执行的命令
+    pub notify: Option<NotifyConfig>,
+    #[serde(default)] // 文件内容更新后对所有文件执行的钩子（命令或 HTTP 回调），files.toml 中的条目可再单独配置
+    pub on_update: Vec<UpdateHook>,
+    #[serde(default)] // 文件下载完成后按前缀推送到次级存储（S3 / WebDAV / 另一个 relayfetch 节点）
+    pub replicate: Option<ReplicateConfig>,
+    #[serde(default)] // 同步长期未成功或连续失败时发送邮件告警（需启用 `smtp` feature）
+    pub alert: Option<AlertConfig>,
+    #[serde(default)] // 清理历史版本、孤立 meta 与长期未完成的下载，可定期执行或通过 RunRetention 执行
+    pub retention: Option<RetentionConfig>,
+    #[serde(default)] // 下载端口内置的 /favicon.ico 与 /robots.txt
+    pub static_assets: StaticAssetsConfig,
+    #[serde(default)] // 下载服务的 HTTP/2、keep-alive 与最大连接数（重启生效）
+    pub server: ServerConfig,
+    #[serde(default)] // 下载端口的 /healthz 与 /readyz 探针
+    pub probes: ProbesConfig,
+    #[serde(default)] // 下载服务的访问日志（combined / JSON，按大小轮转）；未配置时每个请求在程序日志中记一行
+    pub access_log: Option<AccessLogConfig>,
+    #[serde(default)] // 按客户端地址限制下载服务与管理接口的访问，以及受信任的反向代理
+    pub access: AccessConfig,
+    #[serde(default)] // 私有路径只能通过带签名、会过期的链接下载，链接由 SignUrl 生成
+    pub signed_urls: Option<SignedUrlConfig>,
+    #[serde(default)] // 管理接口时间的展示时区与格式；未配置时只返回 unix 时间
+    pub display_time: Option<DisplayTimeConfig>,
+    #[serde(default)] // files.toml 中 `s3://bucket/key` 上游使用的对象存储地址与密钥（需启用 `s3` feature）
+    pub s3: S3Config,
+    #[serde(default)] // `sftp://` 上游的私钥与主机密钥校验（需启用 `sftp` feature）
+    pub sftp: SftpConfig,
+    #[serde(default)] // relayfetch 自身的发布文件：随同步镜像，由 ApplyUpdate 校验签名后替换当前程序并重新执行
+    pub self_update: Option<SelfUpdateConfig>,
+}
+
+/// unix domain socket 监听地址的前缀，如 `unix:/run/relayfetch.sock`
+pub const UNIX_PREFIX: &str = "unix:";
+
+/// 下载服务监听地址：一个地址或地址列表（单个字符串中也可以用逗号分隔），见 server::listen
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum Bind {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Bind {
+    pub fn addrs(&self) -> Vec<String> {
+        let addrs: Vec<&str> = match self {
+            Self::One(s) => s.split(',').collect(),
+            Self::Many(v) => v.iter().map(String::as_str).collect(),
+        };
+        addrs
+            .into_iter()
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Bind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.addrs().join(","))
+    }
+}
+
+/// 反向代理缓存的重新校验策略
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RevalidatePolicy {
+    /// 遵循上游 Cache-Control / Expires
+    #[default]
+    Origin,
+    /// 忽略上游缓存头，固定 default_ttl_secs
+    Fixed,
+    /// 每次命中都向上游发条件请求
+    Always,
+    /// 缓存后永不重新校验（需手动 purge）
+    Never,
+}
+
+/// 多个上游时的尝试顺序
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceSelection {
+    /// 按 files.toml 中的顺序
+    #[default]
+    Ordered,
+    /// 每次同步前并发探测，按响应延迟排序
+    Latency,
+}
+
+/// 请求上游的 HTTP 客户端设置
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct UpstreamHttp {
+    /// 建立连接的超时，0 为不限制
+    pub connect_timeout_secs: u64,
+    /// 两次读取之间的超时，0 为不限制；不限制整个请求的时长
+    pub read_timeout_secs: u64,
+    /// 最多跟随的重定向次数，0 为不跟随
+    pub max_redirects: usize,
+    /// 未配置时不发送 User-Agent
+    pub user_agent: Option<String>,
+    /// 是否通过 ALPN 协商 HTTP/2，关闭时只用 HTTP/1.1；以下各项只能在 config.toml 中配置
+    pub http2: bool,
+    /// 完整下载时通过 Accept-Encoding 协商的内容编码，收到后解码保存；为空时不协商压缩
+    pub accept_encoding: Vec<ContentCoding>,
+    /// 空闲连接保留的时长，0 为一直保留
+    pub pool_idle_timeout_secs: u64,
+    /// 每个主机保留的空闲连接数，未配置时不限制
+    pub pool_max_idle_per_host: Option<usize>,
+}
+
+impl Default for UpstreamHttp {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            max_redirects: 10,
+            user_agent: None,
+            http2: true,
+            accept_encoding: Vec::new(),
+            pool_idle_timeout_secs: 90,
+            pool_max_idle_per_host: None,
+        }
+    }
+}
+
+/// 上游响应的内容编码（Content-Encoding）
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentCoding {
+    Gzip,
+    Deflate,
+    Zstd,
+    #[serde(rename = "br")]
+    Brotli,
+}
+
+impl ContentCoding {
+    /// Accept-Encoding / Content-Encoding 中的名称
+    pub fn token(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Zstd => "zstd",
+            Self::Brotli => "br",
+        }
+    }
+
+    pub fn parse(token: &str) -> Option<Self> {
+        match token.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            "zstd" => Some(Self::Zstd),
+            "br" => Some(Self::Brotli),
+            _ => None,
+        }
+    }
+}
+
+impl UpstreamHttp {
+    /// 以条目的设置覆盖
+    pub fn with(&self, overrides: Option<&HttpOverrides>) -> Self {
+        let Some(o) = overrides else {
+            return self.clone();
+        };
+        Self {
+            connect_timeout_secs: o.connect_timeout_secs.unwrap_or(self.connect_timeout_secs),
+            read_timeout_secs: o.read_timeout_secs.unwrap_or(self.read_timeout_secs),
+            max_redirects: o.max_redirects.unwrap_or(self.max_redirects),
+            user_agent: o.user_agent.clone().or_else(|| self.user_agent.clone()),
+            ..self.clone()
+        }
+    }
+}
+
+/// proxy_pool 的轮换方式
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyRotation {
+    /// 每个文件依次使用下一个代理，重试时不换
+    #[default]
+    PerFile,
+    /// 每次重试换用下一个代理
+    PerAttempt,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// 反向代理缓存规则：`/<prefix>/...` 转发到 `<origin>/...` 并缓存到磁盘
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProxyCacheRule {
+    /// 本地路径前缀（不含首尾 `/`）
+    pub prefix: String,
+    /// 上游地址，如 https://registry.npmjs.org
+    pub origin: String,
+    /// 重新校验策略
+    #[serde(default)]
+    pub revalidate: RevalidatePolicy,
+    /// 上游未给出缓存头时的默认有效期（秒）；fixed 策略下为固定有效期
+    #[serde(default = "default_proxy_cache_ttl")]
+    pub default_ttl_secs: u64,
+    /// 上游 404/410 的负缓存时间（秒），0 表示不缓存
+    #[serde(default = "default_negative_ttl")]
+    pub negative_ttl_secs: u64,
+}
+
+/// 限速时段：`start` - `end`（本地时间，start > end 表示跨午夜）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BandwidthWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// 同步下载总速率（字节/秒），不填表示不限
+    pub download_bytes_per_sec: Option<u64>,
+    /// 下载服务总速率（字节/秒），不填表示不限
+    pub serve_bytes_per_sec: Option<u64>,
+}
+
+/// 周期同步禁止时段；手动触发的同步不受影响
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BlackoutConfig {
+    /// 禁止时段内到期的同步：skip 跳过，等下一个周期；immediate 在时段结束后立即补一次
+    #[serde(default)]
+    pub catch_up: BlackoutCatchUp,
+    #[serde(default)]
+    pub periods: Vec<BlackoutPeriod>,
+    /// 允许同步的每日时段，配置时时段之外都视为禁止时段
+    #[serde(default)]
+    pub windows: Vec<SyncWindow>,
+}
+
+/// 每日时段（本地时间，start > end 表示跨午夜）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SyncWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl SyncWindow {
+    fn contains(&self, t: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= t && t < self.end
+        } else {
+            t >= self.start || t < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlackoutCatchUp {
+    #[default]
+    Skip,
+    Immediate,
+}
+
+/// 一次性时段（`start` - `end`）、每月固定日期（全天）或每天的固定时段，可同时配置
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BlackoutPeriod {
+    #[serde(default)]
+    pub start: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub end: Option<NaiveDateTime>,
+    /// 每月的日期，负数从月末倒数（-1 为最后一天）
+    #[serde(default)]
+    pub monthly_days: Vec<i32>,
+    /// 每天的时段，如工作时间
+    #[serde(default)]
+    pub daily: Option<SyncWindow>,
+    /// daily 只在这些日子生效（跨午夜的时段按开始的那天算），为空时每天生效
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+}
+
+impl BlackoutPeriod {
+    /// `now` 落在本时段内时返回时段结束时间
+    fn end_after(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if let (Some(start), Some(end)) = (self.start, self.end)
+            && start <= now
+            && now < end
+        {
+            return Some(end);
+        }
+        if let Some(end) = self.daily_end_after(now) {
+            return Some(end);
+        }
+        let today = now.date();
+        let days_in_month = NaiveDate::from_ymd_opt(today.year(), today.month(), 1)?
+            .checked_add_months(chrono::Months::new(1))?
+            .pred_opt()?
+            .day() as i32;
+        let day = today.day() as i32;
+        self.monthly_days
+            .iter()
+            .any(|&d| d == day || d == day - days_in_month - 1)
+            .then(|| (today + Duration::days(1)).and_time(NaiveTime::MIN))
+    }
+}
+
+impl BlackoutPeriod {
+    fn daily_end_after(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let daily = self.daily.as_ref()?;
+        if !daily.contains(now.time()) {
+            return None;
+        }
+        let today = now.date();
+        // 跨午夜时段的后半段属于前一天开始的时段
+        let (started, end) = if daily.start <= daily.end || now.time() >= daily.start {
+            (today, if daily.start <= daily.end { today } else { today + Duration::days(1) })
+        } else {
+            (today - Duration::days(1), today)
+        };
+        (self.weekdays.is_empty() || self.weekdays.contains(&started.weekday())).then(|| end.and_time(daily.end))
+    }
+}
+
+impl BlackoutConfig {
+    /// 处于禁止时段时返回（首尾相接的时段合并后的）结束时间
+    pub fn until(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut until = now;
+        // 每天都禁止时不会结束，最多向后看一年
+        while until < now + Duration::days(366)
+            && let Some(end) = self.periods.iter().filter_map(|p| p.end_after(until)).chain(self.next_window(until)).max()
+        {
+            until = end;
+        }
+        (until > now).then_some(until)
+    }
+
+    /// 配置了 windows 且 `now` 不在任何时段内时，返回下一个时段的开始时间
+    fn next_window(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if self.windows.is_empty() || self.windows.iter().any(|w| w.contains(now.time())) {
+            return None;
+        }
+        self.windows
+            .iter()
+            .map(|w| {
+                let start = now.date().and_time(w.start);
+                if start > now { start } else { start + Duration::days(1) }
+            })
+            .min()
+    }
+}
+
+/// ACME 证书配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AcmeConfig {
+    /// 证书域名，不填时取 `url` 中的主机名
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// 联系邮箱
+    #[serde(default)]
+    pub contact: Vec<String>,
+    /// ACME 目录地址
+    #[serde(default = "default_acme_directory")]
+    pub directory: String,
+    /// HTTPS 下载服务监听地址
+    #[serde(default = "default_tls_bind")]
+    pub tls_bind: String,
+    /// 到期前多少天续期
+    #[serde(default = "default_renew_before_days")]
+    pub renew_before_days: u32,
+}
+
+/// 远端 files 清单（格式同 files.toml），需配置签名公钥或校验和地址之一
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FilesManifest {
+    pub url: String,
+    /// 签名公钥（PEM，Ed25519 / RSA / ECDSA），签名从 signature_url 获取
+    #[serde(default)]
+    pub public_key: Option<PathBuf>,
+    /// 签名地址，默认 `<url>.sig`；内容为原始签名或其 base64
+    #[serde(default)]
+    pub signature_url: Option<String>,
+    /// sha256sum 格式的校验和地址
+    #[serde(default)]
+    pub checksum_url: Option<String>,
+}
+
+/// `sftp://` 上游；URL 与条目 basic_auth 都没有密码时用私钥登录，也未配置私钥时尝试 ssh-agent
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SftpConfig {
+    #[serde(default)]
+    pub private_key: Option<PathBuf>,
+    /// 私钥口令所在的环境变量
+    #[serde(default)]
+    pub passphrase_env: Option<String>,
+    /// OpenSSH known_hosts 文件，默认 `~/.ssh/known_hosts`；只接受其中记录的主机密钥
+    #[serde(default)]
+    pub known_hosts: Option<PathBuf>,
+    /// 不校验主机密钥，接受任何服务器（仅用于测试环境）
+    #[serde(default)]
+    pub insecure_accept_any_host_key: bool,
+}
+
+/// 自更新的发布文件
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SelfUpdateConfig {
+    /// 发布文件地址（当前平台的 relayfetch 可执行文件）
+    pub url: String,
+    /// 镜像到存储目录中的路径，其他节点也可从这里下载
+    pub path: String,
+    /// 发布签名公钥（PEM，Ed25519 / RSA / ECDSA）
+    pub public_key: PathBuf,
+    /// 签名地址，默认 `<url>.sig`；内容为原始签名或其 base64
+    #[serde(default)]
+    pub signature_url: Option<String>,
+}
+
+/// 按需回源（pull-through 镜像）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OnDemandConfig {
+    /// 上游基地址，请求 `/<path>` 回源到 `<upstream>/<path>`
+    pub upstream: String,
+    /// 只对匹配的路径回源（glob），为空时全部
+    #[serde(default)]
+    pub include: Vec<String>,
+}
+
+impl OnDemandConfig {
+    pub fn includes(&self, path: &str) -> bool {
+        self.include.is_empty()
+            || self.include.iter().any(|pattern| {
+                globset::Glob::new(pattern)
+                    .map(|g| g.compile_matcher().is_match(path))
+                    .unwrap_or(false)
+            })
+    }
+}
+
+/// 私有路径与签名链接
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SignedUrlConfig {
+    /// HMAC-SHA256 密钥；更换后已发出的链接全部失效
+    pub secret: String,
+    /// 私有路径（glob，匹配本地路径），只能通过签名链接下载，不出现在目录索引与清单中
+    #[serde(default)]
+    pub private: Vec<String>,
+    /// SignUrl 未指定有效期时使用
+    #[serde(default = "default_signed_url_ttl")]
+    pub default_ttl_secs: u64,
+    /// SignUrl 允许的最长有效期，未配置时不限
+    #[serde(default)]
+    pub max_ttl_secs: Option<u64>,
+    /// 生成链接使用的地址，默认 `http://<url>:<端口>`；经反向代理或 HTTPS 提供时设置
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl SignedUrlConfig {
+    pub fn is_private(&self, path: &str) -> bool {
+        self.private_matcher().is_match(path)
+    }
+
+    /// 匹配多个路径时先编译；无效的 glob 忽略
+    pub fn private_matcher(&self) -> globset::GlobSet {
+        let mut builder = globset::GlobSetBuilder::new();
+        for pattern in &self.private {
+            if let Ok(glob) = globset::Glob::new(pattern) {
+                builder.add(glob);
+            }
+        }
+        builder.build().unwrap_or_else(|_| globset::GlobSet::empty())
+    }
+}
+
+/// 客户端清单 `/.well-known/relayfetch.json`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ClientManifestConfig {
+    /// 访问 token（`Authorization: Bearer`），为空时公开
+    #[serde(default)]
+    pub tokens: Vec<String>,
+}
+
+/// 上游 relayfetch 节点
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UpstreamPeer {
+    /// hub 下载服务的地址，如 `http://hub.example.com:8080`；清单取自 `<url>/__manifest`
+    pub url: String,
+    /// hub 的 peer_manifest 配置了 tokens 时，从该环境变量读取 token
+    #[serde(default)]
+    pub token_env: Option<String>,
+    /// 只镜像这些前缀下的文件（按路径段匹配），为空时镜像全部
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+}
+
+/// 增量补丁
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DeltaPatchConfig {
+    /// 新旧版本任一超过此大小时不生成补丁（生成时需整体读入内存）
+    #[serde(default = "default_delta_max_file_bytes")]
+    pub max_file_bytes: u64,
+}
+
+/// mDNS / DNS-SD 广播（`_relayfetch._tcp`）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MdnsConfig {
+    /// 服务实例名，同一网段内应唯一
+    #[serde(default = "default_mdns_instance")]
+    pub instance: String,
+    /// TXT 记录中的下载地址，默认 `http://<url>:<端口>/`
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// 内置静态资源，不查找存储目录
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct StaticAssetsConfig {
+    /// 替换内置图标的文件，Content-Type 按扩展名推断
+    #[serde(default)]
+    pub favicon: Option<PathBuf>,
+    #[serde(default)]
+    pub robots: RobotsPolicy,
+    /// 自定义 robots.txt 全文，设置后忽略 robots
+    #[serde(default)]
+    pub robots_txt: Option<String>,
+}
+
+/// 下载服务（HTTP 与 HTTPS）的连接参数
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ServerConfig {
+    /// 接受 HTTP/2（明文 h2 prior knowledge，HTTPS 经 ALPN 协商）；关闭后只提供 HTTP/1.1
+    #[serde(default = "default_true")]
+    pub http2: bool,
+    /// 每个 HTTP/2 连接的最大并发流数
+    #[serde(default = "default_max_concurrent_streams")]
+    pub max_concurrent_streams: u32,
+    /// HTTP/1.1 keep-alive
+    #[serde(default = "default_true")]
+    pub keep_alive: bool,
+    /// HTTP/1.1 等待请求头（含 keep-alive 连接空闲等待下一个请求）的最长时间，0 表示不限
+    #[serde(default = "default_header_read_timeout")]
+    pub header_read_timeout_secs: u64,
+    /// HTTP/2 PING 保活间隔，0 表示不发送
+    #[serde(default)]
+    pub http2_keep_alive_interval_secs: u64,
+    /// HTTP/2 PING 未在此时间内得到响应时关闭连接
+    #[serde(default = "default_http2_keep_alive_timeout")]
+    pub http2_keep_alive_timeout_secs: u64,
+    /// 每个监听地址的最大并发连接数，达到后暂停 accept；未配置时不限
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            http2: true,
+            max_concurrent_streams: default_max_concurrent_streams(),
+            keep_alive: true,
+            header_read_timeout_secs: default_header_read_timeout(),
+            http2_keep_alive_interval_secs: 0,
+            http2_keep_alive_timeout_secs: default_http2_keep_alive_timeout(),
+            max_connections: None,
+        }
+    }
+}
+
+/// 负载均衡 / 编排系统的探针
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProbesConfig {
+    /// 最近一次成功同步早于此时长时 /readyz 返回 503（从未成功时从启动时算起），未配置时不检查
+    #[serde(default)]
+    pub max_staleness_secs: Option<u64>,
+}
+
+/// 下载服务的访问日志
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AccessLogConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: AccessLogFormat,
+    /// 文件超过此大小时轮转为 `<path>.1`，0 表示不轮转
+    #[serde(default = "default_access_log_max_size")]
+    pub max_size_bytes: u64,
+    /// 保留的轮转文件数（`<path>.1` … `<path>.N`），0 表示轮转时直接丢弃旧文件
+    #[serde(default = "default_access_log_max_files")]
+    pub max_files: usize,
+    /// 同时记录 /healthz 与 /readyz
+    #[serde(default)]
+    pub log_probes: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// Apache / nginx 的 combined 格式，末尾追加耗时（秒）
+    #[default]
+    Combined,
+    Json,
+}
+
+/// 按客户端地址的访问控制；客户端地址的识别见 trusted_proxies
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AccessConfig {
+    /// 受信任的反向代理；直连地址在列表中（或经 unix socket 连接）时，从右向左取 X-Forwarded-For 中
+    /// 第一个不受信任的地址作为客户端地址。同时用于访问日志
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpRange>,
+    /// 下载服务（HTTP 与 HTTPS）
+    #[serde(default)]
+    pub download: AccessRules,
+    /// gRPC 与 HTTP 管理接口
+    #[serde(default)]
+    pub management: AccessRules,
+}
+
+/// 先匹配 deny，命中则拒绝；allow 非空时只放行其中的地址
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AccessRules {
+    #[serde(default)]
+    pub allow: Vec<IpRange>,
+    #[serde(default)]
+    pub deny: Vec<IpRange>,
+}
+
+impl AccessRules {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+}
+
+/// IP 地址或 CIDR，如 `10.0.0.1`、`10.0.0.0/8`、`fd00::/8`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpRange(IpNet);
+
+impl IpRange {
+    /// IPv4 映射的 IPv6 地址（`::ffff:a.b.c.d`）按 IPv4 匹配
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.contains(&ip.to_canonical())
+    }
+}
+
+impl TryFrom<String> for IpRange {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse::<IpNet>()
+            .or_else(|_| s.parse::<IpAddr>().map(IpNet::from))
+            .map(Self)
+            .map_err(|_| format!("invalid IP address or CIDR {:?}", s))
+    }
+}
+
+impl From<IpRange> for String {
+    fn from(r: IpRange) -> Self {
+        // 单个地址不带前缀长度
+        if r.0.prefix_len() == r.0.max_prefix_len() {
+            r.0.addr().to_string()
+        } else {
+            r.0.to_string()
+        }
+    }
+}
+
+/// `s3://` 上游；未配置密钥时读取 AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY / AWS_SESSION_TOKEN，
+/// 都没有则匿名访问
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct S3Config {
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+    /// S3 兼容服务（MinIO 等）的地址，默认 `https://s3.<region>.amazonaws.com`
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// 使用 `<endpoint>/<bucket>/<key>` 形式的地址，默认 `<bucket>.<endpoint 主机>/<key>`
+    #[serde(default)]
+    pub path_style: bool,
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+    #[serde(default)]
+    pub session_token: Option<String>,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            region: default_s3_region(),
+            endpoint: None,
+            path_style: false,
+            access_key_id: None,
+            secret_access_key: None,
+            session_token: None,
+        }
+    }
+}
+
+/// 下载端口根路径的响应
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RootConfig {
+    #[serde(default)]
+    pub mode: RootMode,
+    /// redirect 的目标，默认 `http://<url>:<http_admin 端口>/dashboard`
+    #[serde(default)]
+    pub redirect_to: Option<String>,
+    /// descriptor 中的服务名
+    #[serde(default = "default_root_name")]
+    pub name: String,
+}
+
+impl Default for RootConfig {
+    fn default() -> Self {
+        Self {
+            mode: RootMode::default(),
+            redirect_to: None,
+            name: default_root_name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RootMode {
+    /// 开启 enable_listing 时渲染存储根目录的索引，否则 404
+    #[default]
+    Listing,
+    /// 302 跳转到管理控制台
+    Redirect,
+    /// JSON 服务描述：服务名、版本、文件数、最近一次同步
+    Descriptor,
+    NotFound,
+}
+
+/// robots.txt 策略
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RobotsPolicy {
+    /// 允许抓取（公开镜像希望被搜索引擎收录时）
+    Allow,
+    /// 禁止抓取全部路径，避免爬虫拉取大文件
+    #[default]
+    Disallow,
+}
+
+/// 跨同步轮次的失败退避：连续失败 `after_failures` 轮后跳过 1 轮，之后每多失败一轮跳过的轮数翻倍
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FailureBackoffConfig {
+    /// 开始跳过前允许的连续失败轮数，0 表示关闭退避
+    #[serde(default = "default_backoff_after_failures")]
+    pub after_failures: u32,
+    /// 最多连续跳过的轮数
+    #[serde(default = "default_backoff_max_skip_cycles")]
+    pub max_skip_cycles: u32,
+}
+
+impl Default for FailureBackoffConfig {
+    fn default() -> Self {
+        Self {
+            after_failures: default_backoff_after_failures(),
+            max_skip_cycles: default_backoff_max_skip_cycles(),
+        }
+    }
+}
+
+impl FailureBackoffConfig {
+    /// 连续失败 `failures` 轮后要跳过的轮数
+    pub fn skip_cycles(&self, failures: u32) -> u32 {
+        if self.after_failures == 0 || failures < self.after_failures {
+            return 0;
+        }
+        let exp = failures - self.after_failures;
+        2u32.checked_pow(exp).unwrap_or(u32::MAX).min(self.max_skip_cycles)
+    }
+}
+
+/// 管理接口中时间的展示方式，附加在 unix 时间旁的 `*_display` 字段中
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DisplayTimeConfig {
+    /// IANA 时区名，如 "Asia/Shanghai"、"UTC"
+    pub timezone: chrono_tz::Tz,
+    /// strftime 格式
+    #[serde(default = "default_display_time_format")]
+    pub format: String,
+}
+
+impl DisplayTimeConfig {
+    /// 格式化 unix 秒；格式串无效时退回 RFC 3339
+    pub fn format(&self, unix: u64) -> Option<String> {
+        use std::fmt::Write;
+
+        let dt = chrono::DateTime::from_timestamp(i64::try_from(unix).ok()?, 0)?.with_timezone(&self.timezone);
+        let mut out = String::new();
+        if write!(out, "{}", dt.format(&self.format)).is_err() {
+            return Some(dt.to_rfc3339());
+        }
+        Some(out)
+    }
+}
+
+/// 文件生命周期命令
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NotifyConfig {
+    /// 同时运行的命令数，超出的排队执行
+    #[serde(default = "default_notify_max_concurrent")]
+    pub max_concurrent: usize,
+    /// 单个命令的最长运行时间，超时后强制结束
+    #[serde(default = "default_notify_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub commands: Vec<NotifyCommand>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_notify_max_concurrent(),
+            timeout_secs: default_notify_timeout(),
+            commands: Vec::new(),
+        }
+    }
+}
+
+/// `command` 以 `sh -c` 执行，可用占位符：`{event}` `{file}` `{path}` `{sha256}` `{old_sha256}` `{size}` `{source}` `{error}`，
+/// 替换时已按 shell 规则转义，不要再加引号
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NotifyCommand {
+    pub event: NotifyEvent,
+    pub command: String,
+}
+
+/// 触发命令的事件
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyEvent {
+    /// 下载完成且内容有变化（含按需回源落盘）
+    Downloaded,
+    /// 重试用尽仍下载失败
+    Failed,
+    /// 被清理或 LRU 淘汰
+    Deleted,
+}
+
+impl NotifyEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotifyEvent::Downloaded => "downloaded",
+            NotifyEvent::Failed => "failed",
+            NotifyEvent::Deleted => "deleted",
+        }
+    }
+}
+
+/// 下载完成后的推送
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplicateConfig {
+    /// 同时进行的推送数，超出的排队
+    #[serde(default = "default_replicate_max_concurrent")]
+    pub max_concurrent: usize,
+    /// 每个文件推送到每个目标的尝试次数，失败后按指数退避重试
+    #[serde(default = "default_replicate_attempts")]
+    pub attempts: usize,
+    #[serde(default)]
+    pub targets: Vec<ReplicateTarget>,
+}
+
+/// 推送目标；文件匹配多个目标时分别推送
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplicateTarget {
+    /// 存储目录下的相对路径前缀，按路径段匹配；为空时匹配所有文件
+    #[serde(default)]
+    pub prefix: String,
+    /// `s3://<bucket>[/<key 前缀>]`（需启用 `s3` feature，使用 `[s3]` 的地址与密钥）、
+    /// `http(s)://` WebDAV 目录，或 `relayfetch+http(s)://` 另一节点的 HTTP 管理地址
+    pub target: String,
+    /// 目标中的路径去掉 prefix
+    #[serde(default)]
+    pub strip_prefix: bool,
+    /// relayfetch 目标从本节点下载文件的地址，默认 `http://<url>:<bind 端口>`
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// 请求目标时附加的请求头与认证，写法同 files.toml 条目
+    #[serde(flatten)]
+    pub auth: UpstreamAuth,
+}
+
+/// 存储清理策略；各项未配置时不按该项清理
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RetentionConfig {
+    /// 定期执行的间隔，0 表示只通过管理接口执行
+    #[serde(default)]
+    pub interval_secs: u64,
+    /// 定期执行时只记录将删除的内容
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 历史版本被替换后保留的时长
+    #[serde(default)]
+    pub max_version_age_secs: Option<u64>,
+    /// 每个文件最多保留的历史版本数，与条目的 keep_versions 取较严者
+    #[serde(default)]
+    pub max_versions: Option<usize>,
+    /// 历史版本总大小上限，超出时从最早替换的删起
+    #[serde(default)]
+    pub max_version_bytes: Option<u64>,
+    /// 超过此时长未写入的未完成下载被丢弃，下次从头下载
+    #[serde(default)]
+    pub max_partial_age_secs: Option<u64>,
+    /// 删除对应数据文件已不存在的 meta
+    #[serde(default)]
+    pub orphaned_meta: bool,
+}
+
+/// 同步长期失败时的邮件告警；两个条件都未配置时不告警
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertConfig {
+    /// 最近一次成功同步早于此时长时告警（从未成功时从启动时算起）
+    #[serde(default)]
+    pub stale_after_secs: Option<u64>,
+    /// 连续这么多次同步以失败结束时告警（取消的同步不计入）
+    #[serde(default)]
+    pub consecutive_failures: Option<u32>,
+    /// 告警持续期间重复发送的间隔，0 表示只发送一次
+    #[serde(default)]
+    pub repeat_after_secs: u64,
+    /// 告警解除后发送恢复通知
+    #[serde(default)]
+    pub send_recovery: bool,
+    pub smtp: SmtpConfig,
+}
+
+/// SMTP 服务器与收件人
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// 默认按 tls：none 为 25，starttls 为 587，tls 为 465
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    /// 配置后以 AUTH PLAIN 认证
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// 从环境变量读取密码，优先于 password
+    #[serde(default)]
+    pub password_env: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    /// 邮件标题前缀
+    #[serde(default = "default_subject_prefix")]
+    pub subject_prefix: String,
+}
+
+#[cfg(feature = "smtp")]
+impl SmtpConfig {
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.tls {
+            SmtpTls::None => 25,
+            SmtpTls::Starttls => 587,
+            SmtpTls::Tls => 465,
+        })
+    }
+
+    pub fn password(&self) -> Result<Option<String>, String> {
+        match &self.password_env {
+            Some(var) => super::file::env(var).map(Some),
+            None => Ok(self.password.clone()),
+        }
+    }
+}
+
+/// SMTP 连接方式
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// 明文（仅限可信网络）
+    None,
+    /// 明文连接后 STARTTLS 升级
+    #[default]
+    Starttls,
+    /// 连接即 TLS（SMTPS）
+    Tls,
+}
+
+/// 管理接口访问 token
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiToken {
+    /// 仅用于日志
+    #[serde(default)]
+    pub name: String,
+    pub token: String,
+    /// 允许调用的接口（snake_case，如 status / trigger_sync），支持 glob，默认全部
+    #[serde(default = "default_token_allow")]
+    pub allow: Vec<String>,
 }
 
 impl Config {
-    /// 加载完成后拆分 bind
+    /// 加载完成后拆分 bind（取第一个 TCP 地址）
     pub fn finalize(&mut self) {
-        let mut parts = self.bind.split(':');
-        self.bind_addr = parts.next().unwrap_or("0.0.0.0").to_string();
-        self.bind_port = parts
-            .next()
-            .unwrap_or("8080")
-            .parse::<u16>()
-            .unwrap_or(8080);
+        let addrs = self.bind.addrs();
+        let (addr, port) = addrs
+            .iter()
+            .find(|a| !a.starts_with(UNIX_PREFIX))
+            .and_then(|a| a.rsplit_once(':'))
+            .unwrap_or(("0.0.0.0", "8080"));
+        self.bind_addr = addr.trim_start_matches('[').trim_end_matches(']').to_string();
+        self.bind_port = port.parse::<u16>().unwrap_or(8080);
     }
 }
 
@@ -51,8 +1121,11 @@ fn default_interval() -> u64 {
 fn default_storage_dir() -> PathBuf {
     "data".into()
 }
-fn default_bind() -> String {
-    "0.0.0.0:8080".into()
+fn default_storage_index_ttl() -> u64 {
+    300
+}
+fn default_bind() -> Bind {
+    Bind::One("0.0.0.0:8080".into())
 }
 
 fn default_grpc_admin() -> String {
@@ -79,3 +1152,112 @@ fn default_download_retry() -> usize {
 fn default_retry_base_delay() -> u64 {
     1000
 }
+
+fn default_sync_history() -> usize {
+    20
+}
+
+fn default_subject_prefix() -> String {
+    "[relayfetch]".into()
+}
+fn default_access_log_max_size() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_access_log_max_files() -> usize {
+    5
+}
+
+fn default_signed_url_ttl() -> u64 {
+    3600
+}
+
+fn default_true() -> bool {
+    true
+}
+fn default_max_concurrent_streams() -> u32 {
+    200
+}
+fn default_header_read_timeout() -> u64 {
+    30
+}
+fn default_http2_keep_alive_timeout() -> u64 {
+    20
+}
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+fn default_config_revisions() -> usize {
+    20
+}
+fn default_coalesce_max_waiters() -> usize {
+    256
+}
+
+fn default_acme_directory() -> String {
+    "https://acme-v02.api.letsencrypt.org/directory".into()
+}
+
+fn default_tls_bind() -> String {
+    "0.0.0.0:443".into()
+}
+
+fn default_renew_before_days() -> u32 {
+    30
+}
+
+fn default_delta_max_file_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+fn default_notify_max_concurrent() -> usize {
+    4
+}
+
+fn default_notify_timeout() -> u64 {
+    60
+}
+
+fn default_replicate_max_concurrent() -> usize {
+    2
+}
+
+fn default_replicate_attempts() -> usize {
+    3
+}
+
+fn default_root_name() -> String {
+    "relayfetch".into()
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".into()
+}
+
+fn default_backoff_after_failures() -> u32 {
+    3
+}
+
+fn default_backoff_max_skip_cycles() -> u32 {
+    32
+}
+
+fn default_display_time_format() -> String {
+    "%Y-%m-%d %H:%M:%S %Z".into()
+}
+
+fn default_mdns_instance() -> String {
+    "relayfetch".into()
+}
+
+fn default_token_allow() -> Vec<String> {
+    vec!["*".into()]
+}
+
+fn default_proxy_cache_ttl() -> u64 {
+    3600
+}
+
+fn default_negative_ttl() -> u64 {
+    60
+}
@@ -1,9 +1,846 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path};
 
 use serde::{Deserialize, Serialize};
 
 // ================= files.toml =================
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FilesConfig {
-    pub files: HashMap<String, String>,
+    #[serde(default)]
+    pub files: HashMap<String, FileSource>,
+    /// 目录镜像：key 为本地前缀，同步时枚举远端条目
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub dirs: HashMap<String, DirSource>,
+    /// 文件分组：条目以 `group` 加入，按分组的间隔、并发与重试设置同步
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, FileGroup>,
+}
+
+/// 分组的同步设置，未配置的项沿用 config.toml
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct FileGroup {
+    /// 配置时分组按此间隔单独同步，不再随周期同步进行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+    /// 分组内同时下载的文件数，不超过 download_concurrency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_concurrency: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_retry: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_base_delay_ms: Option<u64>,
+}
+
+impl FilesConfig {
+    pub fn parse(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    /// 检查本地路径：必须是存储目录下的相对路径，不能越出存储目录或写入以 `.` 开头的内部目录，
+    /// 多个条目也不能落到同一个文件（包括 meta）或互相占用对方需要的目录；条目引用的分组必须已定义
+    pub fn validate(&self) -> Result<(), String> {
+        let mut files: Vec<&str> = self.files.keys().map(String::as_str).collect();
+        files.sort_unstable();
+        for file in &files {
+            check_path(file).map_err(|e| format!("[files] {:?}: {}", file, e))?;
+            if file.ends_with(".meta") {
+                return Err(format!("[files] {:?}: the .meta extension is reserved for metadata files", file));
+            }
+        }
+        let mut dirs: Vec<&str> = self.dirs.keys().map(|p| p.trim_matches('/')).collect();
+        dirs.sort_unstable();
+        for dir in &dirs {
+            check_path(dir).map_err(|e| format!("[dirs] {:?}: {}", dir, e))?;
+        }
+        for (name, group) in &self.groups {
+            if name.is_empty() {
+                return Err("[groups]: group name is empty".to_string());
+            }
+            if group.interval_secs == Some(0) || group.download_concurrency == Some(0) || group.download_retry == Some(0) {
+                return Err(format!("[groups] {:?}: interval_secs, download_concurrency and download_retry must be positive", name));
+            }
+        }
+        for file in &files {
+            let source = &self.files[*file];
+            for into in source.unpack_dirs() {
+                check_path(into).map_err(|e| format!("[files] {:?}: unpack into {:?}: {}", file, into, e))?;
+            }
+            source.check_options().map_err(|e| format!("[files] {:?}: {}", file, e))?;
+            if let Some(group) = source.group()
+                && !self.groups.contains_key(group)
+            {
+                return Err(format!("[files] {:?}: group {:?} is not defined in [groups]", file, group));
+            }
+            if let FileSource::Entry(e) = source
+                && e.no_proxy
+                && e.proxy.is_some()
+            {
+                return Err(format!("[files] {:?}: proxy and no_proxy cannot both be set", file));
+            }
+            let undetected = source.post_process().iter().any(|step| matches!(step, PostStep::Unpack { format: None, .. }));
+            if undetected && ArchiveFormat::detect(file).is_none() {
+                return Err(format!("[files] {:?}: cannot detect archive format, set `format` on the unpack step", file));
+            }
+        }
+
+        // 数据文件 `a.txt` 与 `a.bin` 的 meta 都是 `a.meta`
+        let mut metas: HashMap<String, &str> = HashMap::new();
+        for file in &files {
+            let meta = Path::new(file).with_extension("meta").to_string_lossy().into_owned();
+            if let Some(other) = metas.insert(meta.clone(), file) {
+                return Err(format!("[files] {:?} and {:?} both map to metadata file {:?}", other, file, meta));
+            }
+        }
+
+        // 文件路径不能同时是另一个条目或目录镜像的上级目录
+        let file_set: HashSet<&str> = files.iter().copied().collect();
+        for path in files.iter().chain(&dirs) {
+            for (i, _) in path.match_indices('/') {
+                let parent = &path[..i];
+                if file_set.contains(parent) {
+                    return Err(format!("[files] {:?} is a file but {:?} needs it as a directory", parent, path));
+                }
+            }
+        }
+        if let Some(dir) = dirs.iter().find(|d| file_set.contains(*d)) {
+            return Err(format!("{:?} is configured both in [files] and as a [dirs] prefix", dir));
+        }
+
+        // 解包目录发布时整体替换，不能与条目、目录镜像或其他解包目录重叠
+        let mut targets: Vec<(&str, &str)> = Vec::new();
+        for file in &files {
+            for into in self.files[*file].unpack_dirs() {
+                if let Some((_, other)) = targets.iter().find(|(t, _)| *t == into) {
+                    return Err(format!("[files] {:?} and {:?} both unpack into {:?}", other, file, into));
+                }
+                targets.push((into, file));
+            }
+        }
+        let occupied: Vec<&str> = files.iter().chain(&dirs).copied().chain(targets.iter().map(|(t, _)| *t)).collect();
+        for (into, file) in &targets {
+            if files.contains(into) || dirs.contains(into) {
+                return Err(format!("[files] {:?}: unpack target {:?} is also configured as an entry", file, into));
+            }
+            let overlap = occupied.iter().find(|path| {
+                let inside = |outer: &str, inner: &str| inner.match_indices('/').any(|(i, _)| &inner[..i] == outer);
+                inside(into, path) || inside(path, into)
+            });
+            if let Some(path) = overlap {
+                return Err(format!("[files] {:?}: unpack target {:?} overlaps {:?}", file, into, path));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 相对路径的每一段都不能为空、为 `.`/`..` 或以 `.` 开头
+pub fn check_path(path: &str) -> Result<(), &'static str> {
+    if path.is_empty() {
+        return Err("path is empty");
+    }
+    let absolute = Path::new(path)
+        .components()
+        .any(|c| matches!(c, Component::RootDir | Component::Prefix(_)));
+    if absolute || path.starts_with('/') {
+        return Err("path must be relative to storage_dir");
+    }
+    if path.contains('\\') {
+        return Err("path must use '/' as separator");
+    }
+    for seg in path.split('/') {
+        match seg {
+            "" => return Err("path contains an empty segment"),
+            "." | ".." => return Err("path contains a '.' or '..' segment"),
+            _ if seg.starts_with('.') => return Err("segments starting with '.' are reserved for internal directories"),
+            _ if seg.contains('\0') => return Err("path contains a NUL character"),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 单个文件的上游：一个 URL，按优先级排列的多个镜像，或带选项的条目
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum FileSource {
+    Url(String),
+    Mirrors(Vec<String>),
+    Entry(Box<FileEntry>),
+}
+
+/// 带选项的条目
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct FileEntry {
+    /// 主地址与镜像，按优先级排列
+    pub urls: Vec<String>,
+    /// 覆盖按扩展名推断的 Content-Type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// 以下载方式提供（Content-Disposition: attachment）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub attachment: bool,
+    /// 上游是压缩文件，下载时解压后保存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decompress: Option<Compression>,
+    /// 只镜像上游对象的一段字节，如 `"0-1048575"`（前 1 MiB）；不能与 decompress 同时使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<ByteRange>,
+    /// 上游发布的 `.zsync` 控制文件地址；本地已有旧版本时只下载变化的块
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zsync: Option<String>,
+    /// 分成几段并发 Range 下载，用于高延迟链路上的大文件；不能与 decompress / range 同时使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_chunks: Option<usize>,
+    /// 覆盖 config.toml 的 check_method
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_method: Option<CheckMethod>,
+    /// 同步时的优先级，high 的文件最先开始并可使用 high_priority_slots 保留的并发
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// 所属的分组（[groups] 中的名字）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// 期望的内容 sha256：本地副本一致时不再请求上游，下载结果不一致时视为失败
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// 分离签名（如 `.asc`）与信任的密钥环，校验通过才发布；不能与 decompress / range 同时使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureCheck>,
+    /// 内容变化时保留的旧版本数，旧版本移入 `.versions/<时间>/`，下载端口以 `/__versions/` 提供
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_versions: Option<usize>,
+    /// 最近一次同步距今超过此时长即为过期，随 Status 与 GET /freshness 报告
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+    /// 过期时下载端口的 /readyz 返回 503；需配合 max_age_secs
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub critical: bool,
+    /// 内容更新后执行的钩子，在 config.toml 的全局 on_update 之后执行
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_update: Vec<UpdateHook>,
+    /// 校验摘要之后、替换本地文件之前依次执行的后处理步骤
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_process: Vec<PostStep>,
+    /// 经由此代理请求上游，覆盖 config.toml 的 proxy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// 不经过代理直接请求上游（包括环境变量中的代理）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_proxy: bool,
+    /// 覆盖 config.toml [upstream_http] 的超时、重定向与 User-Agent
+    #[serde(flatten)]
+    pub http: HttpOverrides,
+    /// 请求上游时附加的请求头与认证
+    #[serde(flatten)]
+    pub auth: UpstreamAuth,
+}
+
+/// 条目单独的 HTTP 客户端设置，未配置的项沿用 config.toml 的 [upstream_http]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct HttpOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_redirects: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+impl HttpOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 请求上游时附加的请求头与认证，对条目的所有镜像生效
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct UpstreamAuth {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub basic_auth: Option<BasicAuth>,
+    /// 从该环境变量读取 token，以 `Authorization: Bearer <token>` 发送
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+}
+
+impl UpstreamAuth {
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.basic_auth.is_none() && self.token_env.is_none()
+    }
+}
+
+/// 文件内容更新（替换为 sha256 不同的新版本）后执行的钩子
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum UpdateHook {
+    /// 以 `sh -c` 执行，文件信息在 `RELAYFETCH_*` 环境变量中
+    Command { command: String },
+    /// 以 POST 发送 JSON，如 CDN 的刷新接口
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+/// 以 gpgv 校验上游发布的分离签名
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SignatureCheck {
+    /// 签名文件地址，使用条目的请求头与认证
+    pub url: String,
+    /// 信任的公钥（`gpg --export` 导出的二进制密钥环）
+    pub keyring: String,
+}
+
+/// 下载完成后的一个后处理步骤，任一步失败时本次下载作废，本地文件保持不变
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "step", rename_all = "lowercase")]
+pub enum PostStep {
+    /// 把 tar（可带压缩）或 zip 解包到存储目录下的 `into` 目录，整个目录随文件一起替换
+    Unpack {
+        into: String,
+        /// 省略时按本地路径的扩展名判断
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<ArchiveFormat>,
+    },
+    /// 把文件压缩后保存
+    Compress { format: Compression },
+    /// 以 `sh -c` 执行校验命令，退出码非 0 或超时时视为失败；`RELAYFETCH_PATH` 为待发布的文件
+    Validate {
+        command: String,
+        #[serde(default = "default_validate_timeout")]
+        timeout_secs: u64,
+    },
+}
+
+fn default_validate_timeout() -> u64 {
+    300
+}
+
+/// 可解包的归档格式
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "tar.zst")]
+    TarZst,
+    #[serde(rename = "tar.xz")]
+    TarXz,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// 按文件名的扩展名判断
+    pub fn detect(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        [
+            (".tar", Self::Tar),
+            (".tar.gz", Self::TarGz),
+            (".tgz", Self::TarGz),
+            (".tar.zst", Self::TarZst),
+            (".tzst", Self::TarZst),
+            (".tar.xz", Self::TarXz),
+            (".txz", Self::TarXz),
+            (".zip", Self::Zip),
+        ]
+        .into_iter()
+        .find_map(|(ext, format)| name.ends_with(ext).then_some(format))
+    }
+
+    /// tar 外层的压缩
+    pub fn compression(&self) -> Option<Compression> {
+        match self {
+            Self::TarGz => Some(Compression::Gzip),
+            Self::TarZst => Some(Compression::Zstd),
+            Self::TarXz => Some(Compression::Xz),
+            Self::Tar | Self::Zip => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct BasicAuth {
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// 从环境变量读取密码，优先于 password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
+}
+
+impl BasicAuth {
+    pub fn password(&self) -> Result<Option<String>, String> {
+        match &self.password_env {
+            Some(var) => env(var).map(Some),
+            None => Ok(self.password.clone()),
+        }
+    }
+}
+
+/// 读取保存密钥的环境变量
+pub fn env(var: &str) -> Result<String, String> {
+    std::env::var(var).map_err(|_| format!("environment variable {} is not set", var))
+}
+
+/// 字节范围 `<start>-<end>`（含 end），省略 end 表示到对象末尾
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl ByteRange {
+    /// Range 请求头的值
+    pub fn header(&self) -> String {
+        format!("bytes={}", self)
+    }
+
+    /// 范围长度；到对象末尾时未知
+    pub fn len(&self) -> Option<u64> {
+        self.end.map(|end| end - self.start + 1)
+    }
+}
+
+impl std::fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}-{}", self.start, end),
+            None => write!(f, "{}-", self.start),
+        }
+    }
+}
+
+impl TryFrom<String> for ByteRange {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid byte range {:?}, expected \"<start>-<end>\"", s);
+        let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+        let start = start.trim().parse().map_err(|_| invalid())?;
+        let end = match end.trim() {
+            "" => None,
+            end => Some(end.parse::<u64>().map_err(|_| invalid())?),
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err(invalid());
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl From<ByteRange> for String {
+    fn from(r: ByteRange) -> Self {
+        r.to_string()
+    }
+}
+
+/// 同步时的下载顺序，high 最先开始
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+/// 判断本地文件是否过期的方式
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckMethod {
+    /// 带 If-None-Match / If-Modified-Since 的 GET，有变化时直接下载
+    #[default]
+    Get,
+    /// 先 HEAD，比较 ETag / Last-Modified / Content-Length，有变化时再 GET；
+    /// 用于按 GET 次数计费或限制条件 GET 的上游
+    Head,
+}
+
+/// 上游文件的压缩格式
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl FileSource {
+    /// 由主地址与备用镜像构造
+    pub fn new(primary: String, mirrors: Vec<String>) -> Self {
+        if mirrors.is_empty() {
+            Self::Url(primary)
+        } else {
+            Self::Mirrors(std::iter::once(primary).chain(mirrors).collect())
+        }
+    }
+
+    pub fn urls(&self) -> &[String] {
+        match self {
+            Self::Url(u) => std::slice::from_ref(u),
+            Self::Mirrors(urls) => urls,
+            Self::Entry(e) => &e.urls,
+        }
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        match self {
+            Self::Entry(e) => e.content_type.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn attachment(&self) -> bool {
+        matches!(self, Self::Entry(e) if e.attachment)
+    }
+
+    pub fn decompress(&self) -> Option<Compression> {
+        match self {
+            Self::Entry(e) => e.decompress,
+            _ => None,
+        }
+    }
+
+    pub fn range(&self) -> Option<ByteRange> {
+        match self {
+            Self::Entry(e) => e.range,
+            _ => None,
+        }
+    }
+
+    pub fn zsync(&self) -> Option<&str> {
+        match self {
+            Self::Entry(e) => e.zsync.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// 不能同时使用的选项在加载时拒绝，而不是每次同步时失败
+    pub fn check_options(&self) -> Result<(), &'static str> {
+        if self.parallel_chunks() > 1 && (self.decompress().is_some() || self.range().is_some()) {
+            return Err("parallel_chunks cannot be combined with decompress or range");
+        }
+        // 后处理会改变文件内容，与 zsync（依赖本地旧版本与上游一致）及 range 的单独下载流程不兼容
+        if !self.post_process().is_empty() && (self.zsync().is_some() || self.range().is_some()) {
+            return Err("post_process cannot be combined with zsync or range");
+        }
+        // zsync 控制文件描述的是上游原始内容，与解压后保存的文件对不上
+        if self.zsync().is_some() && self.decompress().is_some() {
+            return Err("zsync cannot be combined with decompress");
+        }
+        // 签名针对上游发布的原始内容
+        if self.signature().is_some() && (self.decompress().is_some() || self.range().is_some()) {
+            return Err("signature cannot be combined with decompress or range");
+        }
+        Ok(())
+    }
+
+    /// 未配置时为 1，不分段
+    pub fn parallel_chunks(&self) -> usize {
+        match self {
+            Self::Entry(e) => e.parallel_chunks.unwrap_or(1).max(1),
+            _ => 1,
+        }
+    }
+
+    /// 未配置时为 None，使用 config.toml 的 check_method
+    pub fn check_method(&self) -> Option<CheckMethod> {
+        match self {
+            Self::Entry(e) => e.check_method,
+            _ => None,
+        }
+    }
+
+    /// 未配置时为 normal
+    pub fn priority(&self) -> Priority {
+        match self {
+            Self::Entry(e) => e.priority.unwrap_or_default(),
+            _ => Priority::Normal,
+        }
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            Self::Entry(e) => e.group.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn sha256(&self) -> Option<&str> {
+        match self {
+            Self::Entry(e) => e.sha256.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn signature(&self) -> Option<&SignatureCheck> {
+        match self {
+            Self::Entry(e) => e.signature.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// 未配置时为 0，直接覆盖
+    pub fn keep_versions(&self) -> usize {
+        match self {
+            Self::Entry(e) => e.keep_versions.unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    pub fn max_age_secs(&self) -> Option<u64> {
+        match self {
+            Self::Entry(e) => e.max_age_secs,
+            _ => None,
+        }
+    }
+
+    pub fn critical(&self) -> bool {
+        matches!(self, Self::Entry(e) if e.critical)
+    }
+
+    pub fn on_update(&self) -> &[UpdateHook] {
+        match self {
+            Self::Entry(e) => &e.on_update,
+            _ => &[],
+        }
+    }
+
+    pub fn post_process(&self) -> &[PostStep] {
+        match self {
+            Self::Entry(e) => &e.post_process,
+            _ => &[],
+        }
+    }
+
+    /// 解包步骤写入的目录
+    pub fn unpack_dirs(&self) -> impl Iterator<Item = &str> {
+        self.post_process().iter().filter_map(|step| match step {
+            PostStep::Unpack { into, .. } => Some(into.trim_matches('/')),
+            _ => None,
+        })
+    }
+
+    /// 未配置时为 None，使用全局代理设置；Some(None) 为直连
+    pub fn proxy(&self) -> Option<Option<&str>> {
+        match self {
+            Self::Entry(e) if e.no_proxy => Some(None),
+            Self::Entry(e) => e.proxy.as_deref().map(Some),
+            _ => None,
+        }
+    }
+
+    /// 未配置时为 None
+    pub fn http(&self) -> Option<&HttpOverrides> {
+        match self {
+            Self::Entry(e) if !e.http.is_empty() => Some(&e.http),
+            _ => None,
+        }
+    }
+
+    /// 未配置时为 None
+    pub fn auth(&self) -> Option<&UpstreamAuth> {
+        match self {
+            Self::Entry(e) if !e.auth.is_empty() => Some(&e.auth),
+            _ => None,
+        }
+    }
+
+    /// 去掉请求头与认证设置，返回是否有被去掉的内容
+    pub fn strip_auth(&mut self) -> bool {
+        match self {
+            Self::Entry(e) if !e.auth.is_empty() => {
+                e.auth = UpstreamAuth::default();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 去掉代理设置，返回是否有被去掉的内容
+    pub fn strip_proxy(&mut self) -> bool {
+        match self {
+            Self::Entry(e) if e.proxy.is_some() || e.no_proxy => {
+                e.proxy = None;
+                e.no_proxy = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 去掉 on_update 钩子与后处理步骤（可执行命令、写入其他路径），返回是否有被去掉的内容
+    pub fn strip_hooks(&mut self) -> bool {
+        match self {
+            Self::Entry(e) if !e.on_update.is_empty() || !e.post_process.is_empty() => {
+                e.on_update.clear();
+                e.post_process.clear();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<String> for FileSource {
+    fn from(url: String) -> Self {
+        Self::Url(url)
+    }
+}
+
+/// 远端目录的列举方式
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingKind {
+    /// HTTP 目录索引页（nginx autoindex / Apache 等），按页面中的链接枚举
+    #[default]
+    HttpIndex,
+    /// S3 兼容存储的 ListObjectsV2（匿名访问）
+    S3,
+    /// JSON 清单：字符串数组，或 `{ "path": ..., "url": ... }` 对象数组
+    Manifest,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DirSource {
+    /// 索引页 / bucket 地址 / 清单地址
+    pub url: String,
+    #[serde(default)]
+    pub kind: ListingKind,
+    /// S3：只列举该前缀下的对象
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// 相对路径需匹配其中之一（为空表示全部）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// 匹配任意一个即排除
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// HTTP 索引：递归进入子目录的最大深度
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+}
+
+fn default_max_depth() -> usize {
+    8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(toml: &str) -> Result<(), String> {
+        FilesConfig::parse(toml).unwrap().validate()
+    }
+
+    #[test]
+    fn unpack_targets_must_not_overlap() {
+        let ok = r#"
+[files."dist/app.tar.gz"]
+urls = ["https://example.com/app.tar.gz"]
+post_process = [{ step = "unpack", into = "site/app" }]
+
+[files."site/index.html"]
+urls = ["https://example.com/index.html"]
+"#;
+        validate(ok).unwrap();
+
+        let rejected = [
+            // 解包目录是条目自身的上级目录
+            r#"
+[files."dist/app.tar.gz"]
+urls = ["https://example.com/app.tar.gz"]
+post_process = [{ step = "unpack", into = "dist" }]
+"#,
+            // 解包目录在条目下
+            r#"
+[files."app.tar.gz"]
+urls = ["https://example.com/app.tar.gz"]
+post_process = [{ step = "unpack", into = "mirror/app" }]
+
+[dirs."mirror"]
+url = "https://example.com/mirror/"
+"#,
+            // 解包目录包含目录镜像
+            r#"
+[files."app.tar.gz"]
+urls = ["https://example.com/app.tar.gz"]
+post_process = [{ step = "unpack", into = "site" }]
+
+[dirs."site/mirror"]
+url = "https://example.com/mirror/"
+"#,
+            // 解包目录互相包含
+            r#"
+[files."a.tar.gz"]
+urls = ["https://example.com/a.tar.gz"]
+post_process = [{ step = "unpack", into = "site" }]
+
+[files."b.tar.gz"]
+urls = ["https://example.com/b.tar.gz"]
+post_process = [{ step = "unpack", into = "site/b" }]
+"#,
+            // 两个条目解包到同一目录
+            r#"
+[files."a.tar.gz"]
+urls = ["https://example.com/a.tar.gz"]
+post_process = [{ step = "unpack", into = "site" }]
+
+[files."b.tar.gz"]
+urls = ["https://example.com/b.tar.gz"]
+post_process = [{ step = "unpack", into = "site/" }]
+"#,
+            // 解包目录与条目同名
+            r#"
+[files."a.tar.gz"]
+urls = ["https://example.com/a.tar.gz"]
+post_process = [{ step = "unpack", into = "b.bin" }]
+
+[files."b.bin"]
+urls = ["https://example.com/b.bin"]
+"#,
+        ];
+        for toml in rejected {
+            assert!(validate(toml).unwrap_err().contains("unpack"), "{}", toml);
+        }
+    }
+
+    #[test]
+    fn conflicting_options_are_rejected() {
+        let rejected = [
+            (r#""a.iso" = { urls = ["https://example.com/a.iso"], parallel_chunks = 4, decompress = "gzip" }"#, "parallel_chunks"),
+            (r#""a.iso" = { urls = ["https://example.com/a.iso"], parallel_chunks = 4, range = "0-1023" }"#, "parallel_chunks"),
+            (
+                r#""a.tar" = { urls = ["https://example.com/a.tar"], zsync = "https://example.com/a.tar.zsync", post_process = [{ step = "unpack", into = "a" }] }"#,
+                "post_process",
+            ),
+            (
+                r#""a.tar" = { urls = ["https://example.com/a.tar"], range = "0-1023", post_process = [{ step = "compress", format = "gzip" }] }"#,
+                "post_process",
+            ),
+            (
+                r#""a.gz" = { urls = ["https://example.com/a.gz"], decompress = "gzip", signature = { url = "https://example.com/a.gz.sig", keyring = "keys.gpg" } }"#,
+                "signature",
+            ),
+            (
+                r#""a.iso" = { urls = ["https://example.com/a.iso"], range = "0-1023", signature = { url = "https://example.com/a.iso.sig", keyring = "keys.gpg" } }"#,
+                "signature",
+            ),
+            (r#""a.gz" = { urls = ["https://example.com/a.gz"], decompress = "gzip", zsync = "https://example.com/a.gz.zsync" }"#, "zsync"),
+        ];
+        for (entry, option) in rejected {
+            let err = validate(&format!("[files]\n{}\n", entry)).unwrap_err();
+            assert!(err.contains(option), "{}: {}", entry, err);
+        }
+        validate(r#"[files]
+"a.iso" = { urls = ["https://example.com/a.iso"], parallel_chunks = 4 }
+"#).unwrap();
+    }
 }
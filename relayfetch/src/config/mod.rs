@@ -1,7 +1,16 @@
+#[allow(clippy::module_inception)]
 pub mod config;
 
 pub mod file;
 
+pub mod persist;
+
+pub mod revisions;
+
+pub mod overrides;
+
+pub mod watch;
+
 use std::{path::PathBuf};
 
 
@@ -9,16 +18,19 @@ use std::{path::PathBuf};
 pub struct RuntimeContext {
     pub config_path: PathBuf,
     pub files_path: PathBuf,
+    /// 环境变量与命令行的覆盖项，按应用顺序排列
+    pub overrides: Vec<overrides::Override>,
 }
 
-use std::{collections::HashMap, time::SystemTime};
+use std::{collections::{BTreeMap, HashMap, VecDeque}, time::{Duration, Instant, SystemTime}};
 
 use anyhow::Ok;
 
-use std::{sync::Arc};
+use std::sync::{Arc, atomic::{AtomicU64, AtomicUsize, Ordering}};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
-use crate::{config::{config::Config, file::FilesConfig}, sync::{FileProgress, SyncResult, SyncStatus}};
+use crate::{access::AccessLog, bandwidth::{BandwidthLedger, STATE_DIR}, health::Health, notify::{Notification, Notifier}, quota::StorageQuota, replicate::Replicator, shaping::Shaper, storage_index::StorageIndex, supervise::Restarts, config::{config::{Config, FailureBackoffConfig, NotifyEvent}, file::{FileSource, FilesConfig, UpdateHook}}, sync::{FileBackoff, FileProgress, SyncEvent, SyncResult, SyncRun, SyncStatus}};
 
 use std::{fs};
 
@@ -30,8 +42,46 @@ pub struct ConfigCenter {
     config: Arc<RwLock<Config>>,
     files: Arc<RwLock<FilesConfig>>,
     sync_state: Arc<RwLock<SyncStatus>>,
+    events: tokio::sync::broadcast::Sender<SyncEvent>,
+    bandwidth: Arc<BandwidthLedger>,
+    shaper: Arc<Shaper>,
+    storage_index: Arc<StorageIndex>,
+    restarts: Arc<Restarts>,
+    health: Arc<Health>,
+    quota: Arc<StorageQuota>,
+    access: Arc<AccessLog>,
+    notifier: Arc<Notifier>,
+    replicator: Arc<Replicator>,
+    /// 进行中的同步共用，取消后下一次同步换新的
+    sync_cancel: Arc<std::sync::Mutex<CancellationToken>>,
+    /// 进行中的分组同步数；分组同步不标记 sync_state 的 running
+    group_runs: Arc<AtomicUsize>,
+    /// 收到退出信号后取消，不再开始新的同步
+    shutdown: CancellationToken,
+    scheduler_resumed: Arc<tokio::sync::Notify>,
+    /// 周期同步、手动触发的同步与单文件同步依次执行
+    sync_lock: Arc<tokio::sync::Mutex<()>>,
+    sync_state_path: Arc<PathBuf>,
+    sync_state_saved: Arc<std::sync::Mutex<Instant>>,
+    /// 进行中的同步经网络接收与无需下载的字节数
+    sync_bytes: Arc<(AtomicU64, AtomicU64)>,
+}
+
+/// 分组同步期间持有，结束时从进行中的分组同步数中减去
+pub struct GroupRun(Arc<AtomicUsize>);
+
+impl Drop for GroupRun {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
+/// 同步事件广播缓冲，订阅者落后过多时会丢弃旧事件
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 同步进行中状态落盘的最小间隔
+const SYNC_STATE_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
 impl ConfigCenter {
     /// 启动时初始化，失败直接 panic（daemon 级行为）
     pub fn new(runtime: RuntimeContext) -> Self {
@@ -51,13 +101,14 @@ impl ConfigCenter {
                 )
             });
 
-        let mut cfg: Config = toml::from_str(&cfg_str)
-            .unwrap_or_else(|e| panic!("config.toml parse error: {e}"));
+        let mut cfg = overrides::load_config(&cfg_str, &runtime.overrides)
+            .unwrap_or_else(|e| panic!("config.toml parse error: {e:#}"));
 
         cfg.finalize();
 
-        let files_cfg: FilesConfig = toml::from_str(&files_str)
+        let files_cfg = FilesConfig::parse(&files_str)
             .unwrap_or_else(|e| panic!("files.toml parse error: {e}"));
+        validate_files(&files_cfg).unwrap_or_else(|e| panic!("files.toml invalid: {e}"));
 
         fs::create_dir_all(&cfg.storage_dir)
             .unwrap_or_else(|e| {
@@ -67,21 +118,35 @@ impl ConfigCenter {
                 )
             });
 
+        let bandwidth = Arc::new(BandwidthLedger::load(&cfg.storage_dir));
+        let access = Arc::new(AccessLog::load(&cfg.storage_dir));
+        let sync_state_path = cfg.storage_dir.join(STATE_DIR).join("sync_state.toml");
+        let sync_state = load_sync_state(&sync_state_path);
+        record_revision(&cfg, "startup", None, cfg_str, files_str);
+
         Self {
             runtime: Arc::new(runtime),
             config: Arc::new(RwLock::new(cfg)),
             files: Arc::new(RwLock::new(files_cfg)),
-            sync_state: Arc::new(RwLock::new(SyncStatus {
-                running: false,
-                start_time: None,
-                last_sync: None,
-                last_ok_sync: None,
-                last_result: SyncResult::Pending,
-                total_files: 0,
-                finished_files: 0,
-                failed_files: 0,
-                files: HashMap::new(),
-            })),
+            sync_state: Arc::new(RwLock::new(sync_state)),
+            events: tokio::sync::broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            bandwidth,
+            shaper: Arc::new(Shaper::default()),
+            storage_index: Arc::new(StorageIndex::default()),
+            restarts: Arc::new(Restarts::default()),
+            health: Arc::new(Health::default()),
+            quota: Arc::new(StorageQuota::default()),
+            access,
+            notifier: Arc::new(Notifier::default()),
+            replicator: Arc::new(Replicator::default()),
+            sync_cancel: Arc::new(std::sync::Mutex::new(CancellationToken::new())),
+            group_runs: Arc::default(),
+            shutdown: CancellationToken::new(),
+            scheduler_resumed: Arc::new(tokio::sync::Notify::new()),
+            sync_lock: Arc::new(tokio::sync::Mutex::new(())),
+            sync_state_path: Arc::new(sync_state_path),
+            sync_state_saved: Arc::new(std::sync::Mutex::new(Instant::now())),
+            sync_bytes: Arc::default(),
         }
     }
 
@@ -90,22 +155,38 @@ impl ConfigCenter {
         let cfg_str = fs::read_to_string(&self.runtime.config_path)?;
 
         let files_str = fs::read_to_string(&self.runtime.files_path)?;
-        let mut new_cfg: Config = toml::from_str(&cfg_str)?;
+        let (new_cfg, new_files) = parse_configs(&cfg_str, &files_str, &self.runtime.overrides)?;
 
-        new_cfg.finalize();
+        record_revision(&new_cfg, "reload", None, cfg_str, files_str);
+        *self.config.write().await = new_cfg;
+        *self.files.write().await = new_files;
+        Ok(())
+    }
 
-        let new_files: FilesConfig = toml::from_str(&files_str)?;
+    /// 把 config.toml 与 files.toml 恢复为某个修订的内容并生效，返回回滚后记录的修订
+    pub async fn rollback_config(&self, revision: &revisions::Revision, comment: Option<&str>) -> anyhow::Result<Option<u64>> {
+        // 同步进行中禁止改配置
+        if self.sync_state.read().await.running {
+            anyhow::bail!("cannot modify config while syncing");
+        }
 
-        fs::create_dir_all(&new_cfg.storage_dir)?;
+        let (new_cfg, new_files) = parse_configs(&revision.config, &revision.files, &self.runtime.overrides)?;
+        persist::write_atomic(&self.runtime.config_path, &revision.config).await?;
+        persist::write_atomic(&self.runtime.files_path, &revision.files).await?;
 
+        let comment = comment
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("rollback to revision {}", revision.id));
+        let id = record_revision(&new_cfg, "rollback", Some(&comment), revision.config.clone(), revision.files.clone());
         *self.config.write().await = new_cfg;
         *self.files.write().await = new_files;
-        Ok(())
+        Ok(id)
     }
 
     // ========= 核心：运行时修改并持久化 =========
 
-    pub async fn update_config<F>(&self, f: F) -> anyhow::Result<()>
+    /// `comment` 随修订记录
+    pub async fn update_config<F>(&self, comment: Option<&str>, f: F) -> anyhow::Result<()>
     where
         F: FnOnce(&mut Config) -> anyhow::Result<()>,
     {
@@ -115,30 +196,27 @@ impl ConfigCenter {
         }
 
         let mut cfg = self.config.write().await;
-
-        f(&mut cfg)?;       // 修改
-        cfg.finalize();     // 派生字段
-
-        self.persist_config(&cfg).await?;
-
-        Ok(())
-    }
-
-    async fn persist_config(&self, cfg: &Config) -> anyhow::Result<()> {
-        let toml = toml::to_string_pretty(cfg)?;
-
-        let path = &self.runtime.config_path;
-        let tmp = path.with_extension("toml.tmp");
-
-        tokio::fs::write(&tmp, toml).await?;
-        tokio::fs::rename(&tmp, path).await?;
+        let mut next = cfg.clone();
+
+        f(&mut next)?;       // 修改
+        next.finalize();     // 派生字段
+
+        // 写回成功后才生效
+        let overrides = &self.runtime.overrides;
+        persist(&self.runtime.config_path, &*cfg, &next, |s| {
+            let mut cfg = overrides::load_config(s, overrides)?;
+            cfg.finalize();
+            Ok(cfg)
+        })
+        .await?;
+        self.record_current(&next, "update_config", comment);
+        *cfg = next;
 
         Ok(())
     }
 
-    /// 更新 files.toml 内容（给 gRPC 用）
-
-    pub async fn update_files<F>(&self, f: F) -> anyhow::Result<()>
+    /// 更新 files.toml 内容（给 gRPC 用）；修改后的配置通过校验才生效，`comment` 随修订记录
+    pub async fn update_files<F>(&self, comment: Option<&str>, f: F) -> anyhow::Result<()>
     where
         F: FnOnce(&mut FilesConfig) -> anyhow::Result<()>,
     {
@@ -146,25 +224,31 @@ impl ConfigCenter {
         if self.sync_state.read().await.running {
             anyhow::bail!("cannot modify config while syncing");
         }
-        
+        // 在持有 files 写锁之前读取，避免与回滚的加锁顺序相反
+        let cfg = self.config.read().await.clone();
+
         let mut files = self.files.write().await;
-        f(&mut files)?;
-        self.persist_files(&files).await?;
+        let mut next = files.clone();
+        f(&mut next)?;
+        validate_files(&next).map_err(anyhow::Error::msg)?;
+        persist(&self.runtime.files_path, &*files, &next, |s| Ok(FilesConfig::parse(s)?)).await?;
+        self.record_current(&cfg, "update_files", comment);
+        *files = next;
         Ok(())
     }
 
-    async fn persist_files(&self, files: &FilesConfig) -> anyhow::Result<()> {
-        let toml = toml::to_string_pretty(files)?;
-        let path = &self.runtime.files_path;
-        let tmp = path.with_extension("toml.tmp");
-
-        tokio::fs::write(&tmp, toml).await?;
-        tokio::fs::rename(&tmp, path).await?;
-        Ok(())
+    /// 以两个配置文件当前的内容记录修订
+    fn record_current(&self, cfg: &Config, source: &str, comment: Option<&str>) {
+        let read = |p: &PathBuf| fs::read_to_string(p).unwrap_or_default();
+        record_revision(cfg, source, comment, read(&self.runtime.config_path), read(&self.runtime.files_path));
     }
 
     // ====== 读接口（给 sync / status 用） ======
 
+    pub fn runtime(&self) -> &RuntimeContext {
+        &self.runtime
+    }
+
     pub async fn config(&self) -> tokio::sync::RwLockReadGuard<'_, Config> {
         self.config.read().await
     }
@@ -177,6 +261,178 @@ impl ConfigCenter {
         self.sync_state.read().await
     }
 
+    /// 上游流量统计
+    pub fn bandwidth(&self) -> &BandwidthLedger {
+        &self.bandwidth
+    }
+
+    /// 时段限速
+    pub fn shaper(&self) -> &Shaper {
+        &self.shaper
+    }
+
+    pub fn storage_index(&self) -> &StorageIndex {
+        &self.storage_index
+    }
+
+    /// 后台任务 panic 次数
+    pub fn restarts(&self) -> &Restarts {
+        &self.restarts
+    }
+
+    /// 各子系统健康状态
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// 存储空间配额
+    pub fn quota(&self) -> &StorageQuota {
+        &self.quota
+    }
+
+    /// 下载服务的文件访问记录
+    pub fn access(&self) -> &AccessLog {
+        &self.access
+    }
+
+    /// 按 `[notify]` 执行与事件匹配的命令，内容更新时执行 on_update 钩子，下载完成时再按 `[replicate]` 推送，
+    /// 都不等待结束
+    pub async fn notify(&self, n: Notification) {
+        let file_hooks = if n.updated() {
+            self.files.read().await.files.get(&n.file).map(|s| s.on_update().to_vec()).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let cfg = self.config.read().await;
+        if let Some(notify) = &cfg.notify {
+            self.notifier.emit(notify, &n);
+        }
+        if n.updated() {
+            let hooks: Vec<_> = cfg.on_update.iter().cloned().chain(file_hooks).collect();
+            let client = if hooks.iter().any(|h| matches!(h, UpdateHook::Http { .. })) {
+                crate::sync::build_client(&cfg)
+                    .inspect_err(|e| log::warn!("[notify] cannot build HTTP client: {:#}", e))
+                    .ok()
+            } else {
+                None
+            };
+            let limits = cfg.notify.clone().unwrap_or_default();
+            self.notifier.on_update(&limits, &hooks, client.as_ref(), &n);
+        }
+        if n.event == NotifyEvent::Downloaded
+            && let Some(replicate) = &cfg.replicate
+        {
+            self.replicator.submit(&cfg, replicate, &n);
+        }
+    }
+
+    /// 同步使用的取消令牌；上一次同步已被取消时换新的（退出过程中不换）
+    pub fn sync_token(&self) -> CancellationToken {
+        let mut token = self.sync_cancel.lock().unwrap();
+        if token.is_cancelled() && !self.shutdown.is_cancelled() {
+            *token = CancellationToken::new();
+        }
+        token.clone()
+    }
+
+    /// 标记一次分组同步开始，使 cancel_sync 能取消它；令牌需在此之前取得
+    pub fn group_run(&self) -> GroupRun {
+        self.group_runs.fetch_add(1, Ordering::SeqCst);
+        GroupRun(self.group_runs.clone())
+    }
+
+    /// 取消进行中的同步（整轮或分组）；没有同步在进行时返回 false
+    pub async fn cancel_sync(&self) -> bool {
+        let running = self.sync_state.read().await.running || self.group_runs.load(Ordering::SeqCst) > 0;
+        if running {
+            self.sync_cancel.lock().unwrap().cancel();
+        }
+        running
+    }
+
+    /// 开始退出：取消进行中的同步（已写入的部分保留，下次启动续传），之后不再开始新的同步
+    pub fn begin_shutdown(&self) {
+        self.shutdown.cancel();
+        self.sync_cancel.lock().unwrap().cancel();
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// 整轮同步前持有，避免两轮同步同时进行
+    pub async fn lock_sync(&self) -> tokio::sync::OwnedMutexGuard<()> {
+        self.sync_lock.clone().lock_owned().await
+    }
+
+    /// 不等待：已有同步在进行（或排队中的任务已拿到锁）时返回 None
+    pub fn try_lock_sync(&self) -> Option<tokio::sync::OwnedMutexGuard<()>> {
+        self.sync_lock.clone().try_lock_owned().ok()
+    }
+
+    /// 暂停 / 恢复周期同步并持久化；返回之前是否已暂停
+    pub async fn set_scheduler_paused(&self, paused: bool) -> bool {
+        let mut s = self.sync_state.write().await;
+        let before = std::mem::replace(&mut s.scheduler_paused, paused);
+        self.save_sync_state(&s, true);
+        if !paused {
+            self.scheduler_resumed.notify_waiters();
+        }
+        before
+    }
+
+    /// 周期同步暂停期间等待恢复
+    pub async fn wait_scheduler_resumed(&self) {
+        loop {
+            let resumed = self.scheduler_resumed.notified();
+            if !self.sync_state.read().await.scheduler_paused {
+                return;
+            }
+            resumed.await;
+        }
+    }
+
+    /// 订阅同步事件
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<SyncEvent> {
+        self.events.subscribe()
+    }
+
+    /// 无订阅者时发送失败，忽略即可
+    fn publish(&self, event: SyncEvent) {
+        let _ = self.events.send(event);
+    }
+
+    // ====== 同步状态持久化 ======
+
+    /// 立即写入 sync_state.toml（退出前调用）
+    pub async fn flush_sync_state(&self) {
+        let s = self.sync_state.read().await;
+        self.save_sync_state(&s, true);
+    }
+
+    /// 写入 sync_state.toml；force 为 false 时按间隔节流
+    fn save_sync_state(&self, s: &SyncStatus, force: bool) {
+        {
+            let mut saved = self.sync_state_saved.lock().unwrap();
+            if !force && saved.elapsed() < SYNC_STATE_SAVE_INTERVAL {
+                return;
+            }
+            *saved = Instant::now();
+        }
+
+        let result = (|| -> anyhow::Result<()> {
+            let path = self.sync_state_path.as_path();
+            crate::sync::meta::ensure_parent_dir(path)?;
+            let tmp = path.with_extension("toml.tmp");
+            fs::write(&tmp, toml::to_string(s)?)?;
+            fs::rename(&tmp, path)?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            log::warn!("failed to persist sync state: {e:?}");
+        }
+    }
+
     // ====== 写接口（给 sync 用） ======
 
     pub async fn sync_started(&self, total_files: usize) {
@@ -188,23 +444,55 @@ impl ConfigCenter {
         s.failed_files = 0;
         s.files.clear();
         s.last_result = SyncResult::Pending;
+        self.sync_bytes.0.store(0, Ordering::Relaxed);
+        self.sync_bytes.1.store(0, Ordering::Relaxed);
+        self.save_sync_state(&s, true);
+        self.publish(SyncEvent::SyncStarted { total_files });
     }
 
-    pub async fn sync_finished(&self) {
+    /// 累计本次同步的流量
+    pub fn sync_transferred(&self, received: u64, saved: u64) {
+        self.sync_bytes.0.fetch_add(received, Ordering::Relaxed);
+        self.sync_bytes.1.fetch_add(saved, Ordering::Relaxed);
+    }
+
+    pub async fn sync_finished(&self, cancelled: bool) {
+        let keep = self.config.read().await.sync_history;
         let mut s = self.sync_state.write().await;
         s.running = false;
         let now = SystemTime::now();
         s.last_sync = Some(now);
 
         // 判定逻辑
-        if s.failed_files == 0 && s.finished_files == s.total_files {
+        if cancelled {
+            s.last_result = SyncResult::Failed("sync cancelled".into());
+        } else if s.failed_files == 0 && s.finished_files == s.total_files {
             s.last_result = SyncResult::Success;
             s.last_ok_sync = Some(now);
+            s.consecutive_failures = 0;
         } else if s.failed_files > 0 && s.finished_files > 0 {
             s.last_result = SyncResult::PartialSuccess;
+            s.consecutive_failures = 0;
         } else {
             s.last_result = SyncResult::Failed("Some files missing or process interrupted".into());
+            s.consecutive_failures += 1;
+        }
+
+        let run = SyncRun {
+            started: s.start_time.unwrap_or(now),
+            finished: now,
+            result: s.last_result.clone(),
+            total_files: s.total_files,
+            failed_files: s.failed_files,
+            bytes_transferred: self.sync_bytes.0.load(Ordering::Relaxed),
+            bytes_saved: self.sync_bytes.1.load(Ordering::Relaxed),
+        };
+        s.history.push_back(run);
+        while s.history.len() > keep {
+            s.history.pop_front();
         }
+        self.save_sync_state(&s, true);
+        self.publish(SyncEvent::SyncFinished { result: s.last_result.clone() });
     }
 
     pub async fn file_started(
@@ -214,12 +502,15 @@ impl ConfigCenter {
     ) {
         let mut s = self.sync_state.write().await;
         s.files.insert(file.clone(), FileProgress {
-            file,
+            file: file.clone(),
             downloaded: 0,
             total,
             done: false,
             error: None,
+            cancelled: false,
         });
+        self.save_sync_state(&s, false);
+        self.publish(SyncEvent::FileStarted { file, total });
     }
 
     pub async fn file_progress(
@@ -230,6 +521,9 @@ impl ConfigCenter {
         let mut s = self.sync_state.write().await;
         if let Some(fp) = s.files.get_mut(file) {
             fp.downloaded = downloaded;
+            let total = fp.total;
+            self.save_sync_state(&s, false);
+            self.publish(SyncEvent::FileProgress { file: file.to_string(), downloaded, total });
         }
     }
 
@@ -238,23 +532,237 @@ impl ConfigCenter {
         file: &str,
     ) {
         let mut s = self.sync_state.write().await;
-        if let Some(fp) = s.files.get_mut(file) {
-            fp.done = true;
-        }
+        // 完成的文件不再保留在内存中，明细由 meta 提供
+        s.files.remove(file);
         s.finished_files += 1;
+        self.save_sync_state(&s, false);
+        self.publish(SyncEvent::FileFinished { file: file.to_string() });
     }
 
     pub async fn file_error(&self, file: String, error: String) {
         let mut s = self.sync_state.write().await;
         s.files.insert(file.clone(), FileProgress {
-            file,
+            file: file.clone(),
             downloaded: 0,
             total: None,
             done: true,
-            error: Some(error),
+            error: Some(error.clone()),
+            cancelled: false,
         });
         s.failed_files += 1; // 增加失败计数
         s.finished_files += 1;
+        self.save_sync_state(&s, false);
+        self.publish(SyncEvent::FileError { file, error });
+    }
+
+    /// 被取消的文件：tmp 保留，下次同步续传
+    pub async fn file_cancelled(&self, file: String) {
+        let mut s = self.sync_state.write().await;
+        let downloaded = s.files.get(&file).map_or(0, |f| f.downloaded);
+        let total = s.files.get(&file).and_then(|f| f.total);
+        s.files.insert(file.clone(), FileProgress {
+            file: file.clone(),
+            downloaded,
+            total,
+            done: false,
+            error: Some("cancelled".into()),
+            cancelled: true,
+        });
+        self.save_sync_state(&s, false);
+        self.publish(SyncEvent::FileError { file, error: "cancelled".into() });
+    }
+
+    /// 单独重新同步某个文件后更新其记录：成功时清除上次的失败，失败时记为失败
+    pub async fn file_resynced(&self, file: &str, error: Option<String>) {
+        let mut s = self.sync_state.write().await;
+        let failed_before = s.files.get(file).is_some_and(|f| f.error.is_some());
+        match error {
+            None => {
+                s.backoff.remove(file);
+                if failed_before {
+                    s.files.remove(file);
+                    s.failed_files = s.failed_files.saturating_sub(1);
+                }
+                self.save_sync_state(&s, true);
+                self.publish(SyncEvent::FileFinished { file: file.to_string() });
+            }
+            Some(error) => {
+                if !failed_before {
+                    s.failed_files += 1;
+                }
+                s.files.insert(file.to_string(), FileProgress {
+                    file: file.to_string(),
+                    downloaded: 0,
+                    total: None,
+                    done: true,
+                    error: Some(error.clone()),
+                    cancelled: false,
+                });
+                self.save_sync_state(&s, true);
+                self.publish(SyncEvent::FileError { file: file.to_string(), error });
+            }
+        }
+    }
+
+
+    /// 记录分组的同步完成时间
+    pub async fn groups_synced(&self, groups: impl IntoIterator<Item = String>) {
+        let mut s = self.sync_state.write().await;
+        let now = SystemTime::now();
+        for group in groups {
+            s.group_syncs.insert(group, now);
+        }
+        self.save_sync_state(&s, true);
     }
 
+    // ====== 失败退避 ======
+
+    /// 开始一轮同步：清掉已不是条目的文件（`listed` 为 false）的记录，
+    /// 返回本轮同步的文件（`cycled`）中要跳过的（剩余轮数已扣减）
+    pub async fn backoff_cycle(&self, listed: impl Fn(&str) -> bool, cycled: impl Fn(&str) -> bool) -> Vec<(String, FileBackoff)> {
+        let mut s = self.sync_state.write().await;
+        let before = s.backoff.len();
+        s.backoff.retain(|file, _| listed(file));
+        let mut skipped = Vec::new();
+        for (file, b) in s.backoff.iter_mut() {
+            if b.skip_cycles > 0 && cycled(file) {
+                b.skip_cycles -= 1;
+                skipped.push((file.clone(), b.clone()));
+            }
+        }
+        if before != s.backoff.len() || !skipped.is_empty() {
+            self.save_sync_state(&s, true);
+        }
+        skipped
+    }
+
+    pub async fn backoff_failed(&self, file: &str, error: &str, policy: &FailureBackoffConfig) {
+        let mut s = self.sync_state.write().await;
+        let b = s.backoff.entry(file.to_string()).or_default();
+        b.failures += 1;
+        b.skip_cycles = policy.skip_cycles(b.failures);
+        b.last_error = error.to_string();
+        if b.skip_cycles > 0 {
+            log::warn!(
+                "File {} failed {} cycles in a row, skipping the next {} cycles",
+                file, b.failures, b.skip_cycles
+            );
+        }
+        self.save_sync_state(&s, false);
+    }
+
+    pub async fn backoff_succeeded(&self, file: &str) {
+        let mut s = self.sync_state.write().await;
+        if s.backoff.remove(file).is_some() {
+            self.save_sync_state(&s, false);
+        }
+    }
+
+    /// 清除退避记录（`file` 为 None 时清除全部），返回被清除的文件
+    pub async fn reset_backoff(&self, file: Option<&str>) -> Vec<String> {
+        let mut s = self.sync_state.write().await;
+        let removed: Vec<String> = match file {
+            Some(file) => s.backoff.remove_entry(file).map(|(f, _)| f).into_iter().collect(),
+            None => std::mem::take(&mut s.backoff).into_keys().collect(),
+        };
+        if !removed.is_empty() {
+            self.save_sync_state(&s, true);
+        }
+        removed
+    }
+
+}
+
+/// 解析并校验两个配置文件的内容（应用覆盖项），确保存储目录存在
+fn parse_configs(
+    cfg_str: &str,
+    files_str: &str,
+    overrides: &[overrides::Override],
+) -> anyhow::Result<(Config, FilesConfig)> {
+    let mut cfg = overrides::load_config(cfg_str, overrides)?;
+    cfg.finalize();
+
+    let files = FilesConfig::parse(files_str)?;
+    validate_files(&files).map_err(|e| anyhow::anyhow!("files.toml invalid: {e}"))?;
+
+    fs::create_dir_all(&cfg.storage_dir)?;
+    Ok((cfg, files))
+}
+
+/// [`FilesConfig::validate`] 之外再检查条目的代理地址；file.rs 也被 fuzz 目标引用，不依赖 reqwest
+fn validate_files(files: &FilesConfig) -> Result<(), String> {
+    files.validate()?;
+    let invalid = files.files.iter().find_map(|(file, source)| match source {
+        FileSource::Entry(e) => e
+            .proxy
+            .as_ref()
+            .and_then(|proxy| reqwest::Proxy::all(proxy.as_str()).err())
+            .map(|err| format!("[files] {:?}: invalid proxy: {}", file, err)),
+        _ => None,
+    });
+    invalid.map_or(Result::Ok(()), Err)
+}
+
+/// 记录配置修订；失败只记日志，不影响配置生效
+fn record_revision(cfg: &Config, source: &str, comment: Option<&str>, config: String, files: String) -> Option<u64> {
+    match revisions::record(&cfg.storage_dir, cfg.config_revisions, source, comment, config, files) {
+        Result::Ok(Some(id)) => {
+            log::info!("config revision {} recorded ({})", id, source);
+            Some(id)
+        }
+        Result::Ok(None) => None,
+        Err(e) => {
+            log::warn!("failed to record config revision: {e:#}");
+            None
+        }
+    }
+}
+
+/// 把修改写回配置文件，尽量保留原文件的注释与格式
+async fn persist<T: serde::Serialize>(
+    path: &std::path::Path,
+    old: &T,
+    new: &T,
+    load: impl Fn(&str) -> anyhow::Result<T>,
+) -> anyhow::Result<()> {
+    let original = tokio::fs::read_to_string(path).await.unwrap_or_default();
+    let toml = persist::render(&original, old, new, load)?;
+    persist::write_atomic(path, &toml).await
+}
+
+/// 读取上次保存的同步状态；进行中被中断的同步标记为失败
+fn load_sync_state(path: &std::path::Path) -> SyncStatus {
+    let saved = fs::read_to_string(path)
+        .ok()
+        .and_then(|s| {
+            toml::from_str::<SyncStatus>(&s)
+                .inspect_err(|e| log::warn!("failed to parse {}: {e}", path.display()))
+                .ok()
+        });
+
+    match saved {
+        Some(mut state) => {
+            if state.running {
+                state.running = false;
+                state.last_result = SyncResult::Failed("sync interrupted by restart".into());
+            }
+            state
+        }
+        None => SyncStatus {
+            running: false,
+            start_time: None,
+            last_sync: None,
+            last_ok_sync: None,
+            last_result: SyncResult::Pending,
+            total_files: 0,
+            finished_files: 0,
+            failed_files: 0,
+            files: HashMap::new(),
+            scheduler_paused: false,
+            backoff: BTreeMap::new(),
+            consecutive_failures: 0,
+            history: VecDeque::new(),
+            group_syncs: BTreeMap::new(),
+        },
+    }
 }
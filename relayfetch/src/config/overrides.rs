@@ -0,0 +1,96 @@
+//! 配置分层：默认值 < config.toml < `RELAYFETCH_*` 环境变量 < 命令行
+//!
+//! 覆盖项以键路径指定 config.toml 中的任意字段：
+//! - 环境变量：`RELAYFETCH_DOWNLOAD_CONCURRENCY=8`，嵌套表用 `__` 分隔，如 `RELAYFETCH_RETENTION__MAX_VERSIONS=3`
+//! - 命令行：`--set retention.max_versions=3`（可重复），以及常用字段的专用参数（`--bind`、`--storage-dir` 等）
+//!
+//! 值按 TOML 解析（`8`、`true`、`["a", "b"]`），原值是字符串或无法解析时按字符串处理；空值表示删除该键，
+//! 恢复默认值。启动、重载与回滚时都会重新应用；管理接口修改配置时只写回变化的键，覆盖项不会写入
+//! config.toml（无法逐行修改而整体重写时除外）。
+
+use toml::{Table, Value};
+
+use super::config::Config;
+
+pub const ENV_PREFIX: &str = "RELAYFETCH_";
+
+/// 一个覆盖项
+#[derive(Debug, Clone)]
+pub struct Override {
+    /// 点分隔的键路径，如 `retention.max_versions`
+    pub key: String,
+    pub value: String,
+    /// 来源（环境变量名或命令行参数），用于错误信息
+    pub source: String,
+}
+
+impl Override {
+    pub fn new(key: &str, value: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            key: key.to_string(),
+            value: value.into(),
+            source: source.into(),
+        }
+    }
+}
+
+/// `RELAYFETCH_*` 环境变量，按变量名排序
+pub fn from_env() -> Vec<Override> {
+    let mut out: Vec<Override> = std::env::vars()
+        .filter_map(|(name, value)| {
+            let key = name.strip_prefix(ENV_PREFIX)?.to_ascii_lowercase().replace("__", ".");
+            (!key.is_empty()).then(|| Override::new(&key, value, name))
+        })
+        .collect();
+    out.sort_by(|a, b| a.source.cmp(&b.source));
+    out
+}
+
+/// 解析命令行 `--set key=value`
+pub fn parse_set(s: &str) -> Result<Override, String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got {:?}", s))?;
+    let key = key.trim();
+    if key.is_empty() || key.split('.').any(str::is_empty) {
+        return Err(format!("invalid config key {:?}", key));
+    }
+    Ok(Override::new(key, value, format!("--set {}", key)))
+}
+
+/// 解析 config.toml 的内容并依次应用覆盖项
+pub fn load_config(s: &str, overrides: &[Override]) -> anyhow::Result<Config> {
+    let mut table: Table = toml::from_str(s)?;
+    for o in overrides {
+        apply(&mut table, o).map_err(|e| anyhow::anyhow!("{}: {}", o.source, e))?;
+    }
+    Ok(Value::Table(table).try_into()?)
+}
+
+fn apply(table: &mut Table, o: &Override) -> Result<(), String> {
+    let mut path: Vec<&str> = o.key.split('.').collect();
+    let last = path.pop().ok_or("empty key")?;
+    let mut current = table;
+    for (i, seg) in path.iter().enumerate() {
+        let entry = current
+            .entry(seg.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+        current = entry
+            .as_table_mut()
+            .ok_or_else(|| format!("`{}` is not a table", path[..=i].join(".")))?;
+    }
+
+    if o.value.is_empty() {
+        current.remove(last);
+        return Ok(());
+    }
+    let value = match current.get(last) {
+        Some(Value::String(_)) => Value::String(o.value.clone()),
+        _ => o
+            .value
+            .parse::<Value>()
+            .unwrap_or_else(|_| Value::String(o.value.clone())),
+    };
+    current.insert(last.to_string(), value);
+    Ok(())
+}
@@ -0,0 +1,249 @@
+//! 管理接口修改配置后写回 config.toml / files.toml
+//!
+//! 只改动修改前后有变化的键：原文件中对应的 `key = value` 行原地替换（保留缩进与行尾注释），
+//! 删除的键去掉该行，新增的键插在所属表的最后一个键之后，原文件中没有的表追加到末尾；
+//! 其余的注释、空行与顺序保持不变。
+//! 无法逐行修改（多行数组、点分键、`[[数组表]]` 中的变化等）或修改结果解析后与新配置不一致时，
+//! 退回整体重写（不保留注释）。写入先落到临时文件并 fsync，再 rename 覆盖。
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::Result;
+use log::info;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use toml::{Table, Value};
+
+/// 生成写回的内容；`load` 按启动时的方式解析文件内容（如应用覆盖项），用于检查修改结果
+pub fn render<T: Serialize>(original: &str, old: &T, new: &T, load: impl Fn(&str) -> Result<T>) -> Result<String> {
+    let (old, new) = (Table::try_from(old)?, Table::try_from(new)?);
+    if let Some(patched) = patch(original, &old, &new)
+        && load(&patched)
+            .ok()
+            .and_then(|t| Table::try_from(&t).ok())
+            .is_some_and(|t| t == new)
+    {
+        return Ok(patched);
+    }
+    if !original.trim().is_empty() {
+        info!("config file could not be patched in place, rewriting it without comments");
+    }
+    Ok(toml::to_string_pretty(&new)?)
+}
+
+/// 先写临时文件并落盘，再 rename 覆盖，避免中途失败留下不完整的配置
+pub async fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let tmp = path.with_extension("toml.tmp");
+    let mut file = tokio::fs::File::create(&tmp).await?;
+    file.write_all(contents.as_bytes()).await?;
+    file.sync_all().await?;
+    drop(file);
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// 原文件中的一张表（根表或 `[a.b]`）
+#[derive(Default)]
+struct Section {
+    /// 本表最后一个单行赋值（或表头）所在行，新增的键插在其后；根表没有赋值时为 None
+    last: Option<usize>,
+    /// 单行赋值
+    lines: HashMap<String, Line>,
+}
+
+struct Line {
+    at: usize,
+    indent: String,
+    /// 行尾注释（含 `#`），没有时为空
+    comment: String,
+}
+
+#[derive(Default)]
+struct Edits {
+    replace: HashMap<usize, String>,
+    delete: HashSet<usize>,
+    /// 插在某行之后；None 表示文件开头
+    insert: HashMap<Option<usize>, Vec<String>>,
+    /// 原文件中没有的表，追加到末尾
+    append: Vec<(Vec<String>, Vec<String>)>,
+}
+
+fn patch(original: &str, old: &Table, new: &Table) -> Option<String> {
+    if original.trim().is_empty() {
+        return None;
+    }
+    let parsed: Table = toml::from_str(original).ok()?;
+    let lines: Vec<&str> = original.lines().collect();
+    let sections = scan(&lines);
+
+    let mut edits = Edits::default();
+    diff(&sections, &mut Vec::new(), Some(&parsed), old, new, &mut edits)?;
+    if edits.replace.is_empty() && edits.delete.is_empty() && edits.insert.is_empty() && edits.append.is_empty() {
+        return Some(original.to_string());
+    }
+
+    let mut out = String::new();
+    let mut push = |line: &str| {
+        out.push_str(line);
+        out.push('\n');
+    };
+    for line in edits.insert.get(&None).into_iter().flatten() {
+        push(line);
+    }
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(line) = edits.replace.get(&i) {
+            push(line);
+        } else if !edits.delete.contains(&i) {
+            push(line);
+        }
+        for line in edits.insert.get(&Some(i)).into_iter().flatten() {
+            push(line);
+        }
+    }
+    for (path, body) in &edits.append {
+        push("");
+        push(&format!("[{}]", path.iter().map(|k| key(k)).collect::<Vec<_>>().join(".")));
+        for line in body {
+            push(line);
+        }
+    }
+    Some(out)
+}
+
+/// 找出每张表的单行赋值；`[[数组表]]` 中的内容不记录
+fn scan(lines: &[&str]) -> HashMap<Vec<String>, Section> {
+    let mut sections: HashMap<Vec<String>, Section> = HashMap::new();
+    sections.insert(Vec::new(), Section::default());
+    let mut current = Some(Vec::new());
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("[[") {
+            current = None;
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            current = header_path(trimmed);
+            if let Some(path) = &current {
+                sections.entry(path.clone()).or_default().last = Some(i);
+            }
+            continue;
+        }
+        let Some(path) = &current else {
+            continue;
+        };
+        let Ok(table) = toml::from_str::<Table>(trimmed) else {
+            continue;
+        };
+        // 点分键（`a.b = 1`）解析出的是表，不做逐行修改
+        let Some(k) = table.iter().find(|(_, v)| !v.is_table()).map(|(k, _)| k.clone()) else {
+            continue;
+        };
+        if table.len() != 1 {
+            continue;
+        }
+        let line = Line {
+            at: i,
+            indent: line[..line.len() - trimmed.len()].to_string(),
+            comment: trailing_comment(trimmed, &table),
+        };
+        let section = sections.entry(path.clone()).or_default();
+        section.lines.insert(k, line);
+        section.last = Some(i);
+    }
+    sections
+}
+
+fn header_path(header: &str) -> Option<Vec<String>> {
+    let mut table: Table = toml::from_str(header).ok()?;
+    let mut path = Vec::new();
+    while table.len() == 1 {
+        let (k, v) = table.into_iter().next()?;
+        path.push(k);
+        table = match v {
+            Value::Table(t) => t,
+            _ => return None,
+        };
+    }
+    Some(path)
+}
+
+fn trailing_comment(line: &str, parsed: &Table) -> String {
+    line.match_indices('#')
+        .map(|(i, _)| i)
+        .find(|&i| toml::from_str::<Table>(&line[..i]).is_ok_and(|t| &t == parsed))
+        .map(|i| line[i..].trim_end().to_string())
+        .unwrap_or_default()
+}
+
+/// 比较一张表修改前后的键；无法逐行修改时返回 None
+fn diff(
+    sections: &HashMap<Vec<String>, Section>,
+    path: &mut Vec<String>,
+    parsed: Option<&Table>,
+    old: &Table,
+    new: &Table,
+    edits: &mut Edits,
+) -> Option<()> {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let section = sections.get(path);
+    for k in keys {
+        let (before, after) = (old.get(k), new.get(k));
+        if before == after {
+            continue;
+        }
+        let written = parsed.and_then(|t| t.get(k));
+        if let Some(line) = section.and_then(|s| s.lines.get(k)) {
+            match after {
+                Some(v) => {
+                    let mut text = format!("{}{} = {}", line.indent, key(k), v);
+                    if !line.comment.is_empty() {
+                        text = format!("{} {}", text, line.comment);
+                    }
+                    edits.replace.insert(line.at, text);
+                }
+                None => {
+                    edits.delete.insert(line.at);
+                }
+            }
+            continue;
+        }
+        match (written, after) {
+            // 原文件中以 `[表头]` 写出或未写出的子表，逐键比较
+            (None | Some(Value::Table(_)), Some(Value::Table(after))) => {
+                let empty = Table::new();
+                let before = match before {
+                    Some(Value::Table(t)) => t,
+                    _ => &empty,
+                };
+                path.push(k.clone());
+                let written = written.and_then(Value::as_table);
+                diff(sections, path, written, before, after, edits)?;
+                path.pop();
+            }
+            // 原文件中没有写出（使用默认值），删除时无需改动
+            (None, None) => {}
+            (None, Some(v)) => {
+                let line = format!("{} = {}", key(k), v);
+                match section {
+                    Some(s) => edits.insert.entry(s.last).or_default().push(line),
+                    None => match edits.append.iter_mut().find(|(p, _)| p == path) {
+                        Some((_, body)) => body.push(line),
+                        None => edits.append.push((path.clone(), vec![line])),
+                    },
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+/// 可用作裸键时原样输出，否则加引号
+fn key(k: &str) -> String {
+    if !k.is_empty() && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        k.to_string()
+    } else {
+        Value::String(k.to_string()).to_string()
+    }
+}
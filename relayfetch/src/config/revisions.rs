@@ -0,0 +1,97 @@
+//! 配置修订历史
+//!
+//! 启动、重载、管理接口修改与回滚后，把 config.toml 与 files.toml 的完整内容保存为一个修订
+//! （`storage_dir/.relayfetch/config_revisions/<编号>.toml`），内容与上一个修订相同时不记录，
+//! 只保留最近的 `config_revisions` 个。RollbackConfig 把两个文件恢复为某个修订的原文。
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::bandwidth::STATE_DIR;
+
+const REVISIONS_DIR: &str = "config_revisions";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Revision {
+    pub id: u64,
+    /// 记录时间，unix 秒
+    pub time: u64,
+    /// 产生修订的操作：startup / reload / update_config / update_files / rollback
+    pub source: String,
+    /// 修改时附带的说明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub config: String,
+    pub files: String,
+}
+
+fn dir(storage_dir: &Path) -> PathBuf {
+    storage_dir.join(STATE_DIR).join(REVISIONS_DIR)
+}
+
+/// 全部修订，新的在前
+pub fn list(storage_dir: &Path) -> Vec<Revision> {
+    let mut ids: Vec<u64> = std::fs::read_dir(dir(storage_dir))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.strip_suffix(".toml")?.parse().ok())
+        .collect();
+    ids.sort_unstable_by(|a, b| b.cmp(a));
+    ids.into_iter().filter_map(|id| load(storage_dir, id)).collect()
+}
+
+pub fn load(storage_dir: &Path, id: u64) -> Option<Revision> {
+    let s = std::fs::read_to_string(dir(storage_dir).join(format!("{}.toml", id))).ok()?;
+    toml::from_str(&s)
+        .inspect_err(|e| log::warn!("failed to parse config revision {}: {}", id, e))
+        .ok()
+}
+
+/// 记录一个修订并删除超出 `keep` 个的旧修订；与上一个修订内容相同时不记录，返回 None
+pub fn record(
+    storage_dir: &Path,
+    keep: usize,
+    source: &str,
+    comment: Option<&str>,
+    config: String,
+    files: String,
+) -> Result<Option<u64>> {
+    if keep == 0 {
+        return Ok(None);
+    }
+    let existing = list(storage_dir);
+    if let Some(last) = existing.first()
+        && last.config == config
+        && last.files == files
+    {
+        return Ok(None);
+    }
+
+    let id = existing.first().map_or(1, |r| r.id + 1);
+    let revision = Revision {
+        id,
+        time: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        source: source.to_string(),
+        comment: comment.filter(|c| !c.is_empty()).map(str::to_string),
+        config,
+        files,
+    };
+    let dir = dir(storage_dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.toml", id));
+    let tmp = path.with_extension("toml.tmp");
+    std::fs::write(&tmp, toml::to_string(&revision)?)?;
+    std::fs::rename(&tmp, &path)?;
+
+    for old in existing.iter().skip(keep.saturating_sub(1)) {
+        let _ = std::fs::remove_file(dir.join(format!("{}.toml", old.id)));
+    }
+    Ok(Some(id))
+}
@@ -0,0 +1,66 @@
+//! 配置文件热重载
+//!
+//! 监听 config.toml / files.toml 所在目录（编辑器通常以“写临时文件 + rename”方式保存），
+//! 事件合并后调用 `ConfigCenter::reload_configs`。
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use log::{error, info, warn};
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use super::ConfigCenter;
+
+/// 事件合并窗口
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub fn spawn_config_watcher(cc: Arc<ConfigCenter>) -> anyhow::Result<()> {
+    let targets: Vec<PathBuf> = [&cc.runtime().config_path, &cc.runtime().files_path]
+        .into_iter()
+        .map(|p| std::fs::canonicalize(p).unwrap_or_else(|_| p.clone()))
+        .collect();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    let watched = targets.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        match res {
+            Ok(event) => {
+                if event.kind.is_access() {
+                    return;
+                }
+                if event.paths.iter().any(|p| watched.iter().any(|t| t == p)) {
+                    let _ = tx.send(());
+                }
+            }
+            Err(e) => warn!("[watch] watcher error: {}", e),
+        }
+    })?;
+
+    let mut dirs: Vec<PathBuf> = targets
+        .iter()
+        .filter_map(|p| p.parent().map(|d| d.to_path_buf()))
+        .collect();
+    dirs.dedup();
+    for dir in &dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        info!("[watch] watching {}", dir.display());
+    }
+
+    tokio::spawn(async move {
+        // watcher 需与任务同生命周期
+        let _watcher = watcher;
+
+        while rx.recv().await.is_some() {
+            // 合并短时间内的连续事件
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+
+            match cc.reload_configs().await {
+                Ok(_) => info!("[watch] configuration reloaded"),
+                Err(e) => error!("[watch] reload failed, keeping previous config: {}", e),
+            }
+        }
+    });
+
+    Ok(())
+}
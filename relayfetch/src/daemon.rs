@@ -0,0 +1,103 @@
+//! unix 后台运行（`--daemon`）与 pidfile（`--pidfile`）
+//!
+//! `--daemon` 在创建 tokio 运行时之前两次 fork 并 setsid，脱离终端；标准输入指向 /dev/null，
+//! 标准输出与错误（日志）写入 `--log-file`，未指定时丢弃。工作目录不变，相对路径仍按启动时的目录解析。
+//! pidfile 在启动时检查：记录的进程仍在运行则拒绝启动，进程已不存在则覆盖；正常退出时删除。
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+/// 转入后台；必须在启动任何线程之前调用（fork 只保留调用线程）
+pub fn daemonize(log_file: Option<&Path>) -> Result<()> {
+    let stdin = File::open("/dev/null")?;
+    let output = match log_file {
+        Some(path) => OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("open log file {}", path.display()))?,
+        None => OpenOptions::new().write(true).open("/dev/null")?,
+    };
+
+    // SAFETY: 此时进程只有一个线程，fork 后子进程只调用 async-signal-safe 的系统调用直到返回
+    unsafe {
+        fork_and_exit_parent()?;
+        if libc::setsid() == -1 {
+            bail!("setsid failed: {}", io::Error::last_os_error());
+        }
+        // 再 fork 一次，确保不会重新获得控制终端
+        fork_and_exit_parent()?;
+        libc::umask(0o022);
+        for (from, to) in [
+            (stdin.as_raw_fd(), libc::STDIN_FILENO),
+            (output.as_raw_fd(), libc::STDOUT_FILENO),
+            (output.as_raw_fd(), libc::STDERR_FILENO),
+        ] {
+            if libc::dup2(from, to) == -1 {
+                bail!("dup2 failed: {}", io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+unsafe fn fork_and_exit_parent() -> Result<()> {
+    match unsafe { libc::fork() } {
+        -1 => bail!("fork failed: {}", io::Error::last_os_error()),
+        0 => Ok(()),
+        _ => unsafe { libc::_exit(0) },
+    }
+}
+
+/// 写入当前进程 pid 的 pidfile，drop 时删除
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create(path: &Path) -> Result<Self> {
+        check(path)?;
+        let tmp = path.with_extension("pid.tmp");
+        std::fs::write(&tmp, format!("{}\n", std::process::id()))
+            .with_context(|| format!("write pidfile {}", path.display()))?;
+        std::fs::rename(&tmp, path)?;
+        Ok(Self { path: path.to_path_buf() })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // 只删除自己写入的，避免删掉后启动的实例的 pidfile
+        let ours = std::fs::read_to_string(&self.path)
+            .is_ok_and(|s| s.trim() == std::process::id().to_string());
+        if ours {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// pidfile 记录的进程仍在运行时返回错误
+pub fn check(path: &Path) -> Result<()> {
+    let Ok(s) = std::fs::read_to_string(path) else {
+        return Ok(());
+    };
+    let Ok(pid) = s.trim().parse::<libc::pid_t>() else {
+        return Ok(());
+    };
+    if pid > 0 && pid as u32 != std::process::id() && alive(pid) {
+        bail!("relayfetch is already running (pid {} in {})", pid, path.display());
+    }
+    Ok(())
+}
+
+fn alive(pid: libc::pid_t) -> bool {
+    // SAFETY: 信号 0 只检查进程是否存在与权限，不发送信号
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
@@ -0,0 +1,79 @@
+//! 文件新鲜度（files.toml 中的 `max_age_secs`）
+//!
+//! 最近一次同步（meta 的 `fetched_at`，上游返回 304 时同样刷新）距今超过 `max_age_secs` 的文件为过期，
+//! 从未同步完成的也算过期。过期文件随 Status 的 `stale_files` 与 GetFreshness（HTTP GET /freshness）返回；
+//! 标记了 `critical` 的文件过期时，下载端口的 /readyz 返回 503。
+
+use chrono::{DateTime, Utc};
+
+use crate::config::ConfigCenter;
+use crate::sync::meta::load_meta;
+
+/// 一个过期的文件
+#[derive(Debug, Clone)]
+pub struct Stale {
+    pub file: String,
+    /// 最近一次同步的时间（unix 秒），从未同步完成时为 None
+    pub fetched_at: Option<u64>,
+    /// 距最近一次同步的秒数
+    pub age_secs: Option<u64>,
+    pub max_age_secs: u64,
+    pub critical: bool,
+}
+
+/// 配置了 max_age_secs 且已过期的文件，按路径排序
+pub async fn stale_files(cc: &ConfigCenter) -> Vec<Stale> {
+    let entries: Vec<(String, u64, bool)> = cc
+        .files()
+        .await
+        .files
+        .iter()
+        .filter_map(|(file, source)| source.max_age_secs().map(|max| (file.clone(), max, source.critical())))
+        .collect();
+    if entries.is_empty() {
+        return Vec::new();
+    }
+    let storage_dir = cc.config().await.storage_dir.clone();
+    let now = Utc::now().timestamp().max(0) as u64;
+
+    let checked = tokio::task::spawn_blocking(move || {
+        crate::scan::par_map(
+            &entries,
+            |(file, max_age_secs, critical)| {
+                let meta = load_meta(&storage_dir.join(file).with_extension("meta")).unwrap_or_default();
+                let fetched_at = meta
+                    .fetched_at
+                    .as_deref()
+                    .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                    .and_then(|t| u64::try_from(t.timestamp()).ok());
+                let age_secs = fetched_at.map(|t| now.saturating_sub(t));
+                Stale {
+                    file: file.clone(),
+                    fetched_at,
+                    age_secs,
+                    max_age_secs: *max_age_secs,
+                    critical: *critical,
+                }
+            },
+            |_, _| {},
+        )
+    })
+    .await
+    .unwrap_or_default();
+
+    let mut stale: Vec<Stale> = checked
+        .into_iter()
+        .filter(|s| s.age_secs.is_none_or(|age| age > s.max_age_secs))
+        .collect();
+    stale.sort_by(|a, b| a.file.cmp(&b.file));
+    stale
+}
+
+/// files.toml 中是否有需要 /readyz 检查的文件
+pub async fn has_critical(cc: &ConfigCenter) -> bool {
+    cc.files()
+        .await
+        .files
+        .values()
+        .any(|source| source.critical() && source.max_age_secs().is_some())
+}
@@ -0,0 +1,140 @@
+//! 各子系统的健康状态
+//!
+//! 子系统自行上报 OK / Degraded / Failed 及原因；整体状态取最差的一项，
+//! 通过 /readyz、status 与 metrics 对外提供。
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use log::{info, warn};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthState {
+    #[default]
+    Ok,
+    Degraded,
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Subsystem {
+    Scheduler,
+    GroupScheduler,
+    DownloadServer,
+    #[cfg(feature = "acme")]
+    Https,
+    #[cfg(feature = "grpc_management")]
+    GrpcAdmin,
+    #[cfg(feature = "http_management")]
+    HttpAdmin,
+    Storage,
+    Upstream,
+}
+
+impl Subsystem {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Scheduler => "scheduler",
+            Self::GroupScheduler => "group_scheduler",
+            Self::DownloadServer => "download_server",
+            #[cfg(feature = "acme")]
+            Self::Https => "https",
+            #[cfg(feature = "grpc_management")]
+            Self::GrpcAdmin => "grpc_admin",
+            #[cfg(feature = "http_management")]
+            Self::HttpAdmin => "http_admin",
+            Self::Storage => "storage",
+            Self::Upstream => "upstream",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SubsystemHealth {
+    pub state: HealthState,
+    pub reason: Option<String>,
+    /// 进入当前状态的时间
+    pub since: SystemTime,
+}
+
+#[derive(Default)]
+pub struct Health {
+    entries: Mutex<BTreeMap<Subsystem, SubsystemHealth>>,
+}
+
+impl Health {
+    /// 上报状态；状态或原因不变时保留原来的 since
+    pub fn set(&self, subsystem: Subsystem, state: HealthState, reason: Option<String>) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(current) = entries.get(&subsystem)
+            && current.state == state
+            && current.reason == reason
+        {
+            return;
+        }
+        match (state, &reason) {
+            (HealthState::Ok, _) => info!("[health] {} ok", subsystem.name()),
+            (_, Some(r)) => warn!("[health] {} {:?}: {}", subsystem.name(), state, r),
+            (_, None) => warn!("[health] {} {:?}", subsystem.name(), state),
+        }
+        entries.insert(
+            subsystem,
+            SubsystemHealth {
+                state,
+                reason,
+                since: SystemTime::now(),
+            },
+        );
+    }
+
+    pub fn ok(&self, subsystem: Subsystem) {
+        self.set(subsystem, HealthState::Ok, None);
+    }
+
+    pub fn degraded(&self, subsystem: Subsystem, reason: impl Into<String>) {
+        self.set(subsystem, HealthState::Degraded, Some(reason.into()));
+    }
+
+    pub fn failed(&self, subsystem: Subsystem, reason: impl Into<String>) {
+        self.set(subsystem, HealthState::Failed, Some(reason.into()));
+    }
+
+    /// 已上报过的子系统
+    pub fn snapshot(&self) -> BTreeMap<Subsystem, SubsystemHealth> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    /// 整体状态：最差的一项
+    pub fn overall(&self) -> HealthState {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .map(|h| h.state)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// 检查存储目录可写，并上报到 Storage
+pub fn check_storage(health: &Health, storage_dir: &std::path::Path) {
+    match probe_storage(storage_dir) {
+        Ok(()) => health.ok(Subsystem::Storage),
+        Err(e) => health.failed(Subsystem::Storage, e),
+    }
+}
+
+/// 在内部状态目录下写入并删除探测文件；并发探测各用一个文件
+pub fn probe_storage(storage_dir: &std::path::Path) -> Result<(), String> {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let name = format!(".probe.{}", SEQ.fetch_add(1, Ordering::Relaxed));
+    let probe = storage_dir.join(crate::bandwidth::STATE_DIR).join(name);
+    probe
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map_err(|e| format!("{} is not writable: {}", storage_dir.display(), e))
+}
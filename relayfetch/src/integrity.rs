@@ -0,0 +1,279 @@
+//! 存储完整性检查（管理接口 VerifyStorage）
+//!
+//! 只读取本地数据，不请求上游：逐个重新计算已同步文件的 sha256，与 meta 记录的大小、摘要
+//! 以及 files.toml 中给出的 `sha256` 对照，报告缺失或损坏的文件，用于磁盘故障之后的检查。
+//! 检查的文件为 files.toml、远端清单与 hub 节点清单中的条目，以及目录镜像前缀下的文件。
+//!
+//! 修复（管理接口 RepairStorage）只重新下载检查出问题的文件：先删除其 meta，使下载不会因条件请求
+//! 返回 304 或摘要与记录一致而跳过，再按预取的方式并发下载。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::info;
+use walkdir::WalkDir;
+
+use crate::config::ConfigCenter;
+use crate::storage_index::is_hidden;
+use crate::sync::meta::{Meta, file_sha256, load_meta};
+use crate::sync::{self, manifest, peer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// 数据文件不存在
+    Missing,
+    /// meta 不存在或无法解析
+    BadMeta,
+    /// 大小与 meta 记录的不同
+    SizeMismatch,
+    /// 摘要与 meta 记录的不同
+    Sha256Mismatch,
+    /// 摘要与 files.toml 给出的不同
+    ExpectedMismatch,
+    /// 读取失败
+    Unreadable,
+}
+
+impl Problem {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::BadMeta => "bad_meta",
+            Self::SizeMismatch => "size_mismatch",
+            Self::Sha256Mismatch => "sha256_mismatch",
+            Self::ExpectedMismatch => "expected_mismatch",
+            Self::Unreadable => "unreadable",
+        }
+    }
+}
+
+/// 一个有问题的文件
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub file: String,
+    pub problem: Problem,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// 检查的文件数
+    pub checked: usize,
+    /// 其中 meta 没有摘要、只核对了大小的文件数
+    pub unhashed: usize,
+    /// 读取并计算摘要的字节数
+    pub bytes_hashed: u64,
+    /// 按路径排序
+    pub findings: Vec<Finding>,
+}
+
+/// 单个文件的检查结果
+enum Outcome {
+    Ok { hashed: Option<u64> },
+    Bad(Problem, String),
+}
+
+/// 一个文件的修复结果
+#[derive(Debug, Clone)]
+pub struct Repair {
+    pub file: String,
+    /// 检查出的问题
+    pub problem: Problem,
+    /// 重新下载失败的原因
+    pub error: Option<String>,
+    /// 修复后的 sha256
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RepairReport {
+    /// 检查的文件数
+    pub checked: usize,
+    /// 检查出问题的文件，按路径排序
+    pub repairs: Vec<Repair>,
+}
+
+pub async fn verify_storage(cc: &ConfigCenter) -> Report {
+    let (storage_dir, entries) = targets(cc).await;
+    verify(storage_dir, entries).await
+}
+
+/// 检查 `files`（为空时检查全部），重新下载有问题的文件
+pub async fn repair(cc: &Arc<ConfigCenter>, files: Vec<String>) -> anyhow::Result<RepairReport> {
+    let storage_dir = cc.config().await.storage_dir.clone();
+    let report = if files.is_empty() {
+        verify_storage(cc).await
+    } else {
+        let mut entries = Vec::new();
+        for file in files {
+            let expected = sync::find_source(cc, &file)
+                .await
+                .and_then(|source| expected_sha256(&source).map(str::to_string));
+            entries.push((file, expected));
+        }
+        entries.sort();
+        entries.dedup_by(|a, b| a.0 == b.0);
+        verify(storage_dir.clone(), entries).await
+    };
+
+    // 上游须在删除 meta 之前查找：目录镜像展开的文件只能从 meta 得知上游
+    let mut repairs = Vec::new();
+    let mut downloads = Vec::new();
+    for finding in report.findings {
+        let mut repair = Repair { file: finding.file, problem: finding.problem, error: None, sha256: None };
+        match sync::find_source(cc, &repair.file).await {
+            Some(source) => {
+                let meta = meta_path(&storage_dir, &repair.file);
+                match std::fs::remove_file(&meta) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        repair.error = Some(format!("failed to remove {}: {}", meta.display(), e));
+                    }
+                    _ => downloads.push((repair.file.clone(), source)),
+                }
+            }
+            None => repair.error = Some("no upstream known for this file".to_string()),
+        }
+        repairs.push(repair);
+    }
+
+    if !downloads.is_empty() {
+        info!("[integrity] repairing {} files", downloads.len());
+        let outcomes = sync::prefetch(cc.clone(), downloads, false).await?;
+        for outcome in outcomes {
+            if let Some(repair) = repairs.iter_mut().find(|r| r.file == outcome.file) {
+                repair.error = outcome.error;
+            }
+        }
+    }
+    for repair in repairs.iter_mut().filter(|r| r.error.is_none()) {
+        repair.sha256 = load_meta(&meta_path(&storage_dir, &repair.file)).ok().and_then(|m| m.sha256);
+    }
+    Ok(RepairReport { checked: report.checked, repairs })
+}
+
+fn meta_path(storage_dir: &Path, file: &str) -> PathBuf {
+    storage_dir.join(file).with_extension("meta")
+}
+
+/// 有后处理时本地内容已不是上游发布的内容，期望摘要不适用
+fn expected_sha256(source: &crate::config::file::FileSource) -> Option<&str> {
+    source.sha256().filter(|_| source.post_process().is_empty())
+}
+
+/// 要检查的文件及其期望摘要，按路径排序
+async fn targets(cc: &ConfigCenter) -> (PathBuf, Vec<(String, Option<String>)>) {
+    {
+        let cfg = cc.config().await;
+        let files = cc.files().await;
+        let storage_dir = cfg.storage_dir.clone();
+
+        // 路径 -> files.toml 等给出的期望摘要
+        let mut entries: BTreeMap<String, Option<String>> = BTreeMap::new();
+        let remote = std::fs::read(manifest::cache_path(&storage_dir))
+            .ok()
+            .and_then(|body| manifest::parse(&body).ok());
+        let peer_files = cfg
+            .upstream_peer
+            .as_ref()
+            .and_then(|peer| peer::cached(peer, &storage_dir))
+            .unwrap_or_default();
+        for (file, source) in files
+            .files
+            .iter()
+            .chain(remote.iter().flat_map(|m| m.files.iter()))
+            .chain(peer_files.iter())
+        {
+            entries.entry(file.clone()).or_insert_with(|| expected_sha256(source).map(str::to_string));
+        }
+
+        // 目录镜像展开的文件不在配置中，以磁盘上已有的为准
+        let prefixes: Vec<String> = files
+            .dirs
+            .keys()
+            .chain(remote.iter().flat_map(|m| m.dirs.keys()))
+            .map(|p| p.trim_matches('/').to_string())
+            .collect();
+        for prefix in prefixes {
+            let walker = WalkDir::new(storage_dir.join(&prefix)).into_iter().filter_entry(|e| !is_hidden(e));
+            for entry in walker.flatten().filter(|e| e.file_type().is_file()) {
+                if entry.path().extension().is_some_and(|ext| ext == "meta") {
+                    continue;
+                }
+                if let Some(rel) = entry.path().strip_prefix(&storage_dir).ok().and_then(|p| p.to_str()) {
+                    entries.entry(rel.replace('\\', "/")).or_default();
+                }
+            }
+        }
+        (storage_dir, entries.into_iter().collect())
+    }
+}
+
+async fn verify(storage_dir: PathBuf, entries: Vec<(String, Option<String>)>) -> Report {
+    info!("[integrity] verifying {} files", entries.len());
+    let checked = tokio::task::spawn_blocking(move || {
+        let outcomes = crate::scan::par_map(&entries, |(file, expected)| check(&storage_dir, file, expected.as_deref()), |_, _| {});
+        (entries, outcomes)
+    })
+    .await;
+    let Ok((entries, outcomes)) = checked else {
+        return Report::default();
+    };
+
+    let mut report = Report { checked: entries.len(), ..Default::default() };
+    for ((file, _), outcome) in entries.into_iter().zip(outcomes) {
+        match outcome {
+            Outcome::Ok { hashed: Some(bytes) } => report.bytes_hashed += bytes,
+            Outcome::Ok { hashed: None } => report.unhashed += 1,
+            Outcome::Bad(problem, detail) => report.findings.push(Finding { file, problem, detail }),
+        }
+    }
+    info!(
+        "[integrity] verified {} files, {} with problems",
+        report.checked,
+        report.findings.len()
+    );
+    report
+}
+
+fn check(storage_dir: &Path, file: &str, expected: Option<&str>) -> Outcome {
+    let path = storage_dir.join(file);
+    let size = match std::fs::metadata(&path) {
+        Ok(m) => m.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Outcome::Bad(Problem::Missing, "file does not exist".to_string());
+        }
+        Err(e) => return Outcome::Bad(Problem::Unreadable, e.to_string()),
+    };
+    let meta = match std::fs::read_to_string(meta_path(storage_dir, file)) {
+        Ok(s) => match Meta::parse(&s) {
+            Ok(meta) => meta,
+            Err(e) => return Outcome::Bad(Problem::BadMeta, format!("cannot parse meta: {}", e.message())),
+        },
+        Err(e) => return Outcome::Bad(Problem::BadMeta, format!("cannot read meta: {}", e)),
+    };
+    if let Some(total) = meta.total_size
+        && total != size
+    {
+        return Outcome::Bad(Problem::SizeMismatch, format!("size {} does not match recorded {}", size, total));
+    }
+    if meta.sha256.is_none() && expected.is_none() {
+        return Outcome::Ok { hashed: None };
+    }
+
+    let sha256 = match file_sha256(&path) {
+        Ok(sha) => sha,
+        Err(e) => return Outcome::Bad(Problem::Unreadable, format!("{:#}", e)),
+    };
+    if let Some(recorded) = &meta.sha256
+        && !recorded.eq_ignore_ascii_case(&sha256)
+    {
+        return Outcome::Bad(Problem::Sha256Mismatch, format!("sha256 {} does not match recorded {}", sha256, recorded));
+    }
+    if let Some(expected) = expected
+        && !expected.eq_ignore_ascii_case(&sha256)
+    {
+        return Outcome::Bad(Problem::ExpectedMismatch, format!("sha256 {} does not match expected {}", sha256, expected));
+    }
+    Outcome::Ok { hashed: Some(size) }
+}
@@ -0,0 +1,58 @@
+//! 日志初始化与关联 ID
+//!
+//! - 使用 tracing-subscriber 输出，`log` 宏经 tracing-log 桥接
+//! - 每次同步 / 每个 HTTP 请求都会带上一个 span，关联 ID 出现在其下所有日志中
+
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::io::IsTerminal;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::config::LogFormat;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 初始化全局日志（RUST_LOG 控制级别，默认 info）
+pub fn init(format: &LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(std::io::stdout().is_terminal());
+
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+    }
+}
+
+/// 生成新的关联 ID
+pub fn new_correlation_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+/// HTTP 中间件：为请求分配 request_id（优先沿用客户端传入的 x-request-id），并回写到响应头
+pub async fn request_id(req: Request<axum::body::Body>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 64)
+        .map(|v| v.to_string())
+        .unwrap_or_else(new_correlation_id);
+
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let mut resp = next.run(req).instrument(span).await;
+
+    if let Ok(v) = HeaderValue::from_str(&request_id) {
+        resp.headers_mut().insert(REQUEST_ID_HEADER, v);
+    }
+    resp
+}
@@ -5,25 +5,57 @@
 // 3. 定期同步远端文件到本地（避免并发、避免重复启动）
 // 4. 提供本地 HTTP 下载服务（路径与存储一致）
 
+// 不启用管理接口时，只供管理接口调用的统计、校验与修复等功能都用不到
+#![cfg_attr(not(feature = "management_core"), allow(dead_code))]
+
+mod access;
+mod acl;
+#[cfg(feature = "acme")]
+mod acme;
+#[cfg(feature = "smtp")]
+mod alert;
+mod bandwidth;
 mod config;
+#[cfg(unix)]
+mod daemon;
+mod freshness;
+mod health;
+mod integrity;
+mod logging;
+#[cfg(feature = "mdns")]
+mod mdns;
+mod notify;
+mod proxy_cache;
+mod quota;
+mod replicate;
+mod retention;
+mod scan;
 mod server;
+mod shaping;
 mod signal;
+mod signed_url;
+mod storage_index;
+mod supervise;
 mod sync;
+#[cfg(windows)]
+mod winservice;
 
 #[cfg(feature = "management_core")]
 mod management;
 
-use env_logger::Env;
-use log::{error, info};
+use log::{error, info, warn};
 
 use clap::Parser;
 use std::{path::PathBuf, sync::Arc};
-use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 
-use crate::config::ConfigCenter;
+use crate::config::{ConfigCenter, config::BlackoutCatchUp, overrides::Override};
+use crate::health::Subsystem;
+use crate::proxy_cache::ProxyCache;
+use crate::server::listen::Listener;
 
 #[derive(Parser)]
-#[command(name = "relayfetch")]
+#[command(name = "relayfetch", version)]
 struct Args {
     /// config.toml 路径
     #[arg(long, default_value = "config/config.toml")]
@@ -32,81 +64,395 @@ struct Args {
     /// files.toml 路径
     #[arg(long, default_value = "config/files.toml")]
     files: PathBuf,
+
+    /// 覆盖 config.toml 中的任意字段，如 `--set retention.max_versions=3`，可重复；
+    /// 优先级：默认值 < config.toml < RELAYFETCH_* 环境变量 < 命令行
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = config::overrides::parse_set)]
+    set: Vec<Override>,
+
+    /// 覆盖 bind（下载服务监听地址）
+    #[arg(long)]
+    bind: Option<String>,
+
+    /// 覆盖 storage_dir
+    #[arg(long)]
+    storage_dir: Option<String>,
+
+    /// 覆盖 proxy（上游代理），空字符串表示不使用代理
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// 覆盖 download_concurrency
+    #[arg(long)]
+    download_concurrency: Option<usize>,
+
+    /// 覆盖 grpc_admin
+    #[arg(long)]
+    grpc_admin: Option<String>,
+
+    /// 覆盖 http_admin
+    #[arg(long)]
+    http_admin: Option<String>,
+
+    /// 脱离终端转入后台运行
+    #[cfg(unix)]
+    #[arg(long)]
+    daemon: bool,
+
+    /// 写入进程 pid 的文件，正常退出时删除；记录的进程仍在运行时拒绝启动
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH")]
+    pidfile: Option<PathBuf>,
+
+    /// `--daemon` 时日志写入的文件（追加），默认丢弃
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH", requires = "daemon")]
+    log_file: Option<PathBuf>,
+
+    /// 安装 / 卸载 Windows 服务，或作为服务运行（由服务控制管理器调用）
+    #[cfg(windows)]
+    #[arg(long, value_enum)]
+    service: Option<ServiceAction>,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // 初始化
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+#[cfg(windows)]
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ServiceAction {
+    Install,
+    Uninstall,
+    Run,
+}
+
+impl Args {
+    /// 环境变量在前、命令行在后，后应用的优先；专用参数在 `--set` 之后
+    fn overrides(&self) -> Vec<Override> {
+        let mut out = config::overrides::from_env();
+        out.extend(self.set.iter().cloned());
+        let flags = [
+            ("bind", self.bind.clone()),
+            ("storage_dir", self.storage_dir.clone()),
+            ("proxy", self.proxy.clone()),
+            ("download_concurrency", self.download_concurrency.map(|v| v.to_string())),
+            ("grpc_admin", self.grpc_admin.clone()),
+            ("http_admin", self.http_admin.clone()),
+        ];
+        for (key, value) in flags {
+            if let Some(value) = value {
+                out.push(Override::new(key, value, format!("--{}", key.replace('_', "-"))));
+            }
+        }
+        out
+    }
+}
+
+fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+
+    #[cfg(windows)]
+    if let Some(action) = args.service {
+        return winservice::handle(action, args);
+    }
+
+    // 后台运行与 pidfile 都在创建运行时（启动线程）之前处理
+    #[cfg(unix)]
+    let _pidfile = {
+        if let Some(path) = &args.pidfile {
+            daemon::check(path)?;
+        }
+        if args.daemon {
+            daemon::daemonize(args.log_file.as_deref())?;
+        }
+        args.pidfile.as_deref().map(daemon::PidFile::create).transpose()?
+    };
+
+    run(&args)
+}
+
+/// 创建 tokio 运行时并运行到退出
+fn run(args: &Args) -> anyhow::Result<()> {
+    tokio::runtime::Runtime::new()?.block_on(serve(args))
+}
+
+async fn serve(args: &Args) -> anyhow::Result<()> {
+    // 初始化
     let runtime = config::RuntimeContext {
         config_path: args.config.clone(),
         files_path: args.files.clone(),
+        overrides: args.overrides(),
     };
     let cc = Arc::new(ConfigCenter::new(runtime));
+    logging::init(&cc.config().await.log_format);
+
+    // 配置文件热重载
+    if cc.config().await.watch_config
+        && let Err(e) = config::watch::spawn_config_watcher(cc.clone())
+    {
+        error!("failed to start config watcher: {e:?}");
+    }
+
+    // 存储扫描线程池
+    scan::init(cc.config().await.scan_threads);
+
+    // 存储目录索引：监听外部修改
+    if cc.config().await.storage_index_ttl_secs > 0
+        && let Err(e) = storage_index::spawn_storage_watcher(cc.clone())
+    {
+        error!("failed to start storage watcher: {e:?}");
+    }
+
+    // 流量统计定期落盘
+    bandwidth::spawn_flusher(cc.clone());
+    access::spawn_flusher(cc.clone());
+
+    // 按 [retention] 定期清理
+    retention::spawn_scheduler(cc.clone());
+
+    // 同步长期失败时邮件告警
+    #[cfg(feature = "smtp")]
+    alert::spawn(cc.clone());
+    #[cfg(not(feature = "smtp"))]
+    if cc.config().await.alert.is_some() {
+        error!("alert is configured but relayfetch was built without the `smtp` feature");
+    }
 
     // 启动后台同步任务
     spawn_periodic_sync(cc.clone());
 
+    // 反向代理缓存（下载服务与管理接口共享）
+    let storage_dir = { cc.config().await.storage_dir.clone() };
+    let proxy_cache = Arc::new(ProxyCache::new(cc.clone(), storage_dir.clone()).await);
+
     // Management 服务
     #[cfg(feature = "management_core")]
-    management::admin_server(cc.clone()).await;
+    management::admin_server(cc.clone(), proxy_cache.clone()).await;
 
     // 构建 HTTP 服务
-    let storage_dir = { cc.config().await.storage_dir.clone() };
-    let app = server::build_router(storage_dir);
+    let app = server::build_router(cc.clone(), storage_dir, proxy_cache);
+
+    // ACME 证书 + HTTPS 下载服务
+    let acme_cfg = cc.config().await.acme.clone();
+    if let Some(acme_cfg) = acme_cfg {
+        spawn_tls(cc.clone(), acme_cfg.tls_bind, app.clone());
+    }
+
+    // 局域网服务发现
+    #[cfg(feature = "mdns")]
+    let advertiser = start_mdns(&*cc.config().await);
+    #[cfg(not(feature = "mdns"))]
+    if cc.config().await.mdns.is_some() {
+        error!("mdns is configured but relayfetch was built without the `mdns` feature");
+    }
 
     // 启动 HTTP 服务
-    let bind = { cc.config().await.bind.clone() };
-    run_server(bind, app).await?;
+    let binds = { cc.config().await.bind.addrs() };
+    run_server(&cc, &binds, app).await?;
+
+    #[cfg(feature = "mdns")]
+    if let Some(advertiser) = advertiser {
+        advertiser.stop();
+    }
+
+    if let Err(e) = cc.bandwidth().flush() {
+        error!("failed to persist bandwidth usage: {e:?}");
+    }
+    if let Err(e) = cc.access().flush() {
+        error!("failed to persist access records: {e:?}");
+    }
     Ok(())
 }
 
-/// 启动周期同步任务
+/// 启动周期同步任务；panic 后由 supervise 重启，重启后立即同步一次
 fn spawn_periodic_sync(cc: Arc<ConfigCenter>) {
-    tokio::spawn(async move {
-        let sync_lock = Arc::new(tokio::sync::Semaphore::new(1));
-
-        // 启动时立即同步一次
-        {
-            let _permit = sync_lock.acquire().await.unwrap();
-            if let Err(e) = sync::sync_once(cc.clone()).await {
-                log::error!("[sync] error: {:?}", e);
+    let group_cc = cc.clone();
+    supervise::spawn(cc.clone(), Subsystem::Scheduler, move || periodic_sync(cc.clone()));
+    supervise::spawn(group_cc.clone(), Subsystem::GroupScheduler, move || periodic_group_sync(group_cc.clone()));
+}
+
+async fn periodic_sync(cc: Arc<ConfigCenter>) {
+    // 启动时立即同步一次（暂停中则等到恢复）
+    wait_resumed(&cc).await;
+    if outside_blackout(&cc).await {
+        let _running = cc.lock_sync().await;
+        run_sync(&cc).await;
+    }
+
+    // 使用 interval 循环
+    loop {
+        let interval_secs = {
+            let cfg_read = cc.config().await;
+            cfg_read.interval_secs
+        };
+
+        tokio::time::sleep(std::time::Duration::from_secs(interval_secs)).await;
+        // 暂停期间错过的同步在恢复后立即补一次
+        wait_resumed(&cc).await;
+        if !outside_blackout(&cc).await {
+            continue;
+        }
+
+        let _running = cc.lock_sync().await;
+
+        run_sync(&cc).await;
+    }
+}
+
+/// 设置了 interval_secs 的分组按各自的周期同步，启动时各同步一次；
+/// 至少每分钟重新读取一次分组，files.toml 重新加载后的变化由此生效
+async fn periodic_group_sync(cc: Arc<ConfigCenter>) {
+    const POLL: std::time::Duration = std::time::Duration::from_secs(60);
+    let mut last_run: std::collections::HashMap<String, tokio::time::Instant> = Default::default();
+    loop {
+        let groups: Vec<(String, std::time::Duration)> = cc
+            .files()
+            .await
+            .groups
+            .iter()
+            .filter_map(|(name, g)| g.interval_secs.map(|secs| (name.clone(), std::time::Duration::from_secs(secs))))
+            .collect();
+        last_run.retain(|name, _| groups.iter().any(|(g, _)| g == name));
+
+        for (name, interval) in &groups {
+            if last_run.get(name).is_some_and(|last| last.elapsed() < *interval) {
+                continue;
+            }
+            // 跳过的一次也算作已运行，等下一个周期
+            last_run.insert(name.clone(), tokio::time::Instant::now());
+            wait_resumed(&cc).await;
+            if !outside_blackout(&cc).await {
+                continue;
+            }
+            let _running = cc.lock_sync().await;
+            match sync::sync_group(cc.clone(), name.clone()).await {
+                Ok(_) => cc.health().ok(Subsystem::GroupScheduler),
+                Err(e) => {
+                    error!("[sync] group {} error: {:?}", name, e);
+                    cc.health().failed(Subsystem::GroupScheduler, format!("group {} sync failed: {:#}", name, e));
+                }
             }
         }
 
-        // 使用 interval 循环
-        loop {
-            let interval_secs = {
-                let cfg_read = cc.config().await;
-                cfg_read.interval_secs
-            };
+        let wait = groups
+            .iter()
+            .filter_map(|(name, interval)| last_run.get(name).map(|last| interval.saturating_sub(last.elapsed())))
+            .min()
+            .unwrap_or(POLL)
+            .min(POLL);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+async fn wait_resumed(cc: &ConfigCenter) {
+    if cc.sync_status().await.scheduler_paused {
+        info!("[sync] scheduler paused, waiting for resume");
+        cc.wait_scheduler_resumed().await;
+        info!("[sync] scheduler resumed");
+    }
+}
+
+/// 处于禁止同步时段时按 catch_up 跳过或等到时段结束；返回 false 表示跳过本次同步
+async fn outside_blackout(cc: &ConfigCenter) -> bool {
+    loop {
+        let now = chrono::Local::now().naive_local();
+        let (until, catch_up) = match &cc.config().await.sync_blackout {
+            Some(b) => (b.until(now), b.catch_up),
+            None => return true,
+        };
+        let Some(until) = until else {
+            return true;
+        };
+        match catch_up {
+            BlackoutCatchUp::Skip => {
+                info!("[sync] in blackout until {}, skipping this run", until);
+                return false;
+            }
+            BlackoutCatchUp::Immediate => {
+                info!("[sync] in blackout until {}, will sync right after", until);
+                tokio::time::sleep((until - now).to_std().unwrap_or_default()).await;
+            }
+        }
+    }
+}
 
-            tokio::time::sleep(std::time::Duration::from_secs(interval_secs)).await;
+async fn run_sync(cc: &Arc<ConfigCenter>) {
+    match sync::sync_once(cc.clone()).await {
+        Ok(()) => cc.health().ok(Subsystem::Scheduler),
+        Err(e) => {
+            log::error!("[sync] error: {:?}", e);
+            cc.health().failed(Subsystem::Scheduler, format!("sync failed: {:#}", e));
+        }
+    }
+}
 
-            let _permit = sync_lock.acquire().await.unwrap();
 
-            if let Err(e) = sync::sync_once(cc.clone()).await {
-                log::error!("[sync] error: {:?}", e);
+#[cfg(feature = "acme")]
+fn spawn_tls(cc: Arc<ConfigCenter>, bind: String, app: axum::Router) {
+    let resolver = Arc::new(server::tls::CertResolver::default());
+    acme::spawn_renewal(cc.clone(), resolver.clone());
+    supervise::spawn(cc.clone(), Subsystem::Https, move || {
+        let (cc, bind, app, resolver) = (cc.clone(), bind.clone(), app.clone(), resolver.clone());
+        async move {
+            cc.health().ok(Subsystem::Https);
+            let server_cfg = cc.config().await.server.clone();
+            if let Err(e) = server::tls::serve(bind, app, resolver, &server_cfg).await {
+                error!("HTTPS server error: {e:?}");
+                cc.health().failed(Subsystem::Https, format!("{:#}", e));
             }
         }
     });
 }
 
+#[cfg(not(feature = "acme"))]
+fn spawn_tls(_cc: Arc<ConfigCenter>, _bind: String, _app: axum::Router) {
+    error!("acme is configured but relayfetch was built without the `acme` feature");
+}
+
+#[cfg(feature = "mdns")]
+fn start_mdns(cfg: &config::config::Config) -> Option<mdns::Advertiser> {
+    mdns::Advertiser::start(cfg).unwrap_or_else(|e| {
+        error!("failed to start mDNS advertisement: {e:?}");
+        None
+    })
+}
 
-/// 启动 HTTP 服务并优雅退出
-async fn run_server(bind: String, app: axum::Router) -> anyhow::Result<()> {
-    let listener = TcpListener::bind(&bind).await?;
-    info!("Download server listening on http://{}", bind);
+/// 在所有监听地址上启动 HTTP 服务并优雅退出：
+/// 收到信号后停止接受新连接并取消进行中的同步，在 shutdown_timeout_secs 内等待进行中的下载请求
+/// 与同步结束，最后把同步状态写盘
+async fn run_server(cc: &ConfigCenter, binds: &[String], app: axum::Router) -> anyhow::Result<()> {
+    let mut listeners = Vec::new();
+    for addr in binds {
+        listeners.push(Listener::bind(addr).await?);
+    }
+    cc.health().ok(Subsystem::DownloadServer);
+
+    let stop = CancellationToken::new();
+    let server_cfg = cc.config().await.server.clone();
+    let server = futures::future::join_all(
+        listeners
+            .into_iter()
+            .map(|l| l.serve(app.clone(), server_cfg.clone(), stop.clone())),
+    );
+    tokio::pin!(server);
 
     tokio::select! {
-        res = axum::serve(listener, app) => {
-            if let Err(e) = res { error!("HTTP server error: {e:?}"); }
-        }
-        _ = signal::shutdown_signal() => {
-            info!("Shutdown signal received, exiting...");
-        }
+        _ = &mut server => return Ok(()),
+        _ = signal::shutdown_signal() => {}
+    }
+
+    let timeout = std::time::Duration::from_secs(cc.config().await.shutdown_timeout_secs);
+    info!("Shutdown signal received, draining in-flight requests and syncs (up to {:?})...", timeout);
+    stop.cancel();
+    cc.begin_shutdown();
+
+    let drain = async {
+        server.await;
+        // 拿到同步锁即表示进行中的同步已结束
+        let _idle = cc.lock_sync().await;
+    };
+    if tokio::time::timeout(timeout, drain).await.is_err() {
+        warn!("Shutdown timed out after {:?}, exiting with work still in flight", timeout);
     }
+    cc.flush_sync_state().await;
+    info!("Shutdown complete");
 
     Ok(())
 }
@@ -0,0 +1,63 @@
+//! 管理接口鉴权：静态 token + 每个 token 的接口白名单
+//!
+//! 接口名统一使用 gRPC 方法名的 snake_case 形式（如 `trigger_sync`），
+//! HTTP 路由由适配层映射到同一套名字。
+
+use crate::config::config::ApiToken;
+
+use super::CoreError;
+
+/// 校验 token 能否访问 endpoint；未配置任何 token 时不启用鉴权
+pub fn check(tokens: &[ApiToken], presented: Option<&str>, endpoint: &str) -> Result<(), CoreError> {
+    if tokens.is_empty() {
+        return Ok(());
+    }
+
+    let presented = presented
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| CoreError::Unauthenticated("missing token".into()))?;
+
+    let token = tokens
+        .iter()
+        .find(|t| constant_time_eq(t.token.as_bytes(), presented.as_bytes()))
+        .ok_or_else(|| CoreError::Unauthenticated("invalid token".into()))?;
+
+    if token.allow.iter().any(|pattern| allows(pattern, endpoint)) {
+        Ok(())
+    } else {
+        Err(CoreError::PermissionDenied(format!(
+            "token '{}' is not allowed to call {}",
+            token.name, endpoint
+        )))
+    }
+}
+
+/// 白名单项支持 glob，如 `*`、`get_*`
+fn allows(pattern: &str, endpoint: &str) -> bool {
+    pattern == endpoint
+        || globset::Glob::new(pattern)
+            .map(|g| g.compile_matcher().is_match(endpoint))
+            .unwrap_or(false)
+}
+
+/// 避免逐字节比较泄露 token 前缀
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// gRPC 方法名（CamelCase）转为接口名（snake_case）
+#[cfg(feature = "grpc_management")]
+pub fn endpoint_from_method(method: &str) -> String {
+    let mut out = String::with_capacity(method.len() + 4);
+    for (i, c) in method.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,89 @@
+//! clean_unused_files 的遍历与删除
+//!
+//! 递归遍历存储目录，按完整相对路径与在用的条目比较；以 `.` 开头的内部目录（`.partial/`、
+//! `.relayfetch/`、`.versions/`、反向代理缓存等）不进入。meta 随其数据文件保留，
+//! 删除后变空的父目录一并删除，直到存储目录本身。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use walkdir::WalkDir;
+
+use crate::config::config::OnDemandConfig;
+use crate::storage_index::is_hidden;
+
+/// 在用的路径
+pub struct InUse {
+    /// 条目的相对路径
+    pub files: HashSet<String>,
+    /// 目录镜像的前缀，其下的文件都视为在用
+    pub prefixes: Vec<String>,
+    /// 按需回源拉取的文件同样在用
+    pub on_demand: Option<OnDemandConfig>,
+}
+
+impl InUse {
+    fn contains(&self, rel: &str) -> bool {
+        self.files.contains(rel)
+            || self
+                .prefixes
+                .iter()
+                .any(|p| rel.strip_prefix(p.as_str()).is_some_and(|rest| rest.starts_with('/')))
+            || self.on_demand.as_ref().is_some_and(|o| o.includes(rel))
+    }
+}
+
+/// 删除不在用的文件，返回（相对路径, 完整路径）
+pub fn remove_unused(storage_dir: &Path, in_use: &InUse) -> Vec<(String, PathBuf)> {
+    let files: Vec<(String, PathBuf)> = WalkDir::new(storage_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !is_hidden(e))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(storage_dir).ok()?.to_str()?.replace('\\', "/");
+            Some((rel, e.into_path()))
+        })
+        .collect();
+
+    let is_meta = |p: &Path| p.extension().is_some_and(|ext| ext == "meta");
+    // 保留的数据文件对应的 meta
+    let kept_meta: HashSet<PathBuf> = files
+        .iter()
+        .filter(|(rel, path)| !is_meta(path) && in_use.contains(rel))
+        .map(|(_, path)| path.with_extension("meta"))
+        .collect();
+
+    let mut removed = Vec::new();
+    for (rel, path) in files {
+        let used = if is_meta(&path) {
+            kept_meta.contains(&path) || in_use.files.contains(&rel)
+        } else {
+            in_use.contains(&rel)
+        };
+        if used {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                remove_empty_parents(storage_dir, &path);
+                removed.push((rel, path));
+            }
+            Err(e) => warn!("failed to remove unused file {}: {}", path.display(), e),
+        }
+    }
+    removed
+}
+
+fn remove_empty_parents(storage_dir: &Path, path: &Path) {
+    let mut dir = path.parent();
+    while let Some(d) = dir
+        && d != storage_dir
+        && d.starts_with(storage_dir)
+        && std::fs::remove_dir(d).is_ok()
+    {
+        dir = d.parent();
+    }
+}
@@ -4,11 +4,11 @@
 //! - Used by core logic only
 //! - gRPC / HTTP must convert into these types
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
-use crate::sync;
+use crate::{config::config::DisplayTimeConfig, health, integrity, retention, sync};
 
 /// ===============================
 /// 基础 DTO
@@ -18,7 +18,32 @@ use crate::sync;
 pub struct FileInfoDto {
     pub filename: String,
     pub url: String,
-    pub last_modified: String,
+    /// 本地文件修改时间，未知时为 None
+    pub last_modified: Option<TimestampDto>,
+}
+
+/// 时间：unix 秒；配置了 `[display_time]` 时附带按其时区格式化的文本
+#[derive(Debug, Clone)]
+pub struct TimestampDto {
+    pub unix: u64,
+    pub display: Option<String>,
+}
+
+impl TimestampDto {
+    pub fn new(t: SystemTime, display: Option<&DisplayTimeConfig>) -> Self {
+        let unix = t
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self::from_unix(unix, display)
+    }
+
+    pub fn from_unix(unix: u64, display: Option<&DisplayTimeConfig>) -> Self {
+        Self {
+            unix,
+            display: display.and_then(|d| d.format(unix)),
+        }
+    }
 }
 
 /// ===============================
@@ -37,6 +62,7 @@ pub struct ConfigSnapshot {
     pub download_concurrency: usize,
     pub download_retry: usize,
     pub retry_base_delay_ms: u64,
+    pub enable_listing: bool,
 }
 
 /// 用于“部分更新”的输入模型
@@ -63,6 +89,19 @@ pub struct UpdateConfigInput {
     pub download_concurrency: Option<u32>,
     pub download_retry: Option<u32>,
     pub retry_base_delay_ms: Option<u32>,
+    pub enable_listing: Option<bool>,
+    /// 随配置修订记录的说明
+    pub comment: Option<String>,
+}
+
+/// 一个配置修订（不含文件内容）
+#[derive(Debug, Clone)]
+pub struct ConfigRevisionDto {
+    pub revision: u64,
+    pub time: TimestampDto,
+    /// startup / reload / update_config / update_files / rollback
+    pub source: String,
+    pub comment: Option<String>,
 }
 
 /// ===============================
@@ -73,6 +112,8 @@ pub struct UpdateConfigInput {
 pub struct FileItemInput {
     pub filename: String,
     pub path: String,
+    /// 备用上游，按优先级排列
+    pub mirrors: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -81,6 +122,331 @@ pub struct UpdateFilesInput {
     pub remove_files: Vec<String>,
     pub replace_all: bool,
     pub new_files: Vec<FileItemInput>,
+    /// 随配置修订记录的说明
+    pub comment: Option<String>,
+}
+
+/// ===============================
+/// Proxy cache
+/// ===============================
+
+#[derive(Debug, Clone)]
+pub struct PrefetchInput {
+    /// files.toml 中的本地路径
+    pub names: Vec<String>,
+    /// 跳过时段限速与流量预算
+    pub ignore_rate_limits: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrefetchItemDto {
+    pub name: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// 单个条目的同步结果
+#[derive(Debug, Clone)]
+pub struct SyncFileResult {
+    pub name: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    /// 本地内容是否有变化
+    pub updated: bool,
+    pub sha256: Option<String>,
+}
+
+/// 分组的同步结果
+#[derive(Debug, Clone)]
+pub struct SyncGroupResult {
+    pub group: String,
+    /// 按路径排序
+    pub files: Vec<SyncFileResult>,
+}
+
+/// ApplyUpdate 的结果
+#[derive(Debug, Clone)]
+pub struct ApplyUpdateDto {
+    /// 发布文件的 sha256
+    pub sha256: String,
+    /// 与当前程序相同，无需更新
+    pub up_to_date: bool,
+    /// 新程序 `--version` 的输出
+    pub version: Option<String>,
+    /// 已替换程序，即将以新版本重新执行
+    pub restarting: bool,
+}
+
+/// RunRetention 的结果
+#[derive(Debug, Clone)]
+pub struct RetentionReportDto {
+    pub dry_run: bool,
+    pub removed: Vec<RetentionItemDto>,
+    /// 已删除（dry run 时为将删除）的字节数
+    pub freed_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetentionItemDto {
+    /// 存储目录下的相对路径
+    pub path: String,
+    /// version_age / version_count / version_bytes / orphaned_meta / stale_partial
+    pub reason: String,
+    pub size: u64,
+}
+
+impl From<retention::Report> for RetentionReportDto {
+    fn from(r: retention::Report) -> Self {
+        Self {
+            dry_run: r.dry_run,
+            removed: r
+                .removed
+                .into_iter()
+                .map(|item| RetentionItemDto {
+                    path: item.path,
+                    reason: item.reason.as_str().to_string(),
+                    size: item.size,
+                })
+                .collect(),
+            freed_bytes: r.freed_bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PurgeCacheInput {
+    /// 本地路径 / 上游 URL，支持 glob
+    pub pattern: String,
+    /// 清除后立即回源预热
+    pub prewarm: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PurgeCacheResult {
+    pub purged: Vec<String>,
+    pub prewarmed: Vec<String>,
+}
+
+/// ===============================
+/// Metrics
+/// ===============================
+
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    /// 反向代理缓存：正在回源的对象数
+    pub coalesce_inflight: u64,
+    /// 反向代理缓存：当前等待回源结果的请求数
+    pub coalesce_waiting: u64,
+    /// 反向代理缓存：累计被合并的请求数
+    pub coalesce_waiters_total: u64,
+    /// 反向代理缓存：累计因等待者超限被拒绝（503）的请求数
+    pub coalesce_rejected_total: u64,
+    /// 各上游主机今日下载量
+    pub origin_bandwidth: Vec<OriginBandwidthDto>,
+    /// 各子系统健康状态
+    pub subsystems: Vec<SubsystemHealthDto>,
+}
+
+/// ===============================
+/// Health
+/// ===============================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStateDto {
+    Ok,
+    Degraded,
+    Failed,
+}
+
+impl From<health::HealthState> for HealthStateDto {
+    fn from(v: health::HealthState) -> Self {
+        match v {
+            health::HealthState::Ok => HealthStateDto::Ok,
+            health::HealthState::Degraded => HealthStateDto::Degraded,
+            health::HealthState::Failed => HealthStateDto::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SubsystemHealthDto {
+    pub name: String,
+    pub state: HealthStateDto,
+    pub reason: Option<String>,
+    /// 进入当前状态的时间
+    pub since: TimestampDto,
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthSnapshot {
+    /// 最差的子系统状态
+    pub overall: HealthStateDto,
+    pub subsystems: Vec<SubsystemHealthDto>,
+}
+
+/// ===============================
+/// Bandwidth
+/// ===============================
+
+#[derive(Debug, Clone)]
+pub struct OriginBandwidthDto {
+    pub host: String,
+    /// 今日（UTC）已下载字节数
+    pub bytes_today: u64,
+    /// 每日预算，None 表示不限
+    pub daily_budget: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BandwidthUsageDto {
+    pub day: String,
+    pub host: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BandwidthSnapshot {
+    pub today: Vec<OriginBandwidthDto>,
+    pub history: Vec<BandwidthUsageDto>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileStatsInput {
+    /// requests（默认）/ bytes / last_access，均从大到小
+    pub sort_by: Option<String>,
+    /// 最多返回的文件数
+    pub limit: Option<u32>,
+    /// 包含存储中从未被下载的文件
+    pub include_unused: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileStatsDto {
+    pub file: String,
+    /// 从磁盘提供的请求数
+    pub requests: u64,
+    /// 返回的字节数
+    pub bytes: u64,
+    /// 最近一次被下载的时间
+    pub last_access: Option<TimestampDto>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransferStatsDto {
+    pub file: String,
+    /// 从磁盘提供的请求数
+    pub requests: u64,
+    /// 其中续传（Range 起点大于 0）的请求数
+    pub resumes: u64,
+    /// 最近一次续传的时间
+    pub last_resume: Option<TimestampDto>,
+}
+
+/// 配置了 max_age_secs 且已过期的文件
+#[derive(Debug, Clone)]
+pub struct StaleFileDto {
+    pub file: String,
+    /// 最近一次同步的时间，从未同步完成时为 None
+    pub fetched_at: Option<TimestampDto>,
+    /// 距最近一次同步的秒数
+    pub age_secs: Option<u64>,
+    pub max_age_secs: u64,
+    /// 过期时下载端口的 /readyz 返回 503
+    pub critical: bool,
+}
+
+/// files.toml 中 [groups] 定义的一个分组
+#[derive(Debug, Clone)]
+pub struct GroupStatusDto {
+    pub name: String,
+    /// 分组中的文件数
+    pub files: u32,
+    /// 其中最近一次同步失败的文件数
+    pub failed: u32,
+    /// 分组自己的同步周期
+    pub interval_secs: Option<u64>,
+    /// 分组最近一次同步完成的时间（整体同步或单独同步分组）
+    pub last_sync: Option<TimestampDto>,
+}
+
+/// VerifyStorage 的结果
+#[derive(Debug, Clone)]
+pub struct VerifyStorageDto {
+    /// 检查的文件数
+    pub checked: u64,
+    /// 其中 meta 没有摘要、只核对了大小的文件数
+    pub unhashed: u64,
+    pub bytes_hashed: u64,
+    pub findings: Vec<StorageFindingDto>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageFindingDto {
+    pub file: String,
+    /// missing / bad_meta / size_mismatch / sha256_mismatch / expected_mismatch / unreadable
+    pub problem: String,
+    pub detail: String,
+}
+
+impl From<integrity::Report> for VerifyStorageDto {
+    fn from(r: integrity::Report) -> Self {
+        Self {
+            checked: r.checked as u64,
+            unhashed: r.unhashed as u64,
+            bytes_hashed: r.bytes_hashed,
+            findings: r
+                .findings
+                .into_iter()
+                .map(|f| StorageFindingDto { file: f.file, problem: f.problem.as_str().to_string(), detail: f.detail })
+                .collect(),
+        }
+    }
+}
+
+/// RepairStorage 的结果
+#[derive(Debug, Clone)]
+pub struct RepairStorageDto {
+    /// 检查的文件数
+    pub checked: u64,
+    /// 检查出问题的文件
+    pub files: Vec<RepairOutcomeDto>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RepairOutcomeDto {
+    pub file: String,
+    /// 检查出的问题，同 [`StorageFindingDto::problem`]
+    pub problem: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    /// 修复后的 sha256
+    pub sha256: Option<String>,
+}
+
+impl From<integrity::RepairReport> for RepairStorageDto {
+    fn from(r: integrity::RepairReport) -> Self {
+        Self {
+            checked: r.checked as u64,
+            files: r
+                .repairs
+                .into_iter()
+                .map(|r| RepairOutcomeDto {
+                    file: r.file,
+                    problem: r.problem.as_str().to_string(),
+                    ok: r.error.is_none(),
+                    error: r.error,
+                    sha256: r.sha256,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// SignUrl 的结果
+#[derive(Debug, Clone)]
+pub struct SignedUrlDto {
+    pub url: String,
+    pub expires: TimestampDto,
 }
 
 /// ===============================
@@ -106,13 +472,115 @@ impl From<&sync::SyncResult> for SyncResultDto {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct SyncRunDto {
+    pub start_time: TimestampDto,
+    pub finish_time: TimestampDto,
+    pub duration_ms: u64,
+    pub result: SyncResultDto,
+    pub error_message: Option<String>,
+    pub total_files: u32,
+    pub failed_files: u32,
+    /// 从上游接收的字节数，含失败的尝试
+    pub bytes_transferred: u64,
+    /// 因 304、续传与 zsync 而无需下载的字节数
+    pub bytes_saved: u64,
+    /// 平均下载速度（字节/秒）
+    pub throughput_bytes_per_sec: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileBackoffDto {
+    pub file: String,
+    /// 连续失败的轮数
+    pub failures: u32,
+    /// 之后还要跳过的轮数
+    pub skip_cycles: u32,
+    pub last_error: String,
+}
+
+/// 手动触发的同步任务
+#[derive(Debug, Clone)]
+pub struct SyncJobDto {
+    pub job_id: String,
+    pub state: SyncJobState,
+    pub created: TimestampDto,
+    pub started: Option<TimestampDto>,
+    pub finished: Option<TimestampDto>,
+    /// 运行中为当前进度，结束后为最终计数
+    pub total_files: u32,
+    pub finished_files: u32,
+    pub failed_files: u32,
+    /// 同步正常结束时的结果
+    pub result: Option<SyncResultDto>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncJobState {
+    Queued,
+    Running,
+    /// 同步流程结束（结果见 result，可能有文件失败）
+    Finished,
+    /// 同步流程出错（如清单拉取失败）
+    Failed,
+}
+
+/// 同步事件（WatchSync / SSE 推送）
+#[derive(Debug, Clone)]
+pub enum SyncEventDto {
+    SyncStarted { total_files: u32 },
+    FileStarted { file: String, total: Option<u64> },
+    FileProgress { file: String, downloaded: u64, total: Option<u64> },
+    FileFinished { file: String },
+    FileError { file: String, error: String },
+    SyncFinished { result: SyncResultDto, error_message: Option<String> },
+}
+
+impl From<sync::SyncEvent> for SyncEventDto {
+    fn from(e: sync::SyncEvent) -> Self {
+        match e {
+            sync::SyncEvent::SyncStarted { total_files } => SyncEventDto::SyncStarted {
+                total_files: total_files as u32,
+            },
+            sync::SyncEvent::FileStarted { file, total } => SyncEventDto::FileStarted { file, total },
+            sync::SyncEvent::FileProgress { file, downloaded, total } => SyncEventDto::FileProgress {
+                file,
+                downloaded,
+                total,
+            },
+            sync::SyncEvent::FileFinished { file } => SyncEventDto::FileFinished { file },
+            sync::SyncEvent::FileError { file, error } => SyncEventDto::FileError { file, error },
+            sync::SyncEvent::SyncFinished { result } => SyncEventDto::SyncFinished {
+                result: SyncResultDto::from(&result),
+                error_message: match result {
+                    sync::SyncResult::Failed(msg) => Some(msg),
+                    _ => None,
+                },
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FileProgressDto {
     pub file: String,
     pub downloaded: u64,
-    pub total: u64,
+    pub total: Option<u64>,
     pub done: bool,
     pub error: Option<String>,
+    pub cancelled: bool,
+}
+
+/// 状态查询参数：默认只返回计数、进行中与失败的文件
+#[derive(Debug, Clone, Default)]
+pub struct StatusQuery {
+    /// 同时返回已完成文件的明细（来自 meta）
+    pub detail: bool,
+    /// 按本地路径过滤（glob）
+    pub filter: Option<String>,
+    /// 最多返回的文件数
+    pub limit: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -122,42 +590,46 @@ pub struct StatusSnapshot {
     pub total_files: u32,
     pub finished_files: u32,
     pub failed_files: u32,
+    /// 存储目录中的数据文件数（不含 meta / tmp）
     pub stored_files: u32,
+    /// 有 meta 且大小一致
+    pub complete_files: u32,
+    /// 大小与 meta 不符，或未完成的 tmp 下载
+    pub partial_files: u32,
+    /// 没有 meta 的文件
+    pub orphaned_files: u32,
 
-    pub start_time: Option<SystemTime>,
-    pub last_sync: Option<SystemTime>,
-    pub last_ok_sync: Option<SystemTime>,
+    pub start_time: Option<TimestampDto>,
+    pub last_sync: Option<TimestampDto>,
+    pub last_ok_sync: Option<TimestampDto>,
 
     pub last_result: SyncResultDto,
     pub error_message: Option<String>,
 
     pub files: HashMap<String, FileProgressDto>,
     pub storage_dir: PathBuf,
-}
 
-/// ===============================
-/// 辅助：时间转换（给 Adapter 用）
-/// ===============================
-
-impl StatusSnapshot {
-    pub fn start_time_unix(&self) -> u64 {
-        self.start_time
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0)
-    }
+    /// 月度预算用尽，同步已暂停
+    pub budget_exhausted: bool,
+    /// 本月累计下载字节数
+    pub month_bytes: u64,
+    pub monthly_budget_bytes: Option<u64>,
+    /// 本月是否已被手动放行
+    pub budget_override: bool,
+    /// 后台任务 panic 次数（scheduler、download 等）
+    pub task_restarts: BTreeMap<String, u32>,
+    /// 周期同步已暂停
+    pub scheduler_paused: bool,
+    /// 连续失败、正在退避的文件
+    pub backoff: Vec<FileBackoffDto>,
+    /// 最近几次同步的流量统计，旧的在前
+    pub history: Vec<SyncRunDto>,
+    /// 配置了 max_age_secs 且已过期的文件
+    pub stale_files: Vec<StaleFileDto>,
+    /// 各分组的文件数、失败数与最近同步时间
+    pub groups: Vec<GroupStatusDto>,
 
-    pub fn last_sync_unix(&self) -> u64 {
-        self.last_sync
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0)
-    }
-
-    pub fn last_ok_sync_unix(&self) -> u64 {
-        self.last_ok_sync
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0)
-    }
+    /// 整体健康状态（最差的子系统）
+    pub health: HealthStateDto,
+    pub subsystems: Vec<SubsystemHealthDto>,
 }
@@ -6,6 +6,15 @@ pub enum CoreError {
     #[error("not found: {0}")]
     NotFound(String),
 
+    #[error("unauthenticated: {0}")]
+    Unauthenticated(String),
+
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("conflict: {0}")]
+    Conflict(String),
+
     #[error("internal error: {0}")]
     Internal(String),
 }
@@ -0,0 +1,137 @@
+//! 手动触发的同步任务（TriggerSync）
+//!
+//! 触发后立即返回任务 ID，任务与周期同步依次执行；内存中只保留最近 [`HISTORY`] 个任务，
+//! 重启后清空。
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use log::error;
+use tokio::sync::OwnedMutexGuard;
+
+use crate::config::ConfigCenter;
+use crate::sync;
+
+use super::dto::{SyncJobDto, SyncJobState, SyncResultDto, TimestampDto};
+
+/// 保留的任务数，超出时丢弃最早的已结束任务
+const HISTORY: usize = 32;
+
+struct Job {
+    id: String,
+    state: SyncJobState,
+    created: SystemTime,
+    started: Option<SystemTime>,
+    finished: Option<SystemTime>,
+    total_files: u32,
+    finished_files: u32,
+    failed_files: u32,
+    result: Option<SyncResultDto>,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct SyncJobs {
+    jobs: Mutex<VecDeque<Job>>,
+}
+
+impl SyncJobs {
+    /// 排队一次同步，返回任务 ID；`running` 为调用方已持有的同步锁
+    pub fn submit(self: &Arc<Self>, cc: Arc<ConfigCenter>, running: Option<OwnedMutexGuard<()>>) -> String {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            if jobs.len() >= HISTORY
+                && let Some(pos) = jobs.iter().position(|j| j.finished.is_some())
+            {
+                jobs.remove(pos);
+            }
+            jobs.push_back(Job {
+                id: id.clone(),
+                state: SyncJobState::Queued,
+                created: SystemTime::now(),
+                started: None,
+                finished: None,
+                total_files: 0,
+                finished_files: 0,
+                failed_files: 0,
+                result: None,
+                error: None,
+            });
+        }
+
+        let jobs = self.clone();
+        let job_id = id.clone();
+        tokio::spawn(async move {
+            // 与周期同步共用一把锁，前一轮结束后才开始
+            let _running = match running {
+                Some(guard) => guard,
+                None => cc.lock_sync().await,
+            };
+            jobs.update(&job_id, |j| {
+                j.state = SyncJobState::Running;
+                j.started = Some(SystemTime::now());
+            });
+
+            let res = sync::sync_once(cc.clone()).await;
+            let status = cc.sync_status().await;
+            jobs.update(&job_id, |j| {
+                j.finished = Some(SystemTime::now());
+                j.total_files = status.total_files as u32;
+                j.finished_files = status.finished_files as u32;
+                j.failed_files = status.failed_files as u32;
+                match res {
+                    Ok(()) => {
+                        j.state = SyncJobState::Finished;
+                        j.result = Some(SyncResultDto::from(&status.last_result));
+                        if let sync::SyncResult::Failed(msg) = &status.last_result {
+                            j.error = Some(msg.clone());
+                        }
+                    }
+                    Err(e) => {
+                        error!("Sync job {} failed: {:#}", job_id, e);
+                        j.state = SyncJobState::Failed;
+                        j.error = Some(format!("{:#}", e));
+                    }
+                }
+            });
+        });
+        id
+    }
+
+    /// 查询任务；运行中的任务带上当前同步进度
+    pub async fn get(&self, cc: &ConfigCenter, id: &str) -> Option<SyncJobDto> {
+        let display = cc.config().await.display_time.clone();
+        let stamp = |t: SystemTime| TimestampDto::new(t, display.as_ref());
+        let mut job = {
+            let jobs = self.jobs.lock().unwrap();
+            let j = jobs.iter().find(|j| j.id == id)?;
+            SyncJobDto {
+                job_id: j.id.clone(),
+                state: j.state,
+                created: stamp(j.created),
+                started: j.started.map(stamp),
+                finished: j.finished.map(stamp),
+                total_files: j.total_files,
+                finished_files: j.finished_files,
+                failed_files: j.failed_files,
+                result: j.result,
+                error: j.error.clone(),
+            }
+        };
+        if job.state == SyncJobState::Running {
+            let status = cc.sync_status().await;
+            job.total_files = status.total_files as u32;
+            job.finished_files = status.finished_files as u32;
+            job.failed_files = status.failed_files as u32;
+        }
+        Some(job)
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.lock().unwrap().iter_mut().find(|j| j.id == id) {
+            f(job);
+        }
+    }
+}
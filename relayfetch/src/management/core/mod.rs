@@ -1,21 +1,31 @@
 mod error;
 pub use error::CoreError;
 
-mod utils;
-use utils::read_file_timestamp;
+pub mod auth;
 
 pub mod dto;
+
+mod clean;
+mod jobs;
+mod update;
 use std::{sync::Arc};
 use std::{
     collections::HashMap,
+    ops::Bound,
+    time::Duration,
     net::ToSocketAddrs,
 };
 
+use axum::http::HeaderMap;
+use futures::Stream;
 use log::{error, info};
-use walkdir::WalkDir;
+use tokio_stream::{StreamExt, wrappers::BroadcastStream};
 
 use crate::{
-    config::ConfigCenter,
+    acl, signed_url,
+    config::{ConfigCenter, config::{Bind, DisplayTimeConfig, UNIX_PREFIX}, file::FileSource, revisions},
+    notify::Notification,
+    proxy_cache::ProxyCache,
     management::core::{
         dto::*,
     },
@@ -25,11 +35,40 @@ use crate::{
 #[derive(Clone)]
 pub struct ManagementCore {
     cc: Arc<ConfigCenter>,
+    proxy_cache: Arc<ProxyCache>,
+    jobs: Arc<jobs::SyncJobs>,
 }
 
 impl ManagementCore {
-    pub fn new(cc: Arc<ConfigCenter>) -> Self {
-        Self { cc }
+    pub fn new(cc: Arc<ConfigCenter>, proxy_cache: Arc<ProxyCache>) -> Self {
+        Self { cc, proxy_cache, jobs: Arc::default() }
+    }
+
+    /* =========================
+     * 鉴权
+     * ========================= */
+
+    /// 校验调用方 token 是否允许访问 endpoint（snake_case 接口名）
+    pub async fn authorize(&self, token: Option<&str>, endpoint: &str) -> Result<(), CoreError> {
+        let cfg = self.cc.config().await;
+        auth::check(&cfg.management_tokens, token, endpoint).inspect_err(|e| {
+            log::warn!("Management auth rejected {}: {}", endpoint, e);
+        })
+    }
+
+    /// 按 `[access] management` 校验客户端地址，先于 token 检查
+    pub async fn authorize_client(&self, peer: Option<acl::Peer>, headers: &HeaderMap) -> Result<(), CoreError> {
+        let cfg = self.cc.config().await;
+        let access = &cfg.access;
+        if access.management.is_empty() {
+            return Ok(());
+        }
+        let client = acl::client(peer, headers, &access.trusted_proxies);
+        if acl::allowed(&access.management, &client) {
+            return Ok(());
+        }
+        log::warn!("Management access denied for {}", client);
+        Err(CoreError::PermissionDenied(format!("client {} is not allowed", client)))
     }
 
     /* =========================
@@ -46,77 +85,541 @@ impl ManagementCore {
         Ok(())
     }
 
-    pub async fn trigger_sync(&self) -> Result<(), CoreError> {
-        info!("Triggering immediate sync...");
-        sync::sync_once(self.cc.clone()).await
+    /// 排队一次同步并立即返回任务 ID，进度与结果见 [`Self::get_sync_job`]；
+    /// `reject_if_running` 时已有同步在进行则直接报错，不排队
+    pub async fn trigger_sync(&self, reject_if_running: bool) -> Result<String, CoreError> {
+        let running = if reject_if_running {
+            Some(self.lock_sync_now()?)
+        } else {
+            None
+        };
+        let job_id = self.jobs.submit(self.cc.clone(), running);
+        info!("Queued sync job {}", job_id);
+        Ok(job_id)
+    }
+
+    pub async fn get_sync_job(&self, job_id: &str) -> Result<SyncJobDto, CoreError> {
+        self.jobs
+            .get(&self.cc, job_id)
+            .await
+            .ok_or_else(|| CoreError::NotFound(format!("sync job {}", job_id)))
+    }
+
+    /// 中止进行中的同步；没有同步在进行时返回 false
+    pub async fn cancel_sync(&self) -> Result<bool, CoreError> {
+        let cancelled = self.cc.cancel_sync().await;
+        if cancelled {
+            info!("Cancelling running sync...");
+        }
+        Ok(cancelled)
+    }
+
+    /// 清除失败退避（`file` 为 None 时清除全部），被清除的文件下一轮同步立即重试
+    pub async fn reset_backoff(&self, file: Option<String>) -> Result<Vec<String>, CoreError> {
+        let reset = self.cc.reset_backoff(file.as_deref()).await;
+        info!("Reset failure backoff for {:?}", reset);
+        Ok(reset)
+    }
+
+    /// 校验镜像的 relayfetch 发布文件，替换当前程序后以相同参数重新执行；`dry_run` 只做校验。
+    /// 与同步共用锁，同步进行中时报 Conflict
+    pub async fn apply_update(&self, dry_run: bool) -> Result<ApplyUpdateDto, CoreError> {
+        let cfg = self.cc.config().await.clone();
+        let Some(update) = cfg.self_update.clone() else {
+            return Err(CoreError::InvalidArgument("self_update is not configured".into()));
+        };
+        let running = self.lock_sync_now()?;
+
+        let staged = match update::stage(&cfg, &update).await {
+            Ok(update::Checked::Staged(staged)) => staged,
+            Ok(update::Checked::UpToDate { sha256 }) => {
+                info!("Release {} is already running", sha256);
+                return Ok(ApplyUpdateDto { sha256, up_to_date: true, version: None, restarting: false });
+            }
+            Err(e) => {
+                error!("Self update rejected: {}", e);
+                return Err(e);
+            }
+        };
+        let (sha256, version) = (staged.sha256.clone(), staged.version.clone());
+        if dry_run {
+            info!("Release {} ({}) verified", sha256, version);
+            staged.discard().await;
+            return Ok(ApplyUpdateDto { sha256, up_to_date: false, version: Some(version), restarting: false });
+        }
+
+        let exe = staged.install()?;
+        info!("Installed release {} ({}), restarting...", sha256, version);
+        let cc = self.cc.clone();
+        tokio::spawn(async move {
+            // 先让本次响应发出
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            if let Err(e) = cc.bandwidth().flush() {
+                error!("failed to persist bandwidth usage: {e:?}");
+            }
+            if let Err(e) = cc.access().flush() {
+                error!("failed to persist access records: {e:?}");
+            }
+            let e = update::exec(&exe);
+            error!("Failed to restart into the new release, it takes effect on next start: {}", e);
+            drop(running);
+        });
+        Ok(ApplyUpdateDto { sha256, up_to_date: false, version: Some(version), restarting: true })
+    }
+
+    /// 立即同步单个条目（条件请求、重试、限速与预算同周期同步）
+    pub async fn sync_file(&self, name: String) -> Result<SyncFileResult, CoreError> {
+        let rel = std::path::Path::new(&name);
+        if name.is_empty() || rel.components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+            return Err(CoreError::InvalidArgument(format!("invalid file name: {}", name)));
+        }
+        let source = sync::find_source(&self.cc, &name)
+            .await
+            .ok_or_else(|| CoreError::NotFound(name.clone()))?;
+
+        let _running = self.lock_sync_now()?;
+        info!("Syncing {}...", name);
+        let outcome = sync::sync_file(self.cc.clone(), name, source)
+            .await
             .map_err(|e| {
-                error!("Failed to trigger sync: {}", e);
+                error!("Failed to sync file: {}", e);
                 CoreError::Internal(e.to_string())
             })?;
-        Ok(())
+
+        Ok(sync_file_result(outcome))
+    }
+
+    /// 立即同步一个分组
+    pub async fn sync_group(&self, name: String) -> Result<SyncGroupResult, CoreError> {
+        if !self.cc.files().await.groups.contains_key(&name) {
+            return Err(CoreError::NotFound(name));
+        }
+
+        let _running = self.lock_sync_now()?;
+        info!("Syncing group {}...", name);
+        let outcomes = sync::sync_group(self.cc.clone(), name.clone())
+            .await
+            .map_err(|e| {
+                error!("Failed to sync group: {}", e);
+                CoreError::Internal(e.to_string())
+            })?;
+
+        Ok(SyncGroupResult {
+            group: name,
+            files: outcomes.into_iter().map(sync_file_result).collect(),
+        })
+    }
+
+    /// 立即下载指定条目，可跳过限速与预算
+    pub async fn prefetch(&self, input: PrefetchInput) -> Result<Vec<PrefetchItemDto>, CoreError> {
+        if input.names.is_empty() {
+            return Err(CoreError::InvalidArgument("names must not be empty".into()));
+        }
+
+        let entries = {
+            let files = self.cc.files().await;
+            input
+                .names
+                .iter()
+                .map(|name| {
+                    files
+                        .files
+                        .get(name)
+                        .map(|source| (name.clone(), source.clone()))
+                        .ok_or_else(|| CoreError::NotFound(name.clone()))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let _running = self.lock_sync_now()?;
+        info!("Prefetching {:?}...", input.names);
+        let outcomes = sync::prefetch(self.cc.clone(), entries, input.ignore_rate_limits)
+            .await
+            .map_err(|e| {
+                error!("Failed to prefetch: {}", e);
+                CoreError::Internal(e.to_string())
+            })?;
+
+        Ok(outcomes
+            .into_iter()
+            .map(|o| PrefetchItemDto {
+                ok: o.error.is_none(),
+                name: o.file,
+                error: o.error,
+            })
+            .collect())
+    }
+
+    /// 与整轮同步共用的锁，已被占用时报错而不是等待，避免两边同时写同一个 tmp 文件
+    fn lock_sync_now(&self) -> Result<tokio::sync::OwnedMutexGuard<()>, CoreError> {
+        self.cc
+            .try_lock_sync()
+            .ok_or_else(|| CoreError::Conflict("sync already running".into()))
+    }
+
+    /// 订阅同步事件流（跟不上的订阅者会丢弃积压事件）
+    pub fn watch_sync(&self) -> impl Stream<Item = SyncEventDto> + Send + use<> {
+        BroadcastStream::new(self.cc.subscribe_events())
+            .filter_map(|r| r.ok().map(SyncEventDto::from))
     }
 
-    /// 清理存储目录中未被配置引用的文件
-    /// 返回被删除的文件名列表
+    /// 按 `[retention]` 清理历史版本、孤立 meta 与长期未完成的下载；`dry_run` 只返回将删除的内容
+    pub async fn run_retention(&self, dry_run: bool) -> Result<RetentionReportDto, CoreError> {
+        let Some(policy) = self.cc.config().await.retention.clone() else {
+            return Err(CoreError::InvalidArgument("retention is not configured".into()));
+        };
+        let report = crate::retention::run(&self.cc, &policy, dry_run)
+            .await
+            .map_err(|e| CoreError::Internal(format!("retention failed: {:#}", e)))?;
+        Ok(report.into())
+    }
+
+    /// 清理存储目录中未被配置引用的文件（递归，按完整相对路径比较）
+    /// 返回被删除文件的相对路径（含 meta）
     /// # Errors
     /// 如果读取存储目录失败则返回错误
     pub async fn clean_unused_files(&self) -> Result<Vec<String>, CoreError> {
         log::info!("Cleaning unused files...");
 
-        let cfg_read = self.cc.config().await;
-        let files_read = self.cc.files().await;
+        let (storage_dir, in_use) = {
+            let cfg = self.cc.config().await;
+            let files = self.cc.files().await;
+            let storage_dir = cfg.storage_dir.clone();
 
-        let storage_dir = &cfg_read.storage_dir;
+            // 远端清单（上次校验通过的副本）与 hub 节点清单中的条目同样是在用的
+            let remote = std::fs::read(sync::manifest::cache_path(&storage_dir))
+                .ok()
+                .and_then(|body| sync::manifest::parse(&body).ok());
+            let peer_files = cfg
+                .upstream_peer
+                .as_ref()
+                .and_then(|peer| sync::peer::cached(peer, &storage_dir))
+                .unwrap_or_default();
 
-        // 配置中声明的“合法文件名集合”
-        let valid_files: std::collections::HashSet<&String> =
-            files_read.files.values().collect();
+            // 条目路径（含各自的 meta）
+            let entries: std::collections::HashSet<String> = files
+                .files
+                .keys()
+                .chain(remote.iter().flat_map(|m| m.files.keys()))
+                .chain(peer_files.keys())
+                .chain(cfg.self_update.as_ref().map(|u| &u.path))
+                .flat_map(|k| {
+                    let meta = std::path::Path::new(k).with_extension("meta");
+                    [k.clone(), meta.to_string_lossy().into_owned()]
+                })
+                .collect();
+            // 目录镜像展开的文件与解包出的文件不在配置中，整个前缀视为在用
+            let prefixes = files
+                .dirs
+                .keys()
+                .chain(remote.iter().flat_map(|m| m.dirs.keys()))
+                .map(|p| p.trim_matches('/').to_string())
+                .chain(files.files.values().flat_map(|s| s.unpack_dirs()).map(str::to_string))
+                .collect();
 
-        let mut removed = Vec::new();
+            let in_use = clean::InUse { files: entries, prefixes, on_demand: cfg.on_demand.clone() };
+            (storage_dir, in_use)
+        };
 
-        let entries = std::fs::read_dir(storage_dir)
-            .map_err(|e| {
-                CoreError::Internal(format!(
-                    "failed to read storage dir {}: {}",
-                    storage_dir.display(),
-                    e
-                ))
-            })?;
+        std::fs::read_dir(&storage_dir).map_err(|e| {
+            CoreError::Internal(format!("failed to read storage dir {}: {}", storage_dir.display(), e))
+        })?;
 
-        for entry in entries {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(e) => {
-                    log::warn!("skip invalid dir entry: {}", e);
-                    continue;
-                }
-            };
+        let dir = storage_dir.clone();
+        let removed = tokio::task::spawn_blocking(move || clean::remove_unused(&dir, &in_use))
+            .await
+            .map_err(|e| CoreError::Internal(format!("cleanup task failed: {}", e)))?;
 
-            let path = entry.path();
-            if !path.is_file() {
-                continue;
+        for (name, path) in &removed {
+            self.cc.storage_index().update(&storage_dir, path);
+            if !name.ends_with(".meta") {
+                self.cc.notify(Notification::deleted(name, path)).await;
             }
+        }
 
-            let filename = match path.file_name().and_then(|s| s.to_str()) {
-                Some(v) => v.to_string(),
-                None => continue,
-            };
-
-            if !valid_files.contains(&filename) {
-                match std::fs::remove_file(&path) {
-                    Ok(_) => removed.push(filename),
-                    Err(e) => {
-                        log::warn!(
-                            "failed to remove unused file {}: {}",
-                            path.display(),
-                            e
-                        );
-                    }
+        Ok(removed.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// 清除反向代理缓存中匹配的对象，可选立即回源预热
+    pub async fn purge_cache(&self, input: PurgeCacheInput) -> Result<PurgeCacheResult, CoreError> {
+        if input.pattern.trim().is_empty() {
+            return Err(CoreError::InvalidArgument("pattern must not be empty".into()));
+        }
+
+        info!("Purging cache objects matching {}", input.pattern);
+        let purged = self
+            .proxy_cache
+            .purge(&input.pattern)
+            .map_err(|e| CoreError::InvalidArgument(e.to_string()))?;
+
+        let mut prewarmed = Vec::new();
+        if input.prewarm {
+            for path in &purged {
+                if self.proxy_cache.prewarm(path).await {
+                    prewarmed.push(path.clone());
+                } else {
+                    log::warn!("prewarm failed for {}", path);
                 }
             }
         }
 
-        Ok(removed)
+        Ok(PurgeCacheResult { purged, prewarmed })
+    }
+
+    /* =========================
+     * Metrics
+     * ========================= */
+
+    pub async fn metrics(&self) -> Result<MetricsSnapshot, CoreError> {
+        let coalesce = self.proxy_cache.coalesce_stats();
+
+        Ok(MetricsSnapshot {
+            coalesce_inflight: coalesce.inflight,
+            coalesce_waiting: coalesce.waiting,
+            coalesce_waiters_total: coalesce.coalesced_total,
+            coalesce_rejected_total: coalesce.rejected_total,
+            origin_bandwidth: self.origin_bandwidth().await,
+            subsystems: self.health().await.subsystems,
+        })
+    }
+
+    /// 各子系统健康状态；/readyz 在整体为 Failed 时返回 503
+    pub async fn health(&self) -> HealthSnapshot {
+        let display = self.cc.config().await.display_time.clone();
+        self.health_with(display.as_ref())
+    }
+
+    fn health_with(&self, display: Option<&DisplayTimeConfig>) -> HealthSnapshot {
+        let health = self.cc.health();
+        HealthSnapshot {
+            overall: health.overall().into(),
+            subsystems: health
+                .snapshot()
+                .into_iter()
+                .map(|(subsystem, h)| SubsystemHealthDto {
+                    name: subsystem.name().to_string(),
+                    state: h.state.into(),
+                    reason: h.reason,
+                    since: TimestampDto::new(h.since, display),
+                })
+                .collect(),
+        }
+    }
+
+    /// 上游流量：今日用量与预算 + 历史
+    pub async fn bandwidth(&self) -> Result<BandwidthSnapshot, CoreError> {
+        let history = self
+            .cc
+            .bandwidth()
+            .history()
+            .into_iter()
+            .map(|u| BandwidthUsageDto {
+                day: u.day,
+                host: u.host,
+                bytes: u.bytes,
+            })
+            .collect();
+
+        Ok(BandwidthSnapshot {
+            today: self.origin_bandwidth().await,
+            history,
+        })
+    }
+
+    /// 下载服务各文件的请求数、返回的字节数与最近一次被下载的时间
+    pub async fn file_stats(&self, input: FileStatsInput) -> Result<Vec<FileStatsDto>, CoreError> {
+        enum SortBy {
+            Requests,
+            Bytes,
+            LastAccess,
+        }
+        let sort_by = match input.sort_by.as_deref().unwrap_or("requests") {
+            "requests" => SortBy::Requests,
+            "bytes" => SortBy::Bytes,
+            "last_access" => SortBy::LastAccess,
+            other => {
+                return Err(CoreError::InvalidArgument(format!(
+                    "invalid sort_by: {} (expected requests / bytes / last_access)",
+                    other
+                )));
+            }
+        };
+
+        let cfg = self.cc.config().await;
+        let display = cfg.display_time.clone();
+        let mut stats: Vec<(String, u64, u64, Option<i64>)> = self
+            .cc
+            .access()
+            .file_stats()
+            .into_iter()
+            .map(|(file, s, last)| (file, s.requests, s.bytes, last))
+            .collect();
+        if input.include_unused {
+            let index = self
+                .cc
+                .storage_index()
+                .files(&cfg.storage_dir, Duration::from_secs(cfg.storage_index_ttl_secs))
+                .await;
+            let used: std::collections::HashSet<String> = stats.iter().map(|s| s.0.clone()).collect();
+            stats.extend(
+                index
+                    .files
+                    .iter()
+                    .filter(|(rel, f)| f.state == crate::storage_index::StoredState::Complete && !used.contains(*rel))
+                    .map(|(rel, _)| (rel.clone(), 0, 0, None)),
+            );
+        }
+        drop(cfg);
+
+        match sort_by {
+            SortBy::Requests => stats.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2))),
+            SortBy::Bytes => stats.sort_by(|a, b| b.2.cmp(&a.2).then(b.1.cmp(&a.1))),
+            SortBy::LastAccess => stats.sort_by_key(|s| std::cmp::Reverse(s.3)),
+        }
+        if let Some(limit) = input.limit {
+            stats.truncate(limit as usize);
+        }
+        Ok(stats
+            .into_iter()
+            .map(|(file, requests, bytes, last)| FileStatsDto {
+                file,
+                requests,
+                bytes,
+                last_access: last
+                    .and_then(|t| u64::try_from(t).ok())
+                    .map(|t| TimestampDto::from_unix(t, display.as_ref())),
+            })
+            .collect())
+    }
+
+    /// 为 `path` 生成签名链接；`ttl_secs` 未指定时为 default_ttl_secs，不能超过 max_ttl_secs
+    pub async fn sign_url(&self, path: String, ttl_secs: Option<u64>) -> Result<SignedUrlDto, CoreError> {
+        let cfg = self.cc.config().await;
+        let Some(signed) = cfg.signed_urls.as_ref() else {
+            return Err(CoreError::InvalidArgument("signed_urls is not configured".into()));
+        };
+        let path = match crate::server::path::joined(path.trim_start_matches('/')) {
+            Ok(path) if !path.is_empty() => path,
+            _ => return Err(CoreError::InvalidArgument(format!("invalid path: {}", path))),
+        };
+        let path = path.as_str();
+        let ttl = ttl_secs.unwrap_or(signed.default_ttl_secs);
+        if ttl == 0 {
+            return Err(CoreError::InvalidArgument("ttl_secs must be positive".into()));
+        }
+        if let Some(max) = signed.max_ttl_secs
+            && ttl > max
+        {
+            return Err(CoreError::InvalidArgument(format!("ttl_secs {} exceeds max_ttl_secs {}", ttl, max)));
+        }
+
+        let expires = signed_url::now() + ttl;
+        let sig = signed_url::sign(&signed.secret, path, expires)
+            .map_err(|e| CoreError::Internal(format!("failed to sign: {}", e)))?;
+        let base = signed
+            .base_url
+            .clone()
+            .unwrap_or_else(|| format!("http://{}:{}", cfg.url, cfg.bind_port));
+        let url = format!(
+            "{}/{}?expires={}&sig={}",
+            base.trim_end_matches('/'),
+            crate::server::path::encode(path),
+            expires,
+            sig
+        );
+        Ok(SignedUrlDto {
+            url,
+            expires: TimestampDto::from_unix(expires, cfg.display_time.as_ref()),
+        })
+    }
+
+    /// 下载服务各文件的请求与续传次数，按续传次数从多到少排列
+    pub async fn transfer_stats(&self, limit: Option<u32>) -> Result<Vec<TransferStatsDto>, CoreError> {
+        let display = self.cc.config().await.display_time.clone();
+        let mut stats: Vec<TransferStatsDto> = self
+            .cc
+            .access()
+            .transfers()
+            .into_iter()
+            .map(|(file, s)| TransferStatsDto {
+                file,
+                requests: s.requests,
+                resumes: s.resumes,
+                last_resume: s
+                    .last_resume
+                    .and_then(|t| u64::try_from(t).ok())
+                    .map(|t| TimestampDto::from_unix(t, display.as_ref())),
+            })
+            .collect();
+        stats.sort_by(|a, b| b.resumes.cmp(&a.resumes).then(b.requests.cmp(&a.requests)));
+        if let Some(limit) = limit {
+            stats.truncate(limit as usize);
+        }
+        Ok(stats)
+    }
+
+    /// files.toml 中配置了 max_age_secs 且已过期的文件，按路径排序
+    pub async fn freshness(&self) -> Result<Vec<StaleFileDto>, CoreError> {
+        let display = self.cc.config().await.display_time.clone();
+        Ok(crate::freshness::stale_files(&self.cc)
+            .await
+            .into_iter()
+            .map(|s| stale_file_dto(s, display.as_ref()))
+            .collect())
+    }
+
+    /// 重新计算已同步文件的摘要，报告缺失或损坏的文件；不请求上游
+    pub async fn verify_storage(&self) -> Result<VerifyStorageDto, CoreError> {
+        Ok(crate::integrity::verify_storage(&self.cc).await.into())
+    }
+
+    /// 检查 `files`（为空时检查全部），重新下载缺失或损坏的文件
+    pub async fn repair_storage(&self, files: Vec<String>) -> Result<RepairStorageDto, CoreError> {
+        if let Some(name) = files.iter().find(|name| {
+            name.is_empty() || std::path::Path::new(name).components().any(|c| !matches!(c, std::path::Component::Normal(_)))
+        }) {
+            return Err(CoreError::InvalidArgument(format!("invalid file name: {}", name)));
+        }
+        let _running = self.lock_sync_now()?;
+        info!("Repairing storage...");
+        crate::integrity::repair(&self.cc, files)
+            .await
+            .map(Into::into)
+            .map_err(|e| CoreError::Internal(format!("repair failed: {:#}", e)))
+    }
+
+    /// 放行（或取消放行）本月的月度预算，放行后同步恢复
+    pub async fn set_budget_override(&self, enabled: bool) -> Result<(), CoreError> {
+        info!("Monthly budget override: {}", enabled);
+        self.cc.bandwidth().set_override(enabled);
+        self.cc.bandwidth().flush().map_err(|e| {
+            error!("Failed to persist budget override: {}", e);
+            CoreError::Internal(e.to_string())
+        })
+    }
+
+    /// 暂停 / 恢复周期同步；返回之前是否已暂停
+    pub async fn set_scheduler_paused(&self, paused: bool) -> Result<bool, CoreError> {
+        info!("Scheduler {}", if paused { "paused" } else { "resumed" });
+        Ok(self.cc.set_scheduler_paused(paused).await)
+    }
+
+    /// 今日有流量或配置了预算的主机
+    async fn origin_bandwidth(&self) -> Vec<OriginBandwidthDto> {
+        let budgets = self.cc.config().await.origin_daily_budget_bytes.clone();
+        let mut usage = self.cc.bandwidth().today();
+        for host in budgets.keys() {
+            usage.entry(host.clone()).or_default();
+        }
+
+        usage
+            .into_iter()
+            .map(|(host, bytes_today)| OriginBandwidthDto {
+                daily_budget: budgets.get(&host).copied(),
+                host,
+                bytes_today,
+            })
+            .collect()
     }
 
     /* =========================
@@ -128,7 +631,7 @@ impl ManagementCore {
 
         Ok(ConfigSnapshot {
             storage_dir: cfg.storage_dir.clone(),
-            bind: cfg.bind.clone(),
+            bind: cfg.bind.to_string(),
             grpc_admin: cfg.grpc_admin.clone(),
             http_admin: cfg.http_admin.clone(),
             proxy: cfg.proxy.clone(),
@@ -137,6 +640,7 @@ impl ManagementCore {
             download_concurrency: cfg.download_concurrency,
             download_retry: cfg.download_retry,
             retry_base_delay_ms: cfg.retry_base_delay_ms,
+            enable_listing: cfg.enable_listing,
         })
     }
 
@@ -169,23 +673,35 @@ impl ManagementCore {
         }
 
         // ================== 3. url（host / hostname / IP） ==================
-        if let Some(ref url) = input.url {
-            if url.is_empty()
+        if let Some(ref url) = input.url
+            && (url.is_empty()
                 || url.contains("://")
                 || url.contains('/')
-                || url.contains(' ')
-            {
-                return Err(CoreError::InvalidArgument(
-                    "url must be a valid host or ip".into(),
-                ));
-            }
+                || url.contains(' '))
+        {
+            return Err(CoreError::InvalidArgument(
+                "url must be a valid host or ip".into(),
+            ));
         }
 
-        // ================== 4. bind（本地监听地址） ==================
+        // ================== 4. bind（本地监听地址，逗号分隔多个） ==================
         if let Some(ref bind) = input.bind {
-            bind.to_socket_addrs().map_err(|_| {
-                CoreError::InvalidArgument("bind must be valid socket addr".into())
-            })?;
+            let addrs = Bind::One(bind.clone()).addrs();
+            if addrs.is_empty() {
+                return Err(CoreError::InvalidArgument("bind must not be empty".into()));
+            }
+            for addr in &addrs {
+                let valid = match addr.strip_prefix(UNIX_PREFIX) {
+                    Some(path) => !path.is_empty(),
+                    None => addr.to_socket_addrs().is_ok(),
+                };
+                if !valid {
+                    return Err(CoreError::InvalidArgument(format!(
+                        "bind must be valid socket addrs or unix:<path>, got {:?}",
+                        addr
+                    )));
+                }
+            }
         }
 
         // ================== 5. admin（gRPC和http 管理地址） ==================
@@ -224,36 +740,37 @@ impl ManagementCore {
         }
 
         // ================== 7. download_concurrency ==================
-        if let Some(c) = input.download_concurrency {
-            if !(1..=64).contains(&c) {
-                return Err(CoreError::InvalidArgument(
-                    "download_concurrency must be 1..=64".into(),
-                ));
-            }
+        if let Some(c) = input.download_concurrency
+            && !(1..=64).contains(&c)
+        {
+            return Err(CoreError::InvalidArgument(
+                "download_concurrency must be 1..=64".into(),
+            ));
         }
 
         // ================== 8. download_retry ==================
-        if let Some(r) = input.download_retry {
-            if r > 10 {
-                return Err(CoreError::InvalidArgument(
-                    "download_retry must <= 10".into(),
-                ));
-            }
+        if let Some(r) = input.download_retry
+            && r > 10
+        {
+            return Err(CoreError::InvalidArgument(
+                "download_retry must <= 10".into(),
+            ));
         }
 
         // ================== 9. retry_base_delay_ms ==================
-        if let Some(d) = input.retry_base_delay_ms {
-            if !(10..=60_000).contains(&d) {
-                return Err(CoreError::InvalidArgument(
-                    "retry_base_delay_ms out of range".into(),
-                ));
-            }
+        if let Some(d) = input.retry_base_delay_ms
+            && !(10..=60_000).contains(&d)
+        {
+            return Err(CoreError::InvalidArgument(
+                "retry_base_delay_ms out of range".into(),
+            ));
         }
 
         /* ---------- 原子更新 ---------- */
 
+        let comment = input.comment.clone();
         self.cc
-            .update_config(|cfg| {
+            .update_config(comment.as_deref(), |cfg| {
                 if let Some(v) = input.interval_secs {
                     cfg.interval_secs = v as u64;
                 }
@@ -264,7 +781,7 @@ impl ManagementCore {
                     cfg.url = v;
                 }
                 if let Some(v) = input.bind {
-                    cfg.bind = v;
+                    cfg.bind = Bind::One(v);
                 }
                 if let Some(v) = input.grpc_admin {
                     cfg.grpc_admin = v;
@@ -284,6 +801,9 @@ impl ManagementCore {
                 if let Some(v) = input.retry_base_delay_ms {
                     cfg.retry_base_delay_ms = v as u64;
                 }
+                if let Some(v) = input.enable_listing {
+                    cfg.enable_listing = v;
+                }
                 Ok(())
             })
             .await.map_err(|e| CoreError::Internal(e.to_string()))?;
@@ -291,59 +811,83 @@ impl ManagementCore {
         Ok(())
     }
 
+    /// 配置修订历史，新的在前
+    pub async fn list_config_revisions(&self) -> Result<Vec<ConfigRevisionDto>, CoreError> {
+        let (storage_dir, display) = {
+            let cfg = self.cc.config().await;
+            (cfg.storage_dir.clone(), cfg.display_time.clone())
+        };
+        let revisions = tokio::task::spawn_blocking(move || revisions::list(&storage_dir))
+            .await
+            .map_err(|e| CoreError::Internal(e.to_string()))?;
+        Ok(revisions
+            .into_iter()
+            .map(|r| ConfigRevisionDto {
+                revision: r.id,
+                time: TimestampDto::from_unix(r.time, display.as_ref()),
+                source: r.source,
+                comment: r.comment,
+            })
+            .collect())
+    }
+
+    /// 把 config.toml 与 files.toml 恢复为某个修订的内容，返回回滚后记录的修订
+    pub async fn rollback_config(&self, revision: u64, comment: Option<String>) -> Result<Option<u64>, CoreError> {
+        let storage_dir = self.cc.config().await.storage_dir.clone();
+        let Some(target) = revisions::load(&storage_dir, revision) else {
+            return Err(CoreError::NotFound(format!("config revision {}", revision)));
+        };
+        info!("Rolling back configuration to revision {}", revision);
+        self.cc
+            .rollback_config(&target, comment.as_deref())
+            .await
+            .map_err(|e| CoreError::Internal(format!("rollback failed: {:#}", e)))
+    }
+
     /* =========================
      * Files
      * ========================= */
 
-    pub async fn list_files(&self) -> Result<Vec<FileInfoDto>, CoreError> {
+    /// 流式列出存储目录中的文件（来自存储索引），按相对路径排序
+    pub async fn list_files_stream(&self) -> impl Stream<Item = FileInfoDto> + Send + use<> {
         let cfg = self.cc.config().await;
-        let storage_dir = cfg.storage_dir.clone();
         let base_url = format!("http://{}:{}", cfg.url, cfg.bind_port);
+        let files = self
+            .cc
+            .storage_index()
+            .files(&cfg.storage_dir, Duration::from_secs(cfg.storage_index_ttl_secs))
+            .await;
+        let display = cfg.display_time.clone();
+        drop(cfg);
 
-        let mut result = Vec::new();
-
-        for entry in WalkDir::new(&storage_dir)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e| e.file_type().is_file())
-        {
-            let path = entry.path();
-
-            // 跳过 .meta 文件
-            if path.extension().and_then(|s| s.to_str()) == Some("meta") {
-                continue;
-            }
-
-            let filename = match path.file_name().and_then(|s| s.to_str()) {
-                Some(v) => v.to_string(),
-                None => continue,
-            };
-
-            // ---------- 读取时间 ----------
-            let last_modified = read_file_timestamp(path)
-                .map(|t| t.to_rfc3339())
-                .unwrap_or_else(|| "unknown".into());
-
-            // ---------- 计算相对路径 URL ----------
-            let relative_path = path
-                .strip_prefix(&storage_dir)
-                .unwrap_or(path)
-                .to_string_lossy()
-                .replace('\\', "/");
-
-            result.push(FileInfoDto {
-                filename,
-                url: format!("{}/{}", base_url, relative_path),
-                last_modified,
-            });
-        }
-
-        Ok(result)
+        // 按键续读，不复制整张表
+        futures::stream::iter(std::iter::successors(
+            files.files.iter().next().map(|(k, v)| (k.clone(), v.last_modified)),
+            move |(prev, _)| {
+                files
+                    .files
+                    .range::<String, _>((Bound::Excluded(prev), Bound::Unbounded))
+                    .next()
+                    .map(|(k, v)| (k.clone(), v.last_modified))
+            },
+        ))
+        .map(move |(relative_path, last_modified)| FileInfoDto {
+            filename: relative_path
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            url: format!("{}/{}", base_url, relative_path),
+            last_modified: last_modified
+                .and_then(|t| u64::try_from(t.timestamp()).ok())
+                .map(|t| TimestampDto::from_unix(t, display.as_ref())),
+        })
     }
 
     pub async fn update_files(&self, input: UpdateFilesInput) -> Result<(), CoreError> {
+        let comment = input.comment.clone();
         self.cc
-            .update_files(|files_cfg| {
+            .update_files(comment.as_deref(), |files_cfg| {
                 if input.replace_all {
                     // 替换整个文件列表
                     files_cfg.files.clear();
@@ -353,7 +897,7 @@ impl ManagementCore {
                                 "filename/path empty".into(),
                             ).into());
                         }
-                        files_cfg.files.insert(f.filename, f.path);
+                        files_cfg.files.insert(f.filename, FileSource::new(f.path, f.mirrors));
                     }
                 } else {
                     // 删除指定文件
@@ -367,13 +911,18 @@ impl ManagementCore {
                                 "filename/path empty".into(),
                             ).into());
                         }
-                        files_cfg.files.insert(f.filename, f.path);
+                        files_cfg.files.insert(f.filename, FileSource::new(f.path, f.mirrors));
                     }
                 }
-                Ok(())
+                files_cfg
+                    .validate()
+                    .map_err(|e| CoreError::InvalidArgument(e).into())
             })
             .await
-            .map_err(|e| CoreError::Internal(e.to_string()))?;
+            .map_err(|e| match e.downcast::<CoreError>() {
+                Ok(e) => e,
+                Err(e) => CoreError::Internal(e.to_string()),
+            })?;
 
         Ok(())
     }
@@ -382,46 +931,121 @@ impl ManagementCore {
      * Status
      * ========================= */
 
-    pub async fn status(&self) -> Result<StatusSnapshot, CoreError> {
+    pub async fn status(&self, query: StatusQuery) -> Result<StatusSnapshot, CoreError> {
+        let matcher = match &query.filter {
+            Some(pattern) if !pattern.is_empty() => Some(
+                globset::Glob::new(pattern)
+                    .map_err(|e| CoreError::InvalidArgument(e.to_string()))?
+                    .compile_matcher(),
+            ),
+            _ => None,
+        };
+        let wanted = |name: &str| matcher.as_ref().is_none_or(|m| m.is_match(name));
+
         // 获取配置和同步状态的快照（使用只读锁）
         let cfg = self.cc.config().await;
         let status = self.cc.sync_status().await;
 
-        // 磁盘物理文件扫描
-        let stored_files = WalkDir::new(&cfg.storage_dir)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e| e.file_type().is_file())
-            .count() as u32
-            / 2;
+        // 磁盘文件统计（来自存储索引，按 meta 判定状态）
+        let index = self
+            .cc
+            .storage_index()
+            .files(&cfg.storage_dir, Duration::from_secs(cfg.storage_index_ttl_secs))
+            .await;
+        let stored_files = index.files.len() as u32;
+        let counts = index.counts();
 
-        let files = status
+        // 内存中只有进行中与失败的文件
+        let mut files = status
             .files
             .iter()
+            .filter(|(k, _)| wanted(k))
             .map(|(k, v)| {
                 (
                     k.clone(),
                     FileProgressDto {
                         file: v.file.clone(),
                         downloaded: v.downloaded,
-                        total: v.total.unwrap_or(0),
+                        total: v.total,
                         done: v.done,
                         error: v.error.clone(),
+                        cancelled: v.cancelled,
                     },
                 )
             })
             .collect::<HashMap<_, _>>();
 
+        // 明细模式：其余条目从 meta 读取
+        if query.detail {
+            let entries = self.cc.files().await;
+            for name in entries.files.keys() {
+                if files.contains_key(name) || !wanted(name) {
+                    continue;
+                }
+                let meta_path = cfg.storage_dir.join(name).with_extension("meta");
+                let meta = sync::meta::load_meta(&meta_path).unwrap_or_default();
+                let total = meta.total_size;
+                files.insert(
+                    name.clone(),
+                    FileProgressDto {
+                        file: name.clone(),
+                        downloaded: if meta.fetched_at.is_some() { total.unwrap_or(0) } else { 0 },
+                        total,
+                        done: meta.fetched_at.is_some(),
+                        error: None,
+                        cancelled: false,
+                    },
+                );
+            }
+        }
+
+        if let Some(limit) = query.limit {
+            let mut names: Vec<_> = files.keys().cloned().collect();
+            names.sort();
+            for name in names.into_iter().skip(limit as usize) {
+                files.remove(&name);
+            }
+        }
+
+        let display = cfg.display_time.as_ref();
+        let stamp = |t: std::time::SystemTime| TimestampDto::new(t, display);
+        let health = self.health_with(display);
+        let stale_files = crate::freshness::stale_files(&self.cc)
+            .await
+            .into_iter()
+            .filter(|s| wanted(&s.file))
+            .map(|s| stale_file_dto(s, display))
+            .collect();
+        let defined = self.cc.files().await.groups.clone();
+        let mut groups = Vec::with_capacity(defined.len());
+        for (name, group) in defined {
+            let entries = sync::group_entries(&self.cc, &name).await;
+            let failed = entries
+                .iter()
+                .filter(|(file, _)| status.files.get(*file).is_some_and(|p| p.error.is_some()))
+                .count();
+            groups.push(GroupStatusDto {
+                files: entries.len() as u32,
+                failed: failed as u32,
+                interval_secs: group.interval_secs,
+                last_sync: status.group_syncs.get(&name).copied().map(stamp),
+                name,
+            });
+        }
+
         Ok(StatusSnapshot {
             is_running: status.running,
             total_files: status.total_files as u32,
             finished_files: status.finished_files as u32,
             failed_files: status.failed_files as u32,
             stored_files,
+            complete_files: counts.complete,
+            partial_files: counts.partial,
+            orphaned_files: counts.orphaned,
 
-            start_time: status.start_time,
-            last_sync: status.last_sync,
-            last_ok_sync: status.last_ok_sync,
+            start_time: status.start_time.map(stamp),
+            last_sync: status.last_sync.map(stamp),
+            last_ok_sync: status.last_ok_sync.map(stamp),
 
             last_result: SyncResultDto::from(&status.last_result),
             error_message: match &status.last_result {
@@ -431,6 +1055,67 @@ impl ManagementCore {
 
             files,
             storage_dir: cfg.storage_dir.clone(),
+
+            budget_exhausted: self.cc.bandwidth().monthly_exhausted(cfg.monthly_budget_bytes),
+            month_bytes: self.cc.bandwidth().month_total(),
+            monthly_budget_bytes: cfg.monthly_budget_bytes,
+            budget_override: self.cc.bandwidth().override_active(),
+            task_restarts: self.cc.restarts().snapshot(),
+            scheduler_paused: status.scheduler_paused,
+            backoff: status
+                .backoff
+                .iter()
+                .map(|(file, b)| FileBackoffDto {
+                    file: file.clone(),
+                    failures: b.failures,
+                    skip_cycles: b.skip_cycles,
+                    last_error: b.last_error.clone(),
+                })
+                .collect(),
+            history: status
+                .history
+                .iter()
+                .map(|run| SyncRunDto {
+                    start_time: stamp(run.started),
+                    finish_time: stamp(run.finished),
+                    duration_ms: run.duration().as_millis() as u64,
+                    result: SyncResultDto::from(&run.result),
+                    error_message: match &run.result {
+                        sync::SyncResult::Failed(msg) => Some(msg.clone()),
+                        _ => None,
+                    },
+                    total_files: run.total_files as u32,
+                    failed_files: run.failed_files as u32,
+                    bytes_transferred: run.bytes_transferred,
+                    bytes_saved: run.bytes_saved,
+                    throughput_bytes_per_sec: run.throughput(),
+                })
+                .collect(),
+            stale_files,
+            groups,
+
+            health: health.overall,
+            subsystems: health.subsystems,
         })
     }
 }
+
+fn sync_file_result(outcome: sync::FileSyncOutcome) -> SyncFileResult {
+    SyncFileResult {
+        ok: outcome.error.is_none(),
+        name: outcome.file,
+        error: outcome.error,
+        updated: outcome.updated,
+        sha256: outcome.sha256,
+    }
+}
+
+fn stale_file_dto(s: crate::freshness::Stale, display: Option<&DisplayTimeConfig>) -> StaleFileDto {
+    StaleFileDto {
+        file: s.file,
+        fetched_at: s.fetched_at.map(|t| TimestampDto::from_unix(t, display)),
+        age_secs: s.age_secs,
+        max_age_secs: s.max_age_secs,
+        critical: s.critical,
+    }
+}
@@ -0,0 +1,154 @@
+//! 自更新（ApplyUpdate）
+//!
+//! `[self_update]` 的发布文件随同步镜像到存储目录。应用时依次校验：meta 中记录的 sha256、
+//! 发布签名、新程序能以 `--version` 正常运行；全部通过后替换当前程序（旧版本保留为
+//! `<程序>.old`），再以相同参数重新执行。
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::config::{Config, SelfUpdateConfig};
+use crate::sync::{self, manifest, meta::load_meta};
+
+use super::CoreError;
+
+/// `--version` 试运行的超时
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 已通过校验、等待替换的新程序
+pub struct Staged {
+    pub sha256: String,
+    /// 新程序 `--version` 的输出
+    pub version: String,
+    exe: PathBuf,
+    staged: PathBuf,
+}
+
+pub enum Checked {
+    /// 发布文件与当前程序相同
+    UpToDate { sha256: String },
+    Staged(Staged),
+}
+
+/// 校验镜像的发布文件，与当前程序不同时放到程序旁（`<程序>.new`）并试运行
+pub async fn stage(cfg: &Config, update: &SelfUpdateConfig) -> Result<Checked, CoreError> {
+    if !cfg!(unix) {
+        return Err(CoreError::InvalidArgument("self update is only supported on unix".into()));
+    }
+
+    let path = cfg.storage_dir.join(&update.path);
+    let data = match tokio::fs::read(&path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(CoreError::NotFound(format!("{} has not been mirrored yet", update.path)));
+        }
+        Err(e) => return Err(internal("failed to read release", e)),
+    };
+
+    // 与同步时记录的摘要一致，排除下载中途或被改动的文件
+    let sha256 = hex::encode(Sha256::digest(&data));
+    let recorded = load_meta(&path.with_extension("meta")).ok().and_then(|m| m.sha256);
+    if recorded.as_deref() != Some(sha256.as_str()) {
+        return Err(CoreError::Conflict(format!("{} does not match its recorded sha256", update.path)));
+    }
+
+    let client = sync::build_client(cfg).map_err(|e| internal("failed to build client", e))?;
+    let sig_url = update
+        .signature_url
+        .clone()
+        .unwrap_or_else(|| format!("{}.sig", update.url));
+    let signature = manifest::get(&client, &sig_url)
+        .await
+        .map_err(|e| internal("failed to fetch release signature", e))?;
+    manifest::verify_signature(&update.public_key, &data, &manifest::decode_signature(signature))
+        .map_err(|e| CoreError::Conflict(format!("release signature: {:#}", e)))?;
+
+    let exe = std::env::current_exe().map_err(|e| internal("failed to locate current executable", e))?;
+    let current = tokio::fs::read(&exe).await.map_err(|e| internal("failed to read current executable", e))?;
+    if hex::encode(Sha256::digest(&current)) == sha256 {
+        return Ok(Checked::UpToDate { sha256 });
+    }
+
+    let staged = sibling(&exe, "new");
+    write_executable(&staged, &data)
+        .await
+        .map_err(|e| internal("failed to stage release", e))?;
+    let version = match probe(&staged).await {
+        Ok(version) => version,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&staged).await;
+            return Err(CoreError::Conflict(format!("new binary failed to run: {}", e)));
+        }
+    };
+
+    Ok(Checked::Staged(Staged { sha256, version, exe, staged }))
+}
+
+impl Staged {
+    /// 放弃（dry run）
+    pub async fn discard(self) {
+        let _ = tokio::fs::remove_file(&self.staged).await;
+    }
+
+    /// 替换当前程序，旧版本保留为 `<程序>.old`；返回替换后的程序路径
+    pub fn install(self) -> Result<PathBuf, CoreError> {
+        let old = sibling(&self.exe, "old");
+        if let Err(e) = std::fs::rename(&self.exe, &old) {
+            let _ = std::fs::remove_file(&self.staged);
+            return Err(internal("failed to back up current executable", e));
+        }
+        if let Err(e) = std::fs::rename(&self.staged, &self.exe) {
+            let _ = std::fs::rename(&old, &self.exe);
+            return Err(internal("failed to install release", e));
+        }
+        Ok(self.exe)
+    }
+}
+
+/// 以当前参数重新执行程序；成功时不返回
+#[cfg(unix)]
+pub fn exec(exe: &Path) -> std::io::Error {
+    use std::os::unix::process::CommandExt;
+    std::process::Command::new(exe).args(std::env::args_os().skip(1)).exec()
+}
+
+#[cfg(not(unix))]
+pub fn exec(_exe: &Path) -> std::io::Error {
+    std::io::Error::from(std::io::ErrorKind::Unsupported)
+}
+
+/// 运行 `<新程序> --version`，要求正常退出且输出以 relayfetch 开头
+async fn probe(exe: &Path) -> Result<String, String> {
+    let run = tokio::process::Command::new(exe).arg("--version").kill_on_drop(true).output();
+    let output = tokio::time::timeout(PROBE_TIMEOUT, run)
+        .await
+        .map_err(|_| "timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || !version.starts_with("relayfetch") {
+        return Err(format!("unexpected --version output {:?} ({})", version, output.status));
+    }
+    Ok(version)
+}
+
+async fn write_executable(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    tokio::fs::write(path, data).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+    Ok(())
+}
+
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", suffix));
+    exe.with_file_name(name)
+}
+
+fn internal(what: &str, e: impl std::fmt::Display) -> CoreError {
+    CoreError::Internal(format!("{}: {:#}", what, e))
+}
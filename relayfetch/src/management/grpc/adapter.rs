@@ -12,16 +12,30 @@ use crate::management::grpc::management_proto;
 use management_proto::{
     FileInfo,
     FileItem,
+    GetBandwidthResponse,
+    GetMetricsResponse,
+    PrefetchRequest,
+    PrefetchResponse,
+    PurgeCacheRequest,
+    PurgeCacheResponse,
+    SyncFileResponse,
     UpdateConfigRequest,
     UpdateFilesRequest,
 };
 
 use dto::{
+    BandwidthSnapshot,
     FileInfoDto,
     FileItemInput,
     StatusSnapshot,
     SyncResultDto,
+    SyncEventDto,
     FileProgressDto,
+    HealthStateDto,
+    MetricsSnapshot,
+    SubsystemHealthDto,
+    PurgeCacheInput,
+    PurgeCacheResult,
     UpdateConfigInput,
     UpdateFilesInput,
 };
@@ -42,36 +56,111 @@ impl From<SyncResultDto> for management_proto::SyncResult {
     }
 }
 
+impl From<HealthStateDto> for management_proto::HealthState {
+    fn from(v: HealthStateDto) -> Self {
+        match v {
+            HealthStateDto::Ok => Self::Ok,
+            HealthStateDto::Degraded => Self::Degraded,
+            HealthStateDto::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<SubsystemHealthDto> for management_proto::SubsystemHealth {
+    fn from(h: SubsystemHealthDto) -> Self {
+        Self {
+            since_unix: h.since.unix,
+            since_display: h.since.display.unwrap_or_default(),
+            state: management_proto::HealthState::from(h.state) as i32,
+            name: h.name,
+            reason: h.reason.unwrap_or_default(),
+        }
+    }
+}
+
 impl From<FileProgressDto> for management_proto::FileProgress {
     fn from(f: FileProgressDto) -> Self {
         Self {
             file: f.file,
             downloaded: f.downloaded,
-            total: f.total,
+            total: f.total.unwrap_or(0),
             done: f.done,
             error: f.error.unwrap_or_default(),
+            cancelled: f.cancelled,
+        }
+    }
+}
+
+impl From<SyncEventDto> for management_proto::SyncEvent {
+    fn from(e: SyncEventDto) -> Self {
+        use management_proto::SyncEventKind as Kind;
+
+        let mut out = Self::default();
+        match e {
+            SyncEventDto::SyncStarted { total_files } => {
+                out.kind = Kind::SyncStarted as i32;
+                out.total = total_files as u64;
+            }
+            SyncEventDto::FileStarted { file, total } => {
+                out.kind = Kind::FileStarted as i32;
+                out.file = file;
+                out.total = total.unwrap_or(0);
+            }
+            SyncEventDto::FileProgress { file, downloaded, total } => {
+                out.kind = Kind::FileProgress as i32;
+                out.file = file;
+                out.downloaded = downloaded;
+                out.total = total.unwrap_or(0);
+            }
+            SyncEventDto::FileFinished { file } => {
+                out.kind = Kind::FileFinished as i32;
+                out.file = file;
+            }
+            SyncEventDto::FileError { file, error } => {
+                out.kind = Kind::FileError as i32;
+                out.file = file;
+                out.error = error;
+            }
+            SyncEventDto::SyncFinished { result, error_message } => {
+                out.kind = Kind::SyncFinished as i32;
+                out.result = management_proto::SyncResult::from(result) as i32;
+                out.error = error_message.unwrap_or_default();
+            }
         }
+        out
     }
 }
 
 impl From<StatusSnapshot> for management_proto::StatusResponse {
     fn from(s: StatusSnapshot) -> Self {
-        // helper 优先
-        let start_time_unix = s.start_time_unix();
-        let last_sync_unix = s.last_sync_unix();
-        let last_ok_sync_unix = s.last_ok_sync_unix();
-
         let StatusSnapshot {
             is_running,
             total_files,
             finished_files,
             failed_files,
             stored_files,
+            complete_files,
+            partial_files,
+            orphaned_files,
             last_result,
             error_message,
             files,
             storage_dir,
-            ..
+            budget_exhausted,
+            month_bytes,
+            monthly_budget_bytes,
+            budget_override,
+            task_restarts,
+            scheduler_paused,
+            health,
+            subsystems,
+            backoff,
+            history,
+            stale_files,
+            groups,
+            start_time,
+            last_sync,
+            last_ok_sync,
         } = s;
 
         let files = files
@@ -85,13 +174,279 @@ impl From<StatusSnapshot> for management_proto::StatusResponse {
             finished_files,
             failed_files,
             stored_files,
-            start_time_unix,
-            last_sync_unix,
-            last_ok_sync_unix,
+            complete_files,
+            partial_files,
+            orphaned_files,
+            start_time_unix: unix(&start_time),
+            last_sync_unix: unix(&last_sync),
+            last_ok_sync_unix: unix(&last_ok_sync),
+            start_time_display: display(start_time),
+            last_sync_display: display(last_sync),
+            last_ok_sync_display: display(last_ok_sync),
             last_result: management_proto::SyncResult::from(last_result) as i32,
             error_message: error_message.unwrap_or_default(),
             storage_dir: storage_dir.to_string_lossy().to_string(),
             files,
+            budget_exhausted,
+            month_bytes,
+            monthly_budget_bytes,
+            budget_override,
+            task_restarts: task_restarts.into_iter().collect(),
+            health: management_proto::HealthState::from(health) as i32,
+            subsystems: subsystems.into_iter().map(Into::into).collect(),
+            scheduler_paused,
+            backoff: backoff
+                .into_iter()
+                .map(|b| management_proto::FileBackoff {
+                    file: b.file,
+                    failures: b.failures,
+                    skip_cycles: b.skip_cycles,
+                    last_error: b.last_error,
+                })
+                .collect(),
+            history: history.into_iter().map(Into::into).collect(),
+            stale_files: stale_files.into_iter().map(Into::into).collect(),
+            groups: groups.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<dto::GroupStatusDto> for management_proto::GroupStatus {
+    fn from(g: dto::GroupStatusDto) -> Self {
+        Self {
+            name: g.name,
+            files: g.files,
+            failed: g.failed,
+            interval_secs: g.interval_secs,
+            last_sync_unix: unix(&g.last_sync),
+            last_sync_display: display(g.last_sync),
+        }
+    }
+}
+
+impl From<dto::StaleFileDto> for management_proto::StaleFile {
+    fn from(s: dto::StaleFileDto) -> Self {
+        Self {
+            file: s.file,
+            fetched_at_unix: unix(&s.fetched_at),
+            fetched_at_display: display(s.fetched_at),
+            age_secs: s.age_secs,
+            max_age_secs: s.max_age_secs,
+            critical: s.critical,
+        }
+    }
+}
+
+impl From<dto::SyncRunDto> for management_proto::SyncRun {
+    fn from(r: dto::SyncRunDto) -> Self {
+        Self {
+            start_time_unix: r.start_time.unix,
+            start_time_display: r.start_time.display.unwrap_or_default(),
+            finish_time_unix: r.finish_time.unix,
+            finish_time_display: r.finish_time.display.unwrap_or_default(),
+            duration_ms: r.duration_ms,
+            result: management_proto::SyncResult::from(r.result) as i32,
+            error_message: r.error_message.unwrap_or_default(),
+            total_files: r.total_files,
+            failed_files: r.failed_files,
+            bytes_transferred: r.bytes_transferred,
+            bytes_saved: r.bytes_saved,
+            throughput_bytes_per_sec: r.throughput_bytes_per_sec,
+        }
+    }
+}
+
+impl From<Vec<dto::PrefetchItemDto>> for PrefetchResponse {
+    fn from(items: Vec<dto::PrefetchItemDto>) -> Self {
+        Self {
+            items: items
+                .into_iter()
+                .map(|i| management_proto::PrefetchItem {
+                    name: i.name,
+                    ok: i.ok,
+                    error: i.error.unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<dto::SyncJobDto> for management_proto::GetSyncJobResponse {
+    fn from(j: dto::SyncJobDto) -> Self {
+        use management_proto::SyncJobState as State;
+        Self {
+            created_unix: j.created.unix,
+            started_unix: unix(&j.started),
+            finished_unix: unix(&j.finished),
+            created_display: j.created.display.unwrap_or_default(),
+            started_display: display(j.started),
+            finished_display: display(j.finished),
+            state: match j.state {
+                dto::SyncJobState::Queued => State::Queued,
+                dto::SyncJobState::Running => State::Running,
+                dto::SyncJobState::Finished => State::Finished,
+                dto::SyncJobState::Failed => State::Failed,
+            } as i32,
+            job_id: j.job_id,
+            total_files: j.total_files,
+            finished_files: j.finished_files,
+            failed_files: j.failed_files,
+            result: j.result.map(|r| management_proto::SyncResult::from(r) as i32),
+            error: j.error.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<dto::SyncFileResult> for SyncFileResponse {
+    fn from(r: dto::SyncFileResult) -> Self {
+        Self {
+            name: r.name,
+            ok: r.ok,
+            error: r.error.unwrap_or_default(),
+            updated: r.updated,
+            sha256: r.sha256.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<dto::SyncGroupResult> for management_proto::SyncGroupResponse {
+    fn from(r: dto::SyncGroupResult) -> Self {
+        Self {
+            group: r.group,
+            failed: r.files.iter().filter(|f| !f.ok).count() as u32,
+            files: r.files.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<dto::VerifyStorageDto> for management_proto::VerifyStorageResponse {
+    fn from(r: dto::VerifyStorageDto) -> Self {
+        Self {
+            checked: r.checked,
+            unhashed: r.unhashed,
+            bytes_hashed: r.bytes_hashed,
+            findings: r
+                .findings
+                .into_iter()
+                .map(|f| management_proto::StorageFinding { file: f.file, problem: f.problem, detail: f.detail })
+                .collect(),
+        }
+    }
+}
+
+impl From<dto::RepairStorageDto> for management_proto::RepairStorageResponse {
+    fn from(r: dto::RepairStorageDto) -> Self {
+        Self {
+            checked: r.checked,
+            files: r
+                .files
+                .into_iter()
+                .map(|f| management_proto::RepairOutcome {
+                    file: f.file,
+                    problem: f.problem,
+                    ok: f.ok,
+                    error: f.error.unwrap_or_default(),
+                    sha256: f.sha256.unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<dto::RetentionReportDto> for management_proto::RunRetentionResponse {
+    fn from(r: dto::RetentionReportDto) -> Self {
+        Self {
+            dry_run: r.dry_run,
+            removed: r
+                .removed
+                .into_iter()
+                .map(|item| management_proto::RetentionItem { path: item.path, reason: item.reason, size: item.size })
+                .collect(),
+            freed_bytes: r.freed_bytes,
+        }
+    }
+}
+
+impl From<dto::ConfigRevisionDto> for management_proto::ConfigRevision {
+    fn from(r: dto::ConfigRevisionDto) -> Self {
+        Self {
+            revision: r.revision,
+            time_unix: r.time.unix,
+            time_display: r.time.display.unwrap_or_default(),
+            source: r.source,
+            comment: r.comment.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<dto::ApplyUpdateDto> for management_proto::ApplyUpdateResponse {
+    fn from(r: dto::ApplyUpdateDto) -> Self {
+        Self {
+            sha256: r.sha256,
+            up_to_date: r.up_to_date,
+            version: r.version.unwrap_or_default(),
+            restarting: r.restarting,
+        }
+    }
+}
+
+impl From<PurgeCacheResult> for PurgeCacheResponse {
+    fn from(r: PurgeCacheResult) -> Self {
+        Self {
+            purged: r.purged,
+            prewarmed: r.prewarmed,
+        }
+    }
+}
+
+impl From<MetricsSnapshot> for GetMetricsResponse {
+    fn from(m: MetricsSnapshot) -> Self {
+        Self {
+            coalesce_inflight: m.coalesce_inflight,
+            coalesce_waiting: m.coalesce_waiting,
+            coalesce_waiters_total: m.coalesce_waiters_total,
+            coalesce_rejected_total: m.coalesce_rejected_total,
+            origin_bandwidth: m.origin_bandwidth.into_iter().map(Into::into).collect(),
+            subsystems: m.subsystems.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<dto::OriginBandwidthDto> for management_proto::OriginBandwidth {
+    fn from(o: dto::OriginBandwidthDto) -> Self {
+        Self {
+            host: o.host,
+            bytes_today: o.bytes_today,
+            daily_budget: o.daily_budget,
+        }
+    }
+}
+
+impl From<BandwidthSnapshot> for GetBandwidthResponse {
+    fn from(b: BandwidthSnapshot) -> Self {
+        Self {
+            today: b.today.into_iter().map(Into::into).collect(),
+            history: b
+                .history
+                .into_iter()
+                .map(|u| management_proto::BandwidthUsage {
+                    day: u.day,
+                    host: u.host,
+                    bytes: u.bytes,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<dto::TransferStatsDto> for management_proto::FileTransferStats {
+    fn from(t: dto::TransferStatsDto) -> Self {
+        Self {
+            file: t.file,
+            requests: t.requests,
+            resumes: t.resumes,
+            last_resume_unix: unix(&t.last_resume),
+            last_resume_display: display(t.last_resume),
         }
     }
 }
@@ -101,11 +456,43 @@ impl From<FileInfoDto> for FileInfo {
         Self {
             filename: d.filename,
             url: d.url,
-            last_modified: d.last_modified,
+            last_modified_unix: unix(&d.last_modified),
+            last_modified_display: display(d.last_modified),
+        }
+    }
+}
+
+/// 没有的时间在 proto 中记为 0 / 空串
+impl From<dto::FileStatsDto> for management_proto::FileStat {
+    fn from(s: dto::FileStatsDto) -> Self {
+        Self {
+            file: s.file,
+            requests: s.requests,
+            bytes: s.bytes,
+            last_access_unix: unix(&s.last_access),
+            last_access_display: display(s.last_access),
         }
     }
 }
 
+impl From<dto::SignedUrlDto> for management_proto::SignUrlResponse {
+    fn from(s: dto::SignedUrlDto) -> Self {
+        Self {
+            url: s.url,
+            expires_unix: s.expires.unix,
+            expires_display: s.expires.display.unwrap_or_default(),
+        }
+    }
+}
+
+fn unix(t: &Option<dto::TimestampDto>) -> u64 {
+    t.as_ref().map_or(0, |t| t.unix)
+}
+
+fn display(t: Option<dto::TimestampDto>) -> String {
+    t.and_then(|t| t.display).unwrap_or_default()
+}
+
 
 // ===============================
 // gRPC -> DTO (Inbound)
@@ -133,6 +520,8 @@ impl From<UpdateConfigRequest> for UpdateConfigInput {
             download_concurrency: req.download_concurrency,
             download_retry: req.download_retry,
             retry_base_delay_ms: req.retry_base_delay_ms,
+            enable_listing: req.enable_listing,
+            comment: Some(req.comment).filter(|c| !c.is_empty()),
         }
     }
 }
@@ -142,6 +531,7 @@ impl From<FileItem> for FileItemInput {
         Self {
             filename: item.filename,
             path: item.path,
+            mirrors: item.mirrors,
         }
     }
 }
@@ -153,6 +543,35 @@ impl From<UpdateFilesRequest> for UpdateFilesInput {
             remove_files: req.remove_files,
             replace_all: req.replace_all,
             new_files: req.new_files.into_iter().map(Into::into).collect(),
+            comment: Some(req.comment).filter(|c| !c.is_empty()),
+        }
+    }
+}
+
+impl From<management_proto::StatusRequest> for dto::StatusQuery {
+    fn from(req: management_proto::StatusRequest) -> Self {
+        Self {
+            detail: req.detail,
+            filter: Some(req.filter).filter(|f| !f.is_empty()),
+            limit: req.limit,
+        }
+    }
+}
+
+impl From<PrefetchRequest> for dto::PrefetchInput {
+    fn from(req: PrefetchRequest) -> Self {
+        Self {
+            names: req.names,
+            ignore_rate_limits: req.ignore_rate_limits.unwrap_or(true),
+        }
+    }
+}
+
+impl From<PurgeCacheRequest> for PurgeCacheInput {
+    fn from(req: PurgeCacheRequest) -> Self {
+        Self {
+            pattern: req.pattern,
+            prewarm: req.prewarm,
         }
     }
 }
@@ -162,6 +581,9 @@ pub fn map_core_error(err: CoreError) -> Status {
     match err {
         CoreError::InvalidArgument(msg) => Status::invalid_argument(msg),
         CoreError::NotFound(msg) => Status::not_found(msg),
+        CoreError::Unauthenticated(msg) => Status::unauthenticated(msg),
+        CoreError::PermissionDenied(msg) => Status::permission_denied(msg),
+        CoreError::Conflict(msg) => Status::failed_precondition(msg),
         CoreError::Internal(msg) => Status::internal(msg),
     }
 }
\ No newline at end of file
@@ -1,10 +1,12 @@
+use std::pin::Pin;
 use std::sync::Arc;
 
+use futures::{Stream, StreamExt};
 use log::info;
-use tonic::{Request, Response, Status, transport::Server};
+use tonic::{Request, Response, Status, service::Routes, transport::Server};
 
 use super::core::dto;
-use crate::management::core::ManagementCore;
+use crate::management::core::{ManagementCore, auth::endpoint_from_method};
 use crate::management::grpc::adapter::map_core_error;
 
 pub mod management_proto {
@@ -12,15 +14,33 @@ pub mod management_proto {
 }
 
 mod adapter;
+mod v2;
 
 use management_proto::management_server::{Management, ManagementServer};
 use management_proto::{
-    CleanUnusedFilesRequest, CleanUnusedFilesResponse, GetConfigRequest, GetConfigResponse,
-    ListFilesRequest, ListFilesResponse, PingRequest, PingResponse, ReloadConfigRequest,
+    CleanUnusedFilesRequest, CleanUnusedFilesResponse, RunRetentionRequest, RunRetentionResponse,
+    ListConfigRevisionsRequest, ListConfigRevisionsResponse, RollbackConfigRequest, RollbackConfigResponse,
+    GetConfigRequest, GetConfigResponse,
+    GetBandwidthRequest, GetBandwidthResponse, FileStatsRequest, FileStatsResponse,
+    GetFreshnessRequest, GetFreshnessResponse, VerifyStorageRequest, VerifyStorageResponse,
+    RepairStorageRequest, RepairStorageResponse,
+    GetTransferStatsRequest, GetTransferStatsResponse,
+    SignUrlRequest, SignUrlResponse,
+    PrefetchRequest, PrefetchResponse, SetBudgetOverrideRequest,
+    SetBudgetOverrideResponse, GetMetricsRequest, GetMetricsResponse, SyncFileRequest, SyncFileResponse, SyncGroupRequest, SyncGroupResponse,
+    CancelSyncRequest, CancelSyncResponse, ResetBackoffRequest, ResetBackoffResponse,
+    ApplyUpdateRequest, ApplyUpdateResponse, PauseSchedulerRequest, PauseSchedulerResponse,
+    ResumeSchedulerRequest, ResumeSchedulerResponse, GetSyncJobRequest, GetSyncJobResponse,
+    ListFilesRequest, ListFilesResponse, PingRequest, PingResponse, PurgeCacheRequest,
+    PurgeCacheResponse, ReloadConfigRequest,
     ReloadConfigResponse, StatusRequest, StatusResponse, TriggerSyncRequest, TriggerSyncResponse,
-    UpdateConfigRequest, UpdateConfigResponse, UpdateFilesRequest, UpdateFilesResponse,
+    SyncEvent, UpdateConfigRequest, UpdateConfigResponse, UpdateFilesRequest,
+    UpdateFilesResponse, WatchSyncRequest,
 };
 
+/// ListFiles 每条消息包含的文件数
+const LIST_FILES_BATCH: usize = 500;
+
 #[derive(Clone)]
 pub struct ManagementService {
     core: Arc<ManagementCore>,
@@ -28,6 +48,9 @@ pub struct ManagementService {
 
 #[tonic::async_trait]
 impl Management for ManagementService {
+    type ListFilesStream = Pin<Box<dyn Stream<Item = Result<ListFilesResponse, Status>> + Send + 'static>>;
+    type WatchSyncStream = Pin<Box<dyn Stream<Item = Result<SyncEvent, Status>> + Send + 'static>>;
+
     async fn ping(&self, _req: Request<PingRequest>) -> Result<Response<PingResponse>, Status> {
         Ok(Response::new(PingResponse {
             message: "pong".into(),
@@ -47,15 +70,57 @@ impl Management for ManagementService {
 
     async fn trigger_sync(
         &self,
-        _req: Request<TriggerSyncRequest>,
+        req: Request<TriggerSyncRequest>,
     ) -> Result<Response<TriggerSyncResponse>, Status> {
-        self.core.trigger_sync().await.map_err(map_core_error)?;
+        let job_id = self
+            .core
+            .trigger_sync(req.into_inner().reject_if_running)
+            .await
+            .map_err(map_core_error)?;
 
         Ok(Response::new(TriggerSyncResponse {
-            message: "sync completed".into(),
+            message: "sync queued".into(),
+            job_id,
         }))
     }
 
+    async fn get_sync_job(
+        &self,
+        req: Request<GetSyncJobRequest>,
+    ) -> Result<Response<GetSyncJobResponse>, Status> {
+        let job = self
+            .core
+            .get_sync_job(&req.into_inner().job_id)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(job.into()))
+    }
+
+    async fn cancel_sync(
+        &self,
+        _req: Request<CancelSyncRequest>,
+    ) -> Result<Response<CancelSyncResponse>, Status> {
+        let cancelled = self.core.cancel_sync().await.map_err(map_core_error)?;
+        Ok(Response::new(CancelSyncResponse { cancelled }))
+    }
+
+    async fn reset_backoff(
+        &self,
+        req: Request<ResetBackoffRequest>,
+    ) -> Result<Response<ResetBackoffResponse>, Status> {
+        let file = Some(req.into_inner().file).filter(|f| !f.is_empty());
+        let reset = self.core.reset_backoff(file).await.map_err(map_core_error)?;
+        Ok(Response::new(ResetBackoffResponse { reset }))
+    }
+
+    async fn apply_update(
+        &self,
+        req: Request<ApplyUpdateRequest>,
+    ) -> Result<Response<ApplyUpdateResponse>, Status> {
+        let outcome = self.core.apply_update(req.into_inner().dry_run).await.map_err(map_core_error)?;
+        Ok(Response::new(outcome.into()))
+    }
+
     async fn clean_unused_files(
         &self,
         _req: Request<CleanUnusedFilesRequest>,
@@ -69,11 +134,19 @@ impl Management for ManagementService {
         Ok(Response::new(CleanUnusedFilesResponse { removed }))
     }
 
+    async fn run_retention(
+        &self,
+        req: Request<RunRetentionRequest>,
+    ) -> Result<Response<RunRetentionResponse>, Status> {
+        let report = self.core.run_retention(req.into_inner().dry_run).await.map_err(map_core_error)?;
+        Ok(Response::new(report.into()))
+    }
+
     async fn status(
         &self,
-        _req: Request<StatusRequest>,
+        req: Request<StatusRequest>,
     ) -> Result<Response<StatusResponse>, Status> {
-        let snapshot = self.core.status().await.map_err(map_core_error)?;
+        let snapshot = self.core.status(req.into_inner().into()).await.map_err(map_core_error)?;
         Ok(Response::new(snapshot.into()))
     }
 
@@ -94,6 +167,7 @@ impl Management for ManagementService {
             download_concurrency: cfg.download_concurrency as u32,
             download_retry: cfg.download_retry as u32,
             retry_base_delay_ms: cfg.retry_base_delay_ms as u32,
+            enable_listing: cfg.enable_listing,
         }))
     }
 
@@ -110,13 +184,44 @@ impl Management for ManagementService {
         }))
     }
 
+    async fn list_config_revisions(
+        &self,
+        _req: Request<ListConfigRevisionsRequest>,
+    ) -> Result<Response<ListConfigRevisionsResponse>, Status> {
+        let revisions = self.core.list_config_revisions().await.map_err(map_core_error)?;
+        Ok(Response::new(ListConfigRevisionsResponse {
+            revisions: revisions.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn rollback_config(
+        &self,
+        req: Request<RollbackConfigRequest>,
+    ) -> Result<Response<RollbackConfigResponse>, Status> {
+        let req = req.into_inner();
+        let comment = Some(req.comment).filter(|c| !c.is_empty());
+        let revision = self.core.rollback_config(req.revision, comment).await.map_err(map_core_error)?;
+        Ok(Response::new(RollbackConfigResponse {
+            message: format!("rolled back to revision {}", req.revision),
+            revision: revision.unwrap_or(0),
+        }))
+    }
+
     async fn list_files(
         &self,
         _req: Request<ListFilesRequest>,
-    ) -> Result<Response<ListFilesResponse>, Status> {
-        let files = self.core.list_files().await.map_err(map_core_error)?;
-        let files = files.into_iter().map(Into::into).collect();
-        Ok(Response::new(ListFilesResponse { files }))
+    ) -> Result<Response<Self::ListFilesStream>, Status> {
+        let stream = self
+            .core
+            .list_files_stream()
+            .await
+            .chunks(LIST_FILES_BATCH)
+            .map(|batch| {
+                Ok(ListFilesResponse {
+                    files: batch.into_iter().map(Into::into).collect(),
+                })
+            });
+        Ok(Response::new(Box::pin(stream)))
     }
 
     async fn update_files(
@@ -131,16 +236,220 @@ impl Management for ManagementService {
             message: "files config updated".into(),
         }))
     }
+
+    async fn purge_cache(
+        &self,
+        req: Request<PurgeCacheRequest>,
+    ) -> Result<Response<PurgeCacheResponse>, Status> {
+        let dto = dto::PurgeCacheInput::from(req.into_inner());
+
+        let result = self.core.purge_cache(dto).await.map_err(map_core_error)?;
+
+        Ok(Response::new(result.into()))
+    }
+
+    async fn get_metrics(
+        &self,
+        _req: Request<GetMetricsRequest>,
+    ) -> Result<Response<GetMetricsResponse>, Status> {
+        let metrics = self.core.metrics().await.map_err(map_core_error)?;
+        Ok(Response::new(metrics.into()))
+    }
+
+    async fn get_bandwidth(
+        &self,
+        _req: Request<GetBandwidthRequest>,
+    ) -> Result<Response<GetBandwidthResponse>, Status> {
+        let bandwidth = self.core.bandwidth().await.map_err(map_core_error)?;
+        Ok(Response::new(bandwidth.into()))
+    }
+
+    async fn get_transfer_stats(
+        &self,
+        req: Request<GetTransferStatsRequest>,
+    ) -> Result<Response<GetTransferStatsResponse>, Status> {
+        let stats = self
+            .core
+            .transfer_stats(req.into_inner().limit)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(GetTransferStatsResponse {
+            files: stats.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn file_stats(
+        &self,
+        req: Request<FileStatsRequest>,
+    ) -> Result<Response<FileStatsResponse>, Status> {
+        let req = req.into_inner();
+        let input = dto::FileStatsInput {
+            sort_by: Some(req.sort_by).filter(|s| !s.is_empty()),
+            limit: req.limit,
+            include_unused: req.include_unused,
+        };
+        let stats = self.core.file_stats(input).await.map_err(map_core_error)?;
+        Ok(Response::new(FileStatsResponse {
+            files: stats.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_freshness(
+        &self,
+        _req: Request<GetFreshnessRequest>,
+    ) -> Result<Response<GetFreshnessResponse>, Status> {
+        let stale = self.core.freshness().await.map_err(map_core_error)?;
+        Ok(Response::new(GetFreshnessResponse {
+            files: stale.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn verify_storage(
+        &self,
+        _req: Request<VerifyStorageRequest>,
+    ) -> Result<Response<VerifyStorageResponse>, Status> {
+        let report = self.core.verify_storage().await.map_err(map_core_error)?;
+        Ok(Response::new(report.into()))
+    }
+
+    async fn repair_storage(
+        &self,
+        req: Request<RepairStorageRequest>,
+    ) -> Result<Response<RepairStorageResponse>, Status> {
+        let report = self.core.repair_storage(req.into_inner().files).await.map_err(map_core_error)?;
+        Ok(Response::new(report.into()))
+    }
+
+    async fn sign_url(
+        &self,
+        req: Request<SignUrlRequest>,
+    ) -> Result<Response<SignUrlResponse>, Status> {
+        let req = req.into_inner();
+        let signed = self.core.sign_url(req.path, req.ttl_secs).await.map_err(map_core_error)?;
+        Ok(Response::new(signed.into()))
+    }
+
+    async fn prefetch(
+        &self,
+        req: Request<PrefetchRequest>,
+    ) -> Result<Response<PrefetchResponse>, Status> {
+        let items = self
+            .core
+            .prefetch(req.into_inner().into())
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(items.into()))
+    }
+
+    async fn sync_file(
+        &self,
+        req: Request<SyncFileRequest>,
+    ) -> Result<Response<SyncFileResponse>, Status> {
+        let result = self
+            .core
+            .sync_file(req.into_inner().name)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(result.into()))
+    }
+
+    async fn sync_group(
+        &self,
+        req: Request<SyncGroupRequest>,
+    ) -> Result<Response<SyncGroupResponse>, Status> {
+        let result = self
+            .core
+            .sync_group(req.into_inner().name)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(result.into()))
+    }
+
+    async fn set_budget_override(
+        &self,
+        req: Request<SetBudgetOverrideRequest>,
+    ) -> Result<Response<SetBudgetOverrideResponse>, Status> {
+        self.core
+            .set_budget_override(req.into_inner().enabled)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(SetBudgetOverrideResponse {}))
+    }
+
+    async fn pause_scheduler(
+        &self,
+        _req: Request<PauseSchedulerRequest>,
+    ) -> Result<Response<PauseSchedulerResponse>, Status> {
+        let was_paused = self.core.set_scheduler_paused(true).await.map_err(map_core_error)?;
+        Ok(Response::new(PauseSchedulerResponse { was_paused }))
+    }
+
+    async fn resume_scheduler(
+        &self,
+        _req: Request<ResumeSchedulerRequest>,
+    ) -> Result<Response<ResumeSchedulerResponse>, Status> {
+        let was_paused = self.core.set_scheduler_paused(false).await.map_err(map_core_error)?;
+        Ok(Response::new(ResumeSchedulerResponse { was_paused }))
+    }
+
+    async fn watch_sync(
+        &self,
+        _req: Request<WatchSyncRequest>,
+    ) -> Result<Response<Self::WatchSyncStream>, Status> {
+        let stream = self.core.watch_sync().map(|e| Ok(e.into()));
+        Ok(Response::new(Box::pin(stream)))
+    }
 }
 
-/// 启动 gRPC 管理服务
+/// 启动 gRPC 管理服务：同时提供 management.v2 与兼容旧客户端的 v1
 pub async fn serve_grpc(
     addr: std::net::SocketAddr,
     core: Arc<ManagementCore>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let svc = ManagementServer::new(ManagementService { core });
+    let svc = ManagementServer::new(ManagementService { core: core.clone() });
+
+    // 鉴权需要知道调用的方法名，拦截器拿不到路径，因此挂在路由层
+    let router = Routes::new(svc)
+        .add_service(v2::ManagementServer::new(v2::ManagementService::new(core.clone())))
+        .into_axum_router()
+        .layer(axum::middleware::from_fn_with_state(core, auth));
 
     info!("Management gRPC listening on {}", addr);
-    Server::builder().add_service(svc).serve(addr).await?;
+    Server::builder()
+        .add_routes(Routes::from(router))
+        .serve(addr)
+        .await?;
     Ok(())
 }
+
+/// 支持 metadata `authorization: Bearer <token>` 与 `x-api-key: <token>`
+async fn auth(
+    axum::extract::State(core): axum::extract::State<Arc<ManagementCore>>,
+    req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let headers = req.headers();
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-api-key").and_then(|v| v.to_str().ok()))
+        .map(str::trim);
+
+    // 路径形如 /management.Management/TriggerSync 或 /management.v2.Management/TriggerSync
+    let method = req.uri().path().rsplit('/').next().unwrap_or_default();
+    let endpoint = endpoint_from_method(method);
+
+    let peer = req
+        .extensions()
+        .get::<tonic::transport::server::TcpConnectInfo>()
+        .and_then(|c| c.remote_addr())
+        .map(|a| crate::acl::Peer::Tcp(a.ip()));
+    if let Err(e) = core.authorize_client(peer, headers).await {
+        return map_core_error(e).into_http();
+    }
+    match core.authorize(token, &endpoint).await {
+        Ok(()) => next.run(req).await,
+        Err(e) => map_core_error(e).into_http(),
+    }
+}
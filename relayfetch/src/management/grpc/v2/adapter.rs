@@ -0,0 +1,519 @@
+//! gRPC v2 <-> DTO adapter
+//!
+//! 规则同 v1：只做数据转换。DTO 中的 Option 对应 proto 中的 optional / 消息字段，
+//! 不再折算为 0 或空串。
+
+use tonic::Status;
+
+use crate::management::core::dto;
+
+use super::management_proto as proto;
+
+// ===============================
+// DTO -> gRPC (Outbound)
+// ===============================
+
+impl From<dto::TimestampDto> for proto::Timestamp {
+    fn from(t: dto::TimestampDto) -> Self {
+        Self {
+            unix: t.unix,
+            display: t.display,
+        }
+    }
+}
+
+impl From<dto::SyncResultDto> for proto::SyncResult {
+    fn from(v: dto::SyncResultDto) -> Self {
+        match v {
+            dto::SyncResultDto::Pending => Self::Pending,
+            dto::SyncResultDto::Success => Self::Success,
+            dto::SyncResultDto::PartialSuccess => Self::PartialSuccess,
+            dto::SyncResultDto::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<dto::HealthStateDto> for proto::HealthState {
+    fn from(v: dto::HealthStateDto) -> Self {
+        match v {
+            dto::HealthStateDto::Ok => Self::Ok,
+            dto::HealthStateDto::Degraded => Self::Degraded,
+            dto::HealthStateDto::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<dto::SubsystemHealthDto> for proto::SubsystemHealth {
+    fn from(h: dto::SubsystemHealthDto) -> Self {
+        Self {
+            name: h.name,
+            state: proto::HealthState::from(h.state) as i32,
+            reason: h.reason,
+            since: Some(h.since.into()),
+        }
+    }
+}
+
+impl From<dto::FileProgressDto> for proto::FileProgress {
+    fn from(f: dto::FileProgressDto) -> Self {
+        Self {
+            file: f.file,
+            downloaded: f.downloaded,
+            total: f.total,
+            done: f.done,
+            error: f.error,
+            cancelled: f.cancelled,
+        }
+    }
+}
+
+impl From<dto::SyncEventDto> for proto::SyncEvent {
+    fn from(e: dto::SyncEventDto) -> Self {
+        use proto::SyncEventKind as Kind;
+
+        let mut out = Self::default();
+        match e {
+            dto::SyncEventDto::SyncStarted { total_files } => {
+                out.kind = Kind::SyncStarted as i32;
+                out.total_files = Some(total_files);
+            }
+            dto::SyncEventDto::FileStarted { file, total } => {
+                out.kind = Kind::FileStarted as i32;
+                out.file = Some(file);
+                out.total = total;
+            }
+            dto::SyncEventDto::FileProgress { file, downloaded, total } => {
+                out.kind = Kind::FileProgress as i32;
+                out.file = Some(file);
+                out.downloaded = Some(downloaded);
+                out.total = total;
+            }
+            dto::SyncEventDto::FileFinished { file } => {
+                out.kind = Kind::FileFinished as i32;
+                out.file = Some(file);
+            }
+            dto::SyncEventDto::FileError { file, error } => {
+                out.kind = Kind::FileError as i32;
+                out.file = Some(file);
+                out.error = Some(error);
+            }
+            dto::SyncEventDto::SyncFinished { result, error_message } => {
+                out.kind = Kind::SyncFinished as i32;
+                out.result = Some(proto::SyncResult::from(result) as i32);
+                out.error = error_message;
+            }
+        }
+        out
+    }
+}
+
+impl From<dto::StatusSnapshot> for proto::StatusResponse {
+    fn from(s: dto::StatusSnapshot) -> Self {
+        Self {
+            is_running: s.is_running,
+            total_files: s.total_files,
+            finished_files: s.finished_files,
+            failed_files: s.failed_files,
+            stored_files: s.stored_files,
+            start_time: s.start_time.map(Into::into),
+            last_sync: s.last_sync.map(Into::into),
+            last_ok_sync: s.last_ok_sync.map(Into::into),
+            last_result: proto::SyncResult::from(s.last_result) as i32,
+            files: s.files.into_values().map(Into::into).collect(),
+            storage_dir: s.storage_dir.to_string_lossy().to_string(),
+            error_message: s.error_message,
+            budget_exhausted: s.budget_exhausted,
+            month_bytes: s.month_bytes,
+            monthly_budget_bytes: s.monthly_budget_bytes,
+            budget_override: s.budget_override,
+            complete_files: s.complete_files,
+            partial_files: s.partial_files,
+            orphaned_files: s.orphaned_files,
+            task_restarts: s.task_restarts.into_iter().collect(),
+            health: proto::HealthState::from(s.health) as i32,
+            subsystems: s.subsystems.into_iter().map(Into::into).collect(),
+            scheduler_paused: s.scheduler_paused,
+            backoff: s
+                .backoff
+                .into_iter()
+                .map(|b| proto::FileBackoff {
+                    file: b.file,
+                    failures: b.failures,
+                    skip_cycles: b.skip_cycles,
+                    last_error: b.last_error,
+                })
+                .collect(),
+            history: s.history.into_iter().map(Into::into).collect(),
+            stale_files: s.stale_files.into_iter().map(Into::into).collect(),
+            groups: s.groups.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<dto::GroupStatusDto> for proto::GroupStatus {
+    fn from(g: dto::GroupStatusDto) -> Self {
+        Self {
+            name: g.name,
+            files: g.files,
+            failed: g.failed,
+            interval_secs: g.interval_secs,
+            last_sync: g.last_sync.map(Into::into),
+        }
+    }
+}
+
+impl From<dto::StaleFileDto> for proto::StaleFile {
+    fn from(s: dto::StaleFileDto) -> Self {
+        Self {
+            file: s.file,
+            fetched_at: s.fetched_at.map(Into::into),
+            age_secs: s.age_secs,
+            max_age_secs: s.max_age_secs,
+            critical: s.critical,
+        }
+    }
+}
+
+impl From<dto::SyncRunDto> for proto::SyncRun {
+    fn from(r: dto::SyncRunDto) -> Self {
+        Self {
+            start_time: Some(r.start_time.into()),
+            finish_time: Some(r.finish_time.into()),
+            duration_ms: r.duration_ms,
+            result: proto::SyncResult::from(r.result) as i32,
+            error_message: r.error_message,
+            total_files: r.total_files,
+            failed_files: r.failed_files,
+            bytes_transferred: r.bytes_transferred,
+            bytes_saved: r.bytes_saved,
+            throughput_bytes_per_sec: r.throughput_bytes_per_sec,
+        }
+    }
+}
+
+impl From<dto::ConfigSnapshot> for proto::GetConfigResponse {
+    fn from(cfg: dto::ConfigSnapshot) -> Self {
+        Self {
+            storage_dir: cfg.storage_dir.to_string_lossy().to_string(),
+            bind: cfg.bind,
+            grpc_admin: cfg.grpc_admin,
+            http_admin: cfg.http_admin,
+            proxy: cfg.proxy,
+            url: cfg.url,
+            interval_secs: cfg.interval_secs as u32,
+            download_concurrency: cfg.download_concurrency as u32,
+            download_retry: cfg.download_retry as u32,
+            retry_base_delay_ms: cfg.retry_base_delay_ms as u32,
+            enable_listing: cfg.enable_listing,
+        }
+    }
+}
+
+impl From<Vec<dto::PrefetchItemDto>> for proto::PrefetchResponse {
+    fn from(items: Vec<dto::PrefetchItemDto>) -> Self {
+        Self {
+            items: items
+                .into_iter()
+                .map(|i| proto::PrefetchItem {
+                    name: i.name,
+                    ok: i.ok,
+                    error: i.error,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<dto::SyncJobDto> for proto::GetSyncJobResponse {
+    fn from(j: dto::SyncJobDto) -> Self {
+        use proto::SyncJobState as State;
+        Self {
+            state: match j.state {
+                dto::SyncJobState::Queued => State::Queued,
+                dto::SyncJobState::Running => State::Running,
+                dto::SyncJobState::Finished => State::Finished,
+                dto::SyncJobState::Failed => State::Failed,
+            } as i32,
+            job_id: j.job_id,
+            created: Some(j.created.into()),
+            started: j.started.map(Into::into),
+            finished: j.finished.map(Into::into),
+            total_files: j.total_files,
+            finished_files: j.finished_files,
+            failed_files: j.failed_files,
+            result: j.result.map(|r| proto::SyncResult::from(r) as i32),
+            error: j.error,
+        }
+    }
+}
+
+impl From<dto::SyncFileResult> for proto::SyncFileResponse {
+    fn from(r: dto::SyncFileResult) -> Self {
+        Self {
+            name: r.name,
+            ok: r.ok,
+            error: r.error,
+            updated: r.updated,
+            sha256: r.sha256,
+        }
+    }
+}
+
+impl From<dto::SyncGroupResult> for proto::SyncGroupResponse {
+    fn from(r: dto::SyncGroupResult) -> Self {
+        Self {
+            group: r.group,
+            failed: r.files.iter().filter(|f| !f.ok).count() as u32,
+            files: r.files.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<dto::VerifyStorageDto> for proto::VerifyStorageResponse {
+    fn from(r: dto::VerifyStorageDto) -> Self {
+        Self {
+            checked: r.checked,
+            unhashed: r.unhashed,
+            bytes_hashed: r.bytes_hashed,
+            findings: r
+                .findings
+                .into_iter()
+                .map(|f| proto::StorageFinding { file: f.file, problem: f.problem, detail: f.detail })
+                .collect(),
+        }
+    }
+}
+
+impl From<dto::RepairStorageDto> for proto::RepairStorageResponse {
+    fn from(r: dto::RepairStorageDto) -> Self {
+        Self {
+            checked: r.checked,
+            files: r
+                .files
+                .into_iter()
+                .map(|f| proto::RepairOutcome {
+                    file: f.file,
+                    problem: f.problem,
+                    ok: f.ok,
+                    error: f.error,
+                    sha256: f.sha256,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<dto::RetentionReportDto> for proto::RunRetentionResponse {
+    fn from(r: dto::RetentionReportDto) -> Self {
+        Self {
+            dry_run: r.dry_run,
+            removed: r
+                .removed
+                .into_iter()
+                .map(|item| proto::RetentionItem { path: item.path, reason: item.reason, size: item.size })
+                .collect(),
+            freed_bytes: r.freed_bytes,
+        }
+    }
+}
+
+impl From<dto::ConfigRevisionDto> for proto::ConfigRevision {
+    fn from(r: dto::ConfigRevisionDto) -> Self {
+        Self {
+            revision: r.revision,
+            time: Some(r.time.into()),
+            source: r.source,
+            comment: r.comment,
+        }
+    }
+}
+
+impl From<dto::ApplyUpdateDto> for proto::ApplyUpdateResponse {
+    fn from(r: dto::ApplyUpdateDto) -> Self {
+        Self {
+            sha256: r.sha256,
+            up_to_date: r.up_to_date,
+            version: r.version,
+            restarting: r.restarting,
+        }
+    }
+}
+
+impl From<dto::PurgeCacheResult> for proto::PurgeCacheResponse {
+    fn from(r: dto::PurgeCacheResult) -> Self {
+        Self {
+            purged: r.purged,
+            prewarmed: r.prewarmed,
+        }
+    }
+}
+
+impl From<dto::MetricsSnapshot> for proto::GetMetricsResponse {
+    fn from(m: dto::MetricsSnapshot) -> Self {
+        Self {
+            coalesce_inflight: m.coalesce_inflight,
+            coalesce_waiting: m.coalesce_waiting,
+            coalesce_waiters_total: m.coalesce_waiters_total,
+            coalesce_rejected_total: m.coalesce_rejected_total,
+            origin_bandwidth: m.origin_bandwidth.into_iter().map(Into::into).collect(),
+            subsystems: m.subsystems.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<dto::OriginBandwidthDto> for proto::OriginBandwidth {
+    fn from(o: dto::OriginBandwidthDto) -> Self {
+        Self {
+            host: o.host,
+            bytes_today: o.bytes_today,
+            daily_budget: o.daily_budget,
+        }
+    }
+}
+
+impl From<dto::BandwidthSnapshot> for proto::GetBandwidthResponse {
+    fn from(b: dto::BandwidthSnapshot) -> Self {
+        Self {
+            today: b.today.into_iter().map(Into::into).collect(),
+            history: b
+                .history
+                .into_iter()
+                .map(|u| proto::BandwidthUsage {
+                    day: u.day,
+                    host: u.host,
+                    bytes: u.bytes,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<dto::FileStatsDto> for proto::FileStat {
+    fn from(s: dto::FileStatsDto) -> Self {
+        Self {
+            file: s.file,
+            requests: s.requests,
+            bytes: s.bytes,
+            last_access: s.last_access.map(Into::into),
+        }
+    }
+}
+
+impl From<dto::SignedUrlDto> for proto::SignUrlResponse {
+    fn from(s: dto::SignedUrlDto) -> Self {
+        Self {
+            url: s.url,
+            expires: Some(s.expires.into()),
+        }
+    }
+}
+
+impl From<dto::TransferStatsDto> for proto::FileTransferStats {
+    fn from(t: dto::TransferStatsDto) -> Self {
+        Self {
+            file: t.file,
+            requests: t.requests,
+            resumes: t.resumes,
+            last_resume: t.last_resume.map(Into::into),
+        }
+    }
+}
+
+impl From<dto::FileInfoDto> for proto::FileInfo {
+    fn from(d: dto::FileInfoDto) -> Self {
+        Self {
+            filename: d.filename,
+            url: d.url,
+            last_modified: d.last_modified.map(Into::into),
+        }
+    }
+}
+
+// ===============================
+// gRPC -> DTO (Inbound)
+// ===============================
+
+impl TryFrom<proto::UpdateConfigRequest> for dto::UpdateConfigInput {
+    type Error = Status;
+
+    fn try_from(req: proto::UpdateConfigRequest) -> Result<Self, Status> {
+        use proto::ProxyChange;
+
+        let proxy = match ProxyChange::try_from(req.proxy_change) {
+            Ok(ProxyChange::Unchanged) => None,
+            Ok(ProxyChange::Clear) => Some(None),
+            Ok(ProxyChange::Set) if req.proxy.is_empty() => {
+                return Err(Status::invalid_argument("proxy must not be empty with PROXY_CHANGE_SET"));
+            }
+            Ok(ProxyChange::Set) => Some(Some(req.proxy)),
+            Err(_) => {
+                return Err(Status::invalid_argument(format!("unknown proxy_change {}", req.proxy_change)));
+            }
+        };
+
+        Ok(Self {
+            interval_secs: req.interval_secs,
+            storage_dir: req.storage_dir.map(Into::into),
+            url: req.url,
+            bind: req.bind,
+            grpc_admin: req.grpc_admin,
+            http_admin: req.http_admin,
+            proxy,
+            download_concurrency: req.download_concurrency,
+            download_retry: req.download_retry,
+            retry_base_delay_ms: req.retry_base_delay_ms,
+            enable_listing: req.enable_listing,
+            comment: req.comment,
+        })
+    }
+}
+
+impl From<proto::FileItem> for dto::FileItemInput {
+    fn from(item: proto::FileItem) -> Self {
+        Self {
+            filename: item.filename,
+            path: item.path,
+            mirrors: item.mirrors,
+        }
+    }
+}
+
+impl From<proto::UpdateFilesRequest> for dto::UpdateFilesInput {
+    fn from(req: proto::UpdateFilesRequest) -> Self {
+        Self {
+            add_files: req.add_files.into_iter().map(Into::into).collect(),
+            remove_files: req.remove_files,
+            replace_all: req.replace_all,
+            new_files: req.new_files.into_iter().map(Into::into).collect(),
+            comment: req.comment,
+        }
+    }
+}
+
+impl From<proto::StatusRequest> for dto::StatusQuery {
+    fn from(req: proto::StatusRequest) -> Self {
+        Self {
+            detail: req.detail,
+            filter: req.filter,
+            limit: req.limit,
+        }
+    }
+}
+
+impl From<proto::PrefetchRequest> for dto::PrefetchInput {
+    fn from(req: proto::PrefetchRequest) -> Self {
+        Self {
+            names: req.names,
+            ignore_rate_limits: req.ignore_rate_limits.unwrap_or(true),
+        }
+    }
+}
+
+impl From<proto::PurgeCacheRequest> for dto::PurgeCacheInput {
+    fn from(req: proto::PurgeCacheRequest) -> Self {
+        Self {
+            pattern: req.pattern,
+            prewarm: req.prewarm,
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! gRPC 管理服务 v2（package management.v2）
+//!
+//! 与 v1 调用同一个 ManagementCore，只是消息中的可选字段都有显式存在性。
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+use crate::management::core::{ManagementCore, dto};
+use crate::management::grpc::adapter::map_core_error;
+
+pub mod management_proto {
+    tonic::include_proto!("management.v2");
+}
+
+mod adapter;
+
+use management_proto::management_server::Management;
+pub use management_proto::management_server::ManagementServer;
+use management_proto::{
+    ApplyUpdateRequest, ApplyUpdateResponse, CancelSyncRequest, CancelSyncResponse,
+    CleanUnusedFilesRequest, CleanUnusedFilesResponse, GetBandwidthRequest, GetBandwidthResponse,
+    GetConfigRequest, GetConfigResponse, GetMetricsRequest, GetMetricsResponse, GetSyncJobRequest,
+    GetSyncJobResponse, FileStatsRequest, FileStatsResponse, GetFreshnessRequest,
+    GetFreshnessResponse, VerifyStorageRequest, VerifyStorageResponse,
+    RepairStorageRequest, RepairStorageResponse, GetTransferStatsRequest,
+    GetTransferStatsResponse, SignUrlRequest,
+    SignUrlResponse,
+    ListConfigRevisionsRequest, ListConfigRevisionsResponse, ListFilesRequest, ListFilesResponse,
+    PauseSchedulerRequest, PauseSchedulerResponse, PingRequest, PingResponse, PrefetchRequest,
+    PrefetchResponse, PurgeCacheRequest, PurgeCacheResponse, ReloadConfigRequest,
+    ReloadConfigResponse, ResetBackoffRequest, ResetBackoffResponse, ResumeSchedulerRequest,
+    ResumeSchedulerResponse, RollbackConfigRequest, RollbackConfigResponse, RunRetentionRequest,
+    RunRetentionResponse, SetBudgetOverrideRequest, SetBudgetOverrideResponse, StatusRequest,
+    StatusResponse, SyncEvent, SyncFileRequest, SyncFileResponse, SyncGroupRequest, SyncGroupResponse, TriggerSyncRequest,
+    TriggerSyncResponse, UpdateConfigRequest, UpdateConfigResponse, UpdateFilesRequest,
+    UpdateFilesResponse, WatchSyncRequest,
+};
+
+use super::LIST_FILES_BATCH;
+
+#[derive(Clone)]
+pub struct ManagementService {
+    core: Arc<ManagementCore>,
+}
+
+impl ManagementService {
+    pub fn new(core: Arc<ManagementCore>) -> Self {
+        Self { core }
+    }
+}
+
+#[tonic::async_trait]
+impl Management for ManagementService {
+    type ListFilesStream = Pin<Box<dyn Stream<Item = Result<ListFilesResponse, Status>> + Send + 'static>>;
+    type WatchSyncStream = Pin<Box<dyn Stream<Item = Result<SyncEvent, Status>> + Send + 'static>>;
+
+    async fn ping(&self, _req: Request<PingRequest>) -> Result<Response<PingResponse>, Status> {
+        Ok(Response::new(PingResponse {
+            message: "pong".into(),
+        }))
+    }
+
+    async fn reload_config(
+        &self,
+        _req: Request<ReloadConfigRequest>,
+    ) -> Result<Response<ReloadConfigResponse>, Status> {
+        self.core.reload_config().await.map_err(map_core_error)?;
+        Ok(Response::new(ReloadConfigResponse {
+            message: "config reloaded".into(),
+        }))
+    }
+
+    async fn trigger_sync(
+        &self,
+        req: Request<TriggerSyncRequest>,
+    ) -> Result<Response<TriggerSyncResponse>, Status> {
+        let job_id = self
+            .core
+            .trigger_sync(req.into_inner().reject_if_running)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(TriggerSyncResponse {
+            message: "sync queued".into(),
+            job_id,
+        }))
+    }
+
+    async fn get_sync_job(
+        &self,
+        req: Request<GetSyncJobRequest>,
+    ) -> Result<Response<GetSyncJobResponse>, Status> {
+        let job = self
+            .core
+            .get_sync_job(&req.into_inner().job_id)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(job.into()))
+    }
+
+    async fn cancel_sync(
+        &self,
+        _req: Request<CancelSyncRequest>,
+    ) -> Result<Response<CancelSyncResponse>, Status> {
+        let cancelled = self.core.cancel_sync().await.map_err(map_core_error)?;
+        Ok(Response::new(CancelSyncResponse { cancelled }))
+    }
+
+    async fn reset_backoff(
+        &self,
+        req: Request<ResetBackoffRequest>,
+    ) -> Result<Response<ResetBackoffResponse>, Status> {
+        let reset = self
+            .core
+            .reset_backoff(req.into_inner().file)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(ResetBackoffResponse { reset }))
+    }
+
+    async fn apply_update(
+        &self,
+        req: Request<ApplyUpdateRequest>,
+    ) -> Result<Response<ApplyUpdateResponse>, Status> {
+        let outcome = self.core.apply_update(req.into_inner().dry_run).await.map_err(map_core_error)?;
+        Ok(Response::new(outcome.into()))
+    }
+
+    async fn clean_unused_files(
+        &self,
+        _req: Request<CleanUnusedFilesRequest>,
+    ) -> Result<Response<CleanUnusedFilesResponse>, Status> {
+        let removed = self.core.clean_unused_files().await.map_err(map_core_error)?;
+        Ok(Response::new(CleanUnusedFilesResponse { removed }))
+    }
+
+    async fn run_retention(
+        &self,
+        req: Request<RunRetentionRequest>,
+    ) -> Result<Response<RunRetentionResponse>, Status> {
+        let report = self.core.run_retention(req.into_inner().dry_run).await.map_err(map_core_error)?;
+        Ok(Response::new(report.into()))
+    }
+
+    async fn status(
+        &self,
+        req: Request<StatusRequest>,
+    ) -> Result<Response<StatusResponse>, Status> {
+        let snapshot = self.core.status(req.into_inner().into()).await.map_err(map_core_error)?;
+        Ok(Response::new(snapshot.into()))
+    }
+
+    async fn get_config(
+        &self,
+        _req: Request<GetConfigRequest>,
+    ) -> Result<Response<GetConfigResponse>, Status> {
+        let cfg = self.core.get_config().await.map_err(map_core_error)?;
+        Ok(Response::new(cfg.into()))
+    }
+
+    async fn update_config(
+        &self,
+        req: Request<UpdateConfigRequest>,
+    ) -> Result<Response<UpdateConfigResponse>, Status> {
+        let dto = dto::UpdateConfigInput::try_from(req.into_inner())?;
+        self.core.update_config(dto).await.map_err(map_core_error)?;
+        Ok(Response::new(UpdateConfigResponse {
+            message: "config updated".into(),
+        }))
+    }
+
+    async fn list_config_revisions(
+        &self,
+        _req: Request<ListConfigRevisionsRequest>,
+    ) -> Result<Response<ListConfigRevisionsResponse>, Status> {
+        let revisions = self.core.list_config_revisions().await.map_err(map_core_error)?;
+        Ok(Response::new(ListConfigRevisionsResponse {
+            revisions: revisions.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn rollback_config(
+        &self,
+        req: Request<RollbackConfigRequest>,
+    ) -> Result<Response<RollbackConfigResponse>, Status> {
+        let req = req.into_inner();
+        let revision = self.core.rollback_config(req.revision, req.comment).await.map_err(map_core_error)?;
+        Ok(Response::new(RollbackConfigResponse {
+            message: format!("rolled back to revision {}", req.revision),
+            revision,
+        }))
+    }
+
+    async fn list_files(
+        &self,
+        _req: Request<ListFilesRequest>,
+    ) -> Result<Response<Self::ListFilesStream>, Status> {
+        let stream = self
+            .core
+            .list_files_stream()
+            .await
+            .chunks(LIST_FILES_BATCH)
+            .map(|batch| {
+                Ok(ListFilesResponse {
+                    files: batch.into_iter().map(Into::into).collect(),
+                })
+            });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn update_files(
+        &self,
+        req: Request<UpdateFilesRequest>,
+    ) -> Result<Response<UpdateFilesResponse>, Status> {
+        let dto = dto::UpdateFilesInput::from(req.into_inner());
+        self.core.update_files(dto).await.map_err(map_core_error)?;
+        Ok(Response::new(UpdateFilesResponse {
+            message: "files config updated".into(),
+        }))
+    }
+
+    async fn purge_cache(
+        &self,
+        req: Request<PurgeCacheRequest>,
+    ) -> Result<Response<PurgeCacheResponse>, Status> {
+        let dto = dto::PurgeCacheInput::from(req.into_inner());
+        let result = self.core.purge_cache(dto).await.map_err(map_core_error)?;
+        Ok(Response::new(result.into()))
+    }
+
+    async fn get_metrics(
+        &self,
+        _req: Request<GetMetricsRequest>,
+    ) -> Result<Response<GetMetricsResponse>, Status> {
+        let metrics = self.core.metrics().await.map_err(map_core_error)?;
+        Ok(Response::new(metrics.into()))
+    }
+
+    async fn get_bandwidth(
+        &self,
+        _req: Request<GetBandwidthRequest>,
+    ) -> Result<Response<GetBandwidthResponse>, Status> {
+        let bandwidth = self.core.bandwidth().await.map_err(map_core_error)?;
+        Ok(Response::new(bandwidth.into()))
+    }
+
+    async fn get_transfer_stats(
+        &self,
+        req: Request<GetTransferStatsRequest>,
+    ) -> Result<Response<GetTransferStatsResponse>, Status> {
+        let stats = self
+            .core
+            .transfer_stats(req.into_inner().limit)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(GetTransferStatsResponse {
+            files: stats.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn file_stats(
+        &self,
+        req: Request<FileStatsRequest>,
+    ) -> Result<Response<FileStatsResponse>, Status> {
+        let req = req.into_inner();
+        let input = dto::FileStatsInput {
+            sort_by: req.sort_by,
+            limit: req.limit,
+            include_unused: req.include_unused,
+        };
+        let stats = self.core.file_stats(input).await.map_err(map_core_error)?;
+        Ok(Response::new(FileStatsResponse {
+            files: stats.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_freshness(
+        &self,
+        _req: Request<GetFreshnessRequest>,
+    ) -> Result<Response<GetFreshnessResponse>, Status> {
+        let stale = self.core.freshness().await.map_err(map_core_error)?;
+        Ok(Response::new(GetFreshnessResponse {
+            files: stale.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn verify_storage(
+        &self,
+        _req: Request<VerifyStorageRequest>,
+    ) -> Result<Response<VerifyStorageResponse>, Status> {
+        let report = self.core.verify_storage().await.map_err(map_core_error)?;
+        Ok(Response::new(report.into()))
+    }
+
+    async fn repair_storage(
+        &self,
+        req: Request<RepairStorageRequest>,
+    ) -> Result<Response<RepairStorageResponse>, Status> {
+        let report = self.core.repair_storage(req.into_inner().files).await.map_err(map_core_error)?;
+        Ok(Response::new(report.into()))
+    }
+
+    async fn sign_url(
+        &self,
+        req: Request<SignUrlRequest>,
+    ) -> Result<Response<SignUrlResponse>, Status> {
+        let req = req.into_inner();
+        let signed = self.core.sign_url(req.path, req.ttl_secs).await.map_err(map_core_error)?;
+        Ok(Response::new(signed.into()))
+    }
+
+    async fn prefetch(
+        &self,
+        req: Request<PrefetchRequest>,
+    ) -> Result<Response<PrefetchResponse>, Status> {
+        let items = self
+            .core
+            .prefetch(req.into_inner().into())
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(items.into()))
+    }
+
+    async fn sync_file(
+        &self,
+        req: Request<SyncFileRequest>,
+    ) -> Result<Response<SyncFileResponse>, Status> {
+        let result = self
+            .core
+            .sync_file(req.into_inner().name)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(result.into()))
+    }
+
+    async fn sync_group(
+        &self,
+        req: Request<SyncGroupRequest>,
+    ) -> Result<Response<SyncGroupResponse>, Status> {
+        let result = self
+            .core
+            .sync_group(req.into_inner().name)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(result.into()))
+    }
+
+    async fn set_budget_override(
+        &self,
+        req: Request<SetBudgetOverrideRequest>,
+    ) -> Result<Response<SetBudgetOverrideResponse>, Status> {
+        self.core
+            .set_budget_override(req.into_inner().enabled)
+            .await
+            .map_err(map_core_error)?;
+        Ok(Response::new(SetBudgetOverrideResponse {}))
+    }
+
+    async fn pause_scheduler(
+        &self,
+        _req: Request<PauseSchedulerRequest>,
+    ) -> Result<Response<PauseSchedulerResponse>, Status> {
+        let was_paused = self.core.set_scheduler_paused(true).await.map_err(map_core_error)?;
+        Ok(Response::new(PauseSchedulerResponse { was_paused }))
+    }
+
+    async fn resume_scheduler(
+        &self,
+        _req: Request<ResumeSchedulerRequest>,
+    ) -> Result<Response<ResumeSchedulerResponse>, Status> {
+        let was_paused = self.core.set_scheduler_paused(false).await.map_err(map_core_error)?;
+        Ok(Response::new(ResumeSchedulerResponse { was_paused }))
+    }
+
+    async fn watch_sync(
+        &self,
+        _req: Request<WatchSyncRequest>,
+    ) -> Result<Response<Self::WatchSyncStream>, Status> {
+        let stream = self.core.watch_sync().map(|e| Ok(e.into()));
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
@@ -1,8 +1,6 @@
-use std::path::PathBuf;
-
 // adapter.rs
-use crate::management::{core::dto::{ConfigSnapshot, FileInfoDto, FileItemInput, FileProgressDto, StatusSnapshot, SyncResultDto, UpdateConfigInput, UpdateFilesInput}, http::models::{FileItem, UpdateConfigRequest, UpdateFilesRequest}};
-use super::models::{FileProgressResponse, StatusResponse, SyncResult};
+use crate::management::{core::dto::{BandwidthSnapshot, ConfigSnapshot, FileInfoDto, FileItemInput, FileProgressDto, HealthSnapshot, HealthStateDto, MetricsSnapshot, SubsystemHealthDto, PurgeCacheInput, PurgeCacheResult, StatusSnapshot, SyncEventDto, SyncResultDto, TimestampDto, TransferStatsDto, UpdateConfigInput, UpdateFilesInput}, http::models::{BandwidthResponse, BandwidthUsage, FileItem, FileTransferStats, OriginBandwidth, PurgeCacheRequest, PurgeCacheResponse, UpdateConfigRequest, UpdateFilesRequest}};
+use super::models::{FileProgressResponse, HealthState, ReadyzResponse, StatusResponse, SubsystemHealth, SyncEventMessage, SyncResult};
 
 // ===============================
 // HTTP -> DTO (Inbound)
@@ -12,7 +10,7 @@ impl From<UpdateConfigRequest> for UpdateConfigInput {
     fn from(req: UpdateConfigRequest) -> Self {
         UpdateConfigInput {
             interval_secs: req.interval_secs,
-            storage_dir: req.storage_dir.map(PathBuf::from),
+            storage_dir: req.storage_dir,
             url: req.url,
             bind: req.bind,
             grpc_admin: req.grpc_admin,
@@ -21,6 +19,8 @@ impl From<UpdateConfigRequest> for UpdateConfigInput {
             download_concurrency: req.download_concurrency,
             download_retry: req.download_retry,
             retry_base_delay_ms: req.retry_base_delay_ms,
+            enable_listing: req.enable_listing,
+            comment: req.comment,
         }
     }
 }
@@ -30,6 +30,7 @@ impl From<FileItem> for FileItemInput {
         FileItemInput {
             filename: item.filename,
             path: item.path,
+            mirrors: item.mirrors,
         }
     }
 }
@@ -41,6 +42,35 @@ impl From<UpdateFilesRequest> for UpdateFilesInput {
             remove_files: req.remove_files,
             replace_all: req.replace_all,
             new_files: req.replace_files.into_iter().map(FileItemInput::from).collect(),
+            comment: req.comment,
+        }
+    }
+}
+
+impl From<super::models::PrefetchRequest> for crate::management::core::dto::PrefetchInput {
+    fn from(req: super::models::PrefetchRequest) -> Self {
+        Self {
+            names: req.names,
+            ignore_rate_limits: req.ignore_rate_limits,
+        }
+    }
+}
+
+impl From<super::models::StatusQuery> for crate::management::core::dto::StatusQuery {
+    fn from(q: super::models::StatusQuery) -> Self {
+        Self {
+            detail: q.detail,
+            filter: q.filter,
+            limit: q.limit,
+        }
+    }
+}
+
+impl From<PurgeCacheRequest> for PurgeCacheInput {
+    fn from(req: PurgeCacheRequest) -> Self {
+        PurgeCacheInput {
+            pattern: req.pattern,
+            prewarm: req.prewarm,
         }
     }
 }
@@ -49,42 +79,284 @@ impl From<UpdateFilesRequest> for UpdateFilesInput {
 // DTO -> HTTP (Outbound)
 // ===============================
 
+impl From<Vec<crate::management::core::dto::PrefetchItemDto>> for super::models::PrefetchResponse {
+    fn from(items: Vec<crate::management::core::dto::PrefetchItemDto>) -> Self {
+        Self {
+            items: items
+                .into_iter()
+                .map(|i| super::models::PrefetchItem {
+                    name: i.name,
+                    ok: i.ok,
+                    error: i.error,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::management::core::dto::SyncFileResult> for super::models::SyncFileResponse {
+    fn from(r: crate::management::core::dto::SyncFileResult) -> Self {
+        Self {
+            name: r.name,
+            ok: r.ok,
+            error: r.error,
+            updated: r.updated,
+            sha256: r.sha256,
+        }
+    }
+}
+
+impl From<crate::management::core::dto::SyncGroupResult> for super::models::SyncGroupResponse {
+    fn from(r: crate::management::core::dto::SyncGroupResult) -> Self {
+        Self {
+            group: r.group,
+            failed: r.files.iter().filter(|f| !f.ok).count() as u32,
+            files: r.files.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<crate::management::core::dto::ApplyUpdateDto> for super::models::ApplyUpdateResponse {
+    fn from(r: crate::management::core::dto::ApplyUpdateDto) -> Self {
+        Self {
+            sha256: r.sha256,
+            up_to_date: r.up_to_date,
+            version: r.version,
+            restarting: r.restarting,
+        }
+    }
+}
+
+impl From<crate::management::core::dto::ConfigRevisionDto> for super::models::ConfigRevision {
+    fn from(r: crate::management::core::dto::ConfigRevisionDto) -> Self {
+        Self {
+            revision: r.revision,
+            time: r.time.unix,
+            time_display: r.time.display,
+            source: r.source,
+            comment: r.comment,
+        }
+    }
+}
+
+impl From<crate::management::core::dto::VerifyStorageDto> for super::models::VerifyStorageResponse {
+    fn from(r: crate::management::core::dto::VerifyStorageDto) -> Self {
+        Self {
+            checked: r.checked,
+            unhashed: r.unhashed,
+            bytes_hashed: r.bytes_hashed,
+            findings: r
+                .findings
+                .into_iter()
+                .map(|f| super::models::StorageFinding { file: f.file, problem: f.problem, detail: f.detail })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::management::core::dto::RepairStorageDto> for super::models::RepairStorageResponse {
+    fn from(r: crate::management::core::dto::RepairStorageDto) -> Self {
+        Self {
+            checked: r.checked,
+            files: r
+                .files
+                .into_iter()
+                .map(|f| super::models::RepairOutcome {
+                    file: f.file,
+                    problem: f.problem,
+                    ok: f.ok,
+                    error: f.error,
+                    sha256: f.sha256,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::management::core::dto::RetentionReportDto> for super::models::RunRetentionResponse {
+    fn from(r: crate::management::core::dto::RetentionReportDto) -> Self {
+        Self {
+            dry_run: r.dry_run,
+            removed: r
+                .removed
+                .into_iter()
+                .map(|item| super::models::RetentionItem { path: item.path, reason: item.reason, size: item.size })
+                .collect(),
+            freed_bytes: r.freed_bytes,
+        }
+    }
+}
+
+impl From<PurgeCacheResult> for PurgeCacheResponse {
+    fn from(r: PurgeCacheResult) -> Self {
+        PurgeCacheResponse {
+            purged: r.purged,
+            prewarmed: r.prewarmed,
+        }
+    }
+}
+
+impl From<crate::management::core::dto::SyncJobDto> for super::models::SyncJobResponse {
+    fn from(j: crate::management::core::dto::SyncJobDto) -> Self {
+        use crate::management::core::dto::SyncJobState as State;
+        Self {
+            created: j.created.unix,
+            started: unix(&j.started),
+            finished: unix(&j.finished),
+            created_display: j.created.display,
+            started_display: display(j.started),
+            finished_display: display(j.finished),
+            state: match j.state {
+                State::Queued => super::models::SyncJobState::Queued,
+                State::Running => super::models::SyncJobState::Running,
+                State::Finished => super::models::SyncJobState::Finished,
+                State::Failed => super::models::SyncJobState::Failed,
+            },
+            job_id: j.job_id,
+            total_files: j.total_files,
+            finished_files: j.finished_files,
+            failed_files: j.failed_files,
+            result: j.result.map(Into::into),
+            error: j.error,
+        }
+    }
+}
+
 impl From<FileProgressDto> for FileProgressResponse {
     fn from(dto: FileProgressDto) -> Self {
         FileProgressResponse {
             file: dto.file,
             downloaded: dto.downloaded,
-            total: dto.total,
+            total: dto.total.unwrap_or(0),
             done: dto.done,
             error: dto.error,
+            cancelled: dto.cancelled,
         }
     }
 }
 
 impl From<StatusSnapshot> for StatusResponse {
     fn from(snapshot: StatusSnapshot) -> Self {
-        let start_time_unix = snapshot.start_time_unix();
-        let last_sync_unix = snapshot.last_sync_unix();
-        let last_ok_sync_unix = snapshot.last_ok_sync_unix();
-
         StatusResponse {
             is_running: snapshot.is_running,
             total_files: snapshot.total_files,
             finished_files: snapshot.finished_files,
             failed_files: snapshot.failed_files,
             stored_files: snapshot.stored_files,
-            start_time: Some(start_time_unix),
-            last_sync: Some(last_sync_unix),
-            last_ok_sync: Some(last_ok_sync_unix),
-            last_result: match snapshot.last_result {
-                SyncResultDto::Pending => SyncResult::Pending,
-                SyncResultDto::Success => SyncResult::Success,
-                SyncResultDto::PartialSuccess => SyncResult::PartialSuccess,
-                SyncResultDto::Failed => SyncResult::Failed,
-            },
+            complete_files: snapshot.complete_files,
+            partial_files: snapshot.partial_files,
+            orphaned_files: snapshot.orphaned_files,
+            start_time: unix(&snapshot.start_time),
+            last_sync: unix(&snapshot.last_sync),
+            last_ok_sync: unix(&snapshot.last_ok_sync),
+            start_time_display: display(snapshot.start_time),
+            last_sync_display: display(snapshot.last_sync),
+            last_ok_sync_display: display(snapshot.last_ok_sync),
+            backoff: snapshot
+                .backoff
+                .into_iter()
+                .map(|b| super::models::FileBackoff {
+                    file: b.file,
+                    failures: b.failures,
+                    skip_cycles: b.skip_cycles,
+                    last_error: b.last_error,
+                })
+                .collect(),
+            history: snapshot
+                .history
+                .into_iter()
+                .map(|r| super::models::SyncRun {
+                    start_time: r.start_time.unix,
+                    start_time_display: r.start_time.display,
+                    finish_time: r.finish_time.unix,
+                    finish_time_display: r.finish_time.display,
+                    duration_ms: r.duration_ms,
+                    result: r.result.into(),
+                    error_message: r.error_message,
+                    total_files: r.total_files,
+                    failed_files: r.failed_files,
+                    bytes_transferred: r.bytes_transferred,
+                    bytes_saved: r.bytes_saved,
+                    throughput_bytes_per_sec: r.throughput_bytes_per_sec,
+                })
+                .collect(),
+            stale_files: snapshot.stale_files.into_iter().map(Into::into).collect(),
+            groups: snapshot.groups.into_iter().map(Into::into).collect(),
+            last_result: snapshot.last_result.into(),
             error_message: snapshot.error_message,
             files: snapshot.files.into_iter().map(|(k, v)| (k, v.into())).collect(),
             storage_dir: snapshot.storage_dir,
+            budget_exhausted: snapshot.budget_exhausted,
+            month_bytes: snapshot.month_bytes,
+            monthly_budget_bytes: snapshot.monthly_budget_bytes,
+            budget_override: snapshot.budget_override,
+            task_restarts: snapshot.task_restarts,
+            scheduler_paused: snapshot.scheduler_paused,
+            health: snapshot.health.into(),
+            subsystems: snapshot.subsystems.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<SyncResultDto> for SyncResult {
+    fn from(r: SyncResultDto) -> Self {
+        match r {
+            SyncResultDto::Pending => SyncResult::Pending,
+            SyncResultDto::Success => SyncResult::Success,
+            SyncResultDto::PartialSuccess => SyncResult::PartialSuccess,
+            SyncResultDto::Failed => SyncResult::Failed,
+        }
+    }
+}
+
+impl From<HealthStateDto> for HealthState {
+    fn from(s: HealthStateDto) -> Self {
+        match s {
+            HealthStateDto::Ok => HealthState::Ok,
+            HealthStateDto::Degraded => HealthState::Degraded,
+            HealthStateDto::Failed => HealthState::Failed,
+        }
+    }
+}
+
+impl From<SubsystemHealthDto> for SubsystemHealth {
+    fn from(h: SubsystemHealthDto) -> Self {
+        SubsystemHealth {
+            since: h.since.unix,
+            since_display: h.since.display,
+            name: h.name,
+            state: h.state.into(),
+            reason: h.reason,
+        }
+    }
+}
+
+impl From<HealthSnapshot> for ReadyzResponse {
+    fn from(h: HealthSnapshot) -> Self {
+        ReadyzResponse {
+            status: h.overall.into(),
+            subsystems: h.subsystems.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<SyncEventDto> for SyncEventMessage {
+    fn from(e: SyncEventDto) -> Self {
+        match e {
+            SyncEventDto::SyncStarted { total_files } => SyncEventMessage::SyncStarted { total_files },
+            SyncEventDto::FileStarted { file, total } => SyncEventMessage::FileStarted {
+                file,
+                total: total.unwrap_or(0),
+            },
+            SyncEventDto::FileProgress { file, downloaded, total } => {
+                SyncEventMessage::FileProgress { file, downloaded, total: total.unwrap_or(0) }
+            }
+            SyncEventDto::FileFinished { file } => SyncEventMessage::FileFinished { file },
+            SyncEventDto::FileError { file, error } => SyncEventMessage::FileError { file, error },
+            SyncEventDto::SyncFinished { result, error_message } => SyncEventMessage::SyncFinished {
+                result: result.into(),
+                error_message,
+            },
         }
     }
 }
@@ -102,6 +374,7 @@ impl From<ConfigSnapshot> for super::models::GetConfigResponse {
             download_concurrency: snapshot.download_concurrency,
             download_retry: snapshot.download_retry,
             retry_base_delay_ms: snapshot.retry_base_delay_ms,
+            enable_listing: snapshot.enable_listing,
         }
     }
 }
@@ -111,9 +384,139 @@ impl From<FileInfoDto> for super::models::FileInfo {
         super::models::FileInfo {
             filename: dto.filename,
             url: dto.url,
-            last_modified: dto.last_modified,
+            last_modified: unix(&dto.last_modified),
+            last_modified_display: display(dto.last_modified),
+        }
+    }
+}
+
+/// MetricsSnapshot -> Prometheus 文本格式
+pub fn render_metrics(m: &MetricsSnapshot) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+        out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"));
+    };
+
+    metric("relayfetch_coalesce_inflight", "gauge", "Origin fetches currently in flight", m.coalesce_inflight);
+    metric("relayfetch_coalesce_waiting", "gauge", "Requests waiting on an in-flight origin fetch", m.coalesce_waiting);
+    metric("relayfetch_coalesce_waiters_total", "counter", "Requests coalesced onto an in-flight origin fetch", m.coalesce_waiters_total);
+    metric("relayfetch_coalesce_rejected_total", "counter", "Requests rejected because too many clients were waiting", m.coalesce_rejected_total);
+
+    out.push_str("# HELP relayfetch_health Subsystem health (0 = ok, 1 = degraded, 2 = failed)\n# TYPE relayfetch_health gauge\n");
+    for h in &m.subsystems {
+        let value = match h.state {
+            HealthStateDto::Ok => 0,
+            HealthStateDto::Degraded => 1,
+            HealthStateDto::Failed => 2,
+        };
+        out.push_str(&format!("relayfetch_health{{subsystem=\"{}\"}} {}\n", h.name, value));
+    }
+
+    out.push_str("# HELP relayfetch_origin_bytes_today Bytes downloaded from each origin host today (UTC)\n# TYPE relayfetch_origin_bytes_today gauge\n");
+    for o in &m.origin_bandwidth {
+        out.push_str(&format!("relayfetch_origin_bytes_today{{host=\"{}\"}} {}\n", o.host, o.bytes_today));
+    }
+    out.push_str("# HELP relayfetch_origin_daily_budget_bytes Configured daily byte budget per origin host\n# TYPE relayfetch_origin_daily_budget_bytes gauge\n");
+    for o in &m.origin_bandwidth {
+        if let Some(budget) = o.daily_budget {
+            out.push_str(&format!("relayfetch_origin_daily_budget_bytes{{host=\"{}\"}} {}\n", o.host, budget));
         }
     }
+
+    out
+}
+
+impl From<BandwidthSnapshot> for BandwidthResponse {
+    fn from(b: BandwidthSnapshot) -> Self {
+        Self {
+            today: b
+                .today
+                .into_iter()
+                .map(|o| OriginBandwidth {
+                    host: o.host,
+                    bytes_today: o.bytes_today,
+                    daily_budget: o.daily_budget,
+                })
+                .collect(),
+            history: b
+                .history
+                .into_iter()
+                .map(|u| BandwidthUsage {
+                    day: u.day,
+                    host: u.host,
+                    bytes: u.bytes,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<TransferStatsDto> for FileTransferStats {
+    fn from(t: TransferStatsDto) -> Self {
+        Self {
+            file: t.file,
+            requests: t.requests,
+            resumes: t.resumes,
+            last_resume: unix(&t.last_resume),
+            last_resume_display: display(t.last_resume),
+        }
+    }
+}
+
+impl From<crate::management::core::dto::FileStatsDto> for super::models::FileStat {
+    fn from(s: crate::management::core::dto::FileStatsDto) -> Self {
+        Self {
+            file: s.file,
+            requests: s.requests,
+            bytes: s.bytes,
+            last_access: unix(&s.last_access),
+            last_access_display: display(s.last_access),
+        }
+    }
+}
+
+impl From<crate::management::core::dto::StaleFileDto> for super::models::StaleFile {
+    fn from(s: crate::management::core::dto::StaleFileDto) -> Self {
+        Self {
+            file: s.file,
+            fetched_at: unix(&s.fetched_at),
+            fetched_at_display: display(s.fetched_at),
+            age_secs: s.age_secs,
+            max_age_secs: s.max_age_secs,
+            critical: s.critical,
+        }
+    }
+}
+
+impl From<crate::management::core::dto::GroupStatusDto> for super::models::GroupStatus {
+    fn from(g: crate::management::core::dto::GroupStatusDto) -> Self {
+        Self {
+            name: g.name,
+            files: g.files,
+            failed: g.failed,
+            interval_secs: g.interval_secs,
+            last_sync: unix(&g.last_sync),
+            last_sync_display: display(g.last_sync),
+        }
+    }
+}
+
+impl From<crate::management::core::dto::SignedUrlDto> for super::models::SignUrlResponse {
+    fn from(s: crate::management::core::dto::SignedUrlDto) -> Self {
+        Self {
+            url: s.url,
+            expires: s.expires.unix,
+            expires_display: s.expires.display,
+        }
+    }
+}
+
+fn unix(t: &Option<TimestampDto>) -> Option<u64> {
+    t.as_ref().map(|t| t.unix)
+}
+
+fn display(t: Option<TimestampDto>) -> Option<String> {
+    t.and_then(|t| t.display)
 }
 
 /// 将 CoreError 映射为 HTTP 状态码
@@ -122,6 +525,9 @@ pub fn map_core_error(err: crate::management::core::CoreError) -> axum::http::St
     match err {
         InvalidArgument(_) => axum::http::StatusCode::BAD_REQUEST,
         NotFound(_) => axum::http::StatusCode::NOT_FOUND,
+        Unauthenticated(_) => axum::http::StatusCode::UNAUTHORIZED,
+        PermissionDenied(_) => axum::http::StatusCode::FORBIDDEN,
+        Conflict(_) => axum::http::StatusCode::CONFLICT,
         Internal(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
\ No newline at end of file
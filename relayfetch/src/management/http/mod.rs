@@ -1,13 +1,18 @@
 // mod.rs
+use std::convert::Infallible;
 use std::sync::Arc;
 use std::net::SocketAddr;
 
 use axum::{
     extract::State,
     http::StatusCode,
-    response::Json,
+    response::{
+        IntoResponse, Json,
+        sse::{Event, KeepAlive, Sse},
+    },
     Router,
 };
+use futures::{Stream, StreamExt};
 use log::info;
 use tokio::net::TcpListener;
 
@@ -33,13 +38,76 @@ async fn reload_config(State(core): State<Arc<ManagementCore>>) -> Result<Json<m
     }))
 }
 
-async fn trigger_sync(State(core): State<Arc<ManagementCore>>) -> Result<Json<models::TriggerSyncResponse>, StatusCode> {
-    core.trigger_sync().await.map_err(adapter::map_core_error)?;
+async fn trigger_sync(
+    State(core): State<Arc<ManagementCore>>,
+    axum::extract::Query(query): axum::extract::Query<models::TriggerSyncQuery>,
+) -> Result<Json<models::TriggerSyncResponse>, StatusCode> {
+    let job_id = core
+        .trigger_sync(query.reject_if_running)
+        .await
+        .map_err(adapter::map_core_error)?;
     Ok(Json(models::TriggerSyncResponse {
-        message: "sync completed".to_string(),
+        message: format!("sync queued (job {})", job_id),
+        job_id,
     }))
 }
 
+async fn sync_job(
+    State(core): State<Arc<ManagementCore>>,
+    axum::extract::Query(query): axum::extract::Query<models::SyncJobQuery>,
+) -> Result<Json<models::SyncJobResponse>, StatusCode> {
+    let job = core.get_sync_job(&query.job_id).await.map_err(map_core_error)?;
+    Ok(Json(job.into()))
+}
+
+async fn cancel_sync(State(core): State<Arc<ManagementCore>>) -> Result<Json<models::CancelSyncResponse>, StatusCode> {
+    let cancelled = core.cancel_sync().await.map_err(map_core_error)?;
+    Ok(Json(models::CancelSyncResponse { cancelled }))
+}
+
+async fn reset_backoff(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::ResetBackoffRequest>,
+) -> Result<Json<models::ResetBackoffResponse>, StatusCode> {
+    let reset = core.reset_backoff(req.file).await.map_err(map_core_error)?;
+    Ok(Json(models::ResetBackoffResponse { reset }))
+}
+
+async fn apply_update(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::ApplyUpdateRequest>,
+) -> Result<Json<models::ApplyUpdateResponse>, StatusCode> {
+    let outcome = core.apply_update(req.dry_run).await.map_err(map_core_error)?;
+    Ok(Json(outcome.into()))
+}
+
+async fn prefetch(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::PrefetchRequest>,
+) -> Result<Json<models::PrefetchResponse>, StatusCode> {
+    let items = core
+        .prefetch(dto::PrefetchInput::from(req))
+        .await
+        .map_err(map_core_error)?;
+    Ok(Json(items.into()))
+}
+
+async fn sync_file(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::SyncFileRequest>,
+) -> Result<Json<models::SyncFileResponse>, StatusCode> {
+    let result = core.sync_file(req.name).await.map_err(map_core_error)?;
+    Ok(Json(result.into()))
+}
+
+async fn sync_group(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::SyncGroupRequest>,
+) -> Result<Json<models::SyncGroupResponse>, StatusCode> {
+    let result = core.sync_group(req.name).await.map_err(map_core_error)?;
+    Ok(Json(result.into()))
+}
+
 async fn clean_unused_files(
     State(core): State<Arc<ManagementCore>>,
 ) -> Result<Json<CleanUnusedFilesResponse>, StatusCode> {
@@ -51,8 +119,19 @@ async fn clean_unused_files(
     Ok(Json(CleanUnusedFilesResponse { removed }))
 }
 
-async fn status(State(core): State<Arc<ManagementCore>>) -> Result<Json<models::StatusResponse>, StatusCode> {
-    let snapshot = core.status().await.map_err(adapter::map_core_error)?;
+async fn run_retention(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::RunRetentionRequest>,
+) -> Result<Json<models::RunRetentionResponse>, StatusCode> {
+    let report = core.run_retention(req.dry_run).await.map_err(map_core_error)?;
+    Ok(Json(report.into()))
+}
+
+async fn status(
+    State(core): State<Arc<ManagementCore>>,
+    axum::extract::Query(query): axum::extract::Query<models::StatusQuery>,
+) -> Result<Json<models::StatusResponse>, StatusCode> {
+    let snapshot = core.status(query.into()).await.map_err(adapter::map_core_error)?;
     Ok(Json(models::StatusResponse::from(snapshot)))
 }
 
@@ -73,16 +152,49 @@ async fn update_config(
         }))
 }
 
+async fn list_config_revisions(
+    State(core): State<Arc<ManagementCore>>,
+) -> Result<Json<Vec<models::ConfigRevision>>, StatusCode> {
+    let revisions = core.list_config_revisions().await.map_err(map_core_error)?;
+    Ok(Json(revisions.into_iter().map(Into::into).collect()))
+}
+
+async fn rollback_config(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::RollbackConfigRequest>,
+) -> Result<Json<models::RollbackConfigResponse>, StatusCode> {
+    let revision = core
+        .rollback_config(req.revision, req.comment)
+        .await
+        .map_err(map_core_error)?;
+    Ok(Json(models::RollbackConfigResponse {
+        message: format!("rolled back to revision {}", req.revision),
+        revision,
+    }))
+}
+
 async fn list_files(
     State(core): State<Arc<ManagementCore>>,
 ) -> Result<Json<models::ListFilesResponse>, StatusCode> {
-    let files = core.list_files().await.map_err(map_core_error)?;
-
-    let files = files.into_iter().map(Into::into).collect();
+    let files = core.list_files_stream().await.map(Into::into).collect().await;
 
     Ok(Json(files))
 }
 
+/// NDJSON 流式列表：每行一个 FileInfo，不在内存中拼接完整列表
+async fn list_files_stream(State(core): State<Arc<ManagementCore>>) -> axum::response::Response {
+    let lines = core.list_files_stream().await.map(|f| {
+        let mut line = serde_json::to_vec(&models::FileInfo::from(f))?;
+        line.push(b'\n');
+        Ok::<_, serde_json::Error>(line)
+    });
+    (
+        [(axum::http::header::CONTENT_TYPE, "application/x-ndjson")],
+        axum::body::Body::from_stream(lines),
+    )
+        .into_response()
+}
+
 async fn update_files(
     State(core): State<Arc<ManagementCore>>,
     Json(req): Json<models::UpdateFilesRequest>,
@@ -95,6 +207,201 @@ async fn update_files(
         }))
 }
 
+async fn purge_cache(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::PurgeCacheRequest>,
+) -> Result<Json<models::PurgeCacheResponse>, StatusCode> {
+    let result = core
+        .purge_cache(dto::PurgeCacheInput::from(req))
+        .await
+        .map_err(map_core_error)?;
+    Ok(Json(result.into()))
+}
+
+async fn metrics(
+    State(core): State<Arc<ManagementCore>>,
+) -> Result<([(axum::http::HeaderName, &'static str); 1], String), StatusCode> {
+    let snapshot = core.metrics().await.map_err(map_core_error)?;
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        adapter::render_metrics(&snapshot),
+    ))
+}
+
+/// 就绪探针：不经过鉴权；任一子系统 Failed 时返回 503
+async fn readyz(State(core): State<Arc<ManagementCore>>) -> (StatusCode, Json<models::ReadyzResponse>) {
+    let health = core.health().await;
+    let code = match health.overall {
+        dto::HealthStateDto::Failed => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    };
+    (code, Json(health.into()))
+}
+
+async fn bandwidth(
+    State(core): State<Arc<ManagementCore>>,
+) -> Result<Json<models::BandwidthResponse>, StatusCode> {
+    let snapshot = core.bandwidth().await.map_err(map_core_error)?;
+    Ok(Json(snapshot.into()))
+}
+
+async fn transfer_stats(
+    State(core): State<Arc<ManagementCore>>,
+    axum::extract::Query(query): axum::extract::Query<models::TransferStatsQuery>,
+) -> Result<Json<models::TransferStatsResponse>, StatusCode> {
+    let stats = core.transfer_stats(query.limit).await.map_err(map_core_error)?;
+    Ok(Json(models::TransferStatsResponse {
+        files: stats.into_iter().map(Into::into).collect(),
+    }))
+}
+
+async fn file_stats(
+    State(core): State<Arc<ManagementCore>>,
+    axum::extract::Query(query): axum::extract::Query<models::FileStatsQuery>,
+) -> Result<Json<models::FileStatsResponse>, StatusCode> {
+    let input = crate::management::core::dto::FileStatsInput {
+        sort_by: query.sort_by,
+        limit: query.limit,
+        include_unused: query.include_unused,
+    };
+    let stats = core.file_stats(input).await.map_err(map_core_error)?;
+    Ok(Json(models::FileStatsResponse {
+        files: stats.into_iter().map(Into::into).collect(),
+    }))
+}
+
+async fn freshness(
+    State(core): State<Arc<ManagementCore>>,
+) -> Result<Json<models::FreshnessResponse>, StatusCode> {
+    let stale = core.freshness().await.map_err(map_core_error)?;
+    Ok(Json(models::FreshnessResponse {
+        files: stale.into_iter().map(Into::into).collect(),
+    }))
+}
+
+async fn verify_storage(
+    State(core): State<Arc<ManagementCore>>,
+) -> Result<Json<models::VerifyStorageResponse>, StatusCode> {
+    let report = core.verify_storage().await.map_err(map_core_error)?;
+    Ok(Json(report.into()))
+}
+
+async fn repair_storage(
+    State(core): State<Arc<ManagementCore>>,
+    body: Option<Json<models::RepairStorageRequest>>,
+) -> Result<Json<models::RepairStorageResponse>, StatusCode> {
+    let files = body.map(|Json(req)| req.files).unwrap_or_default();
+    let report = core.repair_storage(files).await.map_err(map_core_error)?;
+    Ok(Json(report.into()))
+}
+
+async fn sign_url(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::SignUrlRequest>,
+) -> Result<Json<models::SignUrlResponse>, StatusCode> {
+    let signed = core.sign_url(req.path, req.ttl_secs).await.map_err(map_core_error)?;
+    Ok(Json(signed.into()))
+}
+
+async fn budget_override(
+    State(core): State<Arc<ManagementCore>>,
+    Json(req): Json<models::BudgetOverrideRequest>,
+) -> Result<Json<models::BudgetOverrideResponse>, StatusCode> {
+    core.set_budget_override(req.enabled)
+        .await
+        .map_err(map_core_error)?;
+    Ok(Json(models::BudgetOverrideResponse {
+        message: if req.enabled { "budget override enabled" } else { "budget override disabled" }.into(),
+    }))
+}
+
+async fn pause_scheduler(
+    State(core): State<Arc<ManagementCore>>,
+) -> Result<Json<models::SchedulerResponse>, StatusCode> {
+    let was_paused = core.set_scheduler_paused(true).await.map_err(map_core_error)?;
+    Ok(Json(models::SchedulerResponse { paused: true, was_paused }))
+}
+
+async fn resume_scheduler(
+    State(core): State<Arc<ManagementCore>>,
+) -> Result<Json<models::SchedulerResponse>, StatusCode> {
+    let was_paused = core.set_scheduler_paused(false).await.map_err(map_core_error)?;
+    Ok(Json(models::SchedulerResponse { paused: false, was_paused }))
+}
+
+/// SSE：推送同步生命周期与单文件进度
+async fn events(
+    State(core): State<Arc<ManagementCore>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = core.watch_sync().map(|e| {
+        let msg = models::SyncEventMessage::from(e);
+        let event = Event::default()
+            .event(msg.name())
+            .json_data(&msg)
+            .unwrap_or_else(|_| Event::default().comment("serialize error"));
+        Ok(event)
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// 内嵌 Web 控制台（单页，数据来自上面的接口）
+#[cfg(feature = "dashboard")]
+async fn dashboard() -> axum::response::Html<&'static str> {
+    axum::response::Html(include_str!("dashboard.html"))
+}
+
+// ======================
+// 鉴权中间件
+// ======================
+
+/// HTTP 路由 -> 统一接口名（与 gRPC 方法名一致）
+fn endpoint_of(path: &str) -> &str {
+    match path.trim_matches('/') {
+        "" => "dashboard",
+        "metrics" => "get_metrics",
+        "bandwidth" => "get_bandwidth",
+        "budget_override" => "set_budget_override",
+        "transfer_stats" => "get_transfer_stats",
+        "stats/files" => "file_stats",
+        "freshness" => "get_freshness",
+        "sync_job" => "get_sync_job",
+        "events" => "watch_sync",
+        "list_files/stream" => "list_files",
+        other => other,
+    }
+}
+
+/// 支持 `Authorization: Bearer <token>` 与 `X-API-Key: <token>`；浏览器的 EventSource 不能设置请求头，
+/// `/events` 也接受查询参数 `token`。控制台页面本身不含数据，只检查客户端地址，页面中输入的 token 用于调用其余接口
+async fn auth(
+    State(core): State<Arc<ManagementCore>>,
+    req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, StatusCode> {
+    let headers = req.headers();
+    let endpoint = endpoint_of(req.uri().path());
+    let query = match endpoint {
+        "watch_sync" => axum::extract::Query::<std::collections::HashMap<String, String>>::try_from_uri(req.uri()).ok(),
+        _ => None,
+    };
+    let token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-api-key").and_then(|v| v.to_str().ok()))
+        .or_else(|| query.as_ref().and_then(|q| q.get("token")).map(String::as_str))
+        .map(str::trim);
+
+    let peer = req
+        .extensions()
+        .get::<axum::extract::ConnectInfo<SocketAddr>>()
+        .map(|c| crate::acl::Peer::Tcp(c.0.ip()));
+    core.authorize_client(peer, headers).await.map_err(map_core_error)?;
+    if endpoint != "dashboard" {
+        core.authorize(token, endpoint).await.map_err(map_core_error)?;
+    }
+    Ok(next.run(req).await)
+}
 
 // ======================
 // HTTP Server 启动
@@ -105,11 +412,45 @@ pub async fn serve_http(addr: SocketAddr, core: Arc<ManagementCore>) -> anyhow::
         .route("/status", axum::routing::get(status))
         .route("/reload_config", axum::routing::post(reload_config))
         .route("/trigger_sync", axum::routing::post(trigger_sync))
+        .route("/sync_job", axum::routing::get(sync_job))
+        .route("/cancel_sync", axum::routing::post(cancel_sync))
+        .route("/reset_backoff", axum::routing::post(reset_backoff))
+        .route("/apply_update", axum::routing::post(apply_update))
+        .route("/prefetch", axum::routing::post(prefetch))
+        .route("/sync_file", axum::routing::post(sync_file))
+        .route("/sync_group", axum::routing::post(sync_group))
         .route("/clean_unused_files", axum::routing::post(clean_unused_files))
+        .route("/run_retention", axum::routing::post(run_retention))
         .route("/get_config", axum::routing::get(get_config))
         .route("/update_config", axum::routing::post(update_config))
+        .route("/list_config_revisions", axum::routing::get(list_config_revisions))
+        .route("/rollback_config", axum::routing::post(rollback_config))
         .route("/list_files", axum::routing::get(list_files))
+        .route("/list_files/stream", axum::routing::get(list_files_stream))
         .route("/update_files", axum::routing::post(update_files))
+        .route("/purge_cache", axum::routing::post(purge_cache))
+        .route("/metrics", axum::routing::get(metrics))
+        .route("/bandwidth", axum::routing::get(bandwidth))
+        .route("/budget_override", axum::routing::post(budget_override))
+        .route("/transfer_stats", axum::routing::get(transfer_stats))
+        .route("/stats/files", axum::routing::get(file_stats))
+        .route("/freshness", axum::routing::get(freshness))
+        .route("/verify_storage", axum::routing::post(verify_storage))
+        .route("/repair_storage", axum::routing::post(repair_storage))
+        .route("/sign_url", axum::routing::post(sign_url))
+        .route("/pause_scheduler", axum::routing::post(pause_scheduler))
+        .route("/resume_scheduler", axum::routing::post(resume_scheduler))
+        .route("/events", axum::routing::get(events));
+
+    #[cfg(feature = "dashboard")]
+    let app = app
+        .route("/", axum::routing::get(dashboard))
+        .route("/dashboard", axum::routing::get(dashboard));
+
+    let app = app
+        .layer(axum::middleware::from_fn_with_state(core.clone(), auth))
+        .route("/readyz", axum::routing::get(readyz))
+        .layer(axum::middleware::from_fn(crate::logging::request_id))
         .with_state(core);
 
     info!("Management HTTP listening on {}", addr);
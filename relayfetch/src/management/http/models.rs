@@ -1,5 +1,5 @@
 // models.rs
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 
@@ -19,12 +19,102 @@ pub struct ReloadConfigResponse {
     pub message: String,
 }
 
+// 时间字段一律为 unix 秒（没有时为 null），`*_display` 为按 [display_time] 格式化的文本
+
 // ======================
 // TriggerSyncResponse DTO
 // ======================
+#[derive(Deserialize)]
+pub struct TriggerSyncQuery {
+    /// 已有同步在进行时返回 409，而不是排队
+    #[serde(default)]
+    pub reject_if_running: bool,
+}
+
 #[derive(Serialize)]
 pub struct TriggerSyncResponse {
     pub message: String,
+    pub job_id: String,
+}
+
+/// GET /sync_job?job_id=<id>
+#[derive(Deserialize)]
+pub struct SyncJobQuery {
+    pub job_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncJobState {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct SyncJobResponse {
+    pub job_id: String,
+    pub state: SyncJobState,
+    pub created: u64,
+    pub started: Option<u64>,
+    pub finished: Option<u64>,
+    pub created_display: Option<String>,
+    pub started_display: Option<String>,
+    pub finished_display: Option<String>,
+    pub total_files: u32,
+    pub finished_files: u32,
+    pub failed_files: u32,
+    pub result: Option<SyncResult>,
+    pub error: Option<String>,
+}
+
+// ======================
+// CancelSyncResponse DTO
+// ======================
+#[derive(Serialize)]
+pub struct CancelSyncResponse {
+    pub cancelled: bool,
+}
+
+// ======================
+// ResetBackoff DTO
+// ======================
+#[derive(Deserialize)]
+pub struct ResetBackoffRequest {
+    /// 省略时清除全部
+    #[serde(default)]
+    pub file: Option<String>,
+}
+#[derive(Serialize)]
+pub struct ResetBackoffResponse {
+    pub reset: Vec<String>,
+}
+
+// ======================
+// ApplyUpdate DTO
+// ======================
+#[derive(Deserialize)]
+pub struct ApplyUpdateRequest {
+    /// 只校验，不替换
+    #[serde(default)]
+    pub dry_run: bool,
+}
+#[derive(Serialize)]
+pub struct ApplyUpdateResponse {
+    pub sha256: String,
+    pub up_to_date: bool,
+    pub version: Option<String>,
+    pub restarting: bool,
+}
+
+// ======================
+// Pause / ResumeScheduler DTO
+// ======================
+#[derive(Serialize)]
+pub struct SchedulerResponse {
+    pub paused: bool,
+    pub was_paused: bool,
 }
 
 // ======================
@@ -35,6 +125,54 @@ pub struct CleanUnusedFilesResponse {
     pub removed: Vec<String>,
 }
 
+// ======================
+// ConfigRevision DTO
+// ======================
+#[derive(Serialize)]
+pub struct ConfigRevision {
+    pub revision: u64,
+    pub time: u64,
+    pub time_display: Option<String>,
+    /// startup / reload / update_config / update_files / rollback
+    pub source: String,
+    pub comment: Option<String>,
+}
+#[derive(Deserialize)]
+pub struct RollbackConfigRequest {
+    pub revision: u64,
+    /// 未提供时记为 "rollback to revision N"
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+#[derive(Serialize)]
+pub struct RollbackConfigResponse {
+    pub message: String,
+    /// 回滚后记录的修订；内容与最新修订相同或未启用时为 null
+    pub revision: Option<u64>,
+}
+
+// ======================
+// RunRetention DTO
+// ======================
+#[derive(Deserialize)]
+pub struct RunRetentionRequest {
+    /// 只报告将删除的内容
+    #[serde(default)]
+    pub dry_run: bool,
+}
+#[derive(Serialize)]
+pub struct RunRetentionResponse {
+    pub dry_run: bool,
+    pub removed: Vec<RetentionItem>,
+    pub freed_bytes: u64,
+}
+#[derive(Serialize)]
+pub struct RetentionItem {
+    pub path: String,
+    pub reason: String,
+    pub size: u64,
+}
+
 // ======================
 // Status DTO
 // ======================
@@ -45,6 +183,7 @@ pub struct FileProgressResponse {
     pub total: u64,
     pub done: bool,
     pub error: Option<String>,
+    pub cancelled: bool,
 }
 
 // ======================
@@ -68,6 +207,10 @@ pub struct UpdateConfigRequest {
     pub download_concurrency: Option<u32>,
     pub download_retry: Option<u32>,
     pub retry_base_delay_ms: Option<u32>,
+    pub enable_listing: Option<bool>,
+    /// 随配置修订记录的说明
+    #[serde(default)]
+    pub comment: Option<String>,
 }
 
 // ======================
@@ -93,6 +236,7 @@ pub struct GetConfigResponse {
     pub download_concurrency: usize,
     pub download_retry: usize,
     pub retry_base_delay_ms: u64,
+    pub enable_listing: bool,
 }
 #[derive(Serialize)]
 pub enum SyncResult {
@@ -102,6 +246,15 @@ pub enum SyncResult {
     Failed,
 }
 
+/// GET /status?detail=true&filter=<glob>&limit=<n>
+#[derive(Deserialize)]
+pub struct StatusQuery {
+    #[serde(default)]
+    pub detail: bool,
+    pub filter: Option<String>,
+    pub limit: Option<u32>,
+}
+
 #[derive(Serialize)]
 pub struct StatusResponse {
     pub is_running: bool,
@@ -109,6 +262,9 @@ pub struct StatusResponse {
     pub finished_files: u32,
     pub failed_files: u32,
     pub stored_files: u32,
+    pub complete_files: u32,
+    pub partial_files: u32,
+    pub orphaned_files: u32,
     pub start_time: Option<u64>,
     pub last_sync: Option<u64>,
     pub last_ok_sync: Option<u64>,
@@ -116,6 +272,78 @@ pub struct StatusResponse {
     pub error_message: Option<String>,
     pub files: HashMap<String, FileProgressResponse>,
     pub storage_dir: PathBuf,
+    pub budget_exhausted: bool,
+    pub month_bytes: u64,
+    pub monthly_budget_bytes: Option<u64>,
+    pub budget_override: bool,
+    pub task_restarts: BTreeMap<String, u32>,
+    pub scheduler_paused: bool,
+    pub health: HealthState,
+    pub subsystems: Vec<SubsystemHealth>,
+    pub start_time_display: Option<String>,
+    pub last_sync_display: Option<String>,
+    pub last_ok_sync_display: Option<String>,
+    pub backoff: Vec<FileBackoff>,
+    pub history: Vec<SyncRun>,
+    pub stale_files: Vec<StaleFile>,
+    pub groups: Vec<GroupStatus>,
+}
+
+#[derive(Serialize)]
+pub struct GroupStatus {
+    pub name: String,
+    pub files: u32,
+    pub failed: u32,
+    pub interval_secs: Option<u64>,
+    pub last_sync: Option<u64>,
+    pub last_sync_display: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SyncRun {
+    pub start_time: u64,
+    pub start_time_display: Option<String>,
+    pub finish_time: u64,
+    pub finish_time_display: Option<String>,
+    pub duration_ms: u64,
+    pub result: SyncResult,
+    pub error_message: Option<String>,
+    pub total_files: u32,
+    pub failed_files: u32,
+    pub bytes_transferred: u64,
+    pub bytes_saved: u64,
+    pub throughput_bytes_per_sec: u64,
+}
+
+#[derive(Serialize)]
+pub struct FileBackoff {
+    pub file: String,
+    pub failures: u32,
+    pub skip_cycles: u32,
+    pub last_error: String,
+}
+
+#[derive(Serialize)]
+pub enum HealthState {
+    Ok,
+    Degraded,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct SubsystemHealth {
+    pub name: String,
+    pub state: HealthState,
+    pub reason: Option<String>,
+    pub since: u64,
+    pub since_display: Option<String>,
+}
+
+/// GET /readyz
+#[derive(Serialize)]
+pub struct ReadyzResponse {
+    pub status: HealthState,
+    pub subsystems: Vec<SubsystemHealth>,
 }
 
 // ======================
@@ -126,7 +354,8 @@ pub type ListFilesResponse = Vec<FileInfo>;
 pub struct FileInfo {
     pub filename: String,
     pub url: String,
-    pub last_modified: String,
+    pub last_modified: Option<u64>,
+    pub last_modified_display: Option<String>,
 }
 
 // ======================
@@ -136,6 +365,9 @@ pub struct FileInfo {
 pub struct FileItem {
     pub filename: String,
     pub path: String,
+    /// 备用上游，按优先级排列
+    #[serde(default)]
+    pub mirrors: Vec<String>,
 }
 #[derive(Deserialize)]
 pub struct UpdateFilesRequest {
@@ -143,6 +375,237 @@ pub struct UpdateFilesRequest {
     pub remove_files: Vec<String>,
     pub replace_all: bool,
     pub replace_files: Vec<FileItem>,
+    /// 随配置修订记录的说明
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+// ======================
+// Prefetch DTO
+// ======================
+#[derive(Deserialize)]
+pub struct PrefetchRequest {
+    pub names: Vec<String>,
+    #[serde(default = "default_true")]
+    pub ignore_rate_limits: bool,
+}
+#[derive(Serialize)]
+pub struct PrefetchItem {
+    pub name: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+#[derive(Serialize)]
+pub struct PrefetchResponse {
+    pub items: Vec<PrefetchItem>,
+}
+
+// ======================
+// SyncFile DTO
+// ======================
+#[derive(Deserialize)]
+pub struct SyncFileRequest {
+    pub name: String,
+}
+#[derive(Serialize)]
+pub struct SyncFileResponse {
+    pub name: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub updated: bool,
+    pub sha256: Option<String>,
+}
+
+// ======================
+// SyncGroup DTO
+// ======================
+#[derive(Deserialize)]
+pub struct SyncGroupRequest {
+    pub name: String,
+}
+#[derive(Serialize)]
+pub struct SyncGroupResponse {
+    pub group: String,
+    pub files: Vec<SyncFileResponse>,
+    pub failed: u32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// ======================
+// PurgeCache DTO
+// ======================
+#[derive(Deserialize)]
+pub struct PurgeCacheRequest {
+    pub pattern: String,
+    #[serde(default)]
+    pub prewarm: bool,
+}
+#[derive(Serialize)]
+pub struct PurgeCacheResponse {
+    pub purged: Vec<String>,
+    pub prewarmed: Vec<String>,
+}
+
+// ======================
+// BudgetOverride DTO
+// ======================
+#[derive(Deserialize)]
+pub struct BudgetOverrideRequest {
+    pub enabled: bool,
+}
+#[derive(Serialize)]
+pub struct BudgetOverrideResponse {
+    pub message: String,
+}
+
+// ======================
+// Bandwidth DTO
+// ======================
+#[derive(Serialize)]
+pub struct OriginBandwidth {
+    pub host: String,
+    pub bytes_today: u64,
+    pub daily_budget: Option<u64>,
+}
+#[derive(Serialize)]
+pub struct BandwidthUsage {
+    pub day: String,
+    pub host: String,
+    pub bytes: u64,
+}
+#[derive(Serialize)]
+pub struct BandwidthResponse {
+    pub today: Vec<OriginBandwidth>,
+    pub history: Vec<BandwidthUsage>,
+}
+
+// ======================
+// 下载续传统计
+// ======================
+#[derive(Deserialize)]
+pub struct TransferStatsQuery {
+    pub limit: Option<u32>,
+}
+#[derive(Serialize)]
+pub struct FileTransferStats {
+    pub file: String,
+    pub requests: u64,
+    pub resumes: u64,
+    pub last_resume: Option<u64>,
+    pub last_resume_display: Option<String>,
+}
+#[derive(Serialize)]
+pub struct TransferStatsResponse {
+    pub files: Vec<FileTransferStats>,
+}
+
+// ======================
+// 下载统计
+// ======================
+#[derive(Deserialize)]
+pub struct FileStatsQuery {
+    pub limit: Option<u32>,
+    /// requests（默认）/ bytes / last_access
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub include_unused: bool,
+}
+#[derive(Serialize)]
+pub struct FileStat {
+    pub file: String,
+    pub requests: u64,
+    pub bytes: u64,
+    pub last_access: Option<u64>,
+    pub last_access_display: Option<String>,
+}
+#[derive(Serialize)]
+pub struct FileStatsResponse {
+    pub files: Vec<FileStat>,
+}
+
+// ======================
+// 新鲜度
+// ======================
+#[derive(Serialize)]
+pub struct StaleFile {
+    pub file: String,
+    pub fetched_at: Option<u64>,
+    pub fetched_at_display: Option<String>,
+    pub age_secs: Option<u64>,
+    pub max_age_secs: u64,
+    pub critical: bool,
+}
+#[derive(Serialize)]
+pub struct FreshnessResponse {
+    pub files: Vec<StaleFile>,
+}
+
+// ======================
+// 存储完整性检查
+// ======================
+#[derive(Serialize)]
+pub struct VerifyStorageResponse {
+    pub checked: u64,
+    pub unhashed: u64,
+    pub bytes_hashed: u64,
+    pub findings: Vec<StorageFinding>,
+}
+#[derive(Serialize)]
+pub struct StorageFinding {
+    pub file: String,
+    pub problem: String,
+    pub detail: String,
+}
+
+#[derive(Deserialize)]
+pub struct RepairStorageRequest {
+    /// 只检查这些本地路径，为空时检查全部
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+#[derive(Serialize)]
+pub struct RepairStorageResponse {
+    pub checked: u64,
+    pub files: Vec<RepairOutcome>,
+}
+#[derive(Serialize)]
+pub struct RepairOutcome {
+    pub file: String,
+    pub problem: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub sha256: Option<String>,
+}
+
+// ======================
+// SSE 同步事件
+// ======================
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncEventMessage {
+    SyncStarted { total_files: u32 },
+    FileStarted { file: String, total: u64 },
+    FileProgress { file: String, downloaded: u64, total: u64 },
+    FileFinished { file: String },
+    FileError { file: String, error: String },
+    SyncFinished { result: SyncResult, error_message: Option<String> },
+}
+
+impl SyncEventMessage {
+    /// SSE event 名称，与 JSON 中的 type 一致
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncEventMessage::SyncStarted { .. } => "sync_started",
+            SyncEventMessage::FileStarted { .. } => "file_started",
+            SyncEventMessage::FileProgress { .. } => "file_progress",
+            SyncEventMessage::FileFinished { .. } => "file_finished",
+            SyncEventMessage::FileError { .. } => "file_error",
+            SyncEventMessage::SyncFinished { .. } => "sync_finished",
+        }
+    }
 }
 
 // ======================
@@ -152,3 +615,20 @@ pub struct UpdateFilesRequest {
 pub struct UpdateFilesResponse {
     pub message: String,
 }
+
+// ======================
+// SignUrl DTO
+// ======================
+#[derive(Deserialize)]
+pub struct SignUrlRequest {
+    pub path: String,
+    /// 有效期，不指定时为 default_ttl_secs
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+}
+#[derive(Serialize)]
+pub struct SignUrlResponse {
+    pub url: String,
+    pub expires: u64,
+    pub expires_display: Option<String>,
+}
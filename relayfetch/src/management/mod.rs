@@ -15,22 +15,31 @@ pub use http::serve_http;
 #[cfg(feature = "management_core")]
 use crate::config::ConfigCenter;
 #[cfg(feature = "management_core")]
+use crate::proxy_cache::ProxyCache;
+#[cfg(feature = "management_core")]
 use std::sync::Arc;
 
 #[cfg(feature = "management_core")]
-pub async fn admin_server(cc: Arc<ConfigCenter>) {
+pub async fn admin_server(cc: Arc<ConfigCenter>, proxy_cache: Arc<ProxyCache>) {
+    use crate::health::Subsystem;
     use crate::management::core::ManagementCore;
     use log::error;
 
-    let core = Arc::new(ManagementCore::new(cc.clone()));
+    let core = Arc::new(ManagementCore::new(cc.clone(), proxy_cache));
 
     #[cfg(feature = "grpc_management")]
     {
         let grpc_addr = cc.config().await.grpc_admin.parse().unwrap();
         let grpc_core = core.clone();
-        tokio::spawn(async move {
-            if let Err(e) = serve_grpc(grpc_addr, grpc_core).await {
-                error!("Management gRPC error: {e:?}");
+        let grpc_cc = cc.clone();
+        crate::supervise::spawn(cc.clone(), Subsystem::GrpcAdmin, move || {
+            let (cc, grpc_core) = (grpc_cc.clone(), grpc_core.clone());
+            async move {
+                cc.health().ok(Subsystem::GrpcAdmin);
+                if let Err(e) = serve_grpc(grpc_addr, grpc_core).await {
+                    error!("Management gRPC error: {e:?}");
+                    cc.health().failed(Subsystem::GrpcAdmin, format!("{:#}", e));
+                }
             }
         });
     }
@@ -39,9 +48,15 @@ pub async fn admin_server(cc: Arc<ConfigCenter>) {
     {
         let http_addr = cc.config().await.http_admin.parse().unwrap();
         let http_core = core.clone();
-        tokio::spawn(async move {
-            if let Err(e) = serve_http(http_addr, http_core).await {
-                error!("Management HTTP error: {e:?}");
+        let http_cc = cc.clone();
+        crate::supervise::spawn(cc.clone(), Subsystem::HttpAdmin, move || {
+            let (cc, http_core) = (http_cc.clone(), http_core.clone());
+            async move {
+                cc.health().ok(Subsystem::HttpAdmin);
+                if let Err(e) = serve_http(http_addr, http_core).await {
+                    error!("Management HTTP error: {e:?}");
+                    cc.health().failed(Subsystem::HttpAdmin, format!("{:#}", e));
+                }
             }
         });
     }
@@ -0,0 +1,78 @@
+//! 通过 mDNS / DNS-SD 在局域网内广播下载服务（`_relayfetch._tcp`）
+//!
+//! TXT 记录给出下载地址、所镜像的远端清单地址与本机的客户端清单路径，
+//! 实验室内的客户端与其他 relay 可据此自动发现最近的镜像。修改配置需重启生效。
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+
+use crate::config::config::Config;
+
+const SERVICE_TYPE: &str = "_relayfetch._tcp.local.";
+
+pub struct Advertiser {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Advertiser {
+    /// 注册服务；`[mdns]` 未配置时返回 None
+    pub fn start(cfg: &Config) -> Result<Option<Self>> {
+        let Some(mdns) = &cfg.mdns else {
+            return Ok(None);
+        };
+
+        let url = mdns
+            .url
+            .clone()
+            .unwrap_or_else(|| format!("http://{}:{}/", cfg.url, cfg.bind_port));
+        let mut txt = vec![
+            ("url", url),
+            ("version", env!("CARGO_PKG_VERSION").to_string()),
+            ("listing", if cfg.enable_listing { "1" } else { "0" }.to_string()),
+        ];
+        if let Some(manifest) = &cfg.files_manifest {
+            txt.push(("manifest", manifest.url.clone()));
+        }
+        if cfg.client_manifest.is_some() {
+            txt.push(("files", "/.well-known/relayfetch.json".to_string()));
+        }
+
+        // 监听具体地址时只广播该地址，否则跟随本机网卡地址变化
+        let ip = match cfg.bind_addr.as_str() {
+            "0.0.0.0" | "::" | "[::]" => "",
+            addr => addr,
+        };
+        let host: String = mdns
+            .instance
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &mdns.instance,
+            &format!("{}.local.", host),
+            ip,
+            cfg.bind_port,
+            &txt[..],
+        )
+        .context("invalid mDNS service info")?
+        .enable_addr_auto();
+
+        let daemon = ServiceDaemon::new().context("failed to start mDNS daemon")?;
+        let fullname = info.get_fullname().to_string();
+        daemon.register(info).context("failed to register mDNS service")?;
+        info!("[mdns] advertising {} on port {}", fullname, cfg.bind_port);
+
+        Ok(Some(Self { daemon, fullname }))
+    }
+
+    /// 注销服务（发送 goodbye）并停止守护线程
+    pub fn stop(self) {
+        if let Err(e) = self.daemon.unregister(&self.fullname) {
+            warn!("[mdns] failed to unregister {}: {}", self.fullname, e);
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
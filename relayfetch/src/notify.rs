@@ -0,0 +1,343 @@
+//! 文件生命周期命令（`[notify]`）与内容更新钩子（`on_update`）
+//!
+//! 文件下载完成（内容有变化）、同步失败、被删除时按配置执行 shell 命令。
+//! 命令模板中的 `{file}` 等占位符替换为单引号转义后的值，整体作为 `sh -c` 的脚本执行，
+//! 因此占位符不要再加引号。同时运行的命令数受 `max_concurrent` 限制，
+//! 其余排队，队列满时丢弃并记录警告。
+//!
+//! 文件被替换为 sha256 不同的新版本后，执行 config.toml 与 files.toml 条目中的 `on_update` 钩子：
+//! 命令不做占位符替换，文件信息在 `RELAYFETCH_*` 环境变量中；HTTP 回调以 POST 发送同样内容的 JSON。
+//! 钩子与 `[notify]` 的命令共用并发数与超时，失败只记录日志。
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde_json::json;
+
+use crate::config::config::{NotifyConfig, NotifyEvent};
+use crate::config::file::UpdateHook;
+use crate::sync::meta::Meta;
+
+/// 等待执行的命令上限
+const MAX_QUEUED: usize = 1024;
+/// 命令失败时日志中保留的 stderr 长度
+const STDERR_LOG_BYTES: usize = 512;
+
+/// 一次生命周期事件
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub event: NotifyEvent,
+    /// 存储目录下的相对路径
+    pub file: String,
+    /// 本地绝对路径
+    pub path: String,
+    pub sha256: Option<String>,
+    /// 被替换的旧版本的 sha256
+    pub old_sha256: Option<String>,
+    pub size: Option<u64>,
+    pub source: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Notification {
+    fn new(event: NotifyEvent, file: &str, path: &Path) -> Self {
+        Self {
+            event,
+            file: file.to_string(),
+            path: path.display().to_string(),
+            sha256: None,
+            old_sha256: None,
+            size: None,
+            source: None,
+            error: None,
+        }
+    }
+
+    pub fn downloaded(file: &str, path: &Path, meta: &Meta) -> Self {
+        Self {
+            sha256: meta.sha256.clone(),
+            size: meta.total_size,
+            source: meta.source.clone(),
+            ..Self::new(NotifyEvent::Downloaded, file, path)
+        }
+    }
+
+    /// 记录被替换的旧版本
+    pub fn replacing(self, old: &Meta) -> Self {
+        Self {
+            old_sha256: old.sha256.clone(),
+            ..self
+        }
+    }
+
+    /// 内容有变化：下载完成且 sha256 与旧版本不同（包括首次下载）
+    pub fn updated(&self) -> bool {
+        self.event == NotifyEvent::Downloaded && self.sha256.is_some() && self.sha256 != self.old_sha256
+    }
+
+    pub fn failed(file: &str, path: &Path, error: &str) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Self::new(NotifyEvent::Failed, file, path)
+        }
+    }
+
+    pub fn deleted(file: &str, path: &Path) -> Self {
+        Self::new(NotifyEvent::Deleted, file, path)
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "event" => self.event.as_str().to_string(),
+            "file" => self.file.clone(),
+            "path" => self.path.clone(),
+            "sha256" => self.sha256.clone().unwrap_or_default(),
+            "old_sha256" => self.old_sha256.clone().unwrap_or_default(),
+            "size" => self.size.map(|s| s.to_string()).unwrap_or_default(),
+            "source" => self.source.clone().unwrap_or_default(),
+            "error" => self.error.clone().unwrap_or_default(),
+            _ => return None,
+        })
+    }
+
+    /// on_update 钩子的环境变量
+    fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![("RELAYFETCH_EVENT", "update".to_string())];
+        for (key, name) in [
+            ("RELAYFETCH_FILE", "file"),
+            ("RELAYFETCH_PATH", "path"),
+            ("RELAYFETCH_SHA256", "sha256"),
+            ("RELAYFETCH_OLD_SHA256", "old_sha256"),
+            ("RELAYFETCH_SIZE", "size"),
+            ("RELAYFETCH_SOURCE", "source"),
+        ] {
+            env.push((key, self.var(name).unwrap_or_default()));
+        }
+        env
+    }
+
+    /// on_update HTTP 回调的请求体
+    fn json(&self) -> serde_json::Value {
+        json!({
+            "event": "update",
+            "file": self.file,
+            "path": self.path,
+            "sha256": self.sha256,
+            "old_sha256": self.old_sha256,
+            "size": self.size,
+            "source": self.source,
+        })
+    }
+}
+
+struct Job {
+    /// 日志中的名称：事件名或 on_update
+    kind: &'static str,
+    file: String,
+    action: Action,
+    timeout: Duration,
+}
+
+enum Action {
+    Script {
+        script: String,
+        env: Vec<(&'static str, String)>,
+    },
+    Post {
+        client: reqwest::Client,
+        url: String,
+        headers: std::collections::BTreeMap<String, String>,
+        body: serde_json::Value,
+    },
+}
+
+#[derive(Default)]
+struct State {
+    running: usize,
+    queue: VecDeque<Job>,
+}
+
+#[derive(Default)]
+pub struct Notifier {
+    state: Arc<Mutex<State>>,
+}
+
+impl Notifier {
+    /// 执行与事件匹配的命令，不等待其结束
+    pub fn emit(&self, cfg: &NotifyConfig, n: &Notification) {
+        for cmd in cfg.commands.iter().filter(|c| c.event == n.event) {
+            let script = match render(&cmd.command, n) {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("[notify] invalid command template {:?}: {}", cmd.command, e);
+                    continue;
+                }
+            };
+            self.submit(cfg, n.event.as_str(), n, Action::Script { script, env: Vec::new() });
+        }
+    }
+
+    /// 执行 on_update 钩子，不等待其结束；并发数与超时取自 `[notify]`
+    pub fn on_update(&self, cfg: &NotifyConfig, hooks: &[UpdateHook], client: Option<&reqwest::Client>, n: &Notification) {
+        for hook in hooks {
+            let action = match hook {
+                UpdateHook::Command { command } => Action::Script {
+                    script: command.clone(),
+                    env: n.env(),
+                },
+                UpdateHook::Http { url, headers } => {
+                    let Some(client) = client else {
+                        warn!("[notify] no HTTP client, skipping on_update callback {} for {}", url, n.file);
+                        continue;
+                    };
+                    Action::Post {
+                        client: client.clone(),
+                        url: url.clone(),
+                        headers: headers.clone(),
+                        body: n.json(),
+                    }
+                }
+            };
+            self.submit(cfg, "on_update", n, action);
+        }
+    }
+
+    fn submit(&self, cfg: &NotifyConfig, kind: &'static str, n: &Notification, action: Action) {
+        let job = Job {
+            kind,
+            file: n.file.clone(),
+            action,
+            timeout: Duration::from_secs(cfg.timeout_secs),
+        };
+        let mut s = self.state.lock().unwrap();
+        if s.running < cfg.max_concurrent.max(1) {
+            s.running += 1;
+            tokio::spawn(run(self.state.clone(), job));
+        } else if s.queue.len() < MAX_QUEUED {
+            s.queue.push_back(job);
+        } else {
+            warn!("[notify] queue full, dropping {} command for {}", kind, n.file);
+        }
+    }
+}
+
+/// 依次执行命令，队列为空时退出
+async fn run(state: Arc<Mutex<State>>, mut job: Job) {
+    loop {
+        match &job.action {
+            Action::Script { script, env } => execute(&job, script, env).await,
+            Action::Post { client, url, headers, body } => post(&job, client, url, headers, body).await,
+        }
+        let mut s = state.lock().unwrap();
+        match s.queue.pop_front() {
+            Some(next) => job = next,
+            None => {
+                s.running -= 1;
+                return;
+            }
+        }
+    }
+}
+
+async fn execute(job: &Job, script: &str, env: &[(&'static str, String)]) {
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("[notify] failed to start {} command for {}: {}", job.kind, job.file, e);
+            return;
+        }
+    };
+
+    match tokio::time::timeout(job.timeout, child.wait_with_output()).await {
+        Ok(Ok(out)) if out.status.success() => {
+            info!("[notify] {} command for {} finished", job.kind, job.file);
+        }
+        Ok(Ok(out)) => {
+            let stderr = String::from_utf8_lossy(&out.stderr[..out.stderr.len().min(STDERR_LOG_BYTES)]);
+            warn!(
+                "[notify] {} command for {} exited with {}: {}",
+                job.kind,
+                job.file,
+                out.status,
+                stderr.trim()
+            );
+        }
+        Ok(Err(e)) => warn!("[notify] {} command for {} failed: {}", job.kind, job.file, e),
+        Err(_) => warn!(
+            "[notify] {} command for {} timed out after {:?}, killed",
+            job.kind,
+            job.file,
+            job.timeout
+        ),
+    }
+}
+
+async fn post(
+    job: &Job,
+    client: &reqwest::Client,
+    url: &str,
+    headers: &std::collections::BTreeMap<String, String>,
+    body: &serde_json::Value,
+) {
+    let mut req = client.post(url).timeout(job.timeout).json(body);
+    for (name, value) in headers {
+        req = req.header(name, value);
+    }
+    match req.send().await {
+        Ok(resp) if resp.status().is_success() => {
+            info!("[notify] {} callback {} for {} returned {}", job.kind, url, job.file, resp.status());
+        }
+        Ok(resp) => warn!("[notify] {} callback {} for {} returned {}", job.kind, url, job.file, resp.status()),
+        Err(e) => warn!("[notify] {} callback {} for {} failed: {}", job.kind, url, job.file, e),
+    }
+}
+
+/// 替换 `{name}` 占位符（`{{` / `}}` 为字面花括号），值一律单引号转义
+fn render(template: &str, n: &Notification) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("unterminated placeholder".into()),
+                    }
+                }
+                let value = n.var(&name).ok_or_else(|| format!("unknown placeholder {{{}}}", name))?;
+                out.push_str(&quote(&value));
+            }
+            '}' => return Err("unmatched '}'".into()),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// POSIX shell 单引号转义：`'` 变为 `'\''`
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
@@ -0,0 +1,124 @@
+//! 回源请求合并（singleflight）
+//!
+//! 同一缓存键同时只有一个请求回源（Leader），其余请求等待其完成后直接读缓存（Waiter）。
+//! 每个回源上的等待者数量有上限，超出返回 503，避免热门新对象拖垮内存。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+
+/// 合并相关指标
+#[derive(Default)]
+pub struct CoalesceMetrics {
+    pub inflight: AtomicU64,        // 正在回源的对象数
+    pub waiting: AtomicU64,         // 当前等待中的请求数
+    pub coalesced_total: AtomicU64, // 累计被合并的请求数
+    pub rejected_total: AtomicU64,  // 累计因等待者超限被拒绝的请求数
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CoalesceStats {
+    pub inflight: u64,
+    pub waiting: u64,
+    pub coalesced_total: u64,
+    pub rejected_total: u64,
+}
+
+struct FlightState {
+    tx: watch::Sender<bool>,
+    waiters: AtomicUsize,
+}
+
+#[derive(Default)]
+pub struct Flights {
+    map: Mutex<HashMap<String, Arc<FlightState>>>,
+    pub metrics: CoalesceMetrics,
+}
+
+pub enum Flight {
+    Leader(FlightGuard),
+    Waiter(Waiter),
+    Overflow,
+}
+
+impl Flights {
+    /// 加入某个键的回源：无进行中的回源则成为 Leader，否则排队等待
+    pub fn join(self: &Arc<Self>, key: &str, max_waiters: usize) -> Flight {
+        let mut map = self.map.lock().unwrap();
+
+        if let Some(state) = map.get(key) {
+            if state.waiters.fetch_add(1, Ordering::SeqCst) >= max_waiters {
+                state.waiters.fetch_sub(1, Ordering::SeqCst);
+                self.metrics.rejected_total.fetch_add(1, Ordering::Relaxed);
+                return Flight::Overflow;
+            }
+            self.metrics.coalesced_total.fetch_add(1, Ordering::Relaxed);
+            self.metrics.waiting.fetch_add(1, Ordering::Relaxed);
+            return Flight::Waiter(Waiter {
+                rx: state.tx.subscribe(),
+                state: state.clone(),
+                flights: self.clone(),
+            });
+        }
+
+        let (tx, _) = watch::channel(false);
+        let state = Arc::new(FlightState {
+            tx,
+            waiters: AtomicUsize::new(0),
+        });
+        map.insert(key.to_string(), state.clone());
+        self.metrics.inflight.fetch_add(1, Ordering::Relaxed);
+
+        Flight::Leader(FlightGuard {
+            key: key.to_string(),
+            state,
+            flights: self.clone(),
+        })
+    }
+
+    pub fn stats(&self) -> CoalesceStats {
+        CoalesceStats {
+            inflight: self.metrics.inflight.load(Ordering::Relaxed),
+            waiting: self.metrics.waiting.load(Ordering::Relaxed),
+            coalesced_total: self.metrics.coalesced_total.load(Ordering::Relaxed),
+            rejected_total: self.metrics.rejected_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Leader 持有；释放时唤醒所有等待者
+pub struct FlightGuard {
+    key: String,
+    state: Arc<FlightState>,
+    flights: Arc<Flights>,
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        self.flights.map.lock().unwrap().remove(&self.key);
+        self.flights.metrics.inflight.fetch_sub(1, Ordering::Relaxed);
+        self.state.tx.send_replace(true);
+    }
+}
+
+pub struct Waiter {
+    rx: watch::Receiver<bool>,
+    state: Arc<FlightState>,
+    flights: Arc<Flights>,
+}
+
+impl Waiter {
+    /// 等待 Leader 完成（成功或失败）
+    pub async fn wait(mut self) {
+        let _ = self.rx.wait_for(|done| *done).await;
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        self.state.waiters.fetch_sub(1, Ordering::SeqCst);
+        self.flights.metrics.waiting.fetch_sub(1, Ordering::Relaxed);
+    }
+}
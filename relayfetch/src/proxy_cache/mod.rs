@@ -0,0 +1,545 @@
+//! Pull-through 反向代理缓存
+//!
+//! - 请求 `/<prefix>/<rest>` 转发到 `<origin>/<rest>`
+//! - 响应按 URL 哈希存放在 `storage_dir/.cache/` 下
+//! - 遵循上游 Cache-Control / Expires / ETag / Last-Modified
+
+use anyhow::{Context, Result};
+use axum::{
+    body::Body,
+    http::{HeaderMap, StatusCode, header},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{ConfigCenter, config::{ProxyCacheRule, RevalidatePolicy}};
+use crate::sync;
+use crate::quota::Reservation;
+
+mod flight;
+use flight::{Flight, Flights};
+pub use flight::CoalesceStats;
+
+/// 缓存目录（位于 storage_dir 下）
+pub const CACHE_DIR: &str = ".cache";
+
+/// 单个缓存对象的元信息
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct CacheMeta {
+    pub url: String,
+    #[serde(default)]
+    pub path: String, // 本地请求路径（prefix/rest?query），用于 purge 匹配
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+    pub stored_at: i64,  // unix 秒
+    pub expires_at: i64, // unix 秒，超过后需要重新校验
+    pub sha256: Option<String>,
+    /// 负缓存：记录上游返回的 404/410，此时没有缓存体
+    pub negative_status: Option<u16>,
+}
+
+/// 从响应头推导出的缓存策略
+struct Freshness {
+    store: bool,
+    ttl: i64,
+}
+
+/// 一次请求命中的规则与上游地址
+pub struct CacheTarget {
+    pub rule: ProxyCacheRule,
+    pub url: String,
+    pub path: String,
+}
+
+pub struct ProxyCache {
+    cc: Arc<ConfigCenter>,
+    root: PathBuf,
+    client: reqwest::Client,
+    flights: Arc<Flights>,
+}
+
+impl ProxyCache {
+    /// 代理配置在启动时确定，运行期修改 proxy 需重启生效
+    pub async fn new(cc: Arc<ConfigCenter>, storage_root: PathBuf) -> Self {
+        let client = sync::build_client(&*cc.config().await).unwrap_or_else(|e| {
+            warn!("[proxy_cache] failed to build client ({e:?}), falling back to defaults");
+            // 与同步客户端一样不自动解压：缓存保存上游原样的响应体，与一同保存的响应头对应
+            reqwest::Client::builder().no_gzip().no_deflate().no_zstd().no_brotli().build().unwrap_or_default()
+        });
+        Self {
+            cc,
+            root: storage_root.join(CACHE_DIR),
+            client,
+            flights: Arc::new(Flights::default()),
+        }
+    }
+
+    /// 上游 HTTP 客户端（按需回源共用）
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn coalesce_stats(&self) -> CoalesceStats {
+        self.flights.stats()
+    }
+
+    /// 按最长前缀匹配规则，返回规则与上游 URL
+    pub async fn match_rule(&self, path: &str, query: Option<&str>) -> Option<CacheTarget> {
+        let cfg = self.cc.config().await;
+        let rule = cfg
+            .proxy_cache
+            .iter()
+            .filter(|r| {
+                let prefix = r.prefix.trim_matches('/');
+                !prefix.is_empty()
+                    && (path == prefix || path.starts_with(&format!("{}/", prefix)))
+            })
+            .max_by_key(|r| r.prefix.trim_matches('/').len())?
+            .clone();
+
+        let rest = path[rule.prefix.trim_matches('/').len()..].trim_start_matches('/');
+        let mut url = format!("{}/{}", rule.origin.trim_end_matches('/'), rest);
+        let mut local = path.to_string();
+        if let Some(q) = query {
+            url.push('?');
+            url.push_str(q);
+            local.push('?');
+            local.push_str(q);
+        }
+        Some(CacheTarget {
+            rule,
+            url,
+            path: local,
+        })
+    }
+
+    pub async fn serve(&self, target: &CacheTarget) -> Response {
+        let CacheTarget { rule, url, path } = target;
+        let url = url.as_str();
+        let key = cache_key(url);
+        let body_path = self.object_path(&key);
+        let meta_path = body_path.with_extension("meta");
+
+        let meta = load_cache_meta(&meta_path);
+        let now = Utc::now().timestamp();
+
+        // ---------- 0. 负缓存命中 ----------
+        if let Some(m) = &meta
+            && let Some(code) = m.negative_status
+            && now < m.expires_at
+        {
+            return negative(code);
+        }
+
+        let cached = meta
+            .filter(|m| m.negative_status.is_none())
+            .filter(|_| body_path.exists());
+
+        // ---------- 1. 新鲜命中 ----------
+        if let Some(meta) = &cached
+            && now < meta.expires_at
+        {
+            return self.serve_cached(&body_path, meta, "HIT").await;
+        }
+
+        // ---------- 2. 请求合并：同一对象同时只回源一次 ----------
+        let max_waiters = self.cc.config().await.coalesce_max_waiters;
+        let _flight = match self.flights.join(&key, max_waiters) {
+            Flight::Leader(guard) => Some(guard),
+            Flight::Waiter(waiter) => {
+                waiter.wait().await;
+                if let Some(m) = load_cache_meta(&meta_path) {
+                    if let Some(code) = m.negative_status {
+                        return negative(code);
+                    }
+                    if body_path.exists() {
+                        return self.serve_cached(&body_path, &m, "COALESCED").await;
+                    }
+                }
+                // Leader 未写入缓存（不可缓存 / 失败），自行回源
+                None
+            }
+            Flight::Overflow => {
+                warn!("[proxy_cache] too many waiters for {}, rejecting", url);
+                return plain(StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable");
+            }
+        };
+
+        // ---------- 3. 过期或未命中：请求上游 ----------
+        let host = crate::bandwidth::host_of(url).unwrap_or_default();
+        let exhausted = {
+            let cfg = self.cc.config().await;
+            let bandwidth = self.cc.bandwidth();
+            bandwidth.monthly_exhausted(cfg.monthly_budget_bytes)
+                || bandwidth.over_budget(&host, &cfg.origin_daily_budget_bytes)
+        };
+        if exhausted {
+            warn!("[proxy_cache] bandwidth budget for {} exhausted, not fetching {}", host, url);
+            if let Some(meta) = &cached {
+                return self.serve_cached(&body_path, meta, "STALE").await;
+            }
+            return plain(StatusCode::SERVICE_UNAVAILABLE, "Bandwidth budget exhausted");
+        }
+
+        let mut req = self.client.get(url);
+        if let Some(meta) = &cached {
+            if let Some(etag) = &meta.etag {
+                req = req.header(header::IF_NONE_MATCH, etag);
+            }
+            if let Some(lm) = &meta.last_modified {
+                req = req.header(header::IF_MODIFIED_SINCE, lm);
+            }
+        }
+
+        let resp = match req.send().await {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("[proxy_cache] upstream error for {}: {}", url, e);
+                // 上游不可用时返回过期副本（stale-if-error）
+                if let Some(meta) = &cached {
+                    return self.serve_cached(&body_path, meta, "STALE").await;
+                }
+                return plain(StatusCode::BAD_GATEWAY, "Bad Gateway");
+            }
+        };
+
+        let status = resp.status();
+        let fresh = freshness(resp.headers(), rule);
+
+        if status == StatusCode::NOT_MODIFIED
+            && let Some(mut meta) = cached.clone()
+        {
+            meta.expires_at = now.saturating_add(fresh.ttl);
+            if let Err(e) = save_cache_meta(&meta_path, &meta) {
+                warn!("[proxy_cache] failed to update meta for {}: {}", url, e);
+            }
+            return self.serve_cached(&body_path, &meta, "REVALIDATED").await;
+        }
+
+        if status.is_server_error()
+            && let Some(meta) = &cached
+        {
+            return self.serve_cached(&body_path, meta, "STALE").await;
+        }
+
+        // 404 / 410：写入负缓存，避免重复回源
+        if (status == StatusCode::NOT_FOUND || status == StatusCode::GONE)
+            && rule.negative_ttl_secs > 0
+        {
+            let meta = CacheMeta {
+                url: url.to_string(),
+                path: path.clone(),
+                stored_at: now,
+                expires_at: now + rule.negative_ttl_secs as i64,
+                negative_status: Some(status.as_u16()),
+                ..Default::default()
+            };
+            if let Err(e) = sync::meta::ensure_parent_dir(&meta_path)
+                .and_then(|_| save_cache_meta(&meta_path, &meta))
+            {
+                warn!("[proxy_cache] failed to store negative entry for {}: {}", url, e);
+            }
+            let _ = tokio::fs::remove_file(&body_path).await;
+            return self.passthrough(resp, host);
+        }
+
+        // 非 200 或不可缓存：直接透传
+        if status != StatusCode::OK || !fresh.store {
+            return self.passthrough(resp, host);
+        }
+
+        // 与同步下载共用配额；放不下时只透传不缓存
+        let (storage_dir, max_storage) = {
+            let cfg = self.cc.config().await;
+            (cfg.storage_dir.clone(), cfg.max_storage_bytes)
+        };
+        let replaced = std::fs::metadata(&body_path).map(|m| m.len()).unwrap_or(0);
+        let needed = resp.content_length().unwrap_or(0);
+        let mut reservation = match self.cc.quota().reserve(&storage_dir, max_storage, needed, replaced) {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("[proxy_cache] not storing {}: {}", url, e);
+                return self.passthrough(resp, host);
+            }
+        };
+
+        match self.store(target, resp, &host, &body_path, fresh.ttl, &mut reservation).await {
+            Ok((meta, size)) => {
+                reservation.commit(size, replaced);
+                info!("[proxy_cache] stored {}", url);
+                self.serve_cached(&body_path, &meta, "MISS").await
+            }
+            Err(e) => {
+                warn!("[proxy_cache] failed to store {}: {:?}", url, e);
+                plain(StatusCode::BAD_GATEWAY, "Bad Gateway")
+            }
+        }
+    }
+
+    fn object_path(&self, key: &str) -> PathBuf {
+        self.root.join(&key[..2]).join(key)
+    }
+
+    /// 将上游响应写入缓存（tmp + rename），返回缓存项与写入的字节数；超出预留且放不下时中止
+    async fn store(
+        &self,
+        target: &CacheTarget,
+        resp: reqwest::Response,
+        host: &str,
+        body_path: &Path,
+        ttl: i64,
+        reservation: &mut Reservation<'_>,
+    ) -> Result<(CacheMeta, u64)> {
+        sync::meta::ensure_parent_dir(body_path)?;
+        let tmp_path = body_path.with_extension("tmp");
+
+        let header_str = |name: header::HeaderName| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        };
+        let etag = header_str(header::ETAG);
+        let last_modified = header_str(header::LAST_MODIFIED);
+        let content_type = header_str(header::CONTENT_TYPE);
+
+        let mut out = tokio::fs::File::create(&tmp_path).await?;
+        let mut hasher = Sha256::new();
+        let mut written = 0u64;
+        let mut stream = resp.bytes_stream();
+        let streamed: Result<()> = async {
+            while let Some(item) = stream.next().await {
+                let chunk = item.context("error while downloading chunk")?;
+                reservation.grow(written + chunk.len() as u64)?;
+                out.write_all(&chunk).await?;
+                hasher.update(&chunk);
+                written += chunk.len() as u64;
+                self.cc.bandwidth().record(host, chunk.len() as u64);
+            }
+            out.flush().await?;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = streamed {
+            drop(out);
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+        tokio::fs::rename(&tmp_path, body_path).await?;
+
+        let now = Utc::now().timestamp();
+        let meta = CacheMeta {
+            url: target.url.clone(),
+            path: target.path.clone(),
+            etag,
+            last_modified,
+            content_type,
+            stored_at: now,
+            expires_at: now.saturating_add(ttl),
+            sha256: Some(hex::encode(hasher.finalize())),
+            negative_status: None,
+        };
+        save_cache_meta(&body_path.with_extension("meta"), &meta)?;
+        Ok((meta, written))
+    }
+
+    async fn serve_cached(&self, body_path: &Path, meta: &CacheMeta, cache_status: &str) -> Response {
+        let data = match tokio::fs::read(body_path).await {
+            Ok(d) => d,
+            Err(_) => return plain(StatusCode::BAD_GATEWAY, "Bad Gateway"),
+        };
+
+        let mut builder = Response::builder()
+            .status(200)
+            .header("x-relayfetch-cache", cache_status);
+        if let Some(ct) = &meta.content_type {
+            builder = builder.header(header::CONTENT_TYPE, ct);
+        }
+        if let Some(etag) = &meta.etag {
+            builder = builder.header(header::ETAG, etag);
+        }
+        if let Some(lm) = &meta.last_modified {
+            builder = builder.header(header::LAST_MODIFIED, lm);
+        }
+        builder.body(crate::shaping::serve_body(&self.cc, data).await).unwrap()
+    }
+
+    /// 不可缓存的响应：原样流式转发（计入上游流量）
+    fn passthrough(&self, resp: reqwest::Response, host: String) -> Response {
+        let mut builder = Response::builder()
+            .status(resp.status())
+            .header("x-relayfetch-cache", "BYPASS");
+        for name in [header::CONTENT_TYPE, header::CACHE_CONTROL, header::ETAG, header::LAST_MODIFIED] {
+            if let Some(v) = resp.headers().get(&name) {
+                builder = builder.header(name, v.clone());
+            }
+        }
+        let cc = self.cc.clone();
+        let stream = resp.bytes_stream().inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                cc.bandwidth().record(&host, chunk.len() as u64);
+            }
+        });
+        builder.body(Body::from_stream(stream)).unwrap()
+    }
+
+    /// 删除匹配 pattern（glob，作用于本地路径或上游 URL）的缓存对象
+    /// 返回被删除对象的本地路径
+    pub fn purge(&self, pattern: &str) -> Result<Vec<String>> {
+        let matcher = globset::Glob::new(pattern)
+            .with_context(|| format!("invalid pattern: {}", pattern))?
+            .compile_matcher();
+
+        let mut purged = Vec::new();
+        if !self.root.exists() {
+            return Ok(purged);
+        }
+
+        for entry in walkdir::WalkDir::new(&self.root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let meta_path = entry.path();
+            if meta_path.extension().and_then(|s| s.to_str()) != Some("meta") {
+                continue;
+            }
+            let Some(meta) = load_cache_meta(meta_path) else {
+                continue;
+            };
+
+            let hit = meta.path == pattern
+                || meta.url == pattern
+                || matcher.is_match(&meta.path)
+                || matcher.is_match(&meta.url);
+            if !hit {
+                continue;
+            }
+
+            let _ = std::fs::remove_file(meta_path.with_extension(""));
+            match std::fs::remove_file(meta_path) {
+                Ok(_) => purged.push(meta.path),
+                Err(e) => warn!("[proxy_cache] failed to purge {}: {}", meta_path.display(), e),
+            }
+        }
+
+        info!("[proxy_cache] purged {} objects matching {}", purged.len(), pattern);
+        Ok(purged)
+    }
+
+    /// 重新回源拉取指定本地路径，返回是否成功写入缓存
+    pub async fn prewarm(&self, path: &str) -> bool {
+        let (p, q) = match path.split_once('?') {
+            Some((p, q)) => (p, Some(q)),
+            None => (path, None),
+        };
+        let Some(target) = self.match_rule(p, q).await else {
+            return false;
+        };
+        self.serve(&target).await.status() == StatusCode::OK
+    }
+}
+
+/// 缓存键：URL 的 sha256
+pub fn cache_key(url: &str) -> String {
+    hex::encode(Sha256::digest(url.as_bytes()))
+}
+
+pub fn load_cache_meta(path: &Path) -> Option<CacheMeta> {
+    let s = std::fs::read_to_string(path).ok()?;
+    toml::from_str(&s).ok()
+}
+
+pub fn save_cache_meta(path: &Path, meta: &CacheMeta) -> Result<()> {
+    std::fs::write(path, toml::to_string(meta)?)?;
+    Ok(())
+}
+
+/// 按规则的重新校验策略计算缓存有效期
+fn freshness(headers: &HeaderMap, rule: &ProxyCacheRule) -> Freshness {
+    match rule.revalidate {
+        RevalidatePolicy::Origin => origin_freshness(headers, rule.default_ttl_secs),
+        RevalidatePolicy::Fixed => Freshness {
+            store: true,
+            ttl: rule.default_ttl_secs as i64,
+        },
+        RevalidatePolicy::Always => Freshness { store: true, ttl: 0 },
+        RevalidatePolicy::Never => Freshness {
+            store: true,
+            ttl: i64::MAX,
+        },
+    }
+}
+
+/// 解析 Cache-Control / Expires
+/// - no-store / private => 不缓存（本服务是共享缓存）
+/// - no-cache          => 可缓存，但每次都需重新校验
+/// - s-maxage > max-age > Expires > 默认 TTL
+fn origin_freshness(headers: &HeaderMap, default_ttl: u64) -> Freshness {
+    let cc = headers
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    let mut store = true;
+    let mut max_age = None;
+    let mut s_maxage = None;
+    let mut no_cache = false;
+
+    for directive in cc.split(',').map(str::trim) {
+        match directive.split_once('=') {
+            Some(("max-age", v)) => max_age = v.trim_matches('"').parse::<i64>().ok(),
+            Some(("s-maxage", v)) => s_maxage = v.trim_matches('"').parse::<i64>().ok(),
+            _ => match directive {
+                "no-store" | "private" => store = false,
+                "no-cache" => no_cache = true,
+                _ => {}
+            },
+        }
+    }
+
+    let ttl = if no_cache {
+        0
+    } else if let Some(v) = s_maxage.or(max_age) {
+        v
+    } else if let Some(exp) = headers
+        .get(header::EXPIRES)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+    {
+        (exp.with_timezone(&Utc) - Utc::now()).num_seconds()
+    } else {
+        default_ttl as i64
+    };
+
+    Freshness {
+        store,
+        ttl: ttl.max(0),
+    }
+}
+
+
+fn negative(code: u16) -> Response {
+    let status = StatusCode::from_u16(code).unwrap_or(StatusCode::NOT_FOUND);
+    Response::builder()
+        .status(status)
+        .header("x-relayfetch-cache", "NEGATIVE")
+        .body(Body::from(status.canonical_reason().unwrap_or("Not Found")))
+        .unwrap()
+}
+
+fn plain(status: StatusCode, msg: &'static str) -> Response {
+    Response::builder()
+        .status(status)
+        .body(Body::from(msg))
+        .unwrap()
+}
@@ -0,0 +1,198 @@
+//! 存储空间配额与下载前的剩余空间检查
+//!
+//! 下载拿到响应头后按 Content-Length 预留空间：超出 `max_storage_bytes` 或磁盘可用空间
+//! 不足时该文件直接失败，不会写到一半把磁盘占满。没有 Content-Length（或解压后变大）时
+//! 边写边追加预留，追加失败时中止写入。已用空间在每次同步开始时遍历统计，
+//! 之后按完成的下载增减；并发下载的预留量互相扣减。
+//!
+//! 开启 `evict_lru` 时，配额不足会先按下载服务的访问记录淘汰最久未被请求的文件；
+//! 淘汰的文件不会被周期同步重新下载，被请求时再按需拉取。
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+
+use crate::config::ConfigCenter;
+use crate::notify::Notification;
+use crate::storage_index::StoredState;
+use crate::sync::{meta::load_meta, partial::Claim};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpaceError {
+    #[error("storage quota exceeded: needs {needed} bytes, {remaining} of {max} bytes left")]
+    Quota { needed: u64, remaining: u64, max: u64 },
+
+    #[error("not enough disk space: needs {needed} bytes, {available} bytes available")]
+    Disk { needed: u64, available: u64 },
+}
+
+#[derive(Default)]
+struct Usage {
+    /// 存储目录当前占用
+    used: u64,
+    /// 进行中的下载预留、尚未写完的字节数
+    reserved: u64,
+}
+
+#[derive(Default)]
+pub struct StorageQuota {
+    usage: Mutex<Usage>,
+}
+
+impl StorageQuota {
+    /// 遍历统计存储目录（含内部数据与未完成的下载）的占用
+    pub fn refresh(&self, storage_dir: &Path) {
+        let used = walkdir::WalkDir::new(storage_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum();
+        debug!("[quota] {} uses {} bytes", storage_dir.display(), used);
+        self.usage.lock().unwrap().used = used;
+    }
+
+    /// 为一次下载预留 `needed` 字节；`replaced` 为完成后被覆盖的旧文件大小，只计入配额
+    pub fn reserve(
+        &self,
+        storage_dir: &Path,
+        max: Option<u64>,
+        needed: u64,
+        replaced: u64,
+    ) -> Result<Reservation<'_>, SpaceError> {
+        let mut usage = self.usage.lock().unwrap();
+        check(&usage, storage_dir, max, needed, replaced)?;
+        usage.reserved += needed;
+        Ok(Reservation {
+            quota: self,
+            storage_dir: storage_dir.to_path_buf(),
+            max,
+            replaced,
+            bytes: needed,
+        })
+    }
+
+    /// 文件被删除后扣减已用空间
+    pub fn release(&self, bytes: u64) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.used = usage.used.saturating_sub(bytes);
+    }
+}
+
+/// 在已有的占用与预留之外是否还放得下 `needed` 字节
+fn check(usage: &Usage, storage_dir: &Path, max: Option<u64>, needed: u64, replaced: u64) -> Result<(), SpaceError> {
+    if let Some(max) = max {
+        let remaining = (max + replaced).saturating_sub(usage.used + usage.reserved);
+        if needed > remaining {
+            return Err(SpaceError::Quota { needed, remaining, max });
+        }
+    }
+
+    // 旧文件在替换前一直占用磁盘，可用空间不扣除它
+    match fs4::available_space(storage_dir) {
+        Ok(available) => {
+            let available = available.saturating_sub(usage.reserved);
+            if needed > available {
+                return Err(SpaceError::Disk { needed, available });
+            }
+        }
+        Err(e) => warn!("[quota] failed to query free space of {}: {}", storage_dir.display(), e),
+    }
+    Ok(())
+}
+
+/// 按最近被下载的时间淘汰已完成的文件，直到腾出 `needed` 字节，返回实际腾出的字节数。
+/// 从未被下载过的文件最先淘汰；meta 中没有记录上游、或正在下载的文件不会被淘汰
+pub async fn evict_lru(cc: &ConfigCenter, storage_dir: &Path, needed: u64) -> u64 {
+    let ttl = Duration::from_secs(cc.config().await.storage_index_ttl_secs);
+    let index = cc.storage_index().files(storage_dir, ttl).await;
+    let mut candidates: Vec<_> = index
+        .files
+        .iter()
+        .filter(|(_, f)| f.state == StoredState::Complete)
+        .map(|(rel, f)| (cc.access().last_served(rel), f.last_modified, rel.clone()))
+        .collect();
+    candidates.sort();
+
+    let mut freed = 0;
+    for (last_served, _, rel) in candidates {
+        if freed >= needed {
+            break;
+        }
+        let path = storage_dir.join(&rel);
+        let Some(_claim) = Claim::acquire(&path) else {
+            continue;
+        };
+        let meta_path = path.with_extension("meta");
+        let Some(source) = load_meta(&meta_path).ok().and_then(|m| m.source) else {
+            continue;
+        };
+
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+            + std::fs::metadata(&meta_path).map(|m| m.len()).unwrap_or(0);
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            warn!("[quota] failed to evict {}: {}", path.display(), e);
+            continue;
+        }
+        let _ = tokio::fs::remove_file(&meta_path).await;
+
+        cc.quota().release(size);
+        cc.storage_index().update(storage_dir, &path);
+        cc.access().mark_evicted(&rel, source);
+        info!("[quota] evicted {} ({} bytes, last served: {:?})", rel, size, last_served);
+        cc.notify(Notification::deleted(&rel, &path)).await;
+        freed += size;
+    }
+    freed
+}
+
+/// 下载期间持有，drop 时释放预留
+pub struct Reservation<'a> {
+    quota: &'a StorageQuota,
+    storage_dir: PathBuf,
+    max: Option<u64>,
+    replaced: u64,
+    bytes: u64,
+}
+
+/// 写入超出预留时每次至少追加的字节数，避免每块都查询磁盘空间
+const GROW_STEP: u64 = 8 * 1024 * 1024;
+
+impl Reservation<'_> {
+    /// 已写入 `written` 字节：超出预留时追加，放不下时返回错误，调用方应中止写入
+    pub fn grow(&mut self, written: u64) -> Result<(), SpaceError> {
+        if written <= self.bytes {
+            return Ok(());
+        }
+        let extra = written - self.bytes;
+        let mut usage = self.quota.usage.lock().unwrap();
+        // 余量不足一步时只追加实际超出的部分
+        let step = extra.max(GROW_STEP);
+        let extra = match check(&usage, &self.storage_dir, self.max, step, self.replaced) {
+            Ok(()) => step,
+            Err(_) => {
+                check(&usage, &self.storage_dir, self.max, extra, self.replaced)?;
+                extra
+            }
+        };
+        usage.reserved += extra;
+        self.bytes += extra;
+        Ok(())
+    }
+
+    /// 下载完成：写入 `stored` 字节并替换了 `replaced` 字节的旧文件
+    pub fn commit(self, stored: u64, replaced: u64) {
+        let mut usage = self.quota.usage.lock().unwrap();
+        usage.used = (usage.used + stored).saturating_sub(replaced);
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let mut usage = self.quota.usage.lock().unwrap();
+        usage.reserved = usage.reserved.saturating_sub(self.bytes);
+    }
+}
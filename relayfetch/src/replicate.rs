@@ -0,0 +1,274 @@
+//! 下载完成后推送到次级存储（`[replicate]`）
+//!
+//! 文件下载完成且内容有变化时，推送到 prefix 匹配的每个目标：
+//! `s3://` 以 PUT 上传对象；`http(s)://` 按 WebDAV PUT，目标返回 409（父目录不存在）时逐级 MKCOL 后重试；
+//! `relayfetch+http(s)://` 通过对方的 HTTP 管理接口登记指向本节点的条目并让它立即同步。
+//! 推送在后台进行，失败按指数退避重试，全部失败只记录日志。同时进行的推送数受 `max_concurrent` 限制，
+//! 其余排队，队列满时丢弃并记录警告。
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use log::{info, warn};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::{Method, StatusCode, header};
+use serde_json::{Value, json};
+
+use crate::config::config::{Config, ReplicateConfig, ReplicateTarget, S3Config};
+use crate::notify::Notification;
+use crate::sync::{build_client, upstream_request};
+
+/// 等待推送的文件上限
+const MAX_QUEUED: usize = 1024;
+/// 上传或让对方同步一个文件的最长时间；客户端默认的 30 秒不够大文件使用
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(3600);
+/// 第一次重试前的等待，之后每次翻倍
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// URL 路径的编码：除 RFC 3986 非保留字符与 `/` 外全部编码
+const PATH: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'/');
+
+struct Job {
+    /// 存储目录下的相对路径
+    file: String,
+    path: PathBuf,
+    /// 目标中的相对路径
+    dest: String,
+    target: ReplicateTarget,
+    attempts: usize,
+    client: reqwest::Client,
+    s3: S3Config,
+    /// 本节点下载服务的地址
+    public_url: String,
+}
+
+#[derive(Default)]
+struct State {
+    running: usize,
+    queue: VecDeque<Job>,
+}
+
+#[derive(Default)]
+pub struct Replicator {
+    state: Arc<Mutex<State>>,
+}
+
+impl Replicator {
+    /// 为 prefix 匹配的每个目标排队推送，不等待其结束
+    pub fn submit(&self, cfg: &Config, rules: &ReplicateConfig, n: &Notification) {
+        let targets: Vec<_> = rules
+            .targets
+            .iter()
+            .filter_map(|t| dest(t, &n.file).map(|d| (t, d)))
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+        let client = match build_client(cfg) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("[replicate] cannot replicate {}: {:#}", n.file, e);
+                return;
+            }
+        };
+
+        let max = rules.max_concurrent.max(1);
+        for (target, dest) in targets {
+            let job = Job {
+                file: n.file.clone(),
+                path: PathBuf::from(&n.path),
+                dest,
+                target: target.clone(),
+                attempts: rules.attempts.max(1),
+                client: client.clone(),
+                s3: cfg.s3.clone(),
+                public_url: target
+                    .public_url
+                    .clone()
+                    .unwrap_or_else(|| format!("http://{}:{}", cfg.url, cfg.bind_port)),
+            };
+            let mut s = self.state.lock().unwrap();
+            if s.running < max {
+                s.running += 1;
+                tokio::spawn(run(self.state.clone(), job));
+            } else if s.queue.len() < MAX_QUEUED {
+                s.queue.push_back(job);
+            } else {
+                warn!("[replicate] queue full, dropping {} -> {}", n.file, target.target);
+            }
+        }
+    }
+}
+
+/// 文件在目标中的相对路径；prefix 不匹配时为 None
+fn dest(target: &ReplicateTarget, file: &str) -> Option<String> {
+    let prefix = target.prefix.trim_matches('/');
+    if prefix.is_empty() {
+        return Some(file.to_string());
+    }
+    let rest = match file.strip_prefix(prefix)? {
+        // prefix 就是这个文件
+        "" => file.rsplit('/').next().unwrap_or(file),
+        rest => rest.strip_prefix('/')?,
+    };
+    Some(if target.strip_prefix { rest } else { file }.to_string())
+}
+
+/// 依次推送，队列为空时退出
+async fn run(state: Arc<Mutex<State>>, mut job: Job) {
+    loop {
+        replicate(&job).await;
+        let mut s = state.lock().unwrap();
+        match s.queue.pop_front() {
+            Some(next) => job = next,
+            None => {
+                s.running -= 1;
+                return;
+            }
+        }
+    }
+}
+
+async fn replicate(job: &Job) {
+    for attempt in 0..job.attempts {
+        if attempt > 0 {
+            tokio::time::sleep(RETRY_BASE_DELAY * 2u32.saturating_pow(attempt as u32 - 1)).await;
+        }
+        match push(job).await {
+            Ok(()) => {
+                info!("[replicate] {} -> {} ({})", job.file, job.target.target, job.dest);
+                return;
+            }
+            Err(e) => warn!(
+                "[replicate] attempt {}/{} for {} -> {} failed: {:#}",
+                attempt + 1,
+                job.attempts,
+                job.file,
+                job.target.target,
+                e
+            ),
+        }
+    }
+    warn!("[replicate] giving up on {} -> {}", job.file, job.target.target);
+}
+
+async fn push(job: &Job) -> Result<()> {
+    let target = job.target.target.trim_end_matches('/');
+    if let Some(admin) = target.strip_prefix("relayfetch+") {
+        return push_peer(job, admin).await;
+    }
+    if target.starts_with("s3://") {
+        // 对象键由 s3 模块编码
+        let resp = put(job, &format!("{}/{}", target, job.dest)).await?;
+        if !resp.status().is_success() {
+            bail!("PUT returned {}", resp.status());
+        }
+        return Ok(());
+    }
+    if !(target.starts_with("http://") || target.starts_with("https://")) {
+        bail!("unsupported replication target {}", target);
+    }
+
+    let url = format!("{}/{}", target, utf8_percent_encode(&job.dest, PATH));
+    let mut resp = put(job, &url).await?;
+    if resp.status() == StatusCode::CONFLICT {
+        mkcol_parents(job, target).await?;
+        resp = put(job, &url).await?;
+    }
+    if !resp.status().is_success() {
+        bail!("PUT {} returned {}", url, resp.status());
+    }
+    Ok(())
+}
+
+fn request(job: &Job, method: Method, url: &str) -> Result<reqwest::RequestBuilder> {
+    let auth = Some(&job.target.auth).filter(|a| !a.is_empty());
+    upstream_request(&job.client, method, url, auth, &job.s3)
+}
+
+/// 上传文件的当前内容；下载期间被替换时仍读取已打开的旧版本，下一次下载完成会再推送
+async fn put(job: &Job, url: &str) -> Result<reqwest::Response> {
+    let file = tokio::fs::File::open(&job.path).await?;
+    let len = file.metadata().await?.len();
+    Ok(request(job, Method::PUT, url)?
+        .header(header::CONTENT_LENGTH, len)
+        .timeout(TRANSFER_TIMEOUT)
+        .body(file)
+        .send()
+        .await?)
+}
+
+/// 逐级创建 WebDAV 目录；已存在的目录返回 405
+async fn mkcol_parents(job: &Job, base: &str) -> Result<()> {
+    let mkcol = Method::from_bytes(b"MKCOL")?;
+    let mut url = base.to_string();
+    let Some((dirs, _)) = job.dest.rsplit_once('/') else {
+        return Ok(());
+    };
+    for dir in dirs.split('/') {
+        url = format!("{}/{}", url, utf8_percent_encode(dir, PATH));
+        let status = request(job, mkcol.clone(), &format!("{}/", url))?.send().await?.status();
+        if !(status.is_success() || status == StatusCode::METHOD_NOT_ALLOWED) {
+            bail!("MKCOL {} returned {}", url, status);
+        }
+    }
+    Ok(())
+}
+
+/// 在另一节点登记指向本节点的条目，再让它立即同步该条目
+async fn push_peer(job: &Job, admin: &str) -> Result<()> {
+    let source = format!(
+        "{}/{}",
+        job.public_url.trim_end_matches('/'),
+        utf8_percent_encode(&job.file, PATH)
+    );
+    let files = json!({
+        "add_files": [{ "filename": job.dest, "path": source, "mirrors": [] }],
+        "remove_files": [],
+        "replace_all": false,
+        "replace_files": [],
+    });
+    let status = request(job, Method::POST, &format!("{}/update_files", admin))?
+        .json(&files)
+        .send()
+        .await?
+        .status();
+    if !status.is_success() {
+        bail!("update_files returned {}", status);
+    }
+
+    let resp = request(job, Method::POST, &format!("{}/sync_file", admin))?
+        .json(&json!({ "name": job.dest }))
+        .timeout(TRANSFER_TIMEOUT)
+        .send()
+        .await?;
+    match resp.status() {
+        // 对方正在同步，排一轮完整同步，新条目随之下载
+        StatusCode::CONFLICT => {
+            let status = request(job, Method::POST, &format!("{}/trigger_sync", admin))?
+                .send()
+                .await?
+                .status();
+            if !status.is_success() {
+                bail!("trigger_sync returned {}", status);
+            }
+            Ok(())
+        }
+        status if status.is_success() => {
+            let result: Value = resp.json().await?;
+            if result["ok"] != true {
+                bail!("peer failed to sync {}: {}", job.dest, result["error"]);
+            }
+            Ok(())
+        }
+        status => bail!("sync_file returned {}", status),
+    }
+}
@@ -0,0 +1,256 @@
+//! 存储清理策略（`[retention]`）
+//!
+//! `clean_unused_files` 只处理存储目录顶层不再被配置引用的文件，这里按策略清理其余会不断累积的数据：
+//! - 历史版本（`.versions/`）：超过保留时长、单个文件超过版本数、总大小超出上限（从最早替换的删起）
+//! - 数据文件已不存在的 meta
+//! - 长期没有写入的未完成下载（`.partial/`）
+//!
+//! 可按 `interval_secs` 定期执行，也可通过管理接口 RunRetention 执行；dry run 只报告将删除的内容。
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::Utc;
+use log::{info, warn};
+use walkdir::WalkDir;
+
+use crate::config::ConfigCenter;
+use crate::config::config::RetentionConfig;
+use crate::storage_index::is_hidden;
+use crate::sync::meta::load_meta;
+use crate::sync::partial::{self, PARTIAL_DIR};
+use crate::sync::versions::{self, Version};
+
+/// 检查配置是否到了执行时间的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// 刚写入的 meta 不视为孤立，避免与进行中的下载或删除竞争
+const ORPHAN_GRACE: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    VersionAge,
+    VersionCount,
+    VersionBytes,
+    OrphanedMeta,
+    StalePartial,
+}
+
+impl Reason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::VersionAge => "version_age",
+            Self::VersionCount => "version_count",
+            Self::VersionBytes => "version_bytes",
+            Self::OrphanedMeta => "orphaned_meta",
+            Self::StalePartial => "stale_partial",
+        }
+    }
+}
+
+/// 按策略应删除的一项
+#[derive(Debug, Clone)]
+pub struct Removal {
+    /// 存储目录下的相对路径
+    pub path: String,
+    pub reason: Reason,
+    pub size: u64,
+    version: Option<Version>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub dry_run: bool,
+    pub removed: Vec<Removal>,
+    /// 已删除（dry run 时为将删除）的字节数
+    pub freed_bytes: u64,
+}
+
+/// 按 `interval_secs` 定期执行；配置热重载后按新的间隔
+pub fn spawn_scheduler(cc: Arc<ConfigCenter>) {
+    tokio::spawn(async move {
+        let mut last = Instant::now();
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            let Some(policy) = cc.config().await.retention.clone() else {
+                continue;
+            };
+            if policy.interval_secs == 0 || last.elapsed() < Duration::from_secs(policy.interval_secs) {
+                continue;
+            }
+            last = Instant::now();
+            if let Err(e) = run(&cc, &policy, policy.dry_run).await {
+                warn!("[retention] scheduled run failed: {:#}", e);
+            }
+        }
+    });
+}
+
+/// 按策略清理存储目录
+pub async fn run(cc: &ConfigCenter, policy: &RetentionConfig, dry_run: bool) -> anyhow::Result<Report> {
+    let storage_dir = cc.config().await.storage_dir.clone();
+    let policy = policy.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let planned = plan(&storage_dir, &policy, SystemTime::now());
+        if dry_run {
+            let freed_bytes = planned.iter().map(|r| r.size).sum();
+            return Report { dry_run, removed: planned, freed_bytes };
+        }
+        apply(&storage_dir, planned)
+    })
+    .await?;
+
+    if !dry_run {
+        cc.quota().release(report.freed_bytes);
+    }
+    let verb = if dry_run { "would remove" } else { "removed" };
+    for r in &report.removed {
+        info!("[retention] {} {} ({}, {} bytes)", verb, r.path, r.reason.as_str(), r.size);
+    }
+    info!("[retention] {} {} items, {} bytes", verb, report.removed.len(), report.freed_bytes);
+    Ok(report)
+}
+
+fn apply(storage_dir: &Path, planned: Vec<Removal>) -> Report {
+    let mut report = Report::default();
+    for r in planned {
+        let res = match &r.version {
+            Some(v) => versions::remove(storage_dir, v),
+            None => std::fs::remove_file(storage_dir.join(&r.path)),
+        };
+        match res {
+            Ok(()) => {
+                report.freed_bytes += r.size;
+                report.removed.push(r);
+            }
+            Err(e) => warn!("[retention] failed to remove {}: {}", r.path, e),
+        }
+    }
+    report
+}
+
+/// 计算应删除的内容，不做修改
+pub fn plan(storage_dir: &Path, policy: &RetentionConfig, now: SystemTime) -> Vec<Removal> {
+    let mut out = plan_versions(storage_dir, policy, now);
+    if policy.orphaned_meta {
+        out.extend(orphaned_meta(storage_dir, now));
+    }
+    if let Some(max_age) = policy.max_partial_age_secs {
+        out.extend(stale_partials(storage_dir, Duration::from_secs(max_age), now));
+    }
+    out
+}
+
+fn plan_versions(storage_dir: &Path, policy: &RetentionConfig, now: SystemTime) -> Vec<Removal> {
+    let mut all = versions::list(storage_dir);
+    // 从新到旧
+    all.sort_unstable_by(|a, b| b.stamp.cmp(&a.stamp));
+
+    let mut out = Vec::new();
+    let mut kept = Vec::new();
+    let mut per_file: HashMap<String, usize> = HashMap::new();
+    let cutoff = policy
+        .max_version_age_secs
+        .map(|secs| chrono::DateTime::<Utc>::from(now) - chrono::Duration::seconds(secs as i64));
+    for v in all {
+        let count = per_file.entry(v.file.clone()).or_default();
+        *count += 1;
+        let reason = if cutoff.is_some_and(|cutoff| v.replaced_at().is_some_and(|t| t < cutoff)) {
+            Some(Reason::VersionAge)
+        } else if policy.max_versions.is_some_and(|max| *count > max) {
+            Some(Reason::VersionCount)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => out.push(removal(v, reason)),
+            None => kept.push(v),
+        }
+    }
+
+    // 剩余版本仍超出总大小时从最旧的删起
+    if let Some(max_bytes) = policy.max_version_bytes {
+        let mut total: u64 = kept.iter().map(|v| v.size).sum();
+        while total > max_bytes
+            && let Some(v) = kept.pop()
+        {
+            total -= v.size;
+            out.push(removal(v, Reason::VersionBytes));
+        }
+    }
+    out
+}
+
+fn removal(v: Version, reason: Reason) -> Removal {
+    Removal {
+        path: format!("{}/{}/{}", versions::VERSIONS_DIR, v.stamp, v.file),
+        reason,
+        size: v.size,
+        version: Some(v),
+    }
+}
+
+/// 数据文件已不存在的 meta；meta 路径为数据文件 `with_extension("meta")`
+fn orphaned_meta(storage_dir: &Path, now: SystemTime) -> Vec<Removal> {
+    let files: Vec<PathBuf> = WalkDir::new(storage_dir)
+        .into_iter()
+        .filter_entry(|e| !is_hidden(e))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    let is_meta = |p: &Path| p.extension().is_some_and(|ext| ext == "meta");
+    let expected: HashSet<PathBuf> = files
+        .iter()
+        .filter(|p| !is_meta(p))
+        .map(|p| p.with_extension("meta"))
+        .collect();
+
+    files
+        .into_iter()
+        .filter(|p| is_meta(p) && !expected.contains(p))
+        // 记录了分段进度、续传检查点或 tmp 来源的 meta 属于未完成的下载，数据还在 `.partial/` 中
+        .filter(|p| !load_meta(p).is_ok_and(|m| m.chunks.is_some() || m.resume.is_some() || m.partial.is_some()))
+        .filter_map(|p| {
+            let meta = std::fs::metadata(&p).ok().filter(|m| older_than(m, ORPHAN_GRACE, now))?;
+            Some(Removal {
+                path: relative(storage_dir, &p)?,
+                reason: Reason::OrphanedMeta,
+                size: meta.len(),
+                version: None,
+            })
+        })
+        .collect()
+}
+
+/// 超过 `max_age` 没有写入、且当前没有下载在写的 tmp 文件
+fn stale_partials(storage_dir: &Path, max_age: Duration, now: SystemTime) -> Vec<Removal> {
+    let Ok(dir) = std::fs::read_dir(storage_dir.join(PARTIAL_DIR)) else {
+        return Vec::new();
+    };
+    let writing = partial::writing();
+    dir.flatten()
+        .filter(|e| !writing.contains(&e.path()))
+        .filter_map(|e| {
+            let meta = e.metadata().ok().filter(|m| m.is_file() && older_than(m, max_age, now))?;
+            Some(Removal {
+                path: relative(storage_dir, &e.path())?,
+                reason: Reason::StalePartial,
+                size: meta.len(),
+                version: None,
+            })
+        })
+        .collect()
+}
+
+fn older_than(meta: &std::fs::Metadata, age: Duration, now: SystemTime) -> bool {
+    meta.modified()
+        .ok()
+        .and_then(|m| now.duration_since(m).ok())
+        .is_some_and(|elapsed| elapsed >= age)
+}
+
+fn relative(storage_dir: &Path, path: &Path) -> Option<String> {
+    Some(path.strip_prefix(storage_dir).ok()?.to_str()?.to_string())
+}
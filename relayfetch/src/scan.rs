@@ -0,0 +1,54 @@
+//! 存储扫描用的有界线程池（rayon）
+//!
+//! 大目录的 stat、meta 读取与哈希在网络文件系统上以 I/O 等待为主，放到独立线程池并发执行，
+//! 线程数由 `scan_threads` 限定（0 表示按 CPU 核数），不占用 tokio 的阻塞线程。
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::warn;
+use rayon::ThreadPool;
+use rayon::prelude::*;
+
+static POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+/// 按配置创建线程池；只在启动时生效
+pub fn init(threads: usize) {
+    let _ = POOL.get_or_init(|| build(threads));
+}
+
+fn build(threads: usize) -> ThreadPool {
+    let builder = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("relayfetch-scan-{}", i));
+    builder.build().unwrap_or_else(|e| {
+        warn!("[scan] failed to build pool with {} threads: {}", threads, e);
+        rayon::ThreadPoolBuilder::new().build().expect("default scan pool")
+    })
+}
+
+fn pool() -> &'static ThreadPool {
+    POOL.get_or_init(|| build(0))
+}
+
+/// 并发处理一批条目，结果与输入顺序一致；每完成一个调用 progress(已完成, 总数)
+pub fn par_map<T, R, F, P>(items: &[T], f: F, progress: P) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    P: Fn(usize, usize) + Sync,
+{
+    let total = items.len();
+    let done = AtomicUsize::new(0);
+    pool().install(|| {
+        items
+            .par_iter()
+            .map(|item| {
+                let r = f(item);
+                progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+                r
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,341 @@
+//! 下载服务的访问日志（`[access_log]`）
+//!
+//! 每个请求在响应体发送完（或连接中途断开）时记一行：客户端地址、方法、路径、状态码、
+//! 实际发送的字节数与耗时。
+//! - combined：Apache / nginx 的组合格式，末尾追加耗时（秒）
+//! - json：每行一个 JSON 对象
+//!
+//! 文件由单独的线程写入，积压过多时丢弃新的行，不拖慢下载；超过 `max_size_bytes` 时轮转为
+//! `<path>.1` … `<path>.<max_files>`，修改 path 后从下一行起写入新文件。
+//! 未配置 `[access_log]` 时以 combined 格式写入程序日志。
+//! 客户端地址的识别见 `acl`（`[access] trusted_proxies`）。
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::{HeaderMap, Version, header},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Local};
+use http_body::{Frame, SizeHint};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::acl::{self, Peer};
+use crate::config::config::{AccessLogConfig, AccessLogFormat};
+use crate::logging::REQUEST_ID_HEADER;
+
+/// 写入线程的队列长度，满了之后丢弃
+const QUEUE: usize = 4096;
+
+pub struct AccessLogger {
+    /// 首次写文件时启动写入线程
+    tx: OnceLock<SyncSender<Line>>,
+}
+
+impl AccessLogger {
+    pub fn new() -> Self {
+        Self { tx: OnceLock::new() }
+    }
+
+    fn write(&self, cfg: &AccessLogConfig, text: String) {
+        let tx = self.tx.get_or_init(|| {
+            let (tx, rx) = std::sync::mpsc::sync_channel(QUEUE);
+            std::thread::Builder::new()
+                .name("access-log".into())
+                .spawn(move || write_loop(rx))
+                .expect("spawn access log writer");
+            tx
+        });
+        let line = Line {
+            text,
+            path: cfg.path.clone(),
+            max_size: cfg.max_size_bytes,
+            max_files: cfg.max_files,
+        };
+        if let Err(TrySendError::Full(_)) = tx.try_send(line) {
+            warn!("[access_log] writer is falling behind, line dropped");
+        }
+    }
+}
+
+/// 访问日志中间件
+pub async fn middleware(State(state): State<super::ServerState>, req: Request, next: Next) -> Response {
+    let (cfg, trusted) = {
+        let config = state.cc.config().await;
+        (config.access_log.clone(), config.access.trusted_proxies.clone())
+    };
+
+    // 探针请求频繁，默认不记录
+    let probe = matches!(req.uri().path(), "/healthz" | "/readyz");
+    if probe && !cfg.as_ref().is_some_and(|c| c.log_probes) {
+        return next.run(req).await;
+    }
+
+    let client = acl::client(req.extensions().get::<Peer>().copied(), req.headers(), &trusted);
+    let entry = Entry {
+        client: client.to_string(),
+        time: Local::now(),
+        method: req.method().to_string(),
+        uri: req.uri().path_and_query().map_or("/", |p| p.as_str()).to_string(),
+        version: req.version(),
+        status: 0,
+        referer: header_str(req.headers(), header::REFERER),
+        user_agent: header_str(req.headers(), header::USER_AGENT),
+        request_id: None,
+    };
+    let started = Instant::now();
+
+    let resp = next.run(req).await;
+    let (parts, body) = resp.into_parts();
+    let entry = Entry {
+        status: parts.status.as_u16(),
+        request_id: header_str(&parts.headers, REQUEST_ID_HEADER),
+        ..entry
+    };
+    let body = Counted {
+        inner: body,
+        bytes: 0,
+        pending: Some(Pending {
+            entry,
+            started,
+            cfg,
+            logger: state.access_log.clone(),
+        }),
+    };
+    Response::from_parts(parts, Body::new(body))
+}
+
+fn header_str(headers: &HeaderMap, name: impl header::AsHeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+}
+
+struct Entry {
+    client: String,
+    /// 收到请求的时间
+    time: DateTime<Local>,
+    method: String,
+    uri: String,
+    version: Version,
+    status: u16,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    request_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    time: String,
+    client: &'a str,
+    method: &'a str,
+    path: &'a str,
+    protocol: String,
+    status: u16,
+    bytes: u64,
+    duration_ms: u64,
+    referer: Option<&'a str>,
+    user_agent: Option<&'a str>,
+    request_id: Option<&'a str>,
+}
+
+impl Entry {
+    fn format(&self, format: AccessLogFormat, bytes: u64, secs: f64) -> String {
+        match format {
+            AccessLogFormat::Combined => format!(
+                "{} - - [{}] \"{} {} {:?}\" {} {} \"{}\" \"{}\" {:.3}",
+                self.client,
+                self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+                self.method,
+                escape(&self.uri),
+                self.version,
+                self.status,
+                if bytes == 0 { "-".to_string() } else { bytes.to_string() },
+                escape(self.referer.as_deref().unwrap_or("-")),
+                escape(self.user_agent.as_deref().unwrap_or("-")),
+                secs,
+            ),
+            AccessLogFormat::Json => serde_json::to_string(&JsonLine {
+                time: self.time.to_rfc3339(),
+                client: &self.client,
+                method: &self.method,
+                path: &self.uri,
+                protocol: format!("{:?}", self.version),
+                status: self.status,
+                bytes,
+                duration_ms: (secs * 1000.0) as u64,
+                referer: self.referer.as_deref(),
+                user_agent: self.user_agent.as_deref(),
+                request_id: self.request_id.as_deref(),
+            })
+            .unwrap(),
+        }
+    }
+}
+
+/// 引号内的字段：转义 `"`、`\` 与控制字符
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+struct Pending {
+    entry: Entry,
+    started: Instant,
+    cfg: Option<AccessLogConfig>,
+    logger: Arc<AccessLogger>,
+}
+
+/// 统计发送的字节数，结束或被丢弃（客户端断开）时写日志；保留内层的 size_hint，
+/// 不影响 Content-Length
+struct Counted {
+    inner: Body,
+    bytes: u64,
+    pending: Option<Pending>,
+}
+
+impl Counted {
+    fn finish(&mut self) {
+        let Some(p) = self.pending.take() else {
+            return;
+        };
+        let secs = p.started.elapsed().as_secs_f64();
+        match &p.cfg {
+            Some(cfg) => p.logger.write(cfg, p.entry.format(cfg.format, self.bytes, secs)),
+            None => info!("{}", p.entry.format(AccessLogFormat::Combined, self.bytes, secs)),
+        }
+    }
+}
+
+impl HttpBody for Counted {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    self.bytes += data.len() as u64;
+                }
+            }
+            Poll::Ready(None) => self.finish(),
+            _ => {}
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+struct Line {
+    text: String,
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+}
+
+fn write_loop(rx: Receiver<Line>) {
+    // 当前打开的文件及其大小
+    let mut out: Option<(PathBuf, File, u64)> = None;
+    // 打开失败的路径，避免每行都报一次
+    let mut failed: Option<PathBuf> = None;
+
+    for line in rx {
+        if out.as_ref().is_none_or(|(path, ..)| *path != line.path) {
+            out = match open(&line.path) {
+                Ok((file, size)) => Some((line.path.clone(), file, size)),
+                Err(e) => {
+                    if failed.as_ref() != Some(&line.path) {
+                        warn!("[access_log] failed to open {}: {}", line.path.display(), e);
+                        failed = Some(line.path.clone());
+                    }
+                    continue;
+                }
+            };
+            failed = None;
+        }
+        let Some((path, file, size)) = out.as_mut() else {
+            continue;
+        };
+
+        let len = line.text.len() as u64 + 1;
+        if line.max_size > 0 && *size > 0 && *size + len > line.max_size {
+            match rotate(path, line.max_files).and_then(|_| open(path)) {
+                Ok((f, s)) => (*file, *size) = (f, s),
+                Err(e) => warn!("[access_log] failed to rotate {}: {}", path.display(), e),
+            }
+        }
+        match writeln!(file, "{}", line.text) {
+            Ok(()) => *size += len,
+            Err(e) => {
+                warn!("[access_log] failed to write {}: {}", path.display(), e);
+                out = None;
+            }
+        }
+    }
+}
+
+fn open(path: &Path) -> std::io::Result<(File, u64)> {
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+/// `<path>` → `<path>.1` → … → `<path>.<max_files>`，最旧的删除
+fn rotate(path: &Path, max_files: usize) -> std::io::Result<()> {
+    let numbered = |n: usize| {
+        let mut p = path.as_os_str().to_owned();
+        p.push(format!(".{}", n));
+        PathBuf::from(p)
+    };
+    if max_files == 0 {
+        return std::fs::remove_file(path);
+    }
+    let _ = std::fs::remove_file(numbered(max_files));
+    for n in (1..max_files).rev() {
+        let from = numbered(n);
+        if from.exists() {
+            std::fs::rename(&from, numbered(n + 1))?;
+        }
+    }
+    std::fs::rename(path, numbered(1))
+}
@@ -0,0 +1,55 @@
+//! 内置 `/favicon.ico` 与 `/robots.txt`
+//!
+//! 两者不查找存储目录，也不触发按需回源；默认使用内置图标与禁止抓取的 robots.txt，
+//! 可在 `[static_assets]` 中替换。
+
+use axum::{
+    body::Body,
+    http::{StatusCode, header},
+    response::Response,
+};
+use log::warn;
+
+use crate::config::config::RobotsPolicy;
+
+const FAVICON: &[u8] = include_bytes!("favicon.ico");
+const ROBOTS_ALLOW: &str = "User-agent: *\nAllow: /\n";
+const ROBOTS_DISALLOW: &str = "User-agent: *\nDisallow: /\n";
+const CACHE_CONTROL: &str = "public, max-age=86400";
+
+pub async fn favicon(state: &super::ServerState) -> Response {
+    let custom = state.cc.config().await.static_assets.favicon.clone();
+    let Some(path) = custom else {
+        return respond("image/x-icon", FAVICON.to_vec());
+    };
+    match tokio::fs::read(&path).await {
+        Ok(data) => {
+            let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+            respond(content_type.as_ref(), data)
+        }
+        Err(e) => {
+            warn!("failed to read favicon {}: {}", path.display(), e);
+            super::not_found()
+        }
+    }
+}
+
+pub async fn robots(state: &super::ServerState) -> Response {
+    let cfg = state.cc.config().await;
+    let body = match (&cfg.static_assets.robots_txt, cfg.static_assets.robots) {
+        (Some(custom), _) => custom.clone(),
+        (None, RobotsPolicy::Allow) => ROBOTS_ALLOW.to_string(),
+        (None, RobotsPolicy::Disallow) => ROBOTS_DISALLOW.to_string(),
+    };
+    respond("text/plain; charset=utf-8", body.into_bytes())
+}
+
+fn respond(content_type: &str, data: Vec<u8>) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, data.len())
+        .header(header::CACHE_CONTROL, CACHE_CONTROL)
+        .body(Body::from(data))
+        .unwrap()
+}
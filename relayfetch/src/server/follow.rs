@@ -0,0 +1,49 @@
+//! 正在下载的文件：跟随写入中的 tmp 文件边写边读，不再另行回源
+
+use std::path::Path;
+
+use axum::{
+    body::Body,
+    http::{StatusCode, header},
+    response::Response,
+};
+use futures::stream;
+
+use crate::sync::partial::{self, Follow};
+
+/// 没有进行中的下载时返回 None
+pub async fn serve(state: &super::ServerState, file: &str, real: &Path) -> Option<Response> {
+    let max_waiters = state.cc.config().await.coalesce_max_waiters;
+    let follower = match partial::follow(real, max_waiters).await {
+        Follow::Idle => return None,
+        Follow::Overflow => {
+            return Some(
+                Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .header(header::RETRY_AFTER, "5")
+                    .body(Body::from("Service Unavailable"))
+                    .unwrap(),
+            );
+        }
+        Follow::Following(f) => f,
+    };
+    state.cc.access().touch(file);
+
+    let source = state.cc.files().await.files.get(file).cloned();
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, super::content_type(source.as_ref(), real));
+    if let Some(total) = follower.total() {
+        builder = builder.header(header::CONTENT_LENGTH, total);
+    }
+
+    let chunks = stream::unfold(Some(follower), |follower| async move {
+        let mut follower = follower?;
+        match follower.next_chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(follower))),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    Some(builder.body(Body::from_stream(chunks)).unwrap())
+}
@@ -0,0 +1,245 @@
+//! 下载服务的监听地址（`bind`）与连接参数（`[server]`）
+//!
+//! `bind` 可以是一个地址或地址列表，同一个路由同时在所有地址上提供服务：
+//! - `0.0.0.0:8080`、`[::]:8080`：TCP
+//! - `unix:/run/relayfetch.sock`：unix domain socket（仅 unix），供同机的反向代理使用；
+//!   启动时删除残留的 socket 文件，退出时删除
+//!
+//! 连接由 hyper 的 auto builder 处理（HTTP/1.1 与明文 h2），按 `[server]` 设置 HTTP/2 并发流、
+//! keep-alive 与每个监听地址的最大连接数；HTTPS 服务使用同一套参数。
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use axum::Router;
+use axum::http::Request;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use log::{debug, info};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use tower_service::Service;
+
+use crate::acl::Peer;
+use crate::config::config::{ServerConfig, UNIX_PREFIX};
+
+/// 已绑定的监听地址
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, std::path::PathBuf),
+}
+
+impl Listener {
+    pub async fn bind(addr: &str) -> Result<Self> {
+        let Some(path) = addr.strip_prefix(UNIX_PREFIX) else {
+            let listener = TcpListener::bind(addr).await.with_context(|| format!("bind {}", addr))?;
+            info!("Download server listening on http://{}", addr);
+            return Ok(Self::Tcp(listener));
+        };
+        bind_unix(path)
+    }
+
+    /// 开始提供服务，`stop` 取消后不再接受新连接，等进行中的连接结束后返回
+    pub async fn serve(self, app: Router, cfg: ServerConfig, stop: CancellationToken) {
+        match self {
+            Self::Tcp(listener) => accept_loop(listener, app, &cfg, stop, |addr| Peer::Tcp(addr.ip())).await,
+            #[cfg(unix)]
+            Self::Unix(listener, path) => {
+                accept_loop(listener, app, &cfg, stop, |_| Peer::Unix).await;
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+/// 按 `[server]` 构建连接参数
+pub fn conn_builder(cfg: &ServerConfig) -> Builder<TokioExecutor> {
+    let mut builder = Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(cfg.keep_alive)
+        .header_read_timeout(secs(cfg.header_read_timeout_secs));
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .max_concurrent_streams(cfg.max_concurrent_streams)
+        .keep_alive_interval(secs(cfg.http2_keep_alive_interval_secs))
+        .keep_alive_timeout(Duration::from_secs(cfg.http2_keep_alive_timeout_secs));
+    if cfg.http2 {
+        builder
+    } else {
+        builder.http1_only()
+    }
+}
+
+/// 0 表示关闭
+fn secs(v: u64) -> Option<Duration> {
+    (v > 0).then(|| Duration::from_secs(v))
+}
+
+async fn accept_loop<L: axum::serve::Listener>(
+    mut listener: L,
+    app: Router,
+    cfg: &ServerConfig,
+    stop: CancellationToken,
+    peer: impl Fn(&L::Addr) -> Peer,
+) {
+    let builder = Arc::new(conn_builder(cfg));
+    let limit = cfg.max_connections.map(|n| Arc::new(Semaphore::new(n)));
+    let graceful = GracefulShutdown::new();
+
+    loop {
+        // 达到连接数上限时暂停 accept，新连接留在内核队列中
+        let permit = match &limit {
+            Some(limit) => tokio::select! {
+                permit = limit.clone().acquire_owned() => permit.ok(),
+                _ = stop.cancelled() => break,
+            },
+            None => None,
+        };
+        let (io, addr) = tokio::select! {
+            conn = listener.accept() => conn,
+            _ = stop.cancelled() => break,
+        };
+
+        let builder = builder.clone();
+        let service = TowerToHyperService::new(WithPeer::new(app.clone(), peer(&addr)));
+        let watcher = graceful.watcher();
+        let first_byte_timeout = secs(cfg.header_read_timeout_secs);
+        tokio::spawn(async move {
+            // 判断协议版本前要先读到数据，这一步不受 header_read_timeout 约束，单独限时
+            let Some(io) = first_bytes(io, first_byte_timeout).await else {
+                return;
+            };
+            let conn = builder.serve_connection_with_upgrades(TokioIo::new(io), service);
+            if let Err(e) = watcher.watch(conn).await {
+                debug!("connection closed: {}", e);
+            }
+            drop(permit);
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+/// 把连接的对端放入每个请求的扩展，供访问日志使用
+#[derive(Clone)]
+pub struct WithPeer {
+    app: Router,
+    peer: Peer,
+}
+
+impl WithPeer {
+    pub fn new(app: Router, peer: Peer) -> Self {
+        Self { app, peer }
+    }
+}
+
+impl<B> Service<Request<B>> for WithPeer
+where
+    Router: Service<Request<B>>,
+{
+    type Response = <Router as Service<Request<B>>>::Response;
+    type Error = <Router as Service<Request<B>>>::Error;
+    type Future = <Router as Service<Request<B>>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Service::<Request<B>>::poll_ready(&mut self.app, cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        req.extensions_mut().insert(self.peer);
+        self.app.call(req)
+    }
+}
+
+/// 等待连接上的第一段数据；超时或连接关闭时返回 None
+async fn first_bytes<I: AsyncRead + Unpin>(mut io: I, timeout: Option<Duration>) -> Option<Prefixed<I>> {
+    let mut buf = vec![0u8; 1024];
+    let read = io.read(&mut buf);
+    let n = match timeout {
+        Some(t) => tokio::time::timeout(t, read).await.ok()?,
+        None => read.await,
+    }
+    .ok()?;
+    if n == 0 {
+        return None;
+    }
+    buf.truncate(n);
+    Some(Prefixed { prefix: buf, pos: 0, io })
+}
+
+/// 先读出已读到的数据，再读底层连接
+struct Prefixed<I> {
+    prefix: Vec<u8>,
+    pos: usize,
+    io: I,
+}
+
+impl<I: AsyncRead + Unpin> AsyncRead for Prefixed<I> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        if self.pos < self.prefix.len() {
+            let n = (self.prefix.len() - self.pos).min(buf.remaining());
+            buf.put_slice(&self.prefix[self.pos..self.pos + n]);
+            self.pos += n;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<I: AsyncWrite + Unpin> AsyncWrite for Prefixed<I> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(unix)]
+fn bind_unix(path: &str) -> Result<Listener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // 上次未正常退出留下的 socket 文件；不是 socket 的同名文件不删除
+    if let Ok(meta) = std::fs::symlink_metadata(path)
+        && meta.file_type().is_socket()
+    {
+        std::fs::remove_file(path).with_context(|| format!("remove stale socket {}", path))?;
+    }
+    let listener = tokio::net::UnixListener::bind(path).with_context(|| format!("bind unix:{}", path))?;
+    info!("Download server listening on unix:{}", path);
+    Ok(Listener::Unix(listener, path.into()))
+}
+
+#[cfg(not(unix))]
+fn bind_unix(path: &str) -> Result<Listener> {
+    anyhow::bail!("unix:{}: unix sockets are not supported on this platform", path)
+}
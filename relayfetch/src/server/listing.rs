@@ -0,0 +1,158 @@
+//! 目录索引（autoindex 风格）
+//!
+//! - HTML：浏览器直接浏览
+//! - JSON：`?format=json` 或 `Accept: application/json`
+
+use axum::{
+    http::{HeaderMap, header},
+    response::Response,
+};
+use serde::Serialize;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+#[derive(Serialize)]
+pub struct ListingEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: u64,
+}
+
+#[derive(Serialize)]
+pub struct Listing {
+    pub path: String,
+    pub entries: Vec<ListingEntry>,
+}
+
+/// 是否为内部文件（meta / 临时文件），不对外展示
+pub fn is_internal_file(name: &str) -> bool {
+    name.ends_with(".meta") || name.ends_with(".tmp")
+}
+
+pub async fn read_listing(dir: &Path, url_path: &str) -> std::io::Result<Listing> {
+    let mut entries = Vec::new();
+    let mut rd = tokio::fs::read_dir(dir).await?;
+
+    while let Some(entry) = rd.next_entry().await? {
+        let name = match entry.file_name().into_string() {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
+        if name.starts_with('.') || is_internal_file(&name) {
+            continue;
+        }
+        let md = match entry.metadata().await {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        let modified = md
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        entries.push(ListingEntry {
+            name,
+            is_dir: md.is_dir(),
+            size: if md.is_dir() { 0 } else { md.len() },
+            modified,
+        });
+    }
+
+    // 目录在前，按名称排序
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(Listing {
+        path: url_path.to_string(),
+        entries,
+    })
+}
+
+pub fn wants_json(headers: &HeaderMap, query: Option<&str>) -> bool {
+    if let Some(q) = query
+        && q.split('&').any(|kv| kv == "format=json")
+    {
+        return true;
+    }
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("application/json"))
+        .unwrap_or(false)
+}
+
+pub fn render(listing: &Listing, json: bool) -> Response {
+    if json {
+        let body = serde_json::to_vec(listing).unwrap_or_default();
+        return Response::builder()
+            .status(200)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+    }
+
+    Response::builder()
+        .status(200)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(axum::body::Body::from(render_html(listing)))
+        .unwrap()
+}
+
+fn render_html(listing: &Listing) -> String {
+    let title = html_escape(&listing.path);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\n<body>\n<h1>Index of {title}</h1><hr><pre>\n"
+    );
+
+    if listing.path != "/" {
+        out.push_str("<a href=\"../\">../</a>\n");
+    }
+
+    for e in &listing.entries {
+        let display = if e.is_dir { format!("{}/", e.name) } else { e.name.clone() };
+        let href = if e.is_dir {
+            format!("{}/", url_encode(&e.name))
+        } else {
+            url_encode(&e.name)
+        };
+        let modified = chrono::DateTime::from_timestamp(e.modified as i64, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let size = if e.is_dir { "-".to_string() } else { e.size.to_string() };
+
+        out.push_str(&format!(
+            "<a href=\"{}\">{}</a>{:pad$} {}  {:>12}\n",
+            href,
+            html_escape(&display),
+            "",
+            modified,
+            size,
+            pad = 50usize.saturating_sub(display.chars().count()),
+        ));
+    }
+
+    out.push_str("</pre><hr></body></html>\n");
+    out
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn url_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
@@ -0,0 +1,160 @@
+//! 客户端清单 `/.well-known/relayfetch.json` 与节点清单 `/__manifest`
+//!
+//! 列出已同步完成的文件及其 sha256、大小与新鲜度，供客户端 / 设备决定拉取哪些文件，
+//! 相当于在下载端口上公开的 list_files。`/__manifest`（别名 `/__relayfetch/manifest.json`）内容相同，
+//! 供其他节点以 `[upstream_peer]` 镜像本节点或供外部工具比对，分别由 `[client_manifest]` 与
+//! `[peer_manifest]` 开启。配置了 `tokens` 时需携带 `Authorization: Bearer <token>`。
+//! 响应带有按文件列表计算的 ETag，列表未变时 `If-None-Match` 得到 304。
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    http::{HeaderMap, StatusCode, header},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::config::{ClientManifestConfig, Config};
+use crate::storage_index::StoredState;
+use crate::sync::meta::load_meta;
+
+use super::path;
+
+#[derive(Serialize)]
+struct ClientManifest {
+    version: &'static str,
+    generated_at: String,
+    /// 最近一次全部成功的同步
+    last_ok_sync: Option<String>,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize)]
+struct ManifestFile {
+    path: String,
+    /// 相对清单地址解析的下载路径（已编码）
+    url: String,
+    size: Option<u64>,
+    sha256: Option<String>,
+    etag: Option<String>,
+    /// 上游给出的修改时间
+    last_modified: Option<String>,
+    /// 最近一次与上游确认的时间
+    fetched_at: Option<String>,
+    /// 只镜像了上游对象的一段时为上游的 Content-Range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_range: Option<String>,
+}
+
+/// `select` 取出对应清单的配置，未配置时返回 404
+pub async fn serve(
+    state: &super::ServerState,
+    headers: &HeaderMap,
+    select: fn(&Config) -> Option<&ClientManifestConfig>,
+) -> Response {
+    let (storage_dir, ttl, private) = {
+        let cfg = state.cc.config().await;
+        let Some(manifest) = select(&cfg) else {
+            return super::not_found();
+        };
+        if !authorized(manifest, headers) {
+            return Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(header::WWW_AUTHENTICATE, "Bearer")
+                .body(axum::body::Body::from("Unauthorized"))
+                .unwrap();
+        }
+        (
+            cfg.storage_dir.clone(),
+            Duration::from_secs(cfg.storage_index_ttl_secs),
+            cfg.signed_urls.as_ref().map(|s| s.private_matcher()),
+        )
+    };
+
+    let index = state.cc.storage_index().files(&storage_dir, ttl).await;
+    let complete: Vec<String> = index
+        .files
+        .iter()
+        .filter(|(_, f)| f.state == StoredState::Complete)
+        // 私有文件只能通过签名链接下载，不列出
+        .filter(|(rel, _)| !private.as_ref().is_some_and(|p| p.is_match(rel)))
+        .map(|(rel, _)| rel.clone())
+        .collect();
+    let files = tokio::task::spawn_blocking(move || {
+        crate::scan::par_map(&complete, |rel| describe(&storage_dir, rel), |_, _| {})
+    })
+    .await
+    .unwrap_or_default();
+
+    // 不含 generated_at 等每次都变的字段，文件列表不变时 ETag 不变
+    let etag = format!(
+        "\"{}\"",
+        &hex::encode(Sha256::digest(serde_json::to_vec(&files).unwrap()))[..32]
+    );
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|v| v.as_bytes() == etag.as_bytes())
+    {
+        return Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag)
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(axum::body::Body::empty())
+            .unwrap();
+    }
+
+    let manifest = ClientManifest {
+        version: env!("CARGO_PKG_VERSION"),
+        generated_at: Utc::now().to_rfc3339(),
+        last_ok_sync: state.cc.sync_status().await.last_ok_sync.map(rfc3339),
+        files,
+    };
+    Response::builder()
+        .status(200)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::ETAG, etag)
+        .body(axum::body::Body::from(serde_json::to_vec(&manifest).unwrap()))
+        .unwrap()
+}
+
+fn describe(storage_dir: &Path, rel: &str) -> ManifestFile {
+    let meta = load_meta(&storage_dir.join(rel).with_extension("meta")).unwrap_or_default();
+    ManifestFile {
+        path: rel.to_string(),
+        url: path::encode(&format!("/{}", rel)),
+        size: meta.total_size,
+        sha256: meta.sha256,
+        etag: meta.etag,
+        last_modified: meta.last_modified,
+        fetched_at: meta.fetched_at,
+        content_range: meta.content_range,
+    }
+}
+
+fn rfc3339(t: SystemTime) -> String {
+    DateTime::<Utc>::from(t).to_rfc3339()
+}
+
+/// 未配置 token 时公开访问
+fn authorized(manifest: &ClientManifestConfig, headers: &HeaderMap) -> bool {
+    if manifest.tokens.is_empty() {
+        return true;
+    }
+    let Some(presented) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+    else {
+        return false;
+    };
+    // 避免逐字节比较泄露 token 前缀
+    manifest.tokens.iter().any(|t| {
+        t.len() == presented.len()
+            && t.bytes().zip(presented.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    })
+}
@@ -0,0 +1,485 @@
+mod access_log;
+mod assets;
+mod follow;
+pub mod listen;
+mod listing;
+mod manifest;
+mod on_demand;
+mod patch;
+pub mod path;
+mod probes;
+mod ranges;
+mod root;
+#[cfg(feature = "acme")]
+pub mod tls;
+
+use axum::{
+    routing::get,
+    Router,
+    extract::{Path, RawQuery, State},
+    response::Response,
+    http::{HeaderMap, Method, StatusCode, Uri, header},
+};
+use base64::Engine;
+use std::io::SeekFrom;
+use std::path::{Component, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use log::{info, warn};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use crate::access::Evicted;
+use crate::config::{ConfigCenter, file::FileSource};
+use crate::proxy_cache::ProxyCache;
+use crate::sync::meta::{Meta, load_meta};
+use crate::sync::versions::VERSIONS_DIR;
+
+#[derive(Clone)]
+struct ServerState {
+    root: PathBuf,
+    cc: Arc<ConfigCenter>,
+    proxy_cache: Arc<ProxyCache>,
+    /// 构建路由的时间，近似进程启动时间
+    started: SystemTime,
+    access_log: Arc<access_log::AccessLogger>,
+}
+
+pub fn build_router(
+    cc: Arc<ConfigCenter>,
+    storage_root: PathBuf,
+    proxy_cache: Arc<ProxyCache>,
+) -> Router {
+    let state = ServerState {
+        root: storage_root,
+        cc,
+        proxy_cache,
+        started: SystemTime::now(),
+        access_log: Arc::new(access_log::AccessLogger::new()),
+    };
+
+    let router = Router::new();
+
+    #[cfg(feature = "acme")]
+    let router = router.route("/.well-known/acme-challenge/{token}", get(acme_challenge));
+
+    router
+        .route("/.well-known/relayfetch.json", get(client_manifest))
+        .route("/__manifest", get(peer_manifest))
+        .route("/__relayfetch/manifest.json", get(peer_manifest))
+        .route("/__versions", get(serve_versions))
+        .route("/__versions/", get(serve_versions))
+        .route("/__versions/{*path}", get(serve_versions))
+        .route("/favicon.ico", get(favicon))
+        .route("/robots.txt", get(robots))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/", get(serve_root))
+        .route("/{*path}", get(serve_file))
+        .layer(axum::middleware::from_fn_with_state(state.cc.clone(), crate::acl::download))
+        .layer(axum::middleware::from_fn(crate::logging::request_id))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), access_log::middleware))
+        .with_state(state)
+}
+
+async fn serve_root(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+) -> Response {
+    root::serve(&state, &headers, query.as_deref()).await
+}
+
+async fn client_manifest(State(state): State<ServerState>, headers: HeaderMap) -> Response {
+    manifest::serve(&state, &headers, |cfg| cfg.client_manifest.as_ref()).await
+}
+
+async fn peer_manifest(State(state): State<ServerState>, headers: HeaderMap) -> Response {
+    manifest::serve(&state, &headers, |cfg| cfg.peer_manifest.as_ref()).await
+}
+
+async fn favicon(State(state): State<ServerState>) -> Response {
+    assets::favicon(&state).await
+}
+
+async fn robots(State(state): State<ServerState>) -> Response {
+    assets::robots(&state).await
+}
+
+async fn healthz(State(state): State<ServerState>) -> Response {
+    probes::healthz(&state)
+}
+
+async fn readyz(State(state): State<ServerState>) -> Response {
+    probes::readyz(&state).await
+}
+
+async fn serve_file(
+    State(state): State<ServerState>,
+    Path(path): Path<String>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+) -> Response {
+    // 私有路径只能通过签名链接下载；`<文件>.patch` 使用 `<文件>` 的签名。
+    // 按规范化后的路径匹配，`a//b`、`a/./b` 与 `a/b` 是同一个文件
+    let entry = path::joined(&path).unwrap_or_else(|_| path.clone());
+    if let Some(resp) = check_signature(&state, &entry, &entry, query.as_deref()).await {
+        return resp;
+    }
+    if let Some(base) = entry.strip_suffix(".patch")
+        && let Some(resp) = check_signature(&state, base, base, query.as_deref()).await
+    {
+        return resp;
+    }
+
+    // 反向代理缓存：使用未解码的原始路径拼接上游 URL
+    let raw_path = uri.path().trim_start_matches('/');
+    if let Some(target) = state.proxy_cache.match_rule(raw_path, query.as_deref()).await {
+        return state.proxy_cache.serve(&target).await;
+    }
+
+    let Some(real) = resolve_path(&state.root, &path) else {
+        return not_found();
+    };
+
+    // 目录：开启 listing 时渲染索引
+    if real.is_dir() {
+        return serve_dir(&state, &real, &format!("/{}", path), &headers, query.as_deref()).await;
+    }
+
+    // 增量补丁：`/<文件>.patch?from=<sha256>`（同名文件存在时按普通文件提供）
+    if !real.exists()
+        && let Some(base) = path.strip_suffix(".patch")
+        && let Some(from) = patch::from_query(query.as_deref())
+    {
+        return patch::serve(&state, base, &from).await;
+    }
+
+    // HEAD 只查看已有的文件，不跟随下载、不回源
+    let head = method == Method::HEAD;
+
+    // 正在下载中（同步、按需回源或恢复淘汰文件）：跟随写入进度读取
+    if !real.exists()
+        && !head
+        && let Some(resp) = follow::serve(&state, &path, &real).await
+    {
+        return resp;
+    }
+
+    // 已被 LRU 淘汰的文件：先从原上游重新下载
+    if !real.exists()
+        && !head
+        && let Some(evicted) = state.cc.access().evicted(&path)
+        && let Err(resp) = restore_evicted(&state, &path, evicted).await
+    {
+        return resp;
+    }
+
+    // 未镜像的路径：配置了按需回源时边转发边落盘
+    if !real.exists()
+        && !head
+        && let Some(resp) = on_demand::fetch(&state, &path, &real).await
+    {
+        return resp;
+    }
+
+    serve_stored(&state, &path, &real, &headers, query.as_deref(), true, head).await
+}
+
+/// 以存储目录中的文件响应；`file` 为对应的条目，`track` 时计入访问记录，`head` 时只返回响应头、不读取文件
+async fn serve_stored(
+    state: &ServerState,
+    file: &str,
+    real: &std::path::Path,
+    headers: &HeaderMap,
+    query: Option<&str>,
+    track: bool,
+    head: bool,
+) -> Response {
+    // 只打开文件，响应体按请求的范围从文件流式读取，不整体读入内存
+    let (opened, len) = if head {
+        match tokio::fs::metadata(real).await {
+            Ok(m) if m.is_file() => (None, m.len()),
+            _ => return not_found(),
+        }
+    } else {
+        let Ok(f) = tokio::fs::File::open(real).await else {
+            return not_found();
+        };
+        match f.metadata().await {
+            Ok(m) if m.is_file() => (Some(f), m.len()),
+            _ => return not_found(),
+        }
+    };
+    let meta = load_meta(&real.with_extension("meta")).unwrap_or_default();
+    let requested = ranges::requested(headers, &meta, len);
+    if requested == ranges::Requested::Unsatisfiable {
+        return Response::builder()
+            .status(416)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(axum::body::Body::empty())
+            .unwrap();
+    }
+    if track && !head {
+        state.cc.access().touch(file);
+        let bytes = match requested {
+            ranges::Requested::Partial { start, end, .. } => end - start + 1,
+            _ => len,
+        };
+        state.cc.access().record_transfer(file, requested.is_resume(), bytes);
+    }
+
+    let source = state.cc.files().await.files.get(file).cloned();
+    let content_type = content_type(source.as_ref(), real);
+    let attachment = source.is_some_and(|s| s.attachment()) || wants_download(query);
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ACCEPT_RANGES, "bytes");
+    let content_length = if let ranges::Requested::Partial { start, end, .. } = requested {
+        builder = builder
+            .status(206)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len));
+        end - start + 1
+    } else {
+        builder = builder.status(200);
+        len
+    };
+    builder = builder.header(header::CONTENT_LENGTH, content_length);
+    if attachment {
+        builder = builder.header(header::CONTENT_DISPOSITION, content_disposition(real));
+    }
+    // 以内容摘要作为强 ETag，下游可据此用 HEAD 判断是否需要重新下载
+    if let Some(etag) = ranges::etag(&meta) {
+        builder = builder.header(header::ETAG, etag);
+    }
+    for (name, value) in digest_headers(&meta) {
+        builder = builder.header(name, value);
+    }
+    // 只镜像了上游对象的一段
+    if let Some(range) = &meta.content_range {
+        builder = builder.header("x-relayfetch-content-range", range);
+    }
+    let Some(mut f) = opened else {
+        return builder.body(axum::body::Body::empty()).unwrap();
+    };
+    if let ranges::Requested::Partial { start, .. } = requested
+        && f.seek(SeekFrom::Start(start)).await.is_err()
+    {
+        return not_found();
+    }
+    let body = ReaderStream::with_capacity(f.take(content_length), 64 * 1024);
+    builder.body(crate::shaping::serve_stream(&state.cc, body).await).unwrap()
+}
+
+/// 按需重新下载已淘汰的文件；失败（含同一文件已在下载中）时返回 503
+async fn restore_evicted(state: &ServerState, path: &str, evicted: Evicted) -> Result<(), Response> {
+    let source = state
+        .cc
+        .files()
+        .await
+        .files
+        .get(path)
+        .cloned()
+        .unwrap_or_else(|| FileSource::from(evicted.source));
+    info!("Fetching evicted file {} on demand", path);
+
+    let error = match crate::sync::prefetch(state.cc.clone(), vec![(path.to_string(), source)], false).await {
+        Ok(outcomes) => outcomes.into_iter().find_map(|o| o.error),
+        Err(e) => Some(e.to_string()),
+    };
+    match error {
+        None => Ok(()),
+        Some(e) => {
+            warn!("Failed to fetch evicted file {}: {}", path, e);
+            Err(Response::builder()
+                .status(503)
+                .header(header::RETRY_AFTER, "30")
+                .body(axum::body::Body::from("Service Unavailable"))
+                .unwrap())
+        }
+    }
+}
+
+/// files.toml 中指定的 content_type，否则按扩展名推断
+fn content_type(source: Option<&FileSource>, real: &std::path::Path) -> String {
+    source
+        .and_then(|s| s.content_type())
+        .map(str::to_string)
+        .unwrap_or_else(|| mime_guess::from_path(real).first_or_octet_stream().to_string())
+}
+
+/// `?download` 或 `?download=1` 请求以附件方式下载
+fn wants_download(query: Option<&str>) -> bool {
+    query.is_some_and(|q| {
+        q.split('&')
+            .any(|p| p == "download" || (p.starts_with("download=") && p != "download=0"))
+    })
+}
+
+/// RFC 5987 attr-char 之外的字符需要编码
+const ATTR_CHAR: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'!').remove(b'#').remove(b'$').remove(b'&').remove(b'+').remove(b'-')
+    .remove(b'.').remove(b'^').remove(b'_').remove(b'`').remove(b'|').remove(b'~');
+
+/// `attachment; filename="..."; filename*=UTF-8''...`（RFC 6266）
+fn content_disposition(real: &std::path::Path) -> String {
+    let name = real
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let fallback: String = name
+        .chars()
+        .map(|c| if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' { c } else { '_' })
+        .collect();
+    let encoded = percent_encoding::utf8_percent_encode(&name, ATTR_CHAR);
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+/// ACME HTTP-01 验证文件
+#[cfg(feature = "acme")]
+async fn acme_challenge(State(state): State<ServerState>, Path(token): Path<String>) -> Response {
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return not_found();
+    }
+    match tokio::fs::read(crate::acme::challenge_dir(&state.root).join(&token)).await {
+        Ok(data) => Response::builder()
+            .status(200)
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(axum::body::Body::from(data))
+            .unwrap(),
+        Err(_) => not_found(),
+    }
+}
+
+/// 目录：开启 listing 时渲染索引；`url_path` 为已解码的请求路径
+async fn serve_dir(
+    state: &ServerState,
+    dir: &std::path::Path,
+    url_path: &str,
+    headers: &HeaderMap,
+    query: Option<&str>,
+) -> Response {
+    if !state.cc.config().await.enable_listing {
+        return not_found();
+    }
+    if !url_path.ends_with('/') {
+        // 补齐结尾的 `/`，保证索引页中的相对链接正确；路径已解码，需重新编码
+        return Response::builder()
+            .status(301)
+            .header(header::LOCATION, format!("{}/", path::encode(url_path)))
+            .body(axum::body::Body::empty())
+            .unwrap();
+    }
+    serve_listing(state, dir, url_path, headers, query).await
+}
+
+/// 历史版本 `/__versions/<时间>/<文件>`，不计入访问记录
+async fn serve_versions(
+    State(state): State<ServerState>,
+    path: Option<Path<String>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+) -> Response {
+    let path = path.map(|Path(p)| p).unwrap_or_default();
+    let Ok(rel) = path::normalize(&path) else {
+        return not_found();
+    };
+    if is_sidecar(&rel) {
+        return not_found();
+    }
+    let real = state.root.join(VERSIONS_DIR).join(&rel);
+    if real.is_dir() {
+        // `/__versions` 本身按原样交给 serve_dir 补齐 `/`
+        let url_path = if path.is_empty() { uri.path().to_string() } else { format!("/__versions/{}", path) };
+        return serve_dir(&state, &real, &url_path, &headers, query.as_deref()).await;
+    }
+    // 去掉时间一段即为条目路径，用于 Content-Type 等；签名按规范化后的路径校验
+    let joined = path::joined(&path).unwrap_or_default();
+    let file = joined.split_once('/').map_or("", |(_, file)| file);
+    if let Some(resp) = check_signature(&state, file, &format!("{}/{}", VERSIONS_DIR, joined), query.as_deref()).await {
+        return resp;
+    }
+    serve_stored(&state, file, &real, &headers, query.as_deref(), false, method == Method::HEAD).await
+}
+
+/// 目录索引，不列出私有文件
+async fn serve_listing(
+    state: &ServerState,
+    dir: &std::path::Path,
+    url_path: &str,
+    headers: &HeaderMap,
+    query: Option<&str>,
+) -> Response {
+    let mut l = match listing::read_listing(dir, url_path).await {
+        Ok(l) => l,
+        Err(_) => return not_found(),
+    };
+    if let Some(signed) = &state.cc.config().await.signed_urls {
+        let prefix = url_path.trim_start_matches('/');
+        let private = signed.private_matcher();
+        l.entries.retain(|e| e.is_dir || !private.is_match(format!("{}{}", prefix, e.name)));
+    }
+    listing::render(&l, listing::wants_json(headers, query))
+}
+
+/// `entry` 是私有路径时校验查询参数中对 `signed_path` 的签名，未通过时返回 403
+async fn check_signature(state: &ServerState, entry: &str, signed_path: &str, query: Option<&str>) -> Option<Response> {
+    let cfg = state.cc.config().await;
+    let signed = cfg.signed_urls.as_ref().filter(|s| s.is_private(entry))?;
+    let reason = crate::signed_url::verify(signed, signed_path, query).err()?;
+    Some(
+        Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(axum::body::Body::from(format!("Forbidden: {}", reason)))
+            .unwrap(),
+    )
+}
+
+/// 将请求路径映射到存储目录，拒绝 `..` / 绝对路径等越界访问及内部状态目录
+fn resolve_path(root: &std::path::Path, path: &str) -> Option<PathBuf> {
+    let rel = path::normalize(path).ok()?;
+    // 以 `.` 开头的内部目录（流量统计、证书私钥、下载中的 tmp、代理缓存等）不对外提供，
+    // 历史版本只经 `/__versions/` 提供
+    if let Some(Component::Normal(first)) = rel.components().next()
+        && first.as_encoded_bytes().starts_with(b".")
+    {
+        return None;
+    }
+    // 文件旁的 meta 记录了上游地址与续传状态，同样不对外提供
+    if is_sidecar(&rel) {
+        return None;
+    }
+    Some(root.join(rel))
+}
+
+/// meta / 临时文件
+fn is_sidecar(rel: &std::path::Path) -> bool {
+    rel.file_name().and_then(|n| n.to_str()).is_some_and(listing::is_internal_file)
+}
+
+fn not_found() -> Response {
+    Response::builder()
+        .status(404)
+        .body(axum::body::Body::from("Not Found"))
+        .unwrap()
+}
+
+/// 根据 meta 中保存的 sha256 生成完整性校验头
+/// - Repr-Digest（RFC 9530）: sha-256=:<base64>:
+/// - Digest（RFC 3230，旧客户端兼容）: SHA-256=<base64>
+fn digest_headers(meta: &Meta) -> Vec<(&'static str, String)> {
+    let Some(raw) = meta.sha256.as_deref().and_then(|h| hex::decode(h).ok()) else {
+        return Vec::new();
+    };
+
+    let b64 = base64::engine::general_purpose::STANDARD.encode(raw);
+    vec![
+        ("repr-digest", format!("sha-256=:{}:", b64)),
+        ("digest", format!("SHA-256={}", b64)),
+    ]
+}
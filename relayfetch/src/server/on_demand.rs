@@ -0,0 +1,292 @@
+//! 按需回源（pull-through 镜像）
+//!
+//! 请求存储目录中不存在的路径时，从 `on_demand.upstream` 拉取同名文件，
+//! 一边转发给客户端一边写入 `.partial/`，完成后与同步下载的文件一样带 meta 落盘，
+//! 之后直接从磁盘提供。回源前先占住该文件，同时到达的其他请求等它公布 tmp 文件后跟随读取
+//! （见 `partial::follow`），不会重复回源；配额 / 磁盘空间不足时只转发不落盘。
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, Bytes},
+    http::{StatusCode, header},
+    response::Response,
+};
+use chrono::Utc;
+use futures::StreamExt;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::config::ConfigCenter;
+use crate::notify::Notification;
+use crate::sync::meta::{Meta, ensure_parent_dir, save_meta};
+use crate::sync::partial::{self, Claim};
+
+use super::{ServerState, path};
+
+/// 回源与转发之间缓冲的块数
+const CHANNEL_CHUNKS: usize = 16;
+
+/// 路径未配置按需回源时返回 None，交由调用方按 404 处理
+pub async fn fetch(state: &ServerState, file: &str, real: &Path) -> Option<Response> {
+    let (url, storage_dir) = {
+        let cfg = state.cc.config().await;
+        let on_demand = cfg.on_demand.as_ref()?;
+        // 隐藏路径（内部数据、`.well-known` 等）不回源
+        if file.split('/').any(|seg| seg.starts_with('.')) || !on_demand.includes(file) {
+            return None;
+        }
+        let url = format!("{}/{}", on_demand.upstream.trim_end_matches('/'), path::encode(file));
+        (url, cfg.storage_dir.clone())
+    };
+
+    // 先占住文件再回源：已有下载在进行时等它公布 tmp 文件后跟随读取，它结束后文件已落盘则由调用方提供，
+    // 否则（上游出错或未落盘）再由自己回源
+    let claim = loop {
+        if let Some(claim) = Claim::acquire(real) {
+            break claim;
+        }
+        partial::settled(real).await;
+        if let Some(resp) = super::follow::serve(state, file, real).await {
+            return Some(resp);
+        }
+        if real.exists() {
+            return None;
+        }
+    };
+
+    let host = crate::bandwidth::host_of(&url).unwrap_or_default();
+    let exhausted = {
+        let cfg = state.cc.config().await;
+        let bandwidth = state.cc.bandwidth();
+        bandwidth.monthly_exhausted(cfg.monthly_budget_bytes)
+            || bandwidth.over_budget(&host, &cfg.origin_daily_budget_bytes)
+    };
+    if exhausted {
+        warn!("[on_demand] bandwidth budget for {} exhausted, not fetching {}", host, url);
+        return Some(plain(StatusCode::SERVICE_UNAVAILABLE, "Bandwidth budget exhausted"));
+    }
+
+    let resp = match state.proxy_cache.client().get(&url).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            warn!("[on_demand] upstream error for {}: {}", url, e);
+            return Some(plain(StatusCode::BAD_GATEWAY, "Bad Gateway"));
+        }
+    };
+    match resp.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND | StatusCode::GONE => return Some(super::not_found()),
+        status => {
+            warn!("[on_demand] upstream returned {} for {}", status, url);
+            return Some(plain(StatusCode::BAD_GATEWAY, "Bad Gateway"));
+        }
+    }
+
+    let content_type = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .cloned()
+        .unwrap_or_else(|| {
+            header::HeaderValue::from_str(mime_guess::from_path(real).first_or_octet_stream().as_ref())
+                .unwrap()
+        });
+    let mut builder = Response::builder()
+        .status(200)
+        .header(header::CONTENT_TYPE, content_type)
+        .header("x-relayfetch-cache", "MISS");
+    if let Some(len) = resp.content_length() {
+        builder = builder.header(header::CONTENT_LENGTH, len);
+    }
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CHUNKS);
+    let job = Job {
+        cc: state.cc.clone(),
+        storage_dir,
+        file: file.to_string(),
+        real: real.to_path_buf(),
+        url,
+        host,
+        claim,
+    };
+    tokio::spawn(job.run(resp, tx));
+
+    let body = tokio_stream::wrappers::ReceiverStream::new(rx);
+    Some(builder.body(Body::from_stream(body)).unwrap())
+}
+
+struct Job {
+    cc: Arc<ConfigCenter>,
+    storage_dir: PathBuf,
+    file: String,
+    real: PathBuf,
+    url: String,
+    host: String,
+    claim: Claim,
+}
+
+impl Job {
+    /// 读取上游并转发；客户端断开后如仍在落盘则继续下载完
+    async fn run(self, resp: reqwest::Response, tx: mpsc::Sender<std::io::Result<Bytes>>) {
+        let expected = resp.content_length();
+        let etag = header_str(&resp, header::ETAG);
+        let last_modified = header_str(&resp, header::LAST_MODIFIED);
+
+        let max_storage = self.cc.config().await.max_storage_bytes;
+        let mut reservation = self
+            .cc
+            .quota()
+            .reserve(&self.storage_dir, max_storage, expected.unwrap_or(0), 0)
+            .inspect_err(|e| warn!("[on_demand] not storing {}: {}", self.file, e))
+            .ok();
+        let tmp_path = partial::tmp_path(&self.storage_dir, &self.file, &self.url);
+        let mut sink = match reservation {
+            Some(_) => match open(&tmp_path).await {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    warn!("[on_demand] failed to create {}: {}", tmp_path.display(), e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // 不落盘时立即放开，等待的请求各自回源
+        let (mut claim, mut publisher) = match sink {
+            Some(_) => {
+                let publisher = self.claim.publish(&tmp_path, expected, 0);
+                (Some(self.claim), Some(publisher))
+            }
+            None => (None, None),
+        };
+
+        let mut hasher = Sha256::new();
+        let mut written = 0u64;
+        let mut client_gone = false;
+        let mut stream = resp.bytes_stream();
+        while let Some(item) = stream.next().await {
+            let chunk = match item {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!("[on_demand] error while fetching {}: {}", self.url, e);
+                    let _ = tx.send(Err(std::io::Error::other(e))).await;
+                    sink = None;
+                    break;
+                }
+            };
+            self.cc.bandwidth().record(&self.host, chunk.len() as u64);
+            self.cc.shaper().throttle_download(&self.cc, chunk.len() as u64).await;
+
+            // 超出预留且放不下时不再落盘，只转发
+            if sink.is_some()
+                && let Some(Err(e)) = reservation.as_mut().map(|r| r.grow(written + chunk.len() as u64))
+            {
+                warn!("[on_demand] not storing {}: {}", self.file, e);
+                sink = None;
+                publisher = None;
+                claim = None;
+            }
+            if let Some(out) = sink.as_mut() {
+                let res = async {
+                    out.write_all(&chunk).await?;
+                    out.flush().await
+                }
+                .await;
+                if let Err(e) = res {
+                    warn!("[on_demand] failed to write {}: {}", tmp_path.display(), e);
+                    sink = None;
+                    publisher = None;
+                } else {
+                    hasher.update(&chunk);
+                    written += chunk.len() as u64;
+                    if let Some(p) = &publisher {
+                        p.advance(written);
+                    }
+                }
+            }
+            if !client_gone && tx.send(Ok(chunk)).await.is_err() {
+                client_gone = true;
+            }
+            if client_gone && sink.is_none() {
+                break;
+            }
+        }
+        drop(tx);
+
+        let Some(mut out) = sink else {
+            drop(publisher);
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            self.cc.storage_index().update(&self.storage_dir, &tmp_path);
+            return;
+        };
+        let finished = async {
+            out.flush().await?;
+            drop(out);
+            if expected.is_some_and(|len| len != written) {
+                anyhow::bail!("truncated response: got {} of {:?} bytes", written, expected);
+            }
+            ensure_parent_dir(&self.real)?;
+            tokio::fs::rename(&tmp_path, &self.real).await?;
+            if let Some(p) = publisher.take() {
+                p.finish(written);
+            }
+            let meta = Meta {
+                etag,
+                last_modified,
+                fetched_at: Some(Utc::now().to_rfc3339()),
+                total_size: Some(written),
+                sha256: Some(hex::encode(hasher.finalize())),
+                source: Some(self.url.clone()),
+                content_range: None,
+                chunks: None,
+                resume: None,
+                partial: None,
+                signed_by: None,
+            };
+            save_meta(&self.real.with_extension("meta"), &meta)?;
+            Ok(meta)
+        }
+        .await;
+
+        match finished {
+            Ok(meta) => {
+                if let Some(r) = reservation {
+                    r.commit(written, 0);
+                }
+                info!("[on_demand] stored {} from {}", self.file, self.url);
+                self.cc.notify(Notification::downloaded(&self.file, &self.real, &meta)).await;
+            }
+            Err(e) => {
+                warn!("[on_demand] failed to store {}: {:#}", self.file, e);
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+            }
+        }
+        self.cc.storage_index().update(&self.storage_dir, &self.real);
+        self.cc.storage_index().update(&self.storage_dir, &tmp_path);
+        drop(claim);
+    }
+}
+
+async fn open(tmp_path: &Path) -> std::io::Result<tokio::fs::File> {
+    if let Some(parent) = tmp_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::File::create(tmp_path).await
+}
+
+fn header_str(resp: &reqwest::Response, name: header::HeaderName) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+fn plain(status: StatusCode, msg: &'static str) -> Response {
+    Response::builder()
+        .status(status)
+        .body(Body::from(msg))
+        .unwrap()
+}
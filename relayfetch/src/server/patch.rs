@@ -0,0 +1,51 @@
+//! 增量补丁 `/<文件>.patch?from=<sha256>`
+//!
+//! 补丁由同步在文件更新时生成（见 `sync::delta`），只有目标版本仍是当前文件时才提供；
+//! 客户端应用补丁后以 `x-relayfetch-patch-to` 中的 sha256 校验结果。
+
+use axum::{
+    http::{StatusCode, header},
+    response::Response,
+};
+
+use crate::sync::{delta, meta::load_meta};
+
+/// 取出 `from=<sha256>`；格式不对时为 None
+pub fn from_query(query: Option<&str>) -> Option<String> {
+    query?
+        .split('&')
+        .find_map(|p| p.strip_prefix("from="))
+        .filter(|sha| sha.len() == 64 && sha.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map(|sha| sha.to_ascii_lowercase())
+}
+
+pub async fn serve(state: &super::ServerState, file: &str, from: &str) -> Response {
+    let storage_dir = {
+        let cfg = state.cc.config().await;
+        if cfg.delta_patches.is_none() {
+            return super::not_found();
+        }
+        cfg.storage_dir.clone()
+    };
+    let Some(real) = super::resolve_path(&state.root, file) else {
+        return super::not_found();
+    };
+
+    // 文件又更新过的补丁已过期
+    let current = load_meta(&real.with_extension("meta")).ok().and_then(|m| m.sha256);
+    let patch = match tokio::fs::read(delta::patch_path(&storage_dir, file, from)).await {
+        Ok(p) => p,
+        Err(_) => return super::not_found(),
+    };
+    let Some(target) = delta::target_sha256(&patch).filter(|t| Some(t) == current.as_ref()) else {
+        return super::not_found();
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, patch.len())
+        .header("x-relayfetch-patch-to", target)
+        .body(crate::shaping::serve_body(&state.cc, patch).await)
+        .unwrap()
+}
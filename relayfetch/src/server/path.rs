@@ -0,0 +1,64 @@
+//! 请求路径规范化
+//!
+//! 不依赖 crate 内其他模块，fuzz 目标直接引用本文件。
+
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+
+/// 重新编码到 URL 路径时需要转义的字符（`/` 保留）
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    /// 含 `..`、`.`、根目录或盘符
+    NotRelative,
+    /// 含 NUL 字节
+    Nul,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotRelative => f.write_str("path escapes the storage root"),
+            Self::Nul => f.write_str("path contains a NUL byte"),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// 已解码的请求路径 → 存储目录下的相对路径，只允许普通路径段
+pub fn normalize(path: &str) -> Result<PathBuf, PathError> {
+    if path.contains('\0') {
+        return Err(PathError::Nul);
+    }
+    let rel = Path::new(path);
+    if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(PathError::NotRelative);
+    }
+    Ok(rel.to_path_buf())
+}
+
+/// 规范化后以 `/` 连接的路径（`a//b`、`a/./b` 与 `a/b/` 都得到 `a/b`），用于私有路径匹配与签名
+pub fn joined(path: &str) -> Result<String, PathError> {
+    let rel = normalize(path)?;
+    let segments: Option<Vec<&str>> = rel.components().map(|c| c.as_os_str().to_str()).collect();
+    Ok(segments.ok_or(PathError::NotRelative)?.join("/"))
+}
+
+/// 将已解码的路径重新编码，可直接用作 Location 等响应头
+pub fn encode(path: &str) -> String {
+    utf8_percent_encode(path, PATH).to_string()
+}
@@ -0,0 +1,109 @@
+//! 下载端口的探针 `/healthz` 与 `/readyz`，供 Kubernetes / haproxy 等摘除不健康的节点
+//!
+//! - `/healthz`：进程存活即返回 200
+//! - `/readyz`：存储目录可写，（配置了 `[probes] max_staleness_secs` 时）最近一次成功同步不过旧，
+//!   且 files.toml 中标记了 `critical` 的文件都未超过各自的 `max_age_secs`，否则返回 503
+//!
+//! 两者都返回 JSON，不经过鉴权，也不查找存储目录；同名文件会被探针遮住。
+
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    body::Body,
+    http::{StatusCode, header},
+    response::Response,
+};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Healthz {
+    status: &'static str,
+    version: &'static str,
+    uptime_secs: u64,
+}
+
+#[derive(Serialize)]
+struct Readyz {
+    /// ready / not_ready
+    status: &'static str,
+    checks: Vec<Check>,
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+pub fn healthz(state: &super::ServerState) -> Response {
+    let body = Healthz {
+        status: "ok",
+        version: env!("CARGO_PKG_VERSION"),
+        uptime_secs: elapsed(state.started).as_secs(),
+    };
+    respond(StatusCode::OK, &body)
+}
+
+pub async fn readyz(state: &super::ServerState) -> Response {
+    let mut checks = Vec::new();
+
+    let root = state.root.clone();
+    let storage = tokio::task::spawn_blocking(move || crate::health::probe_storage(&root))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    checks.push(Check {
+        name: "storage",
+        ok: storage.is_ok(),
+        reason: storage.err(),
+    });
+
+    let max_staleness = state.cc.config().await.probes.max_staleness_secs;
+    if let Some(secs) = max_staleness {
+        let last_ok = state.cc.sync_status().await.last_ok_sync;
+        let age = elapsed(last_ok.unwrap_or(state.started));
+        let ok = age <= Duration::from_secs(secs);
+        let reason = (!ok).then(|| match last_ok {
+            Some(_) => format!("last successful sync was {}s ago (max {}s)", age.as_secs(), secs),
+            None => format!("no successful sync in {}s since startup (max {}s)", age.as_secs(), secs),
+        });
+        checks.push(Check { name: "sync", ok, reason });
+    }
+
+    if crate::freshness::has_critical(&state.cc).await {
+        let stale: Vec<String> = crate::freshness::stale_files(&state.cc)
+            .await
+            .into_iter()
+            .filter(|s| s.critical)
+            .map(|s| s.file)
+            .collect();
+        let reason = (!stale.is_empty()).then(|| format!("critical files are stale: {}", stale.join(", ")));
+        checks.push(Check {
+            name: "freshness",
+            ok: stale.is_empty(),
+            reason,
+        });
+    }
+
+    let ready = checks.iter().all(|c| c.ok);
+    let body = Readyz {
+        status: if ready { "ready" } else { "not_ready" },
+        checks,
+    };
+    let code = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    respond(code, &body)
+}
+
+fn elapsed(since: SystemTime) -> Duration {
+    SystemTime::now().duration_since(since).unwrap_or_default()
+}
+
+fn respond(code: StatusCode, body: &impl Serialize) -> Response {
+    Response::builder()
+        .status(code)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(serde_json::to_vec(body).unwrap()))
+        .unwrap()
+}
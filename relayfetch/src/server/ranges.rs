@@ -0,0 +1,81 @@
+//! 下载服务的 Range 请求（RFC 9110 §14）
+//!
+//! 只支持单个范围；多个范围、格式错误的 Range 头按普通请求返回完整内容。
+//! 显式起点大于 0 的范围视为客户端在中断后续传，计入访问记录（见 `AccessLog::record_transfer`）；
+//! `bytes=-N` 多用于读取文件尾部（如 zip 目录），不算续传。
+
+use axum::http::{HeaderMap, header};
+
+use crate::sync::meta::Meta;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requested {
+    /// 返回完整内容
+    Full,
+    /// 返回 `[start, end]`（含两端），`resume` 表示从中断处续传
+    Partial { start: u64, end: u64, resume: bool },
+    /// 范围超出文件长度（416）
+    Unsatisfiable,
+}
+
+impl Requested {
+    /// 是否为续传
+    pub fn is_resume(&self) -> bool {
+        matches!(self, Requested::Partial { resume: true, .. })
+    }
+}
+
+/// 下载服务返回的 ETag：本地内容的 sha256
+pub fn etag(meta: &Meta) -> Option<String> {
+    meta.sha256.as_ref().map(|sha256| format!("\"{}\"", sha256))
+}
+
+/// 解析 Range / If-Range；If-Range 与本地 ETag、meta 中上游的 ETag / Last-Modified 都不一致时返回完整内容
+pub fn requested(headers: &HeaderMap, meta: &Meta, len: u64) -> Requested {
+    let Some(range) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) else {
+        return Requested::Full;
+    };
+    if let Some(validator) = headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok())
+        && etag(meta).as_deref() != Some(validator)
+        && meta.etag.as_deref() != Some(validator)
+        && meta.last_modified.as_deref() != Some(validator)
+    {
+        return Requested::Full;
+    }
+    parse(range, len)
+}
+
+fn parse(range: &str, len: u64) -> Requested {
+    let Some(spec) = range.trim().strip_prefix("bytes=") else {
+        return Requested::Full;
+    };
+    if spec.contains(',') {
+        return Requested::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return Requested::Full;
+    };
+
+    match (first.parse::<u64>().ok(), last.parse::<u64>().ok()) {
+        // bytes=-N：最后 N 个字节
+        (None, Some(suffix)) if first.is_empty() => {
+            if suffix == 0 || len == 0 {
+                Requested::Unsatisfiable
+            } else {
+                Requested::Partial { start: len.saturating_sub(suffix), end: len - 1, resume: false }
+            }
+        }
+        // bytes=A- / bytes=A-B
+        (Some(start), end) if end.is_some() || last.is_empty() => {
+            if end.is_some_and(|end| end < start) {
+                return Requested::Full;
+            }
+            if start >= len {
+                return Requested::Unsatisfiable;
+            }
+            let end = end.map_or(len - 1, |end| end.min(len - 1));
+            Requested::Partial { start, end, resume: start > 0 }
+        }
+        _ => Requested::Full,
+    }
+}
@@ -0,0 +1,78 @@
+//! 下载端口根路径 `GET /`（`[root]`）
+//!
+//! 默认与其他目录一样按 `enable_listing` 渲染索引；也可跳转到管理控制台，
+//! 或返回一个 JSON 服务描述，方便探活与人工确认这是哪台镜像。
+
+use axum::{
+    http::{HeaderMap, header},
+    response::Response,
+};
+use serde::Serialize;
+
+use crate::config::config::{Config, RootMode};
+use crate::sync::SyncResult;
+
+#[derive(Serialize)]
+struct Descriptor {
+    name: String,
+    version: &'static str,
+    /// files.toml 中配置的条目数（不含目录镜像展开的文件）
+    files: usize,
+    /// unix 秒，从未同步时为 null
+    last_sync: Option<u64>,
+    last_ok_sync: Option<u64>,
+    last_result: &'static str,
+}
+
+pub async fn serve(state: &super::ServerState, headers: &HeaderMap, query: Option<&str>) -> Response {
+    let (mode, redirect_to, name, listing) = {
+        let cfg = state.cc.config().await;
+        (cfg.root.mode, redirect_target(&cfg), cfg.root.name.clone(), cfg.enable_listing)
+    };
+
+    match mode {
+        RootMode::Listing if listing => super::serve_listing(state, &state.root, "/", headers, query).await,
+        RootMode::Listing | RootMode::NotFound => super::not_found(),
+        RootMode::Redirect => Response::builder()
+            .status(302)
+            .header(header::LOCATION, redirect_to)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        RootMode::Descriptor => {
+            let files = state.cc.files().await.files.len();
+            let status = state.cc.sync_status().await;
+            let descriptor = Descriptor {
+                name,
+                version: env!("CARGO_PKG_VERSION"),
+                files,
+                last_sync: status.last_sync.map(unix),
+                last_ok_sync: status.last_ok_sync.map(unix),
+                last_result: match status.last_result {
+                    SyncResult::Success => "success",
+                    SyncResult::PartialSuccess => "partial_success",
+                    SyncResult::Failed(_) => "failed",
+                    SyncResult::Pending => "pending",
+                },
+            };
+            Response::builder()
+                .status(200)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::CACHE_CONTROL, "no-cache")
+                .body(axum::body::Body::from(serde_json::to_vec(&descriptor).unwrap()))
+                .unwrap()
+        }
+    }
+}
+
+/// 未配置 redirect_to 时指向本机管理端口的控制台
+fn redirect_target(cfg: &Config) -> String {
+    if let Some(target) = &cfg.root.redirect_to {
+        return target.clone();
+    }
+    let port = cfg.http_admin.rsplit(':').next().unwrap_or_default();
+    format!("http://{}:{}/dashboard", cfg.url, port)
+}
+
+fn unix(t: std::time::SystemTime) -> u64 {
+    t.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
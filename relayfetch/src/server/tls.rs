@@ -0,0 +1,95 @@
+//! HTTPS 下载服务：证书来自 ACME，续期后热替换
+
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use axum::Router;
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use log::{debug, info};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+use crate::acl::Peer;
+use super::listen::WithPeer;
+use crate::config::config::ServerConfig;
+
+/// 当前证书；尚未签发时握手失败
+#[derive(Debug, Default)]
+pub struct CertResolver {
+    current: RwLock<Option<Arc<CertifiedKey>>>,
+}
+
+impl CertResolver {
+    /// 从 PEM 文件加载证书链与私钥
+    pub fn load(&self, cert_path: &Path, key_path: &Path) -> Result<()> {
+        let chain = CertificateDer::pem_file_iter(cert_path)
+            .with_context(|| format!("failed to read {}", cert_path.display()))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let key = PrivateKeyDer::from_pem_file(key_path)
+            .with_context(|| format!("failed to read {}", key_path.display()))?;
+        let key = rustls::crypto::ring::sign::any_supported_type(&key)?;
+
+        *self.current.write().unwrap() = Some(Arc::new(CertifiedKey::new(chain, key)));
+        debug!("[tls] certificate loaded from {}", cert_path.display());
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.current.read().unwrap().clone()
+    }
+}
+
+/// 在 bind 上提供与 HTTP 相同的路由
+pub async fn serve(bind: String, app: Router, resolver: Arc<CertResolver>, server_cfg: &ServerConfig) -> Result<()> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    config.alpn_protocols = if server_cfg.http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    let builder = Arc::new(super::listen::conn_builder(server_cfg));
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind(&bind).await?;
+    info!("HTTPS download server listening on https://{}", bind);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                debug!("[tls] accept error: {}", e);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        let builder = builder.clone();
+
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(s) => s,
+                Err(e) => {
+                    debug!("[tls] handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            };
+            let service = TowerToHyperService::new(WithPeer::new(app, Peer::Tcp(peer.ip())));
+            if let Err(e) = builder.serve_connection(TokioIo::new(stream), service).await
+            {
+                debug!("[tls] connection with {} closed: {}", peer, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,133 @@
+//! 按时段限速（令牌桶）
+//!
+//! `bandwidth_schedule` 中的时段按本地时间匹配，第一个命中的时段生效，
+//! 不在任何时段内时不限速。同步下载与下载服务各用一个桶，
+//! 同一方向上的所有并发请求共享限额。
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, Bytes};
+use chrono::{Local, NaiveTime};
+use futures::{Stream, StreamExt, TryStreamExt, stream};
+
+use crate::config::{ConfigCenter, config::BandwidthWindow};
+
+/// 下载服务限速时每次发送的块大小
+const SERVE_CHUNK: usize = 64 * 1024;
+
+struct BucketState {
+    available: f64,
+    last: Instant,
+}
+
+/// 令牌桶，容量为一秒的限额
+pub struct TokenBucket {
+    state: Mutex<BucketState>,
+}
+
+impl Default for TokenBucket {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                available: 0.0,
+                last: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl TokenBucket {
+    /// 按 rate（字节/秒）消耗 bytes 个令牌，不足时等待
+    pub async fn consume(&self, bytes: u64, rate: u64) {
+        let wait = {
+            let mut s = self.state.lock().unwrap();
+            let now = Instant::now();
+            let rate = rate as f64;
+            let elapsed = now.duration_since(s.last).as_secs_f64();
+            s.available = (s.available + elapsed * rate).min(rate);
+            s.last = now;
+            s.available -= bytes as f64;
+            if s.available < 0.0 {
+                Duration::from_secs_f64(-s.available / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Shaper {
+    download: TokenBucket,
+    serve: TokenBucket,
+}
+
+impl Shaper {
+    /// 同步下载限速
+    pub async fn throttle_download(&self, cc: &ConfigCenter, bytes: u64) {
+        let rate = active_window(&cc.config().await.bandwidth_schedule)
+            .and_then(|w| w.download_bytes_per_sec);
+        if let Some(rate) = rate.filter(|r| *r > 0) {
+            self.download.consume(bytes, rate).await;
+        }
+    }
+}
+
+/// 当前生效的时段
+pub fn active_window(schedule: &[BandwidthWindow]) -> Option<&BandwidthWindow> {
+    let now = Local::now().time();
+    schedule.iter().find(|w| in_window(now, w.start, w.end))
+}
+
+/// start > end 表示跨越午夜（如 22:00 - 06:00）
+fn in_window(now: NaiveTime, start: NaiveTime, end: NaiveTime) -> bool {
+    if start <= end {
+        start <= now && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+/// 构造响应体：当前时段限制了下载服务速率时分块限速发送
+pub async fn serve_body(cc: &std::sync::Arc<ConfigCenter>, data: Vec<u8>) -> Body {
+    serve_stream(cc, stream::iter([Ok(Bytes::from(data))])).await
+}
+
+/// 同 [`serve_body`]，内容来自数据流（如按范围读取的文件）
+pub async fn serve_stream<S>(cc: &std::sync::Arc<ConfigCenter>, data: S) -> Body
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Send + 'static,
+{
+    let rate = active_window(&cc.config().await.bandwidth_schedule)
+        .and_then(|w| w.serve_bytes_per_sec)
+        .filter(|r| *r > 0);
+    let Some(rate) = rate else {
+        return Body::from_stream(data);
+    };
+
+    let cc = cc.clone();
+    let chunks = data
+        // 按 SERVE_CHUNK 切分后逐块取令牌
+        .map_ok(|data| {
+            let pieces: Vec<Bytes> = (0..data.len())
+                .step_by(SERVE_CHUNK)
+                .map(|offset| data.slice(offset..(offset + SERVE_CHUNK).min(data.len())))
+                .collect();
+            stream::iter(pieces.into_iter().map(Ok::<_, std::io::Error>))
+        })
+        .try_flatten()
+        .then(move |piece| {
+            let cc = cc.clone();
+            async move {
+                if let Ok(piece) = &piece {
+                    cc.shaper().serve.consume(piece.len() as u64, rate).await;
+                }
+                piece
+            }
+        });
+    Body::from_stream(chunks)
+}
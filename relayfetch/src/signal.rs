@@ -8,7 +8,20 @@ pub async fn shutdown_signal() {
     }
 }
 
+/// 服务控制管理器的停止请求（见 winservice）
+#[cfg(not(unix))]
+static REQUESTED: std::sync::LazyLock<tokio_util::sync::CancellationToken> =
+    std::sync::LazyLock::new(tokio_util::sync::CancellationToken::new);
+
+#[cfg(not(unix))]
+pub fn request_shutdown() {
+    REQUESTED.cancel();
+}
+
 #[cfg(not(unix))]
 pub async fn shutdown_signal() {
-    tokio::signal::ctrl_c().await.unwrap();
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = REQUESTED.cancelled() => {},
+    }
 }
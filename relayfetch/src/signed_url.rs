@@ -0,0 +1,128 @@
+//! 私有路径的签名链接（`[signed_urls]`）
+//!
+//! 匹配 `private` 的路径只能通过 `?expires=<unix 秒>&sig=<签名>` 下载。签名为
+//! HMAC-SHA256(secret, "<路径>\n<expires>") 的 base64url（无填充），路径为已解码、规范化（合并 `//` 与 `/./`）
+//! 且不含开头 `/` 的请求路径；`<文件>.patch` 使用 `<文件>` 的签名。链接由管理接口 SignUrl 生成。
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+
+use crate::config::config::SignedUrlConfig;
+
+pub fn sign(secret: &str, path: &str, expires: u64) -> Result<String> {
+    let key = PKey::hmac(secret.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    let mac = signer.sign_oneshot_to_vec(format!("{}\n{}", path, expires).as_bytes())?;
+    Ok(URL_SAFE_NO_PAD.encode(mac))
+}
+
+/// 校验查询参数中的签名，失败时返回原因
+pub fn verify(cfg: &SignedUrlConfig, path: &str, query: Option<&str>) -> Result<(), &'static str> {
+    let (mut expires, mut sig) = (None, None);
+    for pair in query.unwrap_or_default().split('&') {
+        match pair.split_once('=') {
+            Some(("expires", v)) => expires = v.parse::<u64>().ok(),
+            Some(("sig", v)) => sig = Some(v),
+            _ => {}
+        }
+    }
+    let (Some(expires), Some(sig)) = (expires, sig) else {
+        return Err("signature required");
+    };
+    if expires < now() {
+        return Err("link expired");
+    }
+    let expected = sign(&cfg.secret, path, expires).map_err(|_| "invalid signature")?;
+    if expected.len() != sig.len() || !openssl::memcmp::eq(expected.as_bytes(), sig.as_bytes()) {
+        return Err("invalid signature");
+    }
+    Ok(())
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::path::joined;
+
+    fn config() -> SignedUrlConfig {
+        SignedUrlConfig {
+            secret: "s3cret".to_string(),
+            private: vec!["internal/**".to_string()],
+            default_ttl_secs: 3600,
+            max_ttl_secs: None,
+            base_url: None,
+        }
+    }
+
+    fn query(path: &str, expires: u64) -> String {
+        format!("expires={}&sig={}", expires, sign("s3cret", path, expires).unwrap())
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let expires = now() + 60;
+        assert_eq!(verify(&config(), "internal/secret.bin", Some(&query("internal/secret.bin", expires))), Ok(()));
+    }
+
+    #[test]
+    fn rejects_missing_and_expired() {
+        let cfg = config();
+        assert_eq!(verify(&cfg, "internal/secret.bin", None), Err("signature required"));
+        assert_eq!(verify(&cfg, "internal/secret.bin", Some("expires=abc&sig=x")), Err("signature required"));
+        let expired = now() - 1;
+        assert_eq!(verify(&cfg, "internal/secret.bin", Some(&query("internal/secret.bin", expired))), Err("link expired"));
+    }
+
+    #[test]
+    fn rejects_wrong_signature() {
+        let cfg = config();
+        let expires = now() + 60;
+        // 其他路径的签名
+        assert_eq!(verify(&cfg, "internal/secret.bin", Some(&query("internal/other.bin", expires))), Err("invalid signature"));
+        // 篡改过期时间
+        let tampered = query("internal/secret.bin", expires).replacen(&expires.to_string(), &(expires + 1).to_string(), 1);
+        assert_eq!(verify(&cfg, "internal/secret.bin", Some(&tampered)), Err("invalid signature"));
+        // 其他密钥
+        let other = format!("expires={}&sig={}", expires, sign("other", "internal/secret.bin", expires).unwrap());
+        assert_eq!(verify(&cfg, "internal/secret.bin", Some(&other)), Err("invalid signature"));
+    }
+
+    #[test]
+    fn rejects_length_mismatch() {
+        let cfg = config();
+        let expires = now() + 60;
+        let valid = query("internal/secret.bin", expires);
+        assert_eq!(verify(&cfg, "internal/secret.bin", Some(&format!("{}A", valid))), Err("invalid signature"));
+        assert_eq!(verify(&cfg, "internal/secret.bin", Some(&valid[..valid.len() - 1])), Err("invalid signature"));
+        assert_eq!(verify(&cfg, "internal/secret.bin", Some(&format!("expires={}&sig=", expires))), Err("invalid signature"));
+    }
+
+    #[test]
+    fn path_variants_share_one_signature() {
+        let cfg = config();
+        let expires = now() + 60;
+        let q = query("internal/secret.bin", expires);
+        for variant in ["internal/secret.bin", "internal//secret.bin", "internal/./secret.bin", "internal/secret.bin/"] {
+            let entry = joined(variant).unwrap();
+            assert!(cfg.is_private(&entry), "{} should be private", variant);
+            assert_eq!(verify(&cfg, &entry, Some(&q)), Ok(()), "{}", variant);
+        }
+        assert!(joined("internal/../secret.bin").is_err());
+        // 版本路径中的条目同样规范化
+        let versioned = joined("20240101T000000//internal/./secret.bin").unwrap();
+        assert_eq!(versioned.split_once('/').map(|(_, f)| f), Some("internal/secret.bin"));
+    }
+}
@@ -0,0 +1,348 @@
+//! 存储目录索引
+//!
+//! status / list_files 不再每次遍历整个存储目录：首次使用时遍历一次，之后由同步与清理
+//! 直接更新。存储目录下的外部修改通过 notify 监听更新或使索引失效；网络文件系统上可能
+//! 收不到事件，因此索引超过 `storage_index_ttl_secs` 后也会重建。
+//!
+//! 每个文件按 meta 判定状态：meta 完整且大小一致为 complete，大小不符为 partial，
+//! 没有 meta 为 orphaned；`.partial/` 中未完成的下载也计入 partial。
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use walkdir::WalkDir;
+
+use crate::config::ConfigCenter;
+use crate::sync::{meta::load_meta, partial::PARTIAL_DIR};
+
+/// 检查 storage_dir 是否变更的间隔
+const ROOT_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 大目录扫描时每处理多少个文件输出一次进度
+const SCAN_PROGRESS_EVERY: usize = 10_000;
+
+/// 存储文件相对 meta 的状态
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoredState {
+    Complete,
+    /// 大小与 meta 记录的总大小不符
+    Partial,
+    /// 没有对应的 meta
+    Orphaned,
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexedFile {
+    pub last_modified: Option<DateTime<Utc>>,
+    pub state: StoredState,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct IndexedFiles {
+    /// 相对路径（`/` 分隔）→ 文件信息
+    pub files: BTreeMap<String, IndexedFile>,
+    /// 未完成下载的 tmp 文件（相对路径，含旧版本留在数据文件旁的 `.tmp`）
+    pub downloads: BTreeSet<String>,
+}
+
+/// 各状态的文件数
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StoredCounts {
+    pub complete: u32,
+    pub partial: u32,
+    pub orphaned: u32,
+}
+
+impl IndexedFiles {
+    pub fn counts(&self) -> StoredCounts {
+        let mut counts = StoredCounts {
+            partial: self.downloads.len() as u32,
+            ..Default::default()
+        };
+        for f in self.files.values() {
+            match f.state {
+                StoredState::Complete => counts.complete += 1,
+                StoredState::Partial => counts.partial += 1,
+                StoredState::Orphaned => counts.orphaned += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// 存储目录中的路径类别
+enum PathKind {
+    Data(String),
+    Download(String),
+}
+
+struct Snapshot {
+    root: PathBuf,
+    built: Instant,
+    files: Arc<IndexedFiles>,
+}
+
+#[derive(Default)]
+pub struct StorageIndex {
+    snapshot: RwLock<Option<Snapshot>>,
+    /// 每次更新或失效递增，遍历期间发生变化时不缓存遍历结果
+    generation: AtomicU64,
+}
+
+impl StorageIndex {
+    /// 当前文件表；索引缺失、过期或根目录变化时重新遍历。ttl 为 0 时不缓存
+    pub async fn files(&self, root: &Path, ttl: Duration) -> Arc<IndexedFiles> {
+        if !ttl.is_zero()
+            && let Some(s) = self.snapshot.read().unwrap().as_ref()
+            && s.root == root
+            && s.built.elapsed() < ttl
+        {
+            return s.files.clone();
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let dir = root.to_path_buf();
+        let files = Arc::new(
+            tokio::task::spawn_blocking(move || scan(&dir))
+                .await
+                .unwrap_or_default(),
+        );
+
+        if !ttl.is_zero() {
+            let mut snapshot = self.snapshot.write().unwrap();
+            if self.generation.load(Ordering::SeqCst) == generation {
+                *snapshot = Some(Snapshot {
+                    root: root.to_path_buf(),
+                    built: Instant::now(),
+                    files: files.clone(),
+                });
+                debug!("[index] indexed {} files under {}", files.files.len(), root.display());
+            }
+        }
+        files
+    }
+
+    /// 按磁盘现状更新单个文件（数据文件或 `.tmp`）：存在则写入，不存在则移除
+    pub fn update(&self, root: &Path, path: &Path) {
+        let Some(kind) = classify(root, path) else {
+            return;
+        };
+        let exists = path.is_file();
+        let inspected = match &kind {
+            PathKind::Data(_) if exists => Some(inspect(path)),
+            _ => None,
+        };
+
+        let mut snapshot = self.snapshot.write().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Some(s) = snapshot.as_mut()
+            && s.root == root
+        {
+            let index = Arc::make_mut(&mut s.files);
+            match kind {
+                PathKind::Data(key) => match inspected {
+                    Some(file) => {
+                        index.files.insert(key, file);
+                    }
+                    None => {
+                        index.files.remove(&key);
+                    }
+                },
+                PathKind::Download(key) if exists => {
+                    index.downloads.insert(key);
+                }
+                PathKind::Download(key) => {
+                    index.downloads.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// 丢弃索引，下次使用时重新遍历
+    pub fn invalidate(&self) {
+        let mut snapshot = self.snapshot.write().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *snapshot = None;
+    }
+
+    /// 处理一条外部文件事件
+    fn apply_event(&self, root: &Path, path: &Path) {
+        let Some(kind) = classify(root, path) else {
+            return;
+        };
+        if path.is_dir() {
+            // 整个目录被移入等情况，无法局部更新
+            self.invalidate();
+            return;
+        }
+        let known = self.snapshot.read().unwrap().as_ref().is_some_and(|s| match &kind {
+            PathKind::Data(key) => s.files.files.contains_key(key),
+            PathKind::Download(key) => s.files.downloads.contains(key),
+        });
+        if !path.exists() && !known {
+            // 可能是目录被删除或移走
+            self.invalidate();
+            return;
+        }
+        self.update(root, path);
+    }
+}
+
+/// 存储目录下以 `.` 开头的条目（如 `.cache`）属于内部数据，不参与扫描
+pub fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry
+            .file_name()
+            .to_str()
+            .map(|s| s.starts_with('.'))
+            .unwrap_or(false)
+}
+
+/// 读取 meta 判定文件状态；时间优先使用 meta 中的远端时间，其次为文件 mtime
+fn inspect(path: &Path) -> IndexedFile {
+    // 正确的 meta 路径：foo -> foo.meta
+    let meta_path = path.with_extension("meta");
+    let fs_meta = std::fs::metadata(path).ok();
+    let meta = meta_path.exists().then(|| load_meta(&meta_path).ok()).flatten();
+
+    let state = match &meta {
+        None => StoredState::Orphaned,
+        Some(m) => match (m.total_size, &fs_meta) {
+            (Some(total), Some(f)) if f.len() != total => StoredState::Partial,
+            _ => StoredState::Complete,
+        },
+    };
+
+    let last_modified = meta
+        .and_then(|m| m.last_modified)
+        .and_then(|lm| {
+            DateTime::parse_from_rfc2822(&lm)
+                .or_else(|_| DateTime::parse_from_rfc3339(&lm))
+                .ok()
+        })
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|| fs_meta.and_then(|m| m.modified().ok()).map(Into::into));
+
+    IndexedFile { last_modified, state }
+}
+
+/// 归类路径；meta 与存储目录外、隐藏目录下（`.partial/` 除外）的路径返回 None
+fn classify(root: &Path, path: &Path) -> Option<PathKind> {
+    let rel = path.strip_prefix(root).ok()?;
+    if rel.as_os_str().is_empty() {
+        return None;
+    }
+    if let Ok(name) = rel.strip_prefix(PARTIAL_DIR) {
+        let name = name.to_str()?;
+        return (name.ends_with(".tmp") && !name.contains('/'))
+            .then(|| PathKind::Download(format!("{}/{}", PARTIAL_DIR, name)));
+    }
+    let mut parts = Vec::new();
+    for c in rel.components() {
+        let s = c.as_os_str().to_str()?;
+        if s.starts_with('.') {
+            return None;
+        }
+        parts.push(s);
+    }
+    let key = parts.join("/");
+    match path.extension().and_then(|s| s.to_str()) {
+        Some("meta") => None,
+        Some("tmp") => Some(PathKind::Download(key)),
+        _ => Some(PathKind::Data(key)),
+    }
+}
+
+/// 遍历目录本身是顺序的，逐个文件的 stat 与 meta 读取交给扫描线程池
+fn scan(root: &Path) -> IndexedFiles {
+    let paths: Vec<(PathKind, PathBuf)> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| !is_hidden(e) || (e.depth() == 1 && e.file_name() == PARTIAL_DIR))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| classify(root, e.path()).map(|kind| (kind, e.into_path())))
+        .collect();
+
+    let inspected = crate::scan::par_map(
+        &paths,
+        |(kind, path)| matches!(kind, PathKind::Data(_)).then(|| inspect(path)),
+        |done, total| {
+            if done % SCAN_PROGRESS_EVERY == 0 {
+                info!("[index] scanned {}/{} files under {}", done, total, root.display());
+            }
+        },
+    );
+
+    let mut index = IndexedFiles::default();
+    for ((kind, _), file) in paths.into_iter().zip(inspected) {
+        match (kind, file) {
+            (PathKind::Data(key), Some(file)) => {
+                index.files.insert(key, file);
+            }
+            (PathKind::Download(key), _) => {
+                index.downloads.insert(key);
+            }
+            (PathKind::Data(_), None) => {}
+        }
+    }
+    index
+}
+
+/// 监听存储目录的外部修改；storage_dir 变更后切换监听目录
+pub fn spawn_storage_watcher(cc: Arc<ConfigCenter>) -> anyhow::Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<notify::Event>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        match res {
+            Ok(event) => {
+                if !event.kind.is_access() {
+                    let _ = tx.send(event);
+                }
+            }
+            Err(e) => warn!("[index] watcher error: {}", e),
+        }
+    })?;
+
+    tokio::spawn(async move {
+        let mut root: Option<PathBuf> = None;
+        let mut ticker = tokio::time::interval(ROOT_CHECK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let current = cc.config().await.storage_dir.clone();
+                    if root.as_ref() == Some(&current) {
+                        continue;
+                    }
+                    if let Some(old) = root.take() {
+                        let _ = watcher.unwatch(&old);
+                    }
+                    match watcher.watch(&current, RecursiveMode::Recursive) {
+                        Ok(()) => info!("[index] watching {}", current.display()),
+                        // 例如 inotify 监听数不足：仍可依赖 TTL 重建
+                        Err(e) => warn!("[index] cannot watch {}: {}", current.display(), e),
+                    }
+                    root = Some(current);
+                }
+                Some(event) = rx.recv() => {
+                    let Some(root) = root.as_ref() else { continue };
+                    if event.need_rescan() {
+                        cc.storage_index().invalidate();
+                        continue;
+                    }
+                    for path in &event.paths {
+                        cc.storage_index().apply_event(root, path);
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
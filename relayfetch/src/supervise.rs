@@ -0,0 +1,81 @@
+//! 后台任务的 panic 隔离
+//!
+//! 周期同步、管理接口等常驻任务 panic 后按退避间隔重启；下载任务 panic 时只记为该文件失败。
+//! 每个任务的 panic 次数在 status 中给出，panic 时该子系统的健康状态记为 Failed。
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{error, warn};
+use tokio::task::JoinError;
+
+use crate::config::ConfigCenter;
+use crate::health::Subsystem;
+
+/// 首次重启前的等待，之后每次翻倍
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+/// 重启等待的上限
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(60);
+/// 运行超过该时长后再 panic，退避从头计算
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(300);
+
+/// 各任务 panic（即重启）次数
+#[derive(Default)]
+pub struct Restarts {
+    counts: Mutex<BTreeMap<String, u32>>,
+}
+
+impl Restarts {
+    pub fn record(&self, task: &str) {
+        *self.counts.lock().unwrap().entry(task.to_string()).or_default() += 1;
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, u32> {
+        self.counts.lock().unwrap().clone()
+    }
+}
+
+/// 取出 panic 信息
+pub fn panic_message(err: JoinError) -> String {
+    match err.try_into_panic() {
+        Ok(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string()),
+        Err(err) => err.to_string(),
+    }
+}
+
+/// 运行常驻任务；panic 后重新调用 `make` 启动，正常返回则不再重启
+pub fn spawn<F, Fut>(cc: Arc<ConfigCenter>, subsystem: Subsystem, make: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let name = subsystem.name();
+    tokio::spawn(async move {
+        let mut delay = RESTART_BASE_DELAY;
+        loop {
+            let started = Instant::now();
+            match tokio::spawn(make()).await {
+                Ok(()) => return,
+                Err(e) if e.is_cancelled() => return,
+                Err(e) => {
+                    cc.restarts().record(name);
+                    if started.elapsed() >= RESTART_RESET_AFTER {
+                        delay = RESTART_BASE_DELAY;
+                    }
+                    let message = panic_message(e);
+                    error!("[supervise] task {} panicked: {}", name, message);
+                    cc.health().failed(subsystem, format!("panicked: {}", message));
+                    warn!("[supervise] restarting {} in {:?}", name, delay);
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(RESTART_MAX_DELAY);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,257 @@
+//! 分段并发下载（files.toml 中的 `parallel_chunks`）
+//!
+//! 先以 HEAD 取得大小与校验头，上游支持 Range 时把 tmp 文件预分配到完整大小，各段以独立的
+//! Range 请求并发写入各自的位置；每段写完后记入 meta 的 `chunks`，中断后只重新下载未完成的段。
+//! 上游的 ETag / Last-Modified 变化时丢弃已下载的段。全部完成后按续传处理：tmp 文件中已是完整
+//! 内容，摘要、改名与 meta 与普通下载一致。
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use log::{info, warn};
+use reqwest::{StatusCode, header};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::meta::{ChunkState, Meta, save_meta};
+use super::{Cancelled, FileEvent, partial::Publisher, upstream_request};
+use crate::config::{ConfigCenter, config::S3Config, file::UpstreamAuth};
+
+/// 每段至少这么大，文件较小时减少段数
+const MIN_CHUNK_BYTES: u64 = 1024 * 1024;
+
+/// 上游内容在分段下载期间发生了变化
+#[derive(Debug, thiserror::Error)]
+#[error("upstream content changed during chunked download")]
+struct Changed;
+
+/// 一次分段下载的安排
+pub(super) struct Plan {
+    pub total: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    state: ChunkState,
+    /// 沿用 meta 中记录的进度
+    resumed: bool,
+}
+
+impl Plan {
+    fn chunks(&self) -> usize {
+        self.total.div_ceil(self.state.chunk_size) as usize
+    }
+
+    /// 第 i 段的 [start, end)
+    fn bounds(&self, i: usize) -> (u64, u64) {
+        let start = i as u64 * self.state.chunk_size;
+        (start, (start + self.state.chunk_size).min(self.total))
+    }
+
+    /// 从文件开头起连续写完的字节数，供跟随读取的客户端使用
+    fn prefix(&self, progress: &[u64]) -> u64 {
+        let mut written = 0;
+        for (i, &n) in progress.iter().enumerate() {
+            written += n;
+            let (start, end) = self.bounds(i);
+            if n < end - start {
+                break;
+            }
+        }
+        written
+    }
+}
+
+/// HEAD 上游决定是否分段；上游不支持 Range、没有 Content-Length 或文件太小时返回 None，按普通方式下载
+pub(super) async fn probe(
+    client: &reqwest::Client,
+    url: &str,
+    auth: Option<&UpstreamAuth>,
+    s3: &S3Config,
+    chunks: usize,
+    old_meta: &Meta,
+    tmp_path: &Path,
+) -> Result<Option<Plan>> {
+    let resp = upstream_request(client, reqwest::Method::HEAD, url, auth, s3)?
+        .send()
+        .await
+        .context("HEAD request failed")?;
+    if !resp.status().is_success() {
+        info!("{}: HEAD returned {}, downloading in one piece", url, resp.status());
+        return Ok(None);
+    }
+    let header_str = |name| resp.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let ranges = header_str(header::ACCEPT_RANGES).is_some_and(|v| v.eq_ignore_ascii_case("bytes"));
+    // HEAD 的响应体为空，Content-Length 只能从响应头读取
+    let Some(total) = header_str(header::CONTENT_LENGTH).and_then(|v| v.parse::<u64>().ok()).filter(|_| ranges) else {
+        info!("{}: upstream does not support range requests, downloading in one piece", url);
+        return Ok(None);
+    };
+    let chunks = (chunks as u64).min(total / MIN_CHUNK_BYTES);
+    if chunks < 2 {
+        return Ok(None);
+    }
+
+    let state = ChunkState {
+        source: url.to_string(),
+        total,
+        chunk_size: total.div_ceil(chunks),
+        etag: header_str(header::ETAG),
+        last_modified: header_str(header::LAST_MODIFIED),
+        done: Vec::new(),
+    };
+    // 只有上游、大小、校验头与分段方式都相同，且 tmp 文件仍是预分配的大小时才沿用已完成的段
+    let tmp_len = tokio::fs::metadata(tmp_path).await.map(|m| m.len()).ok();
+    let (state, resumed) = match &old_meta.chunks {
+        Some(old) if tmp_len == Some(total) && *old == ChunkState { done: old.done.clone(), ..state.clone() } => {
+            (old.clone(), true)
+        }
+        _ => (state, false),
+    };
+    Ok(Some(Plan {
+        total,
+        etag: state.etag.clone(),
+        last_modified: state.last_modified.clone(),
+        state,
+        resumed,
+    }))
+}
+
+/// 下载所有未完成的段；返回时 tmp 文件已是完整内容
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn download<F, Fut>(
+    client: &reqwest::Client,
+    file: &str,
+    url: &str,
+    auth: Option<&UpstreamAuth>,
+    s3: &S3Config,
+    tmp_path: &Path,
+    meta_path: &Path,
+    old_meta: &Meta,
+    mut plan: Plan,
+    cc: &ConfigCenter,
+    throttle: bool,
+    cancel: &CancellationToken,
+    publisher: &Publisher,
+    report: &mut F,
+) -> Result<()>
+where
+    F: FnMut(FileEvent) -> Fut + Send,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    if !plan.resumed {
+        let out = tokio::fs::File::create(tmp_path).await?;
+        out.set_len(plan.total).await?;
+    }
+    let chunks = plan.chunks();
+    let mut progress = vec![0u64; chunks];
+    for &i in plan.state.done.iter().filter(|&&i| i < chunks) {
+        let (start, end) = plan.bounds(i);
+        progress[i] = end - start;
+    }
+    let reused: u64 = progress.iter().sum();
+    if reused > 0 {
+        info!("File {}: resuming chunked download, {} of {} chunks done", file, plan.state.done.len(), chunks);
+        report(FileEvent::Transferred { received: 0, saved: reused }).await;
+        report(FileEvent::Progress { file: file.to_string(), downloaded: reused }).await;
+    }
+    publisher.advance(plan.prefix(&progress));
+
+    // 各段在同一任务中并发进行，进度经 channel 汇总：Some(n) 为写入了 n 字节，None 为该段完成
+    let (tx, mut rx) = mpsc::unbounded_channel::<(usize, Option<u64>)>();
+    let host = crate::bandwidth::host_of(url).unwrap_or_default();
+    let (etag, last_modified) = (plan.etag.clone(), plan.last_modified.clone());
+    let bounds: Vec<(usize, u64, u64)> = (0..chunks)
+        .filter(|i| !plan.state.done.contains(i))
+        .map(|i| {
+            let (start, end) = plan.bounds(i);
+            (i, start, end)
+        })
+        .collect();
+    let workers = futures::future::try_join_all(bounds.into_iter().map(|(i, start, end)| {
+        let (etag, last_modified) = (etag.as_deref(), last_modified.as_deref());
+        let (tx, host) = (tx.clone(), host.as_str());
+        async move {
+            let req = upstream_request(client, reqwest::Method::GET, url, auth, s3)?
+                .header(header::RANGE, format!("bytes={}-{}", start, end - 1));
+            let resp = req.send().await.with_context(|| format!("chunk {} request failed", i))?;
+            if resp.status() != StatusCode::PARTIAL_CONTENT {
+                anyhow::bail!("chunk {}: upstream returned {} instead of 206", i, resp.status());
+            }
+            let header_str = |name| resp.headers().get(name).and_then(|v| v.to_str().ok());
+            let changed = match etag {
+                Some(_) => header_str(header::ETAG) != etag,
+                None => last_modified.is_some() && header_str(header::LAST_MODIFIED) != last_modified,
+            };
+            if changed {
+                return Err(Changed.into());
+            }
+
+            let mut out = tokio::fs::OpenOptions::new().write(true).open(tmp_path).await?;
+            out.seek(std::io::SeekFrom::Start(start)).await?;
+            let mut written = 0u64;
+            let mut stream = resp.bytes_stream();
+            while let Some(item) = stream.next().await {
+                let chunk = item.with_context(|| format!("error while downloading chunk {}", i))?;
+                if written + chunk.len() as u64 > end - start {
+                    anyhow::bail!("chunk {}: upstream sent more than requested", i);
+                }
+                out.write_all(&chunk).await?;
+                out.flush().await?;
+                written += chunk.len() as u64;
+                cc.bandwidth().record(host, chunk.len() as u64);
+                let _ = tx.send((i, Some(chunk.len() as u64)));
+                if throttle {
+                    cc.shaper().throttle_download(cc, chunk.len() as u64).await;
+                }
+            }
+            if written != end - start {
+                anyhow::bail!("chunk {}: truncated response: got {} of {} bytes", i, written, end - start);
+            }
+            out.sync_data().await?;
+            let _ = tx.send((i, None));
+            Ok(())
+        }
+    }));
+    drop(tx);
+    tokio::pin!(workers);
+
+    let mut handle = async |(i, msg): (usize, Option<u64>), plan: &mut Plan| -> Result<()> {
+        match msg {
+            Some(n) => {
+                progress[i] += n;
+                publisher.advance(plan.prefix(&progress));
+                report(FileEvent::Transferred { received: n, saved: 0 }).await;
+                report(FileEvent::Progress { file: file.to_string(), downloaded: progress.iter().sum() }).await;
+            }
+            None => {
+                plan.state.done.push(i);
+                let meta = Meta { chunks: Some(plan.state.clone()), ..old_meta.clone() };
+                save_meta(meta_path, &meta)?;
+            }
+        }
+        Ok(())
+    };
+    let result = loop {
+        tokio::select! {
+            res = &mut workers => break res.map(|_| ()),
+            Some(msg) = rx.recv() => handle(msg, &mut plan).await?,
+            _ = cancel.cancelled() => break Err(Cancelled.into()),
+        }
+    };
+    // 段完成的消息可能晚于全部任务结束
+    while let Ok(msg) = rx.try_recv() {
+        handle(msg, &mut plan).await?;
+    }
+
+    if let Err(e) = result {
+        if e.is::<Changed>() {
+            warn!("File {}: upstream changed during chunked download, restarting", file);
+            let _ = tokio::fs::remove_file(tmp_path).await;
+            save_meta(meta_path, &Meta { chunks: None, ..old_meta.clone() })?;
+        }
+        return Err(e);
+    }
+    info!("File {}: {} chunks downloaded from {}", file, chunks, url);
+    Ok(())
+}
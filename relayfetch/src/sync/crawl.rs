@@ -0,0 +1,302 @@
+//! 目录镜像：枚举远端目录中的条目，展开为 (本地路径, URL)
+//!
+//! 支持 HTTP 目录索引页、S3 ListObjectsV2（匿名）与 JSON 清单三种来源，
+//! 结果按 include / exclude 过滤后与 `[files]` 一起参与同步。
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{Context, Result, bail};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use log::{info, warn};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::config::file::{DirSource, ListingKind};
+
+/// 单个目录最多跟随的索引页数，防止链接环或超大站点
+const MAX_INDEX_PAGES: usize = 10_000;
+
+/// 路径段编码时保留的字符（RFC 3986 unreserved）
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// 展开所有目录；返回条目（本地路径, URL）与失败的目录（前缀, 原因）
+pub async fn expand_dirs(
+    client: &reqwest::Client,
+    dirs: &HashMap<String, DirSource>,
+) -> (Vec<(String, String)>, Vec<(String, String)>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+
+    for (prefix, source) in dirs {
+        let prefix = prefix.trim_matches('/');
+        match list_dir(client, source).await {
+            Ok(listed) => {
+                info!("[crawl] {} -> {} entries from {}", prefix, listed.len(), source.url);
+                entries.extend(
+                    listed
+                        .into_iter()
+                        .map(|(rel, url)| (format!("{}/{}", prefix, rel), url)),
+                );
+            }
+            Err(e) => errors.push((prefix.to_string(), format!("listing failed: {:#}", e))),
+        }
+    }
+
+    (entries, errors)
+}
+
+/// 列举单个目录，返回按 include / exclude 过滤后的 (相对路径, URL)
+async fn list_dir(client: &reqwest::Client, source: &DirSource) -> Result<Vec<(String, String)>> {
+    let include = glob_set(&source.include)?;
+    let exclude = glob_set(&source.exclude)?;
+
+    let listed = match source.kind {
+        ListingKind::HttpIndex => list_http_index(client, source).await?,
+        ListingKind::S3 => list_s3(client, source).await?,
+        ListingKind::Manifest => list_manifest(client, source).await?,
+    };
+
+    Ok(listed
+        .into_iter()
+        .filter(|(rel, _)| {
+            if !is_safe_path(rel) {
+                warn!("[crawl] skipping unsafe path {:?} from {}", rel, source.url);
+                return false;
+            }
+            (source.include.is_empty() || include.is_match(rel)) && !exclude.is_match(rel)
+        })
+        .collect())
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for p in patterns {
+        builder.add(Glob::new(p).with_context(|| format!("invalid glob {:?}", p))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// 远端给出的路径不能越出前缀，也不能写入隐藏的内部目录
+fn is_safe_path(rel: &str) -> bool {
+    !rel.is_empty()
+        && !rel.contains('\\')
+        && rel
+            .split('/')
+            .all(|seg| !seg.is_empty() && !seg.starts_with('.'))
+}
+
+/// 以 `/` 结尾，保证 join 相对路径时不丢掉最后一段
+fn as_dir(url: &str) -> Result<Url> {
+    let mut url = Url::parse(url).with_context(|| format!("invalid url {}", url))?;
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|seg| utf8_percent_encode(seg, SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/* =========================
+ * HTTP 目录索引
+ * ========================= */
+
+async fn list_http_index(client: &reqwest::Client, source: &DirSource) -> Result<Vec<(String, String)>> {
+    let base = as_dir(&source.url)?;
+    let mut pending = vec![(base.clone(), 0usize)];
+    let mut visited = HashSet::new();
+    let mut found = BTreeMap::new();
+
+    while let Some((page, depth)) = pending.pop() {
+        if !visited.insert(page.clone()) {
+            continue;
+        }
+        if visited.len() > MAX_INDEX_PAGES {
+            bail!("more than {} index pages under {}", MAX_INDEX_PAGES, base);
+        }
+
+        let html = client
+            .get(page.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        for href in hrefs(&html) {
+            let Ok(mut link) = page.join(&href) else {
+                continue;
+            };
+            link.set_fragment(None);
+            // 排序链接（?C=N;O=D）、上级目录与外站链接都不跟随
+            if link.query().is_some()
+                || link.origin() != page.origin()
+                || !link.path().starts_with(page.path())
+                || link.path() == page.path()
+            {
+                continue;
+            }
+
+            if link.path().ends_with('/') {
+                if depth < source.max_depth {
+                    pending.push((link, depth + 1));
+                }
+                continue;
+            }
+
+            let rel = &link.path()[base.path().len()..];
+            if let Ok(rel) = percent_decode_str(rel).decode_utf8() {
+                found.insert(rel.into_owned(), link.to_string());
+            }
+        }
+    }
+
+    Ok(found.into_iter().collect())
+}
+
+/// 提取页面中所有 href 属性值
+fn hrefs(html: &str) -> Vec<String> {
+    // ASCII 小写不改变字节偏移
+    let lower = html.to_ascii_lowercase();
+    let mut out = Vec::new();
+    let mut pos = 0;
+
+    while let Some(i) = lower[pos..].find("href=") {
+        let start = pos + i + "href=".len();
+        let rest = &html[start..];
+        let (value, used) = match rest.chars().next() {
+            Some(q @ ('"' | '\'')) => match rest[1..].find(q) {
+                Some(end) => (&rest[1..1 + end], end + 2),
+                None => break,
+            },
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .unwrap_or(rest.len());
+                (&rest[..end], end)
+            }
+        };
+        out.push(unescape(value));
+        pos = start + used;
+    }
+
+    out
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/* =========================
+ * S3 ListObjectsV2
+ * ========================= */
+
+async fn list_s3(client: &reqwest::Client, source: &DirSource) -> Result<Vec<(String, String)>> {
+    let bucket = as_dir(&source.url)?;
+    let prefix = source.prefix.clone().unwrap_or_default();
+    let mut token: Option<String> = None;
+    let mut found = Vec::new();
+
+    loop {
+        let mut req = bucket.clone();
+        {
+            let mut query = req.query_pairs_mut();
+            query.append_pair("list-type", "2");
+            if !prefix.is_empty() {
+                query.append_pair("prefix", &prefix);
+            }
+            if let Some(t) = &token {
+                query.append_pair("continuation-token", t);
+            }
+        }
+
+        let xml = client.get(req).send().await?.error_for_status()?.text().await?;
+
+        for key in xml_values(&xml, "Key") {
+            // 以 / 结尾的是目录占位对象
+            if key.ends_with('/') {
+                continue;
+            }
+            let rel = key.strip_prefix(&prefix).unwrap_or(&key).trim_start_matches('/');
+            let url = bucket.join(&encode_path(&key))?;
+            found.push((rel.to_string(), url.to_string()));
+        }
+
+        let truncated = xml_values(&xml, "IsTruncated").first().is_some_and(|v| v == "true");
+        token = xml_values(&xml, "NextContinuationToken").into_iter().next();
+        if !truncated || token.is_none() {
+            break;
+        }
+    }
+
+    Ok(found)
+}
+
+/// 取出所有 `<tag>...</tag>` 的文本
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut out = Vec::new();
+    let mut rest = xml;
+
+    while let Some(i) = rest.find(&open) {
+        rest = &rest[i + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        out.push(unescape(&rest[..end]));
+        rest = &rest[end + close.len()..];
+    }
+
+    out
+}
+
+/* =========================
+ * JSON 清单
+ * ========================= */
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestEntry {
+    /// 相对路径，URL 相对清单地址解析
+    Path(String),
+    Entry { path: String, url: Option<String> },
+}
+
+async fn list_manifest(client: &reqwest::Client, source: &DirSource) -> Result<Vec<(String, String)>> {
+    let manifest = Url::parse(&source.url).with_context(|| format!("invalid url {}", source.url))?;
+    let entries: Vec<ManifestEntry> = client
+        .get(manifest.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("invalid manifest")?;
+
+    let mut found = Vec::new();
+    for entry in entries {
+        let (path, url) = match entry {
+            ManifestEntry::Path(path) => (path, None),
+            ManifestEntry::Entry { path, url } => (path, url),
+        };
+        let rel = path.trim_start_matches('/').to_string();
+        let url = match url {
+            Some(u) => u,
+            None => manifest.join(&encode_path(&rel))?.to_string(),
+        };
+        found.push((rel, url));
+    }
+
+    Ok(found)
+}
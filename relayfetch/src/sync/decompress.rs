@@ -0,0 +1,81 @@
+//! 下载时解压：上游只提供 `.gz` / `.zst` / `.xz` 时，或响应带有协商得到的 Content-Encoding 时，
+//! 本地保存解压后的内容
+//!
+//! 网络数据块逐个写入解码器，取出已解压的部分交给调用方写盘与计算摘要；
+//! 流量统计与限速仍按压缩后的字节计算。
+
+use async_compression::tokio::write::{BrotliDecoder, GzipDecoder, XzDecoder, ZlibDecoder, ZstdDecoder};
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{config::ContentCoding, file::Compression};
+
+pub enum Decoder {
+    Gzip(GzipDecoder<Vec<u8>>),
+    Zstd(ZstdDecoder<Vec<u8>>),
+    Xz(XzDecoder<Vec<u8>>),
+    /// HTTP 的 deflate 是 zlib 格式
+    Zlib(ZlibDecoder<Vec<u8>>),
+    Brotli(BrotliDecoder<Vec<u8>>),
+}
+
+impl Decoder {
+    pub fn new(compression: Compression) -> Self {
+        match compression {
+            Compression::Gzip => Self::Gzip(GzipDecoder::new(Vec::new())),
+            Compression::Zstd => Self::Zstd(ZstdDecoder::new(Vec::new())),
+            Compression::Xz => Self::Xz(XzDecoder::new(Vec::new())),
+        }
+    }
+
+    /// 解码响应的 Content-Encoding
+    pub fn content(coding: ContentCoding) -> Self {
+        match coding {
+            ContentCoding::Gzip => Self::Gzip(GzipDecoder::new(Vec::new())),
+            ContentCoding::Deflate => Self::Zlib(ZlibDecoder::new(Vec::new())),
+            ContentCoding::Zstd => Self::Zstd(ZstdDecoder::new(Vec::new())),
+            ContentCoding::Brotli => Self::Brotli(BrotliDecoder::new(Vec::new())),
+        }
+    }
+
+    /// 写入一块压缩数据，返回目前已解压出的内容
+    pub async fn decode(&mut self, chunk: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(d) => d.write_all(chunk).await?,
+            Self::Zstd(d) => d.write_all(chunk).await?,
+            Self::Xz(d) => d.write_all(chunk).await?,
+            Self::Zlib(d) => d.write_all(chunk).await?,
+            Self::Brotli(d) => d.write_all(chunk).await?,
+        }
+        // 解码器内部有缓冲，flush 后已解压的部分才会写入 Vec
+        match self {
+            Self::Gzip(d) => d.flush().await?,
+            Self::Zstd(d) => d.flush().await?,
+            Self::Xz(d) => d.flush().await?,
+            Self::Zlib(d) => d.flush().await?,
+            Self::Brotli(d) => d.flush().await?,
+        }
+        Ok(self.take())
+    }
+
+    /// 输入结束；压缩流不完整时返回错误
+    pub async fn finish(&mut self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(d) => d.shutdown().await?,
+            Self::Zstd(d) => d.shutdown().await?,
+            Self::Xz(d) => d.shutdown().await?,
+            Self::Zlib(d) => d.shutdown().await?,
+            Self::Brotli(d) => d.shutdown().await?,
+        }
+        Ok(self.take())
+    }
+
+    fn take(&mut self) -> Vec<u8> {
+        std::mem::take(match self {
+            Self::Gzip(d) => d.get_mut(),
+            Self::Zstd(d) => d.get_mut(),
+            Self::Xz(d) => d.get_mut(),
+            Self::Zlib(d) => d.get_mut(),
+            Self::Brotli(d) => d.get_mut(),
+        })
+    }
+}
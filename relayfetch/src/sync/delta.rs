@@ -0,0 +1,163 @@
+//! 文件版本之间的增量补丁
+//!
+//! 同步替换文件时，用旧内容与新内容生成补丁，保存在
+//! `.relayfetch/patches/<文件>/<旧 sha256>.patch`，下载端口以 `/<文件>.patch?from=<旧 sha256>`
+//! 提供。每个文件只保留从上一版本到当前版本的补丁。
+//!
+//! 格式（整数均为小端）：
+//! - 头部：`RFDELTA1`、旧文件长度 u64、新文件长度 u64、新文件 sha256（32 字节）
+//! - 之后若干指令：`0x01` + 偏移 u64 + 长度 u64 表示从旧文件复制；
+//!   `0x02` + 长度 u64 + 数据 表示插入新数据
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::bandwidth::STATE_DIR;
+
+const MAGIC: &[u8; 8] = b"RFDELTA1";
+const HEADER_LEN: usize = 8 + 8 + 8 + 32;
+const OP_COPY: u8 = 0x01;
+const OP_INSERT: u8 = 0x02;
+/// 匹配块大小：在旧文件中按块建索引，在新文件中滚动查找
+const BLOCK: usize = 2048;
+
+/// 某个文件的补丁目录
+fn patch_dir(storage_dir: &Path, file: &str) -> PathBuf {
+    storage_dir.join(STATE_DIR).join("patches").join(file)
+}
+
+/// 从 `from`（旧 sha256）到当前版本的补丁
+pub fn patch_path(storage_dir: &Path, file: &str, from: &str) -> PathBuf {
+    patch_dir(storage_dir, file).join(format!("{}.patch", from))
+}
+
+/// 补丁对应的新版本 sha256（hex）；不是补丁时为 None
+pub fn target_sha256(patch: &[u8]) -> Option<String> {
+    (patch.len() >= HEADER_LEN && &patch[..8] == MAGIC).then(|| hex::encode(&patch[24..HEADER_LEN]))
+}
+
+/// 生成旧版本到新版本的补丁并替换该文件原有的补丁；
+/// 任一版本超过 `max_bytes` 或补丁不比新文件小时只清除旧补丁，返回写入的补丁大小
+pub fn generate(
+    storage_dir: &Path,
+    file: &str,
+    old: (&Path, &str),
+    new: (&Path, &str),
+    max_bytes: u64,
+) -> Result<Option<u64>> {
+    let dir = patch_dir(storage_dir, file);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    let (old_path, old_sha) = old;
+    let (new_path, new_sha) = new;
+    if std::fs::metadata(old_path)?.len() > max_bytes || std::fs::metadata(new_path)?.len() > max_bytes {
+        return Ok(None);
+    }
+
+    let old_data = std::fs::read(old_path)?;
+    let new_data = std::fs::read(new_path)?;
+    let target: [u8; 32] = hex::decode(new_sha)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("invalid sha256 {}", new_sha))?;
+    let patch = diff(&old_data, &new_data, &target);
+    if patch.len() >= new_data.len() {
+        return Ok(None);
+    }
+
+    std::fs::create_dir_all(&dir)?;
+    let path = patch_path(storage_dir, file, old_sha);
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, &patch)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(Some(patch.len() as u64))
+}
+
+/// rsync 式的块匹配：命中的块向后尽量延伸，其余作为插入数据
+fn diff(old: &[u8], new: &[u8], target: &[u8; 32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + new.len() / 8);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(old.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(new.len() as u64).to_le_bytes());
+    out.extend_from_slice(target);
+
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for offset in (0..old.len().saturating_sub(BLOCK - 1)).step_by(BLOCK) {
+        index
+            .entry(Rolling::new(&old[offset..offset + BLOCK]).digest())
+            .or_default()
+            .push(offset);
+    }
+
+    let mut literal = 0;
+    let mut i = 0;
+    let mut rolling = (new.len() >= BLOCK).then(|| Rolling::new(&new[..BLOCK]));
+    while let Some(roll) = rolling.as_mut() {
+        let window = &new[i..i + BLOCK];
+        let found = index
+            .get(&roll.digest())
+            .and_then(|offsets| offsets.iter().find(|&&o| &old[o..o + BLOCK] == window));
+        if let Some(&offset) = found {
+            let len = BLOCK
+                + old[offset + BLOCK..]
+                    .iter()
+                    .zip(&new[i + BLOCK..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+            insert(&mut out, &new[literal..i]);
+            out.push(OP_COPY);
+            out.extend_from_slice(&(offset as u64).to_le_bytes());
+            out.extend_from_slice(&(len as u64).to_le_bytes());
+            i += len;
+            literal = i;
+            rolling = (i + BLOCK <= new.len()).then(|| Rolling::new(&new[i..i + BLOCK]));
+        } else if i + BLOCK < new.len() {
+            roll.roll(new[i], new[i + BLOCK]);
+            i += 1;
+        } else {
+            rolling = None;
+        }
+    }
+    insert(&mut out, &new[literal..]);
+    out
+}
+
+fn insert(out: &mut Vec<u8>, data: &[u8]) {
+    if data.is_empty() {
+        return;
+    }
+    out.push(OP_INSERT);
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    out.extend_from_slice(data);
+}
+
+/// 弱滚动校验和（adler32 变体），命中后再逐字节比较
+struct Rolling {
+    a: u32,
+    b: u32,
+}
+
+impl Rolling {
+    fn new(block: &[u8]) -> Self {
+        let (mut a, mut b) = (0u32, 0u32);
+        for (i, &x) in block.iter().enumerate() {
+            a = a.wrapping_add(x as u32);
+            b = b.wrapping_add(((block.len() - i) as u32).wrapping_mul(x as u32));
+        }
+        Self { a, b }
+    }
+
+    fn roll(&mut self, out: u8, incoming: u8) {
+        self.a = self.a.wrapping_sub(out as u32).wrapping_add(incoming as u32);
+        self.b = self
+            .b
+            .wrapping_sub((BLOCK as u32).wrapping_mul(out as u32))
+            .wrapping_add(self.a);
+    }
+
+    fn digest(&self) -> u32 {
+        (self.a & 0xffff) | (self.b << 16)
+    }
+}
@@ -0,0 +1,222 @@
+//! 按优先级启动下载
+//!
+//! 队列已按优先级与路径排好。高优先级文件单独排队，与其余文件的队列同时等待并发：
+//! 高优先级文件可以使用全局并发，也可以使用为其保留的并发，因此在其他文件占满全局并发时，
+//! 分组刚空出的高优先级文件仍能立即开始；两边同时可以开始时高优先级文件优先。
+//! 限制了并发的分组各有一个信号量，分组已满的文件暂时跳过，由队列中后面的文件先开始，
+//! 分组空出后再按原来的顺序启动。
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::file::{FileGroup, FileSource, Priority};
+
+/// 一个下载占用的并发：全局（或保留）的一个，以及所在分组的一个；任务结束时释放
+pub(super) type Permits = (OwnedSemaphorePermit, Option<OwnedSemaphorePermit>);
+
+pub(super) struct Slots {
+    bulk: Arc<Semaphore>,
+    reserved: Arc<Semaphore>,
+    groups: HashMap<String, Arc<Semaphore>>,
+}
+
+impl Slots {
+    /// 为高优先级文件保留 `high_slots` 个并发，其他文件至少保留一个
+    pub(super) fn new(concurrency: usize, high_slots: usize, groups: &BTreeMap<String, FileGroup>) -> Self {
+        let concurrency = concurrency.max(1);
+        let reserved = high_slots.min(concurrency - 1);
+        Self {
+            bulk: Arc::new(Semaphore::new(concurrency - reserved)),
+            reserved: Arc::new(Semaphore::new(reserved)),
+            groups: groups
+                .iter()
+                .filter_map(|(name, g)| g.download_concurrency.map(|n| (name.clone(), Arc::new(Semaphore::new(n)))))
+                .collect(),
+        }
+    }
+
+    /// 条目所在分组的信号量；未限制并发或未定义的分组为 None
+    fn group(&self, source: &FileSource) -> Option<&Arc<Semaphore>> {
+        source.group().and_then(|g| self.groups.get(g))
+    }
+
+    /// 等到队列（不能为空）中有文件可以开始，返回其位置与取得的并发：先等其分组有空位，
+    /// 再取得全局并发（高优先级优先使用保留的）。可以随时放弃，放弃时已取得的并发随之释放
+    async fn next(&self, queue: &VecDeque<(String, FileSource)>, high: bool) -> (usize, Permits) {
+        let (index, group_permit) = self.ready(queue).await;
+        let permit = if high {
+            tokio::select! {
+                biased;
+                permit = self.reserved.clone().acquire_owned() => permit,
+                permit = self.bulk.clone().acquire_owned() => permit,
+            }
+        } else {
+            self.bulk.clone().acquire_owned().await
+        };
+        (index, (permit.expect("semaphore is never closed"), group_permit))
+    }
+
+    /// 按队列顺序第一个所在分组有空位的文件；都在已满的分组中时等任一分组空出
+    async fn ready(&self, queue: &VecDeque<(String, FileSource)>) -> (usize, Option<OwnedSemaphorePermit>) {
+        let mut waiting: Vec<&str> = Vec::new();
+        for (i, (_, source)) in queue.iter().enumerate() {
+            let Some(slots) = self.group(source) else {
+                return (i, None);
+            };
+            if let Ok(permit) = slots.clone().try_acquire_owned() {
+                return (i, Some(permit));
+            }
+            let group = source.group().unwrap_or_default();
+            if !waiting.contains(&group) {
+                waiting.push(group);
+            }
+        }
+        let acquires = waiting.iter().map(|g| Box::pin(self.groups[*g].clone().acquire_owned()));
+        let (permit, n, _) = futures::future::select_all(acquires).await;
+        let index = queue
+            .iter()
+            .position(|(_, s)| s.group() == Some(waiting[n]))
+            .expect("waiting groups come from the queue");
+        (index, permit.ok())
+    }
+}
+
+/// 依次为队列中的文件取得并发并调用 `start`，返回因取消而未启动的文件数
+pub(super) async fn dispatch(
+    queue: VecDeque<(String, FileSource)>,
+    slots: &Slots,
+    cancel: &CancellationToken,
+    mut start: impl FnMut(String, FileSource, Permits),
+) -> usize {
+    let (mut high, mut bulk): (VecDeque<_>, VecDeque<_>) =
+        queue.into_iter().partition(|(_, source)| source.priority() == Priority::High);
+    while !high.is_empty() || !bulk.is_empty() {
+        // 先轮询高优先级队列，同时等待同一个信号量时它排在前面
+        let (from_high, (index, permits)) = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return high.len() + bulk.len(),
+            next = slots.next(&high, true), if !high.is_empty() => (true, next),
+            next = slots.next(&bulk, false), if !bulk.is_empty() => (false, next),
+        };
+        let queue = if from_high { &mut high } else { &mut bulk };
+        if let Some((file, source)) = queue.remove(index) {
+            start(file, source, permits);
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::config::file::FilesConfig;
+
+    const FILES: &str = r#"
+[files]
+"h1" = { urls = ["http://example.com/h1"], priority = "high", group = "g" }
+"h2" = { urls = ["http://example.com/h2"], priority = "high", group = "g" }
+"n1" = "http://example.com/n1"
+"n2" = "http://example.com/n2"
+"n3" = { urls = ["http://example.com/n3"], priority = "low" }
+"g1" = { urls = ["http://example.com/g1"], group = "g" }
+
+[groups.g]
+download_concurrency = 1
+"#;
+
+    fn queue(names: &[&str]) -> (VecDeque<(String, FileSource)>, BTreeMap<String, FileGroup>) {
+        let files = FilesConfig::parse(FILES).unwrap();
+        let queue = names.iter().map(|n| (n.to_string(), files.files[*n].clone())).collect();
+        (queue, files.groups)
+    }
+
+    /// 在后台派发，启动的文件连同其并发从通道取出，测试决定何时释放
+    fn spawn_dispatch(
+        names: &[&str],
+        concurrency: usize,
+        high_slots: usize,
+        cancel: CancellationToken,
+    ) -> (mpsc::UnboundedReceiver<(String, Permits)>, tokio::task::JoinHandle<usize>) {
+        let (queue, groups) = queue(names);
+        let (tx, rx) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(async move {
+            let slots = Slots::new(concurrency, high_slots, &groups);
+            dispatch(queue, &slots, &cancel, |file, _, permits| tx.send((file, permits)).unwrap()).await
+        });
+        (rx, handle)
+    }
+
+    async fn started(rx: &mut mpsc::UnboundedReceiver<(String, Permits)>) -> (String, Permits) {
+        tokio::time::timeout(Duration::from_secs(1), rx.recv()).await.expect("a file should start").unwrap()
+    }
+
+    async fn nothing_started(rx: &mut mpsc::UnboundedReceiver<(String, Permits)>) {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(rx.try_recv().is_err(), "no file should start");
+    }
+
+    #[tokio::test]
+    async fn reserved_slot_admits_high_file_while_bulk_is_full() {
+        let (mut rx, handle) = spawn_dispatch(&["h1", "h2", "n1", "n2", "n3"], 2, 1, CancellationToken::new());
+
+        // h1 用保留的并发，n1 用全局并发；h2 等分组，n2 等全局并发
+        let (h1, h1_permits) = started(&mut rx).await;
+        let (n1, n1_permits) = started(&mut rx).await;
+        assert_eq!((h1.as_str(), n1.as_str()), ("h1", "n1"));
+        nothing_started(&mut rx).await;
+
+        // 其他文件仍占满全局并发时，分组空出的 h2 立即使用保留的并发
+        drop(h1_permits);
+        assert_eq!(started(&mut rx).await.0, "h2");
+        nothing_started(&mut rx).await;
+
+        drop(n1_permits);
+        let (n2, n2_permits) = started(&mut rx).await;
+        assert_eq!(n2, "n2");
+        drop(n2_permits);
+        assert_eq!(started(&mut rx).await.0, "n3");
+        assert_eq!(handle.await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn high_files_start_first_without_reservation() {
+        let (mut rx, handle) = spawn_dispatch(&["h1", "n1", "n2"], 1, 0, CancellationToken::new());
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            let (file, permits) = started(&mut rx).await;
+            order.push(file);
+            drop(permits);
+        }
+        assert_eq!(order, ["h1", "n1", "n2"]);
+        assert_eq!(handle.await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn full_group_does_not_block_later_files() {
+        let (mut rx, handle) = spawn_dispatch(&["g1", "n1", "h1"], 3, 0, CancellationToken::new());
+        // h1 与 g1 同组，h1 先开始；g1 等分组，n1 不受影响
+        let (h1, h1_permits) = started(&mut rx).await;
+        assert_eq!(h1, "h1");
+        assert_eq!(started(&mut rx).await.0, "n1");
+        nothing_started(&mut rx).await;
+        drop(h1_permits);
+        assert_eq!(started(&mut rx).await.0, "g1");
+        assert_eq!(handle.await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancel_reports_files_not_started() {
+        let cancel = CancellationToken::new();
+        let (mut rx, handle) = spawn_dispatch(&["n1", "n2", "n3"], 1, 0, cancel.clone());
+        let _n1 = started(&mut rx).await;
+        cancel.cancel();
+        assert_eq!(handle.await.unwrap(), 2);
+    }
+}
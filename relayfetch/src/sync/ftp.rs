@@ -0,0 +1,206 @@
+//! `ftp://` 上游（`ftp` feature）
+//!
+//! 只实现下载所需的命令：被动模式（EPSV，失败时 PASV）、二进制传输、SIZE / MDTM 取大小与
+//! 修改时间、REST 续传。PASV 返回的地址只取端口，主机沿用控制连接的对端，
+//! 避免 NAT 后的服务器返回内网地址。
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use axum::body::Bytes;
+use chrono::NaiveDateTime;
+use futures::StreamExt;
+use reqwest::Url;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+
+use super::remote::{self, Login, Opened, Stat};
+
+/// 控制连接每次应答、数据连接每个块的超时
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 数据连接每次读取的块大小
+const CHUNK: usize = 64 * 1024;
+
+pub async fn stat(url: &str, login: &Login) -> Result<Stat> {
+    let url = Url::parse(url)?;
+    let mut control = Control::connect(&url, login).await?;
+    let stat = control.stat(&remote::path_of(&url)).await?;
+    let _ = control.command("QUIT").await;
+    Ok(stat)
+}
+
+pub async fn open(url: &str, offset: u64, login: &Login) -> Result<Opened> {
+    let url = Url::parse(url)?;
+    let path = remote::path_of(&url);
+    let mut control = Control::connect(&url, login).await?;
+    let stat = control.stat(&path).await?;
+
+    let data = control.passive().await?;
+    // 不支持 REST 的服务器从头传输
+    let offset = match offset {
+        0 => 0,
+        offset => match control.command(&format!("REST {}", offset)).await? {
+            (350, _) => offset,
+            _ => 0,
+        },
+    };
+    control.expect(&format!("RETR {}", path), &[125, 150]).await?;
+
+    // 数据连接读完后，控制连接上的 226 才表示传输完整
+    let stream = futures::stream::unfold(Some((data, control)), |state| async move {
+        let (mut data, mut control) = state?;
+        let mut buf = vec![0u8; CHUNK];
+        match tokio::time::timeout(TIMEOUT, data.read(&mut buf)).await {
+            Ok(Ok(n)) if n > 0 => {
+                buf.truncate(n);
+                Some((Ok(Bytes::from(buf)), Some((data, control))))
+            }
+            Ok(Err(e)) => Some((Err(e), None)),
+            Err(_) => Some((Err(std::io::ErrorKind::TimedOut.into()), None)),
+            Ok(Ok(_)) => {
+                drop(data);
+                match control.reply().await {
+                    Ok((226 | 250, _)) => None,
+                    Ok((code, text)) => Some((Err(std::io::Error::other(format!("transfer failed: {} {}", code, text))), None)),
+                    Err(e) => Some((Err(std::io::Error::other(e)), None)),
+                }
+            }
+        }
+    });
+
+    Ok(Opened { stat, offset, stream: stream.boxed() })
+}
+
+struct Control {
+    stream: BufStream<TcpStream>,
+    peer: IpAddr,
+}
+
+impl Control {
+    async fn connect(url: &Url, login: &Login) -> Result<Self> {
+        let host = url.host_str().ok_or_else(|| anyhow!("missing host in {}", url))?;
+        let port = url.port().unwrap_or(21);
+        let tcp = tokio::time::timeout(TIMEOUT, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| anyhow!("connection to {}:{} timed out", host, port))?
+            .with_context(|| format!("failed to connect to {}:{}", host, port))?;
+        let peer = tcp.peer_addr()?.ip();
+
+        let mut control = Self { stream: BufStream::new(tcp), peer };
+        match control.reply().await? {
+            (220, _) => {}
+            (code, text) => bail!("unexpected greeting: {} {}", code, text),
+        }
+
+        let user = login.username.as_deref().unwrap_or("anonymous");
+        match control.command(&format!("USER {}", user)).await? {
+            (230, _) => {}
+            (331 | 332, _) => {
+                let pass = login.password.as_deref().unwrap_or("anonymous@");
+                control.expect(&format!("PASS {}", pass), &[230, 202]).await?;
+            }
+            (code, text) => bail!("login failed: {} {}", code, text),
+        }
+        control.expect("TYPE I", &[200]).await?;
+        Ok(control)
+    }
+
+    async fn stat(&mut self, path: &str) -> Result<Stat> {
+        let size = match self.command(&format!("SIZE {}", path)).await? {
+            (213, text) => text.trim().parse().ok(),
+            (550, text) => bail!("{}: {}", path, text),
+            _ => None,
+        };
+        let last_modified = match self.command(&format!("MDTM {}", path)).await? {
+            (213, text) => text
+                .get(..14)
+                .and_then(|ts| NaiveDateTime::parse_from_str(ts, "%Y%m%d%H%M%S").ok())
+                .map(|t| remote::http_date(t.and_utc())),
+            _ => None,
+        };
+        Ok(Stat { size, last_modified })
+    }
+
+    /// 进入被动模式并建立数据连接
+    async fn passive(&mut self) -> Result<TcpStream> {
+        let port = match self.command("EPSV").await? {
+            (229, text) => text
+                .split('|')
+                .nth(3)
+                .and_then(|p| p.parse::<u16>().ok())
+                .ok_or_else(|| anyhow!("malformed EPSV reply: {}", text))?,
+            _ => match self.command("PASV").await? {
+                (227, text) => pasv_port(&text).ok_or_else(|| anyhow!("malformed PASV reply: {}", text))?,
+                (code, text) => bail!("passive mode refused: {} {}", code, text),
+            },
+        };
+        tokio::time::timeout(TIMEOUT, TcpStream::connect((self.peer, port)))
+            .await
+            .map_err(|_| anyhow!("data connection timed out"))?
+            .context("failed to open data connection")
+    }
+
+    async fn command(&mut self, cmd: &str) -> Result<(u16, String)> {
+        self.stream.write_all(cmd.as_bytes()).await?;
+        self.stream.write_all(b"\r\n").await?;
+        self.stream.flush().await?;
+        self.reply().await
+    }
+
+    async fn expect(&mut self, cmd: &str, codes: &[u16]) -> Result<(u16, String)> {
+        let (code, text) = self.command(cmd).await?;
+        if !codes.contains(&code) {
+            let verb = cmd.split(' ').next().unwrap_or(cmd);
+            bail!("{} failed: {} {}", verb, code, text);
+        }
+        Ok((code, text))
+    }
+
+    /// 读取一条应答；多行应答（`123-` 开头）读到 `123 ` 开头的行为止
+    async fn reply(&mut self) -> Result<(u16, String)> {
+        let first = self.line().await?;
+        let code: u16 = first
+            .get(..3)
+            .and_then(|c| c.parse().ok())
+            .ok_or_else(|| anyhow!("malformed reply: {}", first))?;
+        let mut text = first.get(4..).unwrap_or_default().to_string();
+        if first.as_bytes().get(3) == Some(&b'-') {
+            let end = format!("{} ", code);
+            loop {
+                let line = self.line().await?;
+                if let Some(last) = line.strip_prefix(&end) {
+                    text = last.to_string();
+                    break;
+                }
+            }
+        }
+        Ok((code, text))
+    }
+
+    async fn line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        let n = tokio::time::timeout(TIMEOUT, self.stream.read_line(&mut line))
+            .await
+            .map_err(|_| anyhow!("server did not reply in time"))??;
+        if n == 0 {
+            bail!("connection closed by server");
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+/// `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)` 中的端口
+fn pasv_port(text: &str) -> Option<u16> {
+    let start = text.find('(')?;
+    let end = text[start..].find(')')? + start;
+    let nums: Vec<u16> = text[start + 1..end]
+        .split(',')
+        .map(|n| n.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match nums.as_slice() {
+        [_, _, _, _, p1, p2] => Some(p1 * 256 + p2),
+        _ => None,
+    }
+}
@@ -0,0 +1,201 @@
+//! 远端 files 清单：拉取、校验（签名或 sha256 校验和）后与本地 files.toml 合并
+//!
+//! 校验通过的清单保存在 `storage_dir/.relayfetch/manifest.toml`，
+//! 中心服务器不可用或校验失败时沿用上次的副本。
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use log::{info, warn};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{Id, PKey};
+use openssl::sign::Verifier;
+use sha2::{Digest, Sha256};
+
+use crate::bandwidth::STATE_DIR;
+use crate::config::{config::FilesManifest, file::{FilesConfig, check_path}};
+
+/// 上次校验通过的清单
+pub fn cache_path(storage_dir: &Path) -> PathBuf {
+    storage_dir.join(STATE_DIR).join("manifest.toml")
+}
+
+/// 拉取并校验清单；失败时回退到缓存，缓存也不可用时返回 None
+pub async fn load(client: &reqwest::Client, manifest: &FilesManifest, storage_dir: &Path) -> Option<FilesConfig> {
+    let cache = cache_path(storage_dir);
+
+    match fetch(client, manifest).await {
+        Ok(body) => match parse(&body) {
+            Ok(files) => {
+                if let Err(e) = save(&cache, &body) {
+                    warn!("[manifest] failed to cache manifest: {:?}", e);
+                }
+                info!(
+                    "[manifest] applied {} ({} files, {} dirs)",
+                    manifest.url,
+                    files.files.len(),
+                    files.dirs.len()
+                );
+                return Some(files);
+            }
+            Err(e) => warn!("[manifest] {} is invalid: {:#}", manifest.url, e),
+        },
+        Err(e) => warn!("[manifest] failed to fetch {}: {:#}", manifest.url, e),
+    }
+
+    let body = std::fs::read(&cache).ok()?;
+    match parse(&body) {
+        Ok(files) => {
+            warn!("[manifest] using last verified copy from {}", cache.display());
+            Some(files)
+        }
+        Err(e) => {
+            warn!("[manifest] cached copy is invalid: {:#}", e);
+            None
+        }
+    }
+}
+
+/// 解析清单；远端条目不能携带请求头与认证（否则可借 token_env 等读取本机环境变量），
+/// 也不能携带 on_update 钩子与后处理步骤（否则可在本机执行命令）；
+/// 路径不是存储目录内相对路径的条目丢弃（否则可写到存储目录之外），选项不能同时使用的条目也丢弃
+pub fn parse(body: &[u8]) -> Result<FilesConfig> {
+    let mut files = FilesConfig::parse(std::str::from_utf8(body)?)?;
+    files.files.retain(|name, _| {
+        let checked = check_path(name).and_then(|()| match name.ends_with(".meta") {
+            true => Err("the .meta extension is reserved for metadata files"),
+            false => Ok(()),
+        });
+        checked.map_err(|e| warn!("[manifest] ignoring file {:?}: {}", name, e)).is_ok()
+    });
+    files.dirs.retain(|prefix, _| {
+        check_path(prefix.trim_matches('/'))
+            .map_err(|e| warn!("[manifest] ignoring dir {:?}: {}", prefix, e))
+            .is_ok()
+    });
+    for (name, source) in files.files.iter_mut() {
+        if source.strip_auth() {
+            warn!("[manifest] ignoring headers / auth settings of {}", name);
+        }
+        if source.strip_proxy() {
+            warn!("[manifest] ignoring proxy settings of {}", name);
+        }
+        if source.strip_hooks() {
+            warn!("[manifest] ignoring on_update hooks / post_process steps of {}", name);
+        }
+    }
+    files.files.retain(|name, source| {
+        source
+            .check_options()
+            .map_err(|e| warn!("[manifest] ignoring file {:?}: {}", name, e))
+            .is_ok()
+    });
+    Ok(files)
+}
+
+fn save(path: &Path, body: &[u8]) -> Result<()> {
+    crate::sync::meta::ensure_parent_dir(path)?;
+    let tmp = path.with_extension("toml.tmp");
+    std::fs::write(&tmp, body)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 拉取清单并完成所有已配置的校验
+async fn fetch(client: &reqwest::Client, manifest: &FilesManifest) -> Result<Vec<u8>> {
+    if manifest.public_key.is_none() && manifest.checksum_url.is_none() {
+        bail!("files_manifest requires public_key or checksum_url");
+    }
+
+    let body = get(client, &manifest.url).await?;
+
+    if let Some(url) = &manifest.checksum_url {
+        let text = String::from_utf8(get(client, url).await?)?;
+        let expected = text
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| anyhow!("empty checksum file"))?;
+        let actual = hex::encode(Sha256::digest(&body));
+        if !expected.eq_ignore_ascii_case(&actual) {
+            bail!("checksum mismatch: expected {}, got {}", expected, actual);
+        }
+    }
+
+    if let Some(key_path) = &manifest.public_key {
+        let sig_url = manifest
+            .signature_url
+            .clone()
+            .unwrap_or_else(|| format!("{}.sig", manifest.url));
+        let signature = decode_signature(get(client, &sig_url).await?);
+        verify_signature(key_path, &body, &signature)?;
+    }
+
+    Ok(body)
+}
+
+pub async fn get(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    Ok(client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("request to {} failed", url))?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec())
+}
+
+/// 签名文件可以是原始字节，也可以是 base64 文本
+pub fn decode_signature(raw: Vec<u8>) -> Vec<u8> {
+    std::str::from_utf8(&raw)
+        .ok()
+        .and_then(|s| STANDARD.decode(s.trim()).ok())
+        .unwrap_or(raw)
+}
+
+pub fn verify_signature(key_path: &Path, body: &[u8], signature: &[u8]) -> Result<()> {
+    let pem = std::fs::read(key_path)
+        .with_context(|| format!("failed to read {}", key_path.display()))?;
+    let key = PKey::public_key_from_pem(&pem).context("invalid public key")?;
+
+    let valid = if key.id() == Id::ED25519 {
+        Verifier::new_without_digest(&key)?.verify_oneshot(signature, body)?
+    } else {
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &key)?;
+        verifier.update(body)?;
+        verifier.verify(signature)?
+    };
+
+    if !valid {
+        bail!("signature verification failed");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_drops_entries_outside_storage_dir() {
+        let body = br#"
+[files]
+"../../etc/cron.d/x" = "https://example.com/x"
+"/etc/passwd" = "https://example.com/passwd"
+"a/./b" = "https://example.com/b"
+"x.meta" = "https://example.com/meta"
+"rules/geoip.dat" = "https://example.com/geoip.dat"
+
+[dirs."../mirror"]
+url = "https://example.com/mirror/"
+
+[dirs."mirror/ok/"]
+url = "https://example.com/ok/"
+"#;
+        let files = parse(body).unwrap();
+        assert_eq!(files.files.keys().collect::<Vec<_>>(), ["rules/geoip.dat"]);
+        assert_eq!(files.dirs.keys().collect::<Vec<_>>(), ["mirror/ok/"]);
+    }
+}
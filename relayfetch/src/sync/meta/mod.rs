@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
+use std::io::Read;
 use std::path::Path;
 
 #[derive(Debug, Default, Deserialize, Serialize, Clone)]
@@ -8,11 +10,53 @@ pub struct Meta {
     pub last_modified: Option<String>,
     pub fetched_at: Option<String>, // 本地同步时间
     pub total_size: Option<u64>,
+    pub sha256: Option<String>,     // 本地文件内容的 sha256（hex）
+    pub source: Option<String>,     // 成功下载所用的上游 URL（多镜像时）
+    pub content_range: Option<String>, // 只镜像了一段时上游的 Content-Range（如 `bytes 0-1023/4096`）
+    pub chunks: Option<ChunkState>,    // 未完成的分段下载（parallel_chunks），下载完成后清除
+    pub resume: Option<ResumeCheckpoint>, // 未完成下载的续传检查点（verify_resume），下载完成后清除
+    pub partial: Option<PartialSource>,   // 未完成下载的 tmp 文件来自的上游响应，下载完成后清除
+    pub signed_by: Option<String>,     // 通过签名校验（signature）时签名密钥的指纹
+}
+
+/// 续传检查点：tmp 文件前 size 字节的摘要，以及返回这些数据的上游与 ETag
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ResumeCheckpoint {
+    pub source: String,
+    pub size: u64,
+    pub sha256: String,
+    pub etag: Option<String>,
+}
+
+/// tmp 文件创建时上游响应的校验值，续传时作为 If-Range 发送
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct PartialSource {
+    pub source: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// 分段下载的进度：tmp 文件已按 total 预分配，done 中的段已写入
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ChunkState {
+    pub source: String,
+    pub total: u64,
+    pub chunk_size: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub done: Vec<usize>,
+}
+
+impl Meta {
+    /// 解析 meta 内容；损坏的 meta 返回错误而不是 panic
+    pub fn parse(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
 }
 
 pub fn load_meta(path: &Path) -> anyhow::Result<Meta> {
     if path.exists() {
-        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+        Ok(Meta::parse(&fs::read_to_string(path)?)?)
     } else {
         Ok(Meta::default())
     }
@@ -29,3 +73,24 @@ pub fn ensure_parent_dir(path: &Path) -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+/// 将文件内容喂给 hasher（用于续传时补齐已下载部分的摘要）
+pub fn hash_into(path: &Path, hasher: &mut Sha256) -> anyhow::Result<()> {
+    let mut f = fs::File::open(path)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(())
+}
+
+/// 计算整个文件的 sha256（hex）
+pub fn file_sha256(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    hash_into(path, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,312 @@
+//! 未完成的下载
+//!
+//! tmp 文件统一放在 `storage_dir/.partial/`，文件名取自（本地路径, URL）的哈希：
+//! 换了上游的条目不会续传旧 URL 留下的数据。同一文件同时只允许一个下载写入，
+//! 写入方可以公布 tmp 文件（[`Claim::publish`]），同时请求该文件的客户端边写边读（[`follow`]），
+//! 不再各自回源。
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use axum::body::Bytes;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+use tokio::sync::{Notify, watch};
+
+use super::meta::{ResumeCheckpoint, hash_into};
+
+/// 存储目录下存放 tmp 文件的子目录
+pub const PARTIAL_DIR: &str = ".partial";
+
+/// 正在下载的文件（存储目录下的完整路径）
+static ACTIVE: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(Default::default);
+
+/// 已公布的写入（存储目录下的完整路径 -> 进度）
+static INFLIGHT: LazyLock<Mutex<HashMap<PathBuf, Arc<Inflight>>>> = LazyLock::new(Default::default);
+
+/// 有写入公布或下载结束时唤醒等待者（见 [`settled`]）
+static CHANGED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// 跟随读取时每次读出的块大小
+const FOLLOW_CHUNK: usize = 64 * 1024;
+
+/// 某个条目从某个上游下载时使用的 tmp 文件
+pub fn tmp_path(storage_dir: &Path, file: &str, url: &str) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(file.as_bytes());
+    hasher.update([0]);
+    hasher.update(url.as_bytes());
+    let hash = hex::encode(hasher.finalize());
+    storage_dir.join(PARTIAL_DIR).join(format!("{}.tmp", &hash[..32]))
+}
+
+/// 下载期间持有，drop 时释放
+pub struct Claim(PathBuf);
+
+impl Claim {
+    /// 同一文件已有下载在进行时返回 None
+    pub fn acquire(file_path: &Path) -> Option<Self> {
+        ACTIVE
+            .lock()
+            .unwrap()
+            .insert(file_path.to_path_buf())
+            .then(|| Self(file_path.to_path_buf()))
+    }
+}
+
+impl Claim {
+    /// 公布正在写入的 tmp 文件；`written` 为其中已有的字节数（续传时）
+    pub fn publish(&self, tmp: &Path, total: Option<u64>, written: u64) -> Publisher {
+        self.register(tmp, total, written, true)
+    }
+
+    /// 登记正在写入的 tmp 文件但不允许跟随读取：写完后还要经过后处理，内容会变
+    pub fn conceal(&self, tmp: &Path) -> Publisher {
+        self.register(tmp, None, 0, false)
+    }
+
+    fn register(&self, tmp: &Path, total: Option<u64>, written: u64, followable: bool) -> Publisher {
+        let (tx, _) = watch::channel(Written::Partial(written));
+        let inflight = Arc::new(Inflight {
+            tmp: tmp.to_path_buf(),
+            total,
+            tx,
+            followers: AtomicUsize::new(0),
+            followable,
+        });
+        INFLIGHT.lock().unwrap().insert(self.0.clone(), inflight.clone());
+        CHANGED.notify_waiters();
+        Publisher {
+            file: self.0.clone(),
+            inflight,
+            finished: false,
+        }
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        ACTIVE.lock().unwrap().remove(&self.0);
+        CHANGED.notify_waiters();
+    }
+}
+
+/// 等到某个文件的下载公布了可以跟随的 tmp 文件，或已经结束
+pub async fn settled(file_path: &Path) {
+    loop {
+        let changed = CHANGED.notified();
+        tokio::pin!(changed);
+        changed.as_mut().enable();
+        let followable = INFLIGHT.lock().unwrap().get(file_path).is_some_and(|i| i.followable);
+        if followable || !ACTIVE.lock().unwrap().contains(file_path) {
+            return;
+        }
+        changed.await;
+    }
+}
+
+/// tmp 文件中可读的范围
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Written {
+    Partial(u64),
+    Done(u64),
+    Failed,
+}
+
+struct Inflight {
+    tmp: PathBuf,
+    total: Option<u64>,
+    tx: watch::Sender<Written>,
+    followers: AtomicUsize,
+    followable: bool,
+}
+
+/// 写入方持有；未调用 [`Publisher::finish`] 就 drop 时跟随者收到错误
+pub struct Publisher {
+    file: PathBuf,
+    inflight: Arc<Inflight>,
+    finished: bool,
+}
+
+impl Publisher {
+    /// 已写入（并 flush 到文件）的字节数
+    pub fn advance(&self, written: u64) {
+        self.inflight.tx.send_replace(Written::Partial(written));
+    }
+
+    /// 写入完成；应在 tmp 改名为正式文件之后调用
+    pub fn finish(mut self, size: u64) {
+        self.finished = true;
+        self.inflight.tx.send_replace(Written::Done(size));
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        let mut map = INFLIGHT.lock().unwrap();
+        if map.get(&self.file).is_some_and(|i| Arc::ptr_eq(i, &self.inflight)) {
+            map.remove(&self.file);
+        }
+        drop(map);
+        if !self.finished {
+            self.inflight.tx.send_replace(Written::Failed);
+        }
+    }
+}
+
+pub enum Follow {
+    /// 没有进行中的写入
+    Idle,
+    /// 跟随者已达上限
+    Overflow,
+    Following(Follower),
+}
+
+/// 加入某个文件进行中的写入；tmp 已改名（刚好完成）或不允许跟随时返回 Idle，此时提供已有的正式文件
+pub async fn follow(file_path: &Path, max_followers: usize) -> Follow {
+    let Some(inflight) = INFLIGHT.lock().unwrap().get(file_path).filter(|i| i.followable).cloned() else {
+        return Follow::Idle;
+    };
+    if inflight.followers.fetch_add(1, Ordering::SeqCst) >= max_followers {
+        inflight.followers.fetch_sub(1, Ordering::SeqCst);
+        return Follow::Overflow;
+    }
+    // 先订阅再打开：改名后已打开的句柄仍指向同一文件
+    let rx = inflight.tx.subscribe();
+    let follower = match tokio::fs::File::open(&inflight.tmp).await {
+        Ok(file) => Follower {
+            file,
+            pos: 0,
+            rx,
+            inflight: inflight.clone(),
+        },
+        Err(_) => {
+            inflight.followers.fetch_sub(1, Ordering::SeqCst);
+            return Follow::Idle;
+        }
+    };
+    Follow::Following(follower)
+}
+
+/// 从写入中的 tmp 文件按写入进度读取
+pub struct Follower {
+    file: tokio::fs::File,
+    pos: u64,
+    rx: watch::Receiver<Written>,
+    inflight: Arc<Inflight>,
+}
+
+impl Follower {
+    /// 完整文件的大小（写入方已知时）
+    pub fn total(&self) -> Option<u64> {
+        self.inflight.total
+    }
+
+    /// 读取下一块；写入完成后返回 None，写入失败时返回错误
+    pub async fn next_chunk(&mut self) -> std::io::Result<Option<Bytes>> {
+        loop {
+            let state = *self.rx.borrow_and_update();
+            let limit = match state {
+                Written::Partial(n) | Written::Done(n) => n,
+                Written::Failed => return Err(std::io::Error::other("upstream download failed")),
+            };
+            if self.pos < limit {
+                let mut buf = vec![0u8; FOLLOW_CHUNK.min((limit - self.pos) as usize)];
+                let n = self.file.read(&mut buf).await?;
+                if n == 0 {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+                buf.truncate(n);
+                self.pos += n as u64;
+                return Ok(Some(Bytes::from(buf)));
+            }
+            if let Written::Done(_) = state {
+                return Ok(None);
+            }
+            if self.rx.changed().await.is_err() {
+                return Err(std::io::Error::other("upstream download aborted"));
+            }
+        }
+    }
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        self.inflight.followers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 正在写入的 tmp 文件
+pub fn writing() -> HashSet<PathBuf> {
+    INFLIGHT.lock().unwrap().values().map(|i| i.tmp.clone()).collect()
+}
+
+/// 删除不属于当前任何（条目, 上游）的 tmp 文件，以及旧版本留在数据文件旁的 `.tmp`；
+/// 返回已删除的路径
+pub fn clean_stale(storage_dir: &Path, entries: &[(String, Vec<String>)]) -> Vec<PathBuf> {
+    let mut removed = Vec::new();
+    let mut keep = HashSet::new();
+    for (file, urls) in entries {
+        for url in urls {
+            keep.insert(tmp_path(storage_dir, file, url));
+        }
+        let legacy = storage_dir.join(file).with_extension("tmp");
+        if legacy.is_file() && std::fs::remove_file(&legacy).is_ok() {
+            info!("[partial] removed legacy tmp file {}", legacy.display());
+            removed.push(legacy);
+        }
+    }
+
+    let Ok(dir) = std::fs::read_dir(storage_dir.join(PARTIAL_DIR)) else {
+        return removed;
+    };
+    // 按需回源等不在 files.toml 中的写入；后处理的中间文件与 tmp 文件同名、扩展名不同
+    let writing = writing();
+    for entry in dir.flatten() {
+        let path = entry.path();
+        if keep.contains(&path) || writing.contains(&path.with_extension("tmp")) {
+            continue;
+        }
+        let removing = if path.is_dir() { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+        match removing {
+            Ok(()) => {
+                info!("[partial] removed stale tmp file {}", path.display());
+                removed.push(path);
+            }
+            Err(e) => warn!("[partial] failed to remove {}: {}", path.display(), e),
+        }
+    }
+    removed
+}
+
+/// 续传前校验 tmp 文件（`verify_resume`）：截断到检查点记录的长度，核对这部分的摘要；
+/// 返回可续传的字节数。没有同一上游的检查点、上游只给出弱 ETag 或摘要不一致时删除 tmp 文件，返回 0
+pub fn verify_resume(tmp: &Path, url: &str, checkpoint: Option<&ResumeCheckpoint>) -> std::io::Result<u64> {
+    let reason = match checkpoint {
+        None => "no checkpoint",
+        Some(c) if c.source != url => "checkpoint is for another upstream",
+        Some(c) if c.etag.as_deref().is_some_and(|e| e.starts_with("W/")) => "upstream only sent a weak ETag",
+        Some(c) => {
+            let file = std::fs::OpenOptions::new().write(true).open(tmp)?;
+            if file.metadata()?.len() >= c.size {
+                file.set_len(c.size)?;
+                let mut hasher = Sha256::new();
+                hash_into(tmp, &mut hasher).map_err(std::io::Error::other)?;
+                if hex::encode(hasher.finalize()) == c.sha256 {
+                    return Ok(c.size);
+                }
+                "sha256 of the downloaded part does not match"
+            } else {
+                "tmp file is shorter than the checkpoint"
+            }
+        }
+    };
+    warn!("[partial] not resuming {}: {}, restarting", tmp.display(), reason);
+    match std::fs::remove_file(tmp) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(0),
+    }
+}
@@ -0,0 +1,181 @@
+//! 上游 relayfetch 节点（`[upstream_peer]`）
+//!
+//! 拉取 hub 下载端口的 `/__manifest`，把其中已同步完成的文件展开为条目：地址指向 hub 的下载服务，
+//! 并带上 hub 记录的 sha256，本地副本一致时不再请求，下载结果不一致时视为失败。
+//! 最近一次成功拉取的清单保存在 `storage_dir/.relayfetch/peer_manifest.json`，
+//! hub 不可用时沿用，单文件同步与清理也据此识别这些条目。清单的 ETag 另存一份，
+//! 下次拉取时带上 If-None-Match，hub 上没有变化时直接使用缓存。
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use reqwest::{StatusCode, header};
+use serde::Deserialize;
+
+use crate::bandwidth::STATE_DIR;
+use crate::config::config::UpstreamPeer;
+use crate::config::file::{self, FileEntry, FileSource};
+
+/// `/__manifest` 中用到的字段
+#[derive(Deserialize)]
+struct Manifest {
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Deserialize)]
+struct ManifestFile {
+    path: String,
+    /// 相对 hub 下载地址的路径（已编码）
+    url: String,
+    sha256: Option<String>,
+}
+
+pub fn cache_path(storage_dir: &Path) -> PathBuf {
+    storage_dir.join(STATE_DIR).join("peer_manifest.json")
+}
+
+fn etag_path(storage_dir: &Path) -> PathBuf {
+    storage_dir.join(STATE_DIR).join("peer_manifest.etag")
+}
+
+/// 拉取 hub 清单并展开为条目；失败时回退到缓存，缓存也不可用时返回 None
+pub async fn load(client: &reqwest::Client, peer: &UpstreamPeer, storage_dir: &Path) -> Option<BTreeMap<String, FileSource>> {
+    let cache = cache_path(storage_dir);
+    match fetch(client, peer, storage_dir).await {
+        Ok(Some((body, etag))) => {
+            let entries = entries(peer, &body);
+            // 旧 ETag 不能留给新内容
+            let etag_path = etag_path(storage_dir);
+            let _ = std::fs::remove_file(&etag_path);
+            match save(&cache, &body) {
+                Ok(()) => {
+                    if let Some(etag) = etag
+                        && let Err(e) = save(&etag_path, etag.as_bytes())
+                    {
+                        warn!("[peer] failed to cache manifest etag: {:?}", e);
+                    }
+                }
+                Err(e) => warn!("[peer] failed to cache manifest: {:?}", e),
+            }
+            info!("[peer] {} lists {} files", peer.url, entries.len());
+            return Some(entries);
+        }
+        Ok(None) => {
+            let entries = cached(peer, storage_dir)?;
+            info!("[peer] {} unchanged, {} files", peer.url, entries.len());
+            return Some(entries);
+        }
+        Err(e) => warn!("[peer] failed to fetch manifest from {}: {:#}", peer.url, e),
+    }
+    let entries = cached(peer, storage_dir)?;
+    warn!("[peer] using last manifest from {}", cache.display());
+    Some(entries)
+}
+
+/// 上次拉取的清单展开的条目
+pub fn cached(peer: &UpstreamPeer, storage_dir: &Path) -> Option<BTreeMap<String, FileSource>> {
+    let body = std::fs::read(cache_path(storage_dir)).ok()?;
+    Some(entries(peer, &body))
+}
+
+/// 清单内容与 ETag；与缓存一致（304）时返回 None
+async fn fetch(
+    client: &reqwest::Client,
+    peer: &UpstreamPeer,
+    storage_dir: &Path,
+) -> Result<Option<(Vec<u8>, Option<String>)>> {
+    let url = format!("{}/__manifest", peer.url.trim_end_matches('/'));
+    let mut req = client.get(&url);
+    if let Some(var) = &peer.token_env {
+        req = req.bearer_auth(file::env(var).map_err(anyhow::Error::msg)?);
+    }
+    if cache_path(storage_dir).exists()
+        && let Ok(etag) = std::fs::read_to_string(etag_path(storage_dir))
+    {
+        req = req.header(header::IF_NONE_MATCH, etag.trim());
+    }
+    let resp = req
+        .send()
+        .await
+        .with_context(|| format!("request to {} failed", url))?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let resp = resp.error_for_status()?;
+    let etag = resp
+        .headers()
+        .get(header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = resp.bytes().await?;
+    // 先确认能解析，避免缓存无效的清单
+    serde_json::from_slice::<Manifest>(&body).context("invalid manifest")?;
+    Ok(Some((body.to_vec(), etag)))
+}
+
+fn entries(peer: &UpstreamPeer, body: &[u8]) -> BTreeMap<String, FileSource> {
+    let manifest: Manifest = match serde_json::from_slice(body) {
+        Ok(m) => m,
+        Err(e) => {
+            warn!("[peer] invalid manifest: {}", e);
+            return BTreeMap::new();
+        }
+    };
+    let base = peer.url.trim_end_matches('/');
+    manifest
+        .files
+        .into_iter()
+        .filter(|f| {
+            // 路径来自远端，只接受存储目录内的相对路径
+            let safe = !f.path.is_empty() && Path::new(&f.path).components().all(|c| matches!(c, Component::Normal(_)));
+            if !safe {
+                warn!("[peer] ignoring unsafe path {:?}", f.path);
+            }
+            safe && wanted(peer, &f.path)
+        })
+        .map(|f| {
+            let source = FileSource::Entry(Box::new(FileEntry {
+                urls: vec![format!("{}{}", base, f.url)],
+                content_type: None,
+                attachment: false,
+                decompress: None,
+                range: None,
+                zsync: None,
+                parallel_chunks: None,
+                check_method: None,
+                priority: None,
+                group: None,
+                sha256: f.sha256,
+                signature: None,
+                keep_versions: None,
+                max_age_secs: None,
+                critical: false,
+                on_update: Vec::new(),
+                post_process: Vec::new(),
+                proxy: None,
+                no_proxy: false,
+                http: Default::default(),
+                auth: Default::default(),
+            }));
+            (f.path, source)
+        })
+        .collect()
+}
+
+fn wanted(peer: &UpstreamPeer, path: &str) -> bool {
+    peer.prefixes.is_empty()
+        || peer.prefixes.iter().any(|p| {
+            let p = p.trim_matches('/');
+            p.is_empty() || path == p || path.strip_prefix(p).is_some_and(|rest| rest.starts_with('/'))
+        })
+}
+
+fn save(path: &Path, body: &[u8]) -> Result<()> {
+    crate::sync::meta::ensure_parent_dir(path)?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, body)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
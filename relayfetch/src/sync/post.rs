@@ -0,0 +1,266 @@
+//! 下载后处理（files.toml 中的 `post_process`）
+//!
+//! 摘要校验通过之后、tmp 文件改名为正式文件之前依次执行各步骤：解包、重新压缩、运行校验命令。
+//! 步骤直接修改 tmp 文件，或把输出放在 `.partial/` 下的暂存目录（[`Context::staged`]），
+//! 全部成功后暂存目录先于文件发布；任一步失败时本次下载作废，正式文件与已解包的目录保持不变。
+//! 新的步骤实现 [`PostProcessor`] 并在 [`processor`] 中登记。
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use async_compression::tokio::bufread::{GzipDecoder, XzDecoder, ZstdDecoder};
+use async_compression::tokio::write::{GzipEncoder, XzEncoder, ZstdEncoder};
+use futures::future::BoxFuture;
+use log::{info, warn};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::config::file::{ArchiveFormat, Compression, PostStep};
+
+/// 一次下载的后处理状态
+pub(super) struct Context<'a> {
+    /// 条目的本地路径
+    pub file: &'a str,
+    pub storage_dir: &'a Path,
+    /// 待发布的文件，步骤可以原地替换
+    pub tmp_path: &'a Path,
+    /// （暂存目录, 发布位置），由 [`publish`] 整体替换
+    pub staged: Vec<(PathBuf, PathBuf)>,
+}
+
+/// 一个后处理步骤
+pub(super) trait PostProcessor: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn process<'a>(&'a self, ctx: &'a mut Context<'_>) -> BoxFuture<'a, Result<()>>;
+}
+
+pub(super) fn processor(step: &PostStep) -> Box<dyn PostProcessor> {
+    match step {
+        PostStep::Unpack { into, format } => Box::new(Unpack { into: into.trim_matches('/').to_string(), format: *format }),
+        PostStep::Compress { format } => Box::new(Compress { format: *format }),
+        PostStep::Validate { command, timeout_secs } => Box::new(Validate {
+            command: command.clone(),
+            timeout: Duration::from_secs(*timeout_secs),
+        }),
+    }
+}
+
+/// 依次执行；失败时清理已暂存的输出
+pub(super) async fn run(steps: &[PostStep], ctx: &mut Context<'_>) -> Result<()> {
+    for step in steps {
+        let processor = processor(step);
+        if let Err(e) = processor.process(ctx).await {
+            discard(&ctx.staged).await;
+            anyhow::bail!("post_process step {} failed: {:#}", processor.name(), e);
+        }
+    }
+    Ok(())
+}
+
+/// 用暂存目录替换发布位置：旧目录先改名移开，新目录就位后再删除
+pub(super) async fn publish(staged: &[(PathBuf, PathBuf)]) -> Result<()> {
+    for (staging, target) in staged {
+        super::meta::ensure_parent_dir(target)?;
+        let old = staging.with_extension("old");
+        let _ = tokio::fs::remove_dir_all(&old).await;
+        let replaced = match tokio::fs::rename(target, &old).await {
+            Ok(()) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e).with_context(|| format!("failed to move {} aside", target.display())),
+        };
+        if let Err(e) = tokio::fs::rename(staging, target).await {
+            if replaced {
+                let _ = tokio::fs::rename(&old, target).await;
+            }
+            return Err(e).with_context(|| format!("failed to publish {}", target.display()));
+        }
+        if replaced && let Err(e) = tokio::fs::remove_dir_all(&old).await {
+            warn!("failed to remove replaced directory {}: {}", old.display(), e);
+        }
+    }
+    Ok(())
+}
+
+/// 删除暂存目录
+pub(super) async fn discard(staged: &[(PathBuf, PathBuf)]) {
+    for (staging, _) in staged {
+        let _ = tokio::fs::remove_dir_all(staging).await;
+    }
+}
+
+struct Unpack {
+    into: String,
+    format: Option<ArchiveFormat>,
+}
+
+impl PostProcessor for Unpack {
+    fn name(&self) -> &'static str {
+        "unpack"
+    }
+
+    fn process<'a>(&'a self, ctx: &'a mut Context<'_>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let format = self
+                .format
+                .or_else(|| ArchiveFormat::detect(ctx.file))
+                .context("cannot detect archive format")?;
+            let staging = ctx.tmp_path.with_extension(format!("unpack{}", ctx.staged.len()));
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            tokio::fs::create_dir_all(&staging).await?;
+            ctx.staged.push((staging.clone(), ctx.storage_dir.join(&self.into)));
+
+            // 压缩的 tar 先解压到旁边，再交给同步的 tar 解包
+            let archive = match format.compression() {
+                Some(compression) => {
+                    let tar = ctx.tmp_path.with_extension("tar");
+                    let input = BufReader::new(tokio::fs::File::open(ctx.tmp_path).await?);
+                    let mut out = tokio::fs::File::create(&tar).await?;
+                    let copied = match compression {
+                        Compression::Gzip => tokio::io::copy(&mut GzipDecoder::new(input), &mut out).await,
+                        Compression::Zstd => tokio::io::copy(&mut ZstdDecoder::new(input), &mut out).await,
+                        Compression::Xz => tokio::io::copy(&mut XzDecoder::new(input), &mut out).await,
+                    };
+                    if let Err(e) = copied {
+                        let _ = tokio::fs::remove_file(&tar).await;
+                        return Err(e).context("decompression failed");
+                    }
+                    tar
+                }
+                None => ctx.tmp_path.to_path_buf(),
+            };
+
+            let (path, dest) = (archive.clone(), staging.clone());
+            let unpacked = tokio::task::spawn_blocking(move || match format {
+                ArchiveFormat::Zip => unzip(&path, &dest),
+                _ => untar(&path, &dest),
+            })
+            .await;
+            if archive != ctx.tmp_path {
+                let _ = tokio::fs::remove_file(&archive).await;
+            }
+            let entries = unpacked??;
+            info!("File {}: unpacked {} entries into {}", ctx.file, entries, self.into);
+            Ok(())
+        })
+    }
+}
+
+/// 解包 tar；链接条目跳过，路径越出目标目录的条目由 `unpack_in` 拒绝
+fn untar(path: &Path, dest: &Path) -> Result<usize> {
+    let mut archive = tar::Archive::new(std::fs::File::open(path)?);
+    archive.set_preserve_permissions(false);
+    let mut count = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            warn!("skipping link {} in archive", entry.path()?.display());
+            continue;
+        }
+        if entry.unpack_in(dest)? {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// 解包 zip；符号链接与越出目标目录的条目跳过
+fn unzip(path: &Path, dest: &Path) -> Result<usize> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let mut count = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(rel) = entry.enclosed_name() else {
+            warn!("skipping unsafe path {:?} in archive", entry.name());
+            continue;
+        };
+        if entry.is_symlink() {
+            warn!("skipping link {} in archive", rel.display());
+            continue;
+        }
+        let out = dest.join(rel);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&out)?;
+            continue;
+        }
+        super::meta::ensure_parent_dir(&out)?;
+        std::io::copy(&mut entry, &mut std::fs::File::create(&out)?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+struct Compress {
+    format: Compression,
+}
+
+impl PostProcessor for Compress {
+    fn name(&self) -> &'static str {
+        "compress"
+    }
+
+    fn process<'a>(&'a self, ctx: &'a mut Context<'_>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let out_path = ctx.tmp_path.with_extension("compress");
+            let mut input = tokio::fs::File::open(ctx.tmp_path).await?;
+            let out = tokio::fs::File::create(&out_path).await?;
+            let compressed = match self.format {
+                Compression::Gzip => encode(&mut input, GzipEncoder::new(out)).await,
+                Compression::Zstd => encode(&mut input, ZstdEncoder::new(out)).await,
+                Compression::Xz => encode(&mut input, XzEncoder::new(out)).await,
+            };
+            if let Err(e) = compressed {
+                let _ = tokio::fs::remove_file(&out_path).await;
+                return Err(e).context("compression failed");
+            }
+            tokio::fs::rename(&out_path, ctx.tmp_path).await?;
+            Ok(())
+        })
+    }
+}
+
+async fn encode<R, W>(input: &mut R, mut encoder: W) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    tokio::io::copy(input, &mut encoder).await?;
+    encoder.shutdown().await
+}
+
+struct Validate {
+    command: String,
+    timeout: Duration,
+}
+
+impl PostProcessor for Validate {
+    fn name(&self) -> &'static str {
+        "validate"
+    }
+
+    fn process<'a>(&'a self, ctx: &'a mut Context<'_>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let child = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(&self.command)
+                .env("RELAYFETCH_FILE", ctx.file)
+                .env("RELAYFETCH_PATH", ctx.tmp_path)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .context("failed to start validation command")?;
+            let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+                .await
+                .with_context(|| format!("validation command timed out after {:?}", self.timeout))??;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("validation command exited with {}: {}", output.status, stderr.trim());
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,322 @@
+//! 只镜像上游对象的一段字节（files.toml 中的 `range`）
+//!
+//! 以 Range 请求下载，上游忽略 Range 返回 200 时从完整响应中截取所需部分后断开。
+//! 上游的 Content-Range 记录在 meta 中，下载服务与客户端清单据此标明文件只是一部分。
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures::StreamExt;
+use log::{error, info, warn};
+use reqwest::{StatusCode, header};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+use super::meta::{Meta, ensure_parent_dir, load_meta, save_meta};
+use super::{Cancelled, FileEvent, or_cancel, partial};
+use crate::config::{ConfigCenter, file::{ByteRange, UpstreamAuth}};
+use crate::notify::Notification;
+use crate::quota::SpaceError;
+
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn download<F, Fut>(
+    client: &reqwest::Client,
+    dir: &Path,
+    file: &str,
+    urls: &[String],
+    range: ByteRange,
+    keep_versions: usize,
+    auth: Option<&UpstreamAuth>,
+    max_retry: usize,
+    base_delay: u64,
+    cc: &ConfigCenter,
+    throttle: bool,
+    cancel: &CancellationToken,
+    mut report: F,
+) -> Result<()>
+where
+    F: FnMut(FileEvent) -> Fut + Send,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    for attempt in 0..max_retry {
+        let mut res = Err(anyhow::anyhow!("no upstream configured for {}", file));
+        for url in urls {
+            res = fetch(client, dir, file, url, range, keep_versions, auth, cc, throttle, cancel, &mut report).await;
+            match &res {
+                Ok(_) => break,
+                Err(e) if e.is::<SpaceError>() || e.is::<Cancelled>() => break,
+                Err(e) if urls.len() > 1 => warn!("File {}: source {} failed: {}", file, url, e),
+                Err(_) => {}
+            }
+        }
+
+        match res {
+            Ok(_) => return Ok(()),
+            Err(e) if e.is::<Cancelled>() => {
+                report(FileEvent::Cancelled { file: file.to_string() }).await;
+                return Err(e);
+            }
+            Err(e) if e.is::<SpaceError>() || attempt + 1 == max_retry => {
+                error!("File {}: {}", file, e);
+                cc.notify(Notification::failed(file, &dir.join(file), &e.to_string())).await;
+                report(FileEvent::Error { file: file.to_string(), error: e.to_string() }).await;
+                return Err(e);
+            }
+            Err(e) => {
+                error!("File {}: attempt {} failed: {}", file, attempt + 1, e);
+                let delay = std::time::Duration::from_millis(base_delay * 2u64.pow(attempt as u32));
+                if let Err(e) = or_cancel(cancel, tokio::time::sleep(delay)).await {
+                    report(FileEvent::Cancelled { file: file.to_string() }).await;
+                    return Err(e);
+                }
+            }
+        }
+    }
+    anyhow::bail!("no download attempts configured for {}", file)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn fetch<F, Fut>(
+    client: &reqwest::Client,
+    dir: &Path,
+    file: &str,
+    url: &str,
+    range: ByteRange,
+    keep_versions: usize,
+    auth: Option<&UpstreamAuth>,
+    cc: &ConfigCenter,
+    throttle: bool,
+    cancel: &CancellationToken,
+    report: &mut F,
+) -> Result<()>
+where
+    F: FnMut(FileEvent) -> Fut + Send,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    let file_path = dir.join(file);
+    let meta_path = file_path.with_extension("meta");
+    let tmp_path = partial::tmp_path(dir, file, url);
+    ensure_parent_dir(&file_path)?;
+    ensure_parent_dir(&tmp_path)?;
+    let old_meta = load_meta(&meta_path).unwrap_or_default();
+    let local_size = tokio::fs::metadata(&file_path).await.map(|m| m.len()).unwrap_or(0);
+    let host = crate::bandwidth::host_of(url).unwrap_or_default();
+
+    let s3 = cc.config().await.s3.clone();
+    let mut req = super::upstream_request(client, reqwest::Method::GET, url, auth, &s3)?.header(header::RANGE, range.header());
+    // 本地已是同一段的完整副本时带条件头
+    let complete = old_meta.total_size == Some(local_size)
+        && old_meta.content_range.as_deref().is_some_and(|cr| covers(cr, range));
+    if complete {
+        if let Some(etag) = &old_meta.etag {
+            req = req.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(lm) = &old_meta.last_modified {
+            req = req.header(header::IF_MODIFIED_SINCE, lm);
+        }
+    }
+    let resp = or_cancel(cancel, req.send()).await?.context("request failed")?;
+
+    // 上游返回 200 时需要跳过的字节数与保留的长度
+    let (skip, take, content_range) = match resp.status() {
+        StatusCode::NOT_MODIFIED if complete => {
+            report(FileEvent::Started { file: file.to_string(), total: Some(local_size) }).await;
+            let mut meta = old_meta;
+            meta.fetched_at = Some(Utc::now().to_rfc3339());
+            save_meta(&meta_path, &meta)?;
+            report(FileEvent::Progress { file: file.to_string(), downloaded: local_size }).await;
+            report(FileEvent::Transferred { received: 0, saved: local_size }).await;
+            info!("File {} (range {}) not modified, skipping", file, range);
+            report(FileEvent::Finished { file: file.to_string() }).await;
+            return Ok(());
+        }
+        StatusCode::PARTIAL_CONTENT => {
+            let content_range = resp
+                .headers()
+                .get(header::CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+                .context("206 response without Content-Range")?;
+            if parse_content_range(&content_range).map(|(start, _, _)| start) != Some(range.start) {
+                anyhow::bail!("upstream returned unexpected range {}", content_range);
+            }
+            (0, resp.content_length(), content_range)
+        }
+        StatusCode::OK => {
+            let (take, content_range) = slice_of_full(range, resp.content_length())?;
+            (range.start, Some(take), content_range)
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE => anyhow::bail!("range {} not satisfiable", range),
+        status => anyhow::bail!("download failed: {}", status),
+    };
+
+    let max_storage = cc.config().await.max_storage_bytes;
+    let mut reservation = cc.quota().reserve(dir, max_storage, take.unwrap_or(0), local_size)?;
+    report(FileEvent::Started { file: file.to_string(), total: take }).await;
+
+    let etag = header_str(&resp, header::ETAG);
+    let last_modified = header_str(&resp, header::LAST_MODIFIED);
+    let fetch_time = Utc::now();
+
+    let mut out = tokio::fs::File::create(&tmp_path).await?;
+    let mut hasher = Sha256::new();
+    let mut skipped = 0u64;
+    let mut stored = 0u64;
+    let mut stream = resp.bytes_stream();
+    while let Some(item) = or_cancel(cancel, stream.next()).await? {
+        let chunk = item.context("error while downloading chunk")?;
+        cc.bandwidth().record(&host, chunk.len() as u64);
+        if throttle {
+            or_cancel(cancel, cc.shaper().throttle_download(cc, chunk.len() as u64)).await?;
+        }
+        let mut data = &chunk[..];
+        if skipped < skip {
+            let n = (skip - skipped).min(data.len() as u64) as usize;
+            skipped += n as u64;
+            data = &data[n..];
+        }
+        if let Some(take) = take {
+            data = &data[..(take - stored).min(data.len() as u64) as usize];
+        }
+        reservation.grow(stored + data.len() as u64)?;
+        out.write_all(data).await?;
+        hasher.update(data);
+        stored += data.len() as u64;
+        report(FileEvent::Progress { file: file.to_string(), downloaded: stored }).await;
+        report(FileEvent::Transferred { received: chunk.len() as u64, saved: 0 }).await;
+        // 已拿到所需部分：不再读取上游的剩余内容
+        if take.is_some_and(|take| stored >= take) {
+            break;
+        }
+    }
+    out.flush().await?;
+    drop(out);
+    if take.is_some_and(|take| stored != take) {
+        anyhow::bail!("truncated response: got {} of {:?} bytes", stored, take);
+    }
+
+    let sha256 = hex::encode(hasher.finalize());
+    let kept = super::versions::keep_replaced(dir, file, &file_path, keep_versions, &old_meta, local_size, &sha256).await;
+    tokio::fs::rename(&tmp_path, &file_path).await?;
+    let meta = Meta {
+        etag,
+        last_modified,
+        fetched_at: Some(fetch_time.to_rfc3339()),
+        total_size: Some(stored),
+        sha256: Some(sha256),
+        source: Some(url.to_string()),
+        content_range: Some(content_range),
+        chunks: None,
+        resume: None,
+        partial: None,
+        signed_by: None,
+    };
+    save_meta(&meta_path, &meta)?;
+    reservation.commit(stored, if kept { 0 } else { local_size });
+    cc.access().restored(file);
+    cc.storage_index().update(dir, &file_path);
+    cc.storage_index().update(dir, &tmp_path);
+    cc.notify(Notification::downloaded(file, &file_path, &meta).replacing(&old_meta)).await;
+
+    report(FileEvent::Finished { file: file.to_string() }).await;
+    info!("File {} (range {}) downloaded successfully from {}", file, range, url);
+    Ok(())
+}
+
+/// 上游忽略 Range 返回完整对象（共 `total` 字节）时保留的长度与对应的 Content-Range；
+/// 范围起点超出对象末尾时不可满足
+fn slice_of_full(range: ByteRange, total: Option<u64>) -> Result<(u64, String)> {
+    let last = match (range.end, total) {
+        (_, Some(0)) => anyhow::bail!("range {} not satisfiable: object is empty", range),
+        (Some(end), Some(total)) => end.min(total - 1),
+        (Some(end), None) => end,
+        (None, Some(total)) => total - 1,
+        (None, None) => anyhow::bail!("upstream ignored Range and sent no Content-Length"),
+    };
+    if range.start > last {
+        anyhow::bail!("range {} not satisfiable: object has {} bytes", range, last + 1);
+    }
+    let total = total.map_or("*".to_string(), |t| t.to_string());
+    Ok((last + 1 - range.start, format!("bytes {}-{}/{}", range.start, last, total)))
+}
+
+/// `bytes <start>-<end>/<total>` -> (start, end, total)
+fn parse_content_range(s: &str) -> Option<(u64, u64, Option<u64>)> {
+    let (span, total) = s.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = span.split_once('-')?;
+    Some((start.parse().ok()?, end.parse().ok()?, total.parse().ok()))
+}
+
+/// 已保存的 Content-Range 是否正好是配置的范围（到末尾的范围需覆盖到对象末尾）
+fn covers(content_range: &str, range: ByteRange) -> bool {
+    let Some((start, end, total)) = parse_content_range(content_range) else {
+        return false;
+    };
+    start == range.start
+        && match (range.end, total) {
+            (Some(want), Some(total)) => end == want.min(total.saturating_sub(1)),
+            (Some(want), None) => end == want,
+            (None, total) => total == Some(end + 1),
+        }
+}
+
+fn header_str(resp: &reqwest::Response, name: header::HeaderName) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(s: &str) -> ByteRange {
+        ByteRange::try_from(s.to_string()).unwrap()
+    }
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(range("0-1023"), ByteRange { start: 0, end: Some(1023) });
+        assert_eq!(range(" 512 - 512 "), ByteRange { start: 512, end: Some(512) });
+        assert_eq!(range("100-"), ByteRange { start: 100, end: None });
+        assert_eq!(range("0-1023").len(), Some(1024));
+        assert_eq!(range("100-").len(), None);
+        assert_eq!(range("0-1023").header(), "bytes=0-1023");
+        for invalid in ["", "-", "-100", "10", "a-b", "10-5", "1-2-3", "18446744073709551616-"] {
+            assert!(ByteRange::try_from(invalid.to_string()).is_err(), "{:?} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn slices_full_response() {
+        assert_eq!(slice_of_full(range("0-9"), Some(100)).unwrap(), (10, "bytes 0-9/100".to_string()));
+        // 对象比范围短：截到对象末尾
+        assert_eq!(slice_of_full(range("90-199"), Some(100)).unwrap(), (10, "bytes 90-99/100".to_string()));
+        assert_eq!(slice_of_full(range("10-"), Some(100)).unwrap(), (90, "bytes 10-99/100".to_string()));
+        assert_eq!(slice_of_full(range("99-"), Some(100)).unwrap(), (1, "bytes 99-99/100".to_string()));
+        // 没有 Content-Length 时按配置的终点截取
+        assert_eq!(slice_of_full(range("5-14"), None).unwrap(), (10, "bytes 5-14/*".to_string()));
+        assert!(slice_of_full(range("5-"), None).is_err());
+    }
+
+    #[test]
+    fn rejects_start_beyond_end_of_object() {
+        for (r, total) in [("100-199", 100), ("150-", 100), ("0-9", 0), ("0-", 0), ("101-101", 100)] {
+            let err = slice_of_full(range(r), Some(total)).unwrap_err();
+            assert!(err.to_string().contains("not satisfiable"), "{} of {}: {}", r, total, err);
+        }
+    }
+
+    #[test]
+    fn content_range_covers_configured_range() {
+        assert!(covers("bytes 0-9/100", range("0-9")));
+        assert!(covers("bytes 90-99/100", range("90-199")));
+        assert!(covers("bytes 10-99/100", range("10-")));
+        assert!(!covers("bytes 10-98/100", range("10-")));
+        assert!(!covers("bytes 1-9/100", range("0-9")));
+        assert!(!covers("garbage", range("0-9")));
+    }
+}
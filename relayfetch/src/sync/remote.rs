@@ -0,0 +1,116 @@
+//! 非 HTTP 上游：`ftp://`（`ftp` feature）与 `sftp://`（`sftp` feature）
+//!
+//! 没有 ETag 与条件请求，新鲜度按远端的大小与修改时间判断：修改时间以 HTTP 日期格式
+//! 存入 meta 的 `last_modified`，下载服务照常作为 Last-Modified 返回。
+//! 用户名与密码取自 URL，或条目的 `basic_auth`。
+
+use anyhow::Result;
+use axum::body::Bytes;
+use futures::stream::BoxStream;
+
+use crate::config::config::SftpConfig;
+use crate::config::file::UpstreamAuth;
+
+use super::meta::Meta;
+
+/// 远端文件的大小与修改时间
+#[derive(Debug, Clone, Default)]
+pub struct Stat {
+    pub size: Option<u64>,
+    /// HTTP 日期格式
+    pub last_modified: Option<String>,
+}
+
+impl Stat {
+    /// 与上次下载时记录的大小、修改时间一致（缺少任一项时视为已变化）
+    pub fn unchanged(&self, meta: &Meta) -> bool {
+        self.size.is_some()
+            && self.last_modified.is_some()
+            && self.size == meta.total_size
+            && self.last_modified == meta.last_modified
+    }
+}
+
+/// 已开始的下载
+pub struct Opened {
+    pub stat: Stat,
+    /// 实际开始的偏移；服务器不支持续传时为 0
+    pub offset: u64,
+    pub stream: BoxStream<'static, std::io::Result<Bytes>>,
+}
+
+/// 上游的登录信息
+pub struct Login {
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+pub fn is_remote(url: &str) -> bool {
+    url.starts_with("ftp://") || url.starts_with("sftp://")
+}
+
+pub async fn stat(url: &str, auth: Option<&UpstreamAuth>, sftp: &SftpConfig) -> Result<Stat> {
+    let login = login(url, auth)?;
+    match scheme(url) {
+        #[cfg(feature = "ftp")]
+        "ftp" => super::ftp::stat(url, &login).await,
+        #[cfg(feature = "sftp")]
+        "sftp" => super::sftp::stat(url, login, sftp.clone()).await,
+        _ => {
+            let _ = (login, sftp);
+            unsupported(url)
+        }
+    }
+}
+
+/// 从 `offset` 处开始下载
+pub async fn open(url: &str, offset: u64, auth: Option<&UpstreamAuth>, sftp: &SftpConfig) -> Result<Opened> {
+    let login = login(url, auth)?;
+    match scheme(url) {
+        #[cfg(feature = "ftp")]
+        "ftp" => super::ftp::open(url, offset, &login).await,
+        #[cfg(feature = "sftp")]
+        "sftp" => super::sftp::open(url, offset, login, sftp.clone()).await,
+        _ => {
+            let _ = (login, offset, sftp);
+            unsupported(url)
+        }
+    }
+}
+
+fn unsupported<T>(url: &str) -> Result<T> {
+    let scheme = scheme(url);
+    anyhow::bail!("{}:// upstreams require relayfetch to be built with the `{}` feature", scheme, scheme)
+}
+
+fn scheme(url: &str) -> &str {
+    url.split_once("://").map(|(scheme, _)| scheme).unwrap_or_default()
+}
+
+/// 条目的 basic_auth 优先于 URL 中的用户名与密码
+fn login(url: &str, auth: Option<&UpstreamAuth>) -> Result<Login> {
+    if let Some(basic) = auth.and_then(|a| a.basic_auth.as_ref()) {
+        return Ok(Login {
+            username: Some(basic.username.clone()),
+            password: basic.password().map_err(anyhow::Error::msg)?,
+        });
+    }
+    let parsed = reqwest::Url::parse(url)?;
+    let decode = |s: &str| percent_encoding::percent_decode_str(s).decode_utf8_lossy().into_owned();
+    Ok(Login {
+        username: Some(parsed.username()).filter(|u| !u.is_empty()).map(decode),
+        password: parsed.password().map(decode),
+    })
+}
+
+/// 远端路径（已解码）
+#[cfg(any(feature = "ftp", feature = "sftp"))]
+pub fn path_of(url: &reqwest::Url) -> String {
+    percent_encoding::percent_decode_str(url.path()).decode_utf8_lossy().into_owned()
+}
+
+/// HTTP 日期格式（与 Last-Modified 相同）
+#[cfg(any(feature = "ftp", feature = "sftp"))]
+pub fn http_date(t: chrono::DateTime<chrono::Utc>) -> String {
+    t.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
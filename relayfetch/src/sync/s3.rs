@@ -0,0 +1,193 @@
+//! `s3://bucket/key` 上游（`s3` feature）
+//!
+//! 请求改写为 `[s3]` 配置的对象存储地址，有密钥时按 AWS Signature V4 签名（负载不签名），
+//! 否则匿名访问公开桶。
+//!
+//! 单段上传对象的 ETag 是内容的 MD5，下载完成后据此校验；分段上传对象的 ETag
+//! 形如 `<md5>-<段数>`，不是内容摘要，只用于续传时确认对象未被替换。
+
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use openssl::hash::{Hasher, MessageDigest};
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::{Method, Url};
+use sha2::{Digest, Sha256};
+
+use crate::config::config::S3Config;
+
+/// 对象键的编码：除 RFC 3986 非保留字符与 `/` 外全部编码
+const KEY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'/');
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl Credentials {
+    /// 优先使用配置中的密钥，否则读取 AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY / AWS_SESSION_TOKEN
+    fn resolve(cfg: &S3Config) -> Option<Self> {
+        let env = |var: &str| std::env::var(var).ok().filter(|v| !v.is_empty());
+        match (&cfg.access_key_id, &cfg.secret_access_key) {
+            (Some(id), Some(secret)) => Some(Self {
+                access_key_id: id.clone(),
+                secret_access_key: secret.clone(),
+                session_token: cfg.session_token.clone(),
+            }),
+            _ => Some(Self {
+                access_key_id: env("AWS_ACCESS_KEY_ID")?,
+                secret_access_key: env("AWS_SECRET_ACCESS_KEY")?,
+                session_token: env("AWS_SESSION_TOKEN"),
+            }),
+        }
+    }
+}
+
+/// 构造对象请求；`url` 为 `s3://bucket/key`
+pub fn request(client: &reqwest::Client, method: Method, url: &str, cfg: &S3Config) -> Result<reqwest::RequestBuilder> {
+    let (bucket, key) = parse(url)?;
+    let target = object_url(cfg, bucket, key)?;
+
+    let mut req = client.request(method.clone(), target.clone());
+    if let Some(creds) = Credentials::resolve(cfg) {
+        for (name, value) in sign(&method, &target, &cfg.region, &creds, Utc::now())? {
+            req = req.header(name, value);
+        }
+    }
+    Ok(req)
+}
+
+/// 单段上传对象的 ETag 应等于文件的 MD5；分段上传或无 ETag 时不校验
+pub fn verify_etag(path: &Path, etag: Option<&str>) -> Result<()> {
+    let Some(expected) = etag.map(|e| e.trim_matches('"').to_ascii_lowercase()) else {
+        return Ok(());
+    };
+    if expected.len() != 32 || !expected.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Ok(());
+    }
+
+    let mut hasher = Hasher::new(MessageDigest::md5())?;
+    let mut f = std::fs::File::open(path)?;
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = std::io::Read::read(&mut f, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n])?;
+    }
+    let actual = hex::encode(hasher.finish()?);
+    if actual != expected {
+        bail!("content MD5 {} does not match ETag {}, discarding download", actual, expected);
+    }
+    Ok(())
+}
+
+fn parse(url: &str) -> Result<(&str, &str)> {
+    let rest = url
+        .strip_prefix("s3://")
+        .ok_or_else(|| anyhow!("not an s3:// URL: {}", url))?;
+    match rest.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => Ok((bucket, key)),
+        _ => bail!("s3 URL must be s3://<bucket>/<key>: {}", url),
+    }
+}
+
+fn object_url(cfg: &S3Config, bucket: &str, key: &str) -> Result<Url> {
+    let key = utf8_percent_encode(key, KEY).to_string();
+    let endpoint = cfg
+        .endpoint
+        .clone()
+        .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", cfg.region));
+    let mut url = Url::parse(&endpoint).with_context(|| format!("invalid s3 endpoint {}", endpoint))?;
+
+    if cfg.path_style {
+        let base = url.path().trim_end_matches('/').to_string();
+        url.set_path(&format!("{}/{}/{}", base, bucket, key));
+    } else {
+        let host = url.host_str().ok_or_else(|| anyhow!("s3 endpoint has no host: {}", endpoint))?;
+        let host = format!("{}.{}", bucket, host);
+        url.set_host(Some(&host))?;
+        url.set_path(&format!("/{}", key));
+    }
+    Ok(url)
+}
+
+/// 计算 SigV4 所需的请求头（含 Authorization）
+fn sign(
+    method: &Method,
+    url: &Url,
+    region: &str,
+    creds: &Credentials,
+    now: DateTime<Utc>,
+) -> Result<Vec<(&'static str, String)>> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_string()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &creds.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+
+    let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v.trim())).collect();
+    let signed_headers = headers.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method.as_str(),
+        url.path(),
+        url.query().unwrap_or_default(),
+        canonical_headers,
+        signed_headers,
+        UNSIGNED_PAYLOAD
+    );
+
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let mut key = hmac(format!("AWS4{}", creds.secret_access_key).as_bytes(), date.as_bytes())?;
+    for part in [region, "s3", "aws4_request"] {
+        key = hmac(&key, part.as_bytes())?;
+    }
+    let signature = hex::encode(hmac(&key, string_to_sign.as_bytes())?);
+
+    // host 由 reqwest 根据 URL 填写
+    headers.retain(|(k, _)| *k != "host");
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            creds.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    Ok(headers)
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let key = PKey::hmac(key)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    Ok(signer.sign_oneshot_to_vec(data)?)
+}
@@ -0,0 +1,168 @@
+//! `sftp://` 上游（`sftp` feature）
+//!
+//! libssh2 是阻塞接口，连接与读取都在 blocking 线程中进行，数据经 channel 交给同步任务。
+//! 登录顺序：URL / 条目 basic_auth 中的密码、`[sftp].private_key`、ssh-agent。
+//! 登录前按 known_hosts（默认 `~/.ssh/known_hosts`）校验主机密钥，未记录的主机拒绝连接。
+
+use std::io::{Read, Seek, SeekFrom};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use axum::body::Bytes;
+use futures::StreamExt;
+use reqwest::Url;
+use ssh2::{CheckResult, KnownHostFileKind, Session, Sftp};
+use tokio::sync::mpsc;
+
+use crate::config::config::SftpConfig;
+use crate::config::file;
+
+use super::remote::{self, Login, Opened, Stat};
+
+/// 连接与每次读写的超时
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 每次读取的块大小
+const CHUNK: usize = 64 * 1024;
+
+/// 读取与转发之间缓冲的块数
+const CHANNEL_CHUNKS: usize = 16;
+
+pub async fn stat(url: &str, login: Login, cfg: SftpConfig) -> Result<Stat> {
+    let url = Url::parse(url)?;
+    tokio::task::spawn_blocking(move || {
+        let (_session, sftp) = connect(&url, &login, &cfg)?;
+        stat_path(&sftp, &remote::path_of(&url))
+    })
+    .await?
+}
+
+pub async fn open(url: &str, offset: u64, login: Login, cfg: SftpConfig) -> Result<Opened> {
+    let url = Url::parse(url)?;
+    let (tx, rx) = mpsc::channel(CHANNEL_CHUNKS);
+    let (opened_tx, opened_rx) = tokio::sync::oneshot::channel();
+
+    tokio::task::spawn_blocking(move || {
+        // 会话需在读取期间保持
+        let (_session, _sftp, mut file) = match open_file(&url, offset, &login, &cfg) {
+            Ok((session, sftp, file, stat)) => {
+                if opened_tx.send(Ok(stat)).is_err() {
+                    return;
+                }
+                (session, sftp, file)
+            }
+            Err(e) => {
+                let _ = opened_tx.send(Err(e));
+                return;
+            }
+        };
+
+        loop {
+            let mut buf = vec![0u8; CHUNK];
+            let item = match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    buf.truncate(n);
+                    Ok(Bytes::from(buf))
+                }
+                Err(e) => Err(e),
+            };
+            let failed = item.is_err();
+            // 接收方已放弃（取消 / 出错）时停止读取
+            if tx.blocking_send(item).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let stat = opened_rx.await.map_err(|_| anyhow!("sftp worker exited"))??;
+    let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+    Ok(Opened { stat, offset, stream: stream.boxed() })
+}
+
+fn open_file(url: &Url, offset: u64, login: &Login, cfg: &SftpConfig) -> Result<(Session, Sftp, ssh2::File, Stat)> {
+    let path = remote::path_of(url);
+    let (session, sftp) = connect(url, login, cfg)?;
+    let stat = stat_path(&sftp, &path)?;
+    let mut file = sftp.open(Path::new(&path)).with_context(|| format!("failed to open {}", path))?;
+    if offset > 0 {
+        file.seek(SeekFrom::Start(offset))?;
+    }
+    Ok((session, sftp, file, stat))
+}
+
+fn connect(url: &Url, login: &Login, cfg: &SftpConfig) -> Result<(Session, Sftp)> {
+    let host = url.host_str().ok_or_else(|| anyhow!("missing host in {}", url))?;
+    let port = url.port().unwrap_or(22);
+    let user = login
+        .username
+        .as_deref()
+        .ok_or_else(|| anyhow!("sftp upstream {} needs a username", url))?;
+
+    let addr = std::net::ToSocketAddrs::to_socket_addrs(&(host, port))?
+        .next()
+        .ok_or_else(|| anyhow!("failed to resolve {}", host))?;
+    let tcp = TcpStream::connect_timeout(&addr, TIMEOUT)
+        .with_context(|| format!("failed to connect to {}:{}", host, port))?;
+
+    let mut session = Session::new()?;
+    session.set_timeout(TIMEOUT.as_millis() as u32);
+    session.set_tcp_stream(tcp);
+    session.handshake().context("ssh handshake failed")?;
+
+    if !cfg.insecure_accept_any_host_key {
+        let known_hosts = match &cfg.known_hosts {
+            Some(path) => path.clone(),
+            None => default_known_hosts()?,
+        };
+        verify_host(&session, &known_hosts, host, port)?;
+    }
+
+    if let Some(password) = &login.password {
+        session.userauth_password(user, password)?;
+    } else if let Some(key) = &cfg.private_key {
+        let passphrase = cfg.passphrase_env.as_deref().map(file::env).transpose().map_err(anyhow::Error::msg)?;
+        session.userauth_pubkey_file(user, None, key, passphrase.as_deref())?;
+    } else {
+        session.userauth_agent(user)?;
+    }
+    if !session.authenticated() {
+        bail!("ssh authentication as {} failed", user);
+    }
+
+    let sftp = session.sftp()?;
+    Ok((session, sftp))
+}
+
+fn default_known_hosts() -> Result<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .ok_or_else(|| anyhow!("HOME is not set; configure [sftp].known_hosts to verify host keys"))?;
+    Ok(PathBuf::from(home).join(".ssh").join("known_hosts"))
+}
+
+fn verify_host(session: &Session, known_hosts: &Path, host: &str, port: u16) -> Result<()> {
+    let mut hosts = session.known_hosts()?;
+    hosts
+        .read_file(known_hosts, KnownHostFileKind::OpenSSH)
+        .with_context(|| format!("failed to read {}", known_hosts.display()))?;
+    let (key, _) = session.host_key().ok_or_else(|| anyhow!("server sent no host key"))?;
+    match hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => bail!("host key for {} does not match {}", host, known_hosts.display()),
+        CheckResult::NotFound => bail!("{} is not listed in {}", host, known_hosts.display()),
+        CheckResult::Failure => bail!("failed to check host key for {}", host),
+    }
+}
+
+fn stat_path(sftp: &Sftp, path: &str) -> Result<Stat> {
+    let st = sftp.stat(Path::new(path)).with_context(|| format!("failed to stat {}", path))?;
+    Ok(Stat {
+        size: st.size,
+        last_modified: st
+            .mtime
+            .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+            .map(remote::http_date),
+    })
+}
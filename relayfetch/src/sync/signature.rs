@@ -0,0 +1,115 @@
+//! 分离签名校验（files.toml 中的 `signature`）
+//!
+//! 下载完成、摘要校验之后取回签名文件，以 `gpgv` 对照条目配置的密钥环校验 tmp 文件；
+//! 通过时返回签名密钥的指纹记入 meta，不通过时本次下载作废，正式文件保持不变。
+
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+
+use super::upstream_request;
+use crate::config::{config::S3Config, file::{SignatureCheck, UpstreamAuth}};
+
+/// 签名文件的大小上限
+const MAX_SIGNATURE_BYTES: usize = 1024 * 1024;
+
+/// gpgv 的超时
+const GPGV_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub(super) async fn verify(
+    client: &reqwest::Client,
+    check: &SignatureCheck,
+    auth: Option<&UpstreamAuth>,
+    s3: &S3Config,
+    tmp_path: &Path,
+) -> Result<String> {
+    let resp = upstream_request(client, reqwest::Method::GET, &check.url, auth, s3)?
+        .send()
+        .await
+        .context("signature request failed")?;
+    if !resp.status().is_success() {
+        anyhow::bail!("signature download failed: {}", resp.status());
+    }
+    let mut sig = Vec::new();
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        sig.extend_from_slice(&chunk.context("error while downloading signature")?);
+        if sig.len() > MAX_SIGNATURE_BYTES {
+            anyhow::bail!("signature is larger than {} bytes", MAX_SIGNATURE_BYTES);
+        }
+    }
+
+    let sig_path = tmp_path.with_extension("sig");
+    tokio::fs::write(&sig_path, &sig).await?;
+    let verified = gpgv(&check.keyring, &sig_path, tmp_path).await;
+    let _ = tokio::fs::remove_file(&sig_path).await;
+    verified
+}
+
+/// 返回签名密钥的指纹；gpgv 把相对路径的密钥环当作 ~/.gnupg 下的文件，先转为绝对路径
+async fn gpgv(keyring: &str, sig: &Path, data: &Path) -> Result<String> {
+    let keyring = std::path::absolute(keyring)?;
+    let child = tokio::process::Command::new("gpgv")
+        .arg("--status-fd")
+        .arg("1")
+        .arg("--keyring")
+        .arg(&keyring)
+        .arg(sig)
+        .arg(data)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("failed to run gpgv")?;
+    let output = tokio::time::timeout(GPGV_TIMEOUT, child.wait_with_output())
+        .await
+        .context("gpgv timed out")??;
+
+    let status = String::from_utf8_lossy(&output.stdout);
+    match valid_signer(&status) {
+        Some(fingerprint) if output.status.success() => Ok(fingerprint.to_string()),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason: Vec<&str> = stderr.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+            anyhow::bail!("signature verification failed: {}", reason.join("; "));
+        }
+    }
+}
+
+/// 从 `--status-fd` 输出中取出签名密钥的指纹；
+/// `[GNUPG:] VALIDSIG <签名密钥指纹> ...` 只在签名有效且密钥在密钥环中时出现
+fn valid_signer(status: &str) -> Option<&str> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .and_then(|rest| rest.split_whitespace().next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::valid_signer;
+
+    #[test]
+    fn fingerprint_is_taken_from_validsig() {
+        let status = "\
+[GNUPG:] NEWSIG
+[GNUPG:] KEY_CONSIDERED 3F1A7C2B9D4E5F60718293A4B5C6D7E8F9012345 0
+[GNUPG:] GOODSIG B5C6D7E8F9012345 Release Signing Key <release@example.com>
+[GNUPG:] VALIDSIG 3F1A7C2B9D4E5F60718293A4B5C6D7E8F9012345 2026-01-01 1767225600 0 4 0 1 10 00 3F1A7C2B9D4E5F60718293A4B5C6D7E8F9012345
+";
+        assert_eq!(valid_signer(status), Some("3F1A7C2B9D4E5F60718293A4B5C6D7E8F9012345"));
+    }
+
+    #[test]
+    fn bad_or_unknown_signatures_have_no_signer() {
+        let bad = "[GNUPG:] NEWSIG\n[GNUPG:] BADSIG B5C6D7E8F9012345 Release Signing Key <release@example.com>\n";
+        let missing_key = "[GNUPG:] NEWSIG\n[GNUPG:] ERRSIG B5C6D7E8F9012345 1 10 00 1767225600 9 -\n[GNUPG:] NO_PUBKEY B5C6D7E8F9012345\n";
+        assert_eq!(valid_signer(bad), None);
+        assert_eq!(valid_signer(missing_key), None);
+        assert_eq!(valid_signer(""), None);
+    }
+}
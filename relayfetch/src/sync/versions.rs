@@ -0,0 +1,146 @@
+//! 文件的历史版本（条目的 `keep_versions`）
+//!
+//! 同步替换内容有变化的文件前，把旧文件（硬链接，不支持时复制）连同 meta 保存到
+//! `storage_dir/.versions/<时间>/<文件>`，时间为被替换的时刻（UTC，如 `20240102T030405Z`）。
+//! 每个文件只保留最近的 `keep_versions` 个版本，更早的在下一次替换时删除，空目录随之清理。
+//! 下载端口以 `/__versions/<时间>/<文件>` 提供。
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::{info, warn};
+
+use super::meta::{Meta, ensure_parent_dir};
+
+pub const VERSIONS_DIR: &str = ".versions";
+/// 版本目录名的时间格式
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// 一个历史版本
+#[derive(Debug, Clone)]
+pub struct Version {
+    /// 版本目录名，即被替换的时刻
+    pub stamp: String,
+    /// 条目路径
+    pub file: String,
+    pub size: u64,
+}
+
+impl Version {
+    /// 目录名不是本模块生成的格式时为 None
+    pub fn replaced_at(&self) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(&self.stamp, STAMP_FORMAT)
+            .ok()
+            .map(|t| t.and_utc())
+    }
+}
+
+/// 内容有变化时保留即将被替换的本地文件；返回是否已保留（旧内容此后仍占用空间）
+pub async fn keep_replaced(
+    storage_dir: &Path,
+    file: &str,
+    file_path: &Path,
+    keep: usize,
+    old: &Meta,
+    local_size: u64,
+    new_sha256: &str,
+) -> bool {
+    // 只保留完整且确实被替换的旧版本
+    if keep == 0
+        || local_size == 0
+        || old.total_size != Some(local_size)
+        || old.sha256.as_deref().is_some_and(|sha| sha.eq_ignore_ascii_case(new_sha256))
+    {
+        return false;
+    }
+    let (root, rel, path) = (storage_dir.to_path_buf(), file.to_string(), file_path.to_path_buf());
+    match tokio::task::spawn_blocking(move || archive(&root, &rel, &path, keep)).await {
+        Ok(Ok(dest)) => {
+            info!("File {}: kept previous version as {}", file, dest.display());
+            true
+        }
+        Ok(Err(e)) => {
+            warn!("File {}: failed to keep previous version: {:#}", file, e);
+            false
+        }
+        Err(e) => {
+            warn!("File {}: version task failed: {}", file, e);
+            false
+        }
+    }
+}
+
+fn archive(storage_dir: &Path, file: &str, file_path: &Path, keep: usize) -> Result<PathBuf> {
+    let root = storage_dir.join(VERSIONS_DIR);
+    let dest = root.join(Utc::now().format(STAMP_FORMAT).to_string()).join(file);
+    ensure_parent_dir(&dest)?;
+    // 同一秒内替换两次时只保留后一次
+    let _ = std::fs::remove_file(&dest);
+    if std::fs::hard_link(file_path, &dest).is_err() {
+        std::fs::copy(file_path, &dest)?;
+    }
+    let meta = file_path.with_extension("meta");
+    if meta.exists() {
+        std::fs::copy(&meta, dest.with_extension("meta"))?;
+    }
+    prune(&root, file, keep)?;
+    Ok(dest)
+}
+
+/// 删除 `file` 超出 `keep` 个的旧版本
+fn prune(root: &Path, file: &str, keep: usize) -> Result<()> {
+    let mut stamps: Vec<String> = std::fs::read_dir(root)?
+        .flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|stamp| root.join(stamp).join(file).is_file())
+        .collect();
+    // 时间格式定长，按字符串排序即按时间排序
+    stamps.sort_unstable_by(|a, b| b.cmp(a));
+
+    for stamp in stamps.into_iter().skip(keep) {
+        remove_in(root, &stamp, file)?;
+    }
+    Ok(())
+}
+
+/// 全部历史版本（不含 meta）
+pub fn list(storage_dir: &Path) -> Vec<Version> {
+    let root = storage_dir.join(VERSIONS_DIR);
+    walkdir::WalkDir::new(&root)
+        .min_depth(2)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file() && e.path().extension().is_none_or(|ext| ext != "meta"))
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(&root).ok()?.to_str()?.replace('\\', "/");
+            let (stamp, file) = rel.split_once('/')?;
+            Some(Version {
+                stamp: stamp.to_string(),
+                file: file.to_string(),
+                size: e.metadata().map(|m| m.len()).unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+/// 删除一个版本及其 meta
+pub fn remove(storage_dir: &Path, version: &Version) -> std::io::Result<()> {
+    remove_in(&storage_dir.join(VERSIONS_DIR), &version.stamp, &version.file)
+}
+
+fn remove_in(root: &Path, stamp: &str, file: &str) -> std::io::Result<()> {
+    let top = root.join(stamp);
+    let path = top.join(file);
+    std::fs::remove_file(&path)?;
+    let _ = std::fs::remove_file(path.with_extension("meta"));
+    // 向上清理空目录，直到时间目录本身
+    let mut dir = path.parent();
+    while let Some(d) = dir
+        && d.starts_with(&top)
+        && std::fs::remove_dir(d).is_ok()
+    {
+        dir = d.parent();
+    }
+    Ok(())
+}
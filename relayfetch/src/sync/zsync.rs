@@ -0,0 +1,678 @@
+//! zsync 增量同步
+//!
+//! 条目配置了 `zsync`（上游发布的 `.zsync` 控制文件）且本地已有完整的旧版本时，按控制文件中
+//! 每块的滚动校验和（rsum）与 MD4 在旧文件中查找可复用的块，其余部分用 Range 请求下载。
+//! 重建的内容按顺序作为数据流交给常规下载流程（写 tmp、摘要、替换），流结束时按控制文件的
+//! SHA-1 校验整个文件。只支持未压缩的目标文件（不支持 `Z-Map2`）。
+
+use std::collections::HashMap;
+use std::io::{Read, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, Result, anyhow, bail};
+use axum::body::Bytes;
+use chrono::{DateTime, FixedOffset};
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use log::info;
+use reqwest::header;
+use tokio::io::AsyncReadExt;
+
+use crate::config::config::S3Config;
+use crate::config::file::UpstreamAuth;
+
+use super::{Fetched, upstream_request};
+
+/// 从旧文件读取时每块的大小
+const CHUNK: u64 = 256 * 1024;
+
+/// 扫描旧文件时缓冲区超过该大小就丢弃已处理的部分
+const COMPACT_AT: usize = 8 * 1024 * 1024;
+
+/// 控制文件
+struct Control {
+    block_size: usize,
+    length: u64,
+    /// 需要连续匹配的块数（1 或 2）
+    seq_matches: usize,
+    checksum_bytes: usize,
+    /// rsum 的 a 分量只保存了一部分，比较前按此掩码截断
+    a_mask: u16,
+    sha1: String,
+    /// 生成控制文件时目标文件的修改时间（可选）
+    mtime: Option<DateTime<FixedOffset>>,
+    blocks: Vec<BlockSum>,
+}
+
+struct BlockSum {
+    a: u16,
+    b: u16,
+    /// MD4 的前 checksum_bytes 字节
+    checksum: Vec<u8>,
+}
+
+/// 重建目标文件的一段
+enum Segment {
+    /// 旧文件中的字节
+    Local { offset: u64, len: u64 },
+    /// 上游的字节（含 end）
+    Remote { start: u64, end: u64 },
+}
+
+/// 用控制文件与本地旧版本 `old` 开始重建；没有可复用的块或控制文件与上游不符时报错，
+/// 由调用方改为完整下载
+#[allow(clippy::too_many_arguments)]
+pub async fn open(
+    client: &reqwest::Client,
+    url: &str,
+    zsync_url: &str,
+    auth: Option<&UpstreamAuth>,
+    s3: &S3Config,
+    old: &Path,
+    tmp: &Path,
+) -> Result<Fetched> {
+    let data = upstream_request(client, reqwest::Method::GET, zsync_url, auth, s3)?
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("failed to fetch {}", zsync_url))?
+        .bytes()
+        .await?;
+    let control = parse(&data).with_context(|| format!("invalid zsync control file {}", zsync_url))?;
+
+    // 控制文件可能落后于上游文件
+    let head = upstream_request(client, reqwest::Method::HEAD, url, auth, s3)?
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .context("HEAD request failed")?;
+    let header = |name| head.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let etag = header(header::ETAG);
+    let last_modified = header(header::LAST_MODIFIED);
+    if let Some(len) = header(header::CONTENT_LENGTH).and_then(|v| v.parse::<u64>().ok())
+        && len != control.length
+    {
+        bail!("control file describes {} bytes but upstream has {}", control.length, len);
+    }
+    if let (Some(mtime), Some(lm)) = (control.mtime, last_modified.as_deref())
+        && DateTime::parse_from_rfc2822(lm).is_ok_and(|lm| lm > mtime)
+    {
+        bail!("control file is older than the upstream file");
+    }
+
+    let (control, known) = {
+        let old = old.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let known = scan(&control, &old)?;
+            anyhow::Ok((control, known))
+        })
+        .await??
+    };
+    let reused = known.iter().filter(|k| k.is_some()).count();
+    if reused == 0 {
+        bail!("no blocks in common with the local copy");
+    }
+    // 已知上游有更新，控制文件却与本地副本完全一致，说明它还没重新生成
+    let bs = control.block_size as u64;
+    let identical = known.iter().enumerate().all(|(i, k)| *k == Some(i as u64 * bs));
+    if identical && tokio::fs::metadata(old).await?.len() == control.length {
+        bail!("control file describes the local copy, not the new upstream version");
+    }
+
+    let segments = segments(&control, &known);
+    let remote: u64 = segments
+        .iter()
+        .map(|s| match s {
+            Segment::Remote { start, end } => end - start + 1,
+            Segment::Local { .. } => 0,
+        })
+        .sum();
+    info!(
+        "zsync: reusing {} of {} blocks from {}, fetching {} of {} bytes",
+        reused,
+        control.blocks.len(),
+        old.display(),
+        remote,
+        control.length
+    );
+
+    let transferred = Arc::new(AtomicU64::new(0));
+    let mut parts = Vec::with_capacity(segments.len());
+    for segment in segments {
+        parts.push(match segment {
+            Segment::Local { offset, len } => read_local(old.to_path_buf(), offset, len),
+            Segment::Remote { start, end } => {
+                let mut req = upstream_request(client, reqwest::Method::GET, url, auth, s3)?
+                    .header(header::RANGE, format!("bytes={}-{}", start, end));
+                // 上游在此期间变化时返回 200，下面按错误处理
+                if let Some(etag) = &etag {
+                    req = req.header(header::IF_RANGE, etag);
+                }
+                let transferred = transferred.clone();
+                fetch_range(req)
+                    .inspect_ok(move |chunk| {
+                        transferred.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                    })
+                    .boxed()
+            }
+        });
+    }
+
+    Ok(Fetched {
+        resumed: false,
+        etag,
+        last_modified,
+        content_length: Some(control.length),
+        stream: verify_sha1(stream::iter(parts).flatten().boxed(), control.sha1, tmp.to_path_buf()),
+        transferred: Some(transferred),
+        chunked: None,
+        encoding: None,
+    })
+}
+
+fn parse(data: &[u8]) -> Result<Control> {
+    let end = data
+        .windows(2)
+        .position(|w| w == b"\n\n")
+        .ok_or_else(|| anyhow!("missing header terminator"))?;
+    let header = std::str::from_utf8(&data[..end])?;
+    let fields: HashMap<&str, &str> = header
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let field = |name: &str| fields.get(name).copied().ok_or_else(|| anyhow!("missing {}", name));
+
+    let block_size: usize = field("Blocksize")?.parse()?;
+    if !block_size.is_power_of_two() {
+        bail!("block size {} is not a power of two", block_size);
+    }
+    let length: u64 = field("Length")?.parse()?;
+    let lengths: Vec<usize> = field("Hash-Lengths")?
+        .split(',')
+        .map(|n| n.trim().parse())
+        .collect::<Result<_, _>>()?;
+    let [seq_matches, rsum_bytes, checksum_bytes] = lengths[..] else {
+        bail!("malformed Hash-Lengths");
+    };
+    if !(1..=2).contains(&seq_matches) || !(1..=4).contains(&rsum_bytes) || !(3..=16).contains(&checksum_bytes) {
+        bail!("unsupported Hash-Lengths {},{},{}", seq_matches, rsum_bytes, checksum_bytes);
+    }
+    let sha1 = field("SHA-1")?.to_ascii_lowercase();
+    let mtime = fields.get("MTime").and_then(|t| DateTime::parse_from_rfc2822(t).ok());
+
+    let count = length.div_ceil(block_size as u64) as usize;
+    let sums = &data[end + 2..];
+    let entry = rsum_bytes + checksum_bytes;
+    if sums.len() != count * entry {
+        bail!("expected {} block checksums, got {} bytes", count, sums.len());
+    }
+    let blocks = sums
+        .chunks(entry)
+        .map(|c| {
+            // 保存的是 (a, b) 大端序 4 字节的末尾 rsum_bytes 字节
+            let mut r = [0u8; 4];
+            r[4 - rsum_bytes..].copy_from_slice(&c[..rsum_bytes]);
+            BlockSum {
+                a: u16::from_be_bytes([r[0], r[1]]),
+                b: u16::from_be_bytes([r[2], r[3]]),
+                checksum: c[rsum_bytes..].to_vec(),
+            }
+        })
+        .collect();
+
+    Ok(Control {
+        block_size,
+        length,
+        seq_matches,
+        checksum_bytes,
+        a_mask: match rsum_bytes {
+            1 | 2 => 0,
+            3 => 0xff,
+            _ => 0xffff,
+        },
+        sha1,
+        mtime,
+        blocks,
+    })
+}
+
+/// 在旧文件中查找目标文件的各块，返回每块在旧文件中的偏移
+fn scan(control: &Control, old: &Path) -> Result<Vec<Option<u64>>> {
+    let bs = control.block_size;
+    let shift = bs.trailing_zeros();
+    let mut index: HashMap<(u16, u16), Vec<usize>> = HashMap::new();
+    for (i, block) in control.blocks.iter().enumerate() {
+        index.entry((block.a, block.b)).or_default().push(i);
+    }
+    let mut known = vec![None; control.blocks.len()];
+
+    let mut window = Window::open(old, bs)?;
+    let file_len = window.file_len;
+    let mut pos = 0usize;
+    if !window.fill(pos + bs)? {
+        return Ok(known);
+    }
+    let (mut a, mut b) = rsum(&window.buf[pos..pos + bs]);
+
+    // 从填充区开始的窗口没有意义
+    while window.base + (pos as u64) < file_len {
+        if let Some(candidates) = index.get(&(a & control.a_mask, b)) {
+            window.fill(pos + bs * control.seq_matches)?;
+            let offset = window.base + pos as u64;
+            let checksum = md4(&window.buf[pos..pos + bs]);
+            let mut matched = false;
+            for &i in candidates {
+                if known[i].is_some() || checksum[..control.checksum_bytes] != control.blocks[i].checksum[..] {
+                    continue;
+                }
+                // 要求后一块也紧接着匹配，弥补较短的校验和
+                if control.seq_matches > 1 && i + 1 < known.len() {
+                    let Some(next) = window.buf.get(pos + bs..pos + 2 * bs) else {
+                        continue;
+                    };
+                    if !block_matches(control, i + 1, next) {
+                        continue;
+                    }
+                    known[i + 1].get_or_insert(offset + bs as u64);
+                }
+                known[i] = Some(offset);
+                matched = true;
+            }
+            if matched {
+                pos += bs;
+                if !window.fill(pos + bs)? {
+                    break;
+                }
+                (a, b) = rsum(&window.buf[pos..pos + bs]);
+                continue;
+            }
+        }
+
+        if !window.fill(pos + bs + 1)? {
+            break;
+        }
+        let (out, new) = (window.buf[pos] as u16, window.buf[pos + bs] as u16);
+        a = a.wrapping_add(new).wrapping_sub(out);
+        b = b.wrapping_add(a).wrapping_sub(((out as u32) << shift) as u16);
+        pos += 1;
+
+        if pos >= COMPACT_AT {
+            window.buf.drain(..pos);
+            window.base += pos as u64;
+            pos = 0;
+        }
+    }
+    Ok(known)
+}
+
+fn block_matches(control: &Control, i: usize, data: &[u8]) -> bool {
+    let block = &control.blocks[i];
+    let (a, b) = rsum(data);
+    (a & control.a_mask, b) == (block.a, block.b) && md4(data)[..control.checksum_bytes] == block.checksum[..]
+}
+
+/// 旧文件的滑动缓冲区；文件末尾补一块零，与 zsyncmake 对最后一块的处理一致
+struct Window {
+    file: std::fs::File,
+    file_len: u64,
+    buf: Vec<u8>,
+    /// buf[0] 在文件中的偏移
+    base: u64,
+    padded: bool,
+    block_size: usize,
+}
+
+impl Window {
+    fn open(path: &Path, block_size: usize) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let file_len = file.metadata()?.len();
+        Ok(Self { file, file_len, buf: Vec::new(), base: 0, padded: false, block_size })
+    }
+
+    /// 读到 buf 至少有 `end` 字节；文件（含填充）不够时返回 false
+    fn fill(&mut self, end: usize) -> Result<bool> {
+        while self.buf.len() < end && !self.padded {
+            let start = self.buf.len();
+            self.buf.resize(start + CHUNK as usize, 0);
+            let n = self.file.read(&mut self.buf[start..])?;
+            self.buf.truncate(start + n);
+            if n == 0 {
+                self.buf.resize(start + self.block_size, 0);
+                self.padded = true;
+            }
+        }
+        Ok(self.buf.len() >= end)
+    }
+}
+
+/// 把已知块合并为连续的本地段与远端段
+fn segments(control: &Control, known: &[Option<u64>]) -> Vec<Segment> {
+    let bs = control.block_size as u64;
+    let mut out: Vec<Segment> = Vec::new();
+    for (i, source) in known.iter().enumerate() {
+        let start = i as u64 * bs;
+        let len = bs.min(control.length - start);
+        match (source, out.last_mut()) {
+            (Some(offset), Some(Segment::Local { offset: o, len: l })) if *o + *l == *offset => *l += len,
+            (Some(offset), _) => out.push(Segment::Local { offset: *offset, len }),
+            (None, Some(Segment::Remote { end, .. })) if *end + 1 == start => *end += len,
+            (None, _) => out.push(Segment::Remote { start, end: start + len - 1 }),
+        }
+    }
+    out
+}
+
+/// 从旧文件读取一段；超出文件末尾的部分（最后一块的填充）读作零
+fn read_local(path: PathBuf, offset: u64, len: u64) -> BoxStream<'static, std::io::Result<Bytes>> {
+    stream::unfold((None::<tokio::fs::File>, len), move |(file, left)| {
+        let path = path.clone();
+        async move {
+            if left == 0 {
+                return None;
+            }
+            let mut file = match file {
+                Some(f) => f,
+                None => {
+                    let opened = async {
+                        let mut f = tokio::fs::File::open(&path).await?;
+                        tokio::io::AsyncSeekExt::seek(&mut f, SeekFrom::Start(offset)).await?;
+                        std::io::Result::Ok(f)
+                    };
+                    match opened.await {
+                        Ok(f) => f,
+                        Err(e) => return Some((Err(e), (None, 0))),
+                    }
+                }
+            };
+            let mut buf = vec![0u8; left.min(CHUNK) as usize];
+            let mut filled = 0;
+            while filled < buf.len() {
+                match file.read(&mut buf[filled..]).await {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(e) => return Some((Err(e), (None, 0))),
+                }
+            }
+            let n = buf.len() as u64;
+            Some((Ok(Bytes::from(buf)), (Some(file), left - n)))
+        }
+    })
+    .boxed()
+}
+
+fn fetch_range(req: reqwest::RequestBuilder) -> BoxStream<'static, std::io::Result<Bytes>> {
+    stream::once(async move {
+        let resp = req.send().await.map_err(std::io::Error::other)?;
+        if resp.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(std::io::Error::other(format!("range request returned {}", resp.status())));
+        }
+        Ok(resp.bytes_stream().map_err(std::io::Error::other))
+    })
+    .try_flatten()
+    .boxed()
+}
+
+/// 流结束时校验 SHA-1；不符时删除 tmp 文件，避免下次从错误的内容续传
+fn verify_sha1(
+    inner: BoxStream<'static, std::io::Result<Bytes>>,
+    expected: String,
+    tmp: PathBuf,
+) -> BoxStream<'static, std::io::Result<Bytes>> {
+    let state = (inner, Some(openssl::sha::Sha1::new()));
+    stream::unfold(state, move |(mut inner, hasher)| {
+        let (expected, tmp) = (expected.clone(), tmp.clone());
+        async move {
+            let mut hasher = hasher?;
+            match inner.next().await {
+                Some(Ok(chunk)) => {
+                    hasher.update(&chunk);
+                    Some((Ok(chunk), (inner, Some(hasher))))
+                }
+                Some(Err(e)) => Some((Err(e), (inner, None))),
+                None => {
+                    let actual = hex::encode(hasher.finish());
+                    if actual == expected {
+                        return None;
+                    }
+                    let _ = tokio::fs::remove_file(&tmp).await;
+                    let e = std::io::Error::other(format!("rebuilt file SHA-1 {} does not match {}", actual, expected));
+                    Some((Err(e), (inner, None)))
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+/// zsync 的弱校验和：a 为字节和，b 为按剩余长度加权的和，均取低 16 位
+fn rsum(data: &[u8]) -> (u16, u16) {
+    let (mut a, mut b) = (0u16, 0u16);
+    let len = data.len();
+    for (i, &c) in data.iter().enumerate() {
+        a = a.wrapping_add(c as u16);
+        b = b.wrapping_add(((len - i) as u16).wrapping_mul(c as u16));
+    }
+    (a, b)
+}
+
+/// MD4（RFC 1320）；OpenSSL 3 只在 legacy provider 中提供
+fn md4(data: &[u8]) -> [u8; 16] {
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    let mut blocks = data.chunks_exact(64);
+    for block in &mut blocks {
+        md4_block(&mut state, block);
+    }
+    // 末尾补 0x80、零与位长度，共一到两块
+    let rest = blocks.remainder();
+    let mut tail = [0u8; 128];
+    tail[..rest.len()].copy_from_slice(rest);
+    tail[rest.len()] = 0x80;
+    let end = if rest.len() < 56 { 64 } else { 128 };
+    tail[end - 8..end].copy_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+    for block in tail[..end].chunks_exact(64) {
+        md4_block(&mut state, block);
+    }
+
+    let mut out = [0u8; 16];
+    for (chunk, word) in out.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+fn md4_block(state: &mut [u32; 4], block: &[u8]) {
+    let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+    let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+    let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+    let mut x = [0u32; 16];
+    for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    let [mut a, mut b, mut c, mut d] = *state;
+
+    for i in [0, 4, 8, 12] {
+        a = a.wrapping_add(f(b, c, d)).wrapping_add(x[i]).rotate_left(3);
+        d = d.wrapping_add(f(a, b, c)).wrapping_add(x[i + 1]).rotate_left(7);
+        c = c.wrapping_add(f(d, a, b)).wrapping_add(x[i + 2]).rotate_left(11);
+        b = b.wrapping_add(f(c, d, a)).wrapping_add(x[i + 3]).rotate_left(19);
+    }
+    for i in [0, 1, 2, 3] {
+        a = a.wrapping_add(g(b, c, d)).wrapping_add(x[i]).wrapping_add(0x5a827999).rotate_left(3);
+        d = d.wrapping_add(g(a, b, c)).wrapping_add(x[i + 4]).wrapping_add(0x5a827999).rotate_left(5);
+        c = c.wrapping_add(g(d, a, b)).wrapping_add(x[i + 8]).wrapping_add(0x5a827999).rotate_left(9);
+        b = b.wrapping_add(g(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(0x5a827999).rotate_left(13);
+    }
+    for i in [0, 2, 1, 3] {
+        a = a.wrapping_add(h(b, c, d)).wrapping_add(x[i]).wrapping_add(0x6ed9eba1).rotate_left(3);
+        d = d.wrapping_add(h(a, b, c)).wrapping_add(x[i + 8]).wrapping_add(0x6ed9eba1).rotate_left(9);
+        c = c.wrapping_add(h(d, a, b)).wrapping_add(x[i + 4]).wrapping_add(0x6ed9eba1).rotate_left(11);
+        b = b.wrapping_add(h(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(0x6ed9eba1).rotate_left(15);
+    }
+
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 伪随机内容，避免块之间偶然相同
+    fn noise(seed: u32, len: usize) -> Vec<u8> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x = x.wrapping_mul(1664525).wrapping_add(1013904223);
+                (x >> 24) as u8
+            })
+            .collect()
+    }
+
+    /// 按 zsyncmake 的格式为 `data` 生成控制文件：最后一块补零后计算校验和
+    fn control_file(data: &[u8], block_size: usize, (seq, rsum_bytes, checksum_bytes): (usize, usize, usize)) -> Vec<u8> {
+        let mut out = format!(
+            "zsync: 0.6.2\nFilename: new.bin\nMTime: Tue, 01 Sep 2026 10:00:00 +0000\nBlocksize: {}\nLength: {}\nHash-Lengths: {},{},{}\nURL: new.bin\nSHA-1: {}\n\n",
+            block_size,
+            data.len(),
+            seq,
+            rsum_bytes,
+            checksum_bytes,
+            hex::encode(openssl::sha::sha1(data)),
+        )
+        .into_bytes();
+        for block in data.chunks(block_size) {
+            let mut block = block.to_vec();
+            block.resize(block_size, 0);
+            let (a, b) = rsum(&block);
+            let r = [a.to_be_bytes(), b.to_be_bytes()].concat();
+            out.extend_from_slice(&r[4 - rsum_bytes..]);
+            out.extend_from_slice(&md4(&block)[..checksum_bytes]);
+        }
+        out
+    }
+
+    #[test]
+    fn md4_matches_rfc1320() {
+        for (input, digest) in [
+            ("", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            ("a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            ("abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            ("message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ] {
+            assert_eq!(hex::encode(md4(input.as_bytes())), digest, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn header_is_parsed() {
+        let data = noise(1, 1000);
+        let control = parse(&control_file(&data, 256, (2, 3, 5))).unwrap();
+        assert_eq!(control.block_size, 256);
+        assert_eq!(control.length, 1000);
+        assert_eq!(control.seq_matches, 2);
+        assert_eq!(control.checksum_bytes, 5);
+        assert_eq!(control.a_mask, 0xff);
+        assert_eq!(control.sha1, hex::encode(openssl::sha::sha1(&data)));
+        assert_eq!(control.mtime, DateTime::parse_from_rfc2822("Tue, 01 Sep 2026 10:00:00 +0000").ok());
+        assert_eq!(control.blocks.len(), 4);
+        assert!(control.blocks.iter().all(|b| b.checksum.len() == 5));
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        let data = noise(1, 1000);
+        let good = control_file(&data, 256, (1, 4, 16));
+        let text = |b: &[u8]| String::from_utf8_lossy(b).into_owned();
+        let replace = |from: &str, to: &str| {
+            let end = good.windows(2).position(|w| w == b"\n\n").unwrap();
+            let mut out = text(&good[..end]).replace(from, to).into_bytes();
+            out.extend_from_slice(&good[end..]);
+            out
+        };
+
+        assert!(parse(&replace("Blocksize: 256", "Blocksize: 300")).is_err());
+        assert!(parse(&replace("Hash-Lengths: 1,4,16", "Hash-Lengths: 1,4")).is_err());
+        assert!(parse(&replace("Hash-Lengths: 1,4,16", "Hash-Lengths: 3,4,16")).is_err());
+        assert!(parse(&replace("Length: 1000", "Length: 2000")).is_err());
+        assert!(parse(&replace("SHA-1", "SHA-256")).is_err());
+        assert!(parse(&good[..good.len() - 1]).is_err());
+        let end = good.windows(2).position(|w| w == b"\n\n").unwrap();
+        assert!(parse(&good[..end]).is_err());
+    }
+
+    #[test]
+    fn blocks_match_on_weak_and_strong_checksum() {
+        let data = noise(2, 1024);
+        let control = parse(&control_file(&data, 512, (1, 4, 16))).unwrap();
+        assert!(block_matches(&control, 0, &data[..512]));
+        assert!(block_matches(&control, 1, &data[512..]));
+        assert!(!block_matches(&control, 1, &data[..512]));
+
+        // a、b 都不变的改动：rsum 相同，只有 MD4 能区分
+        let mut collision = data[..512].to_vec();
+        for (i, delta) in [(10, 1i16), (11, -1), (20, -1), (21, 1)] {
+            collision[i] = (collision[i] as i16 + delta).rem_euclid(256) as u8;
+        }
+        assert_eq!(rsum(&collision), rsum(&data[..512]));
+        assert!(!block_matches(&control, 0, &collision));
+
+        // rsum 只保存 3 字节时 a 的高位不参与比较
+        let control = parse(&control_file(&data, 512, (1, 3, 16))).unwrap();
+        assert_eq!(control.blocks[0].a, rsum(&data[..512]).0 & 0xff);
+        assert!(block_matches(&control, 0, &data[..512]));
+    }
+
+    #[tokio::test]
+    async fn file_is_rebuilt_from_seed_and_ranges() {
+        let old = noise(3, 20_000);
+        // 中间插入一段新内容（不在块边界上），并修改末尾附近的几个字节
+        let mut new = old[..5000].to_vec();
+        new.extend_from_slice(&noise(4, 700));
+        new.extend_from_slice(&old[5000..]);
+        let tail = new.len() - 100;
+        new[tail..tail + 4].copy_from_slice(b"edit");
+
+        let dir = tempfile::tempdir().unwrap();
+        let old_path = dir.path().join("old.bin");
+        std::fs::write(&old_path, &old).unwrap();
+
+        for hash_lengths in [(1, 4, 16), (2, 2, 4)] {
+            let control = parse(&control_file(&new, 1024, hash_lengths)).unwrap();
+            let known = scan(&control, &old_path).unwrap();
+            let reused = known.iter().filter(|k| k.is_some()).count();
+            assert!(reused >= known.len() - 3, "{:?}: reused {} of {}", hash_lengths, reused, known.len());
+
+            let mut rebuilt = Vec::new();
+            let mut fetched = 0;
+            for segment in segments(&control, &known) {
+                match segment {
+                    Segment::Local { offset, len } => {
+                        let mut local = read_local(old_path.clone(), offset, len);
+                        while let Some(chunk) = local.next().await {
+                            rebuilt.extend_from_slice(&chunk.unwrap());
+                        }
+                    }
+                    Segment::Remote { start, end } => {
+                        rebuilt.extend_from_slice(&new[start as usize..=end as usize]);
+                        fetched += end - start + 1;
+                    }
+                }
+            }
+            assert!(fetched > 0 && fetched < new.len() as u64 / 2, "{:?}: fetched {}", hash_lengths, fetched);
+            assert_eq!(rebuilt.len(), new.len());
+            assert!(rebuilt == new, "{:?}: rebuilt content differs", hash_lengths);
+            assert_eq!(hex::encode(openssl::sha::sha1(&rebuilt)), control.sha1);
+        }
+    }
+}
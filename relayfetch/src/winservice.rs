@@ -0,0 +1,139 @@
+//! Windows 服务（`--service install / uninstall / run`）
+//!
+//! - install：以当前程序注册名为 `relayfetch` 的自动启动服务，启动参数为 `--service run` 加上
+//!   config / files 的绝对路径与 `--set` 覆盖项（服务的工作目录是 System32，相对路径无法使用）
+//! - uninstall：停止并删除服务
+//! - run：由服务控制管理器启动；收到停止请求时按正常退出流程（见 `shutdown_timeout_secs`）结束
+
+use std::ffi::OsString;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+        ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+use crate::{Args, ServiceAction};
+
+const SERVICE_NAME: &str = "relayfetch";
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+/// 服务入口由系统回调，拿不到 main 的参数，先存起来
+static ARGS: OnceLock<Args> = OnceLock::new();
+
+pub fn handle(action: ServiceAction, args: Args) -> Result<()> {
+    match action {
+        ServiceAction::Install => install(&args),
+        ServiceAction::Uninstall => uninstall(),
+        ServiceAction::Run => {
+            let _ = ARGS.set(args);
+            service_dispatcher::start(SERVICE_NAME, ffi_service_main).context("start service dispatcher")
+        }
+    }
+}
+
+fn install(args: &Args) -> Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )?;
+    let config = std::path::absolute(&args.config)?;
+    let files = std::path::absolute(&args.files)?;
+    let mut launch_arguments: Vec<OsString> = vec![
+        "--service".into(),
+        "run".into(),
+        "--config".into(),
+        config.into(),
+        "--files".into(),
+        files.into(),
+    ];
+    for o in &args.set {
+        launch_arguments.push("--set".into());
+        launch_arguments.push(format!("{}={}", o.key, o.value).into());
+    }
+
+    let info = ServiceInfo {
+        name: SERVICE_NAME.into(),
+        display_name: "relayfetch".into(),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments,
+        dependencies: vec![],
+        account_name: None, // LocalSystem
+        account_password: None,
+    };
+    let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG)?;
+    service.set_description("Mirrors upstream files and serves them over HTTP")?;
+    println!("service {} installed, start it with `sc start {}`", SERVICE_NAME, SERVICE_NAME);
+    Ok(())
+}
+
+fn uninstall() -> Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service = manager.open_service(
+        SERVICE_NAME,
+        ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+    )?;
+    // 标记删除后，服务停止且句柄全部关闭时才真正删除
+    service.delete()?;
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+    }
+    println!("service {} uninstalled", SERVICE_NAME);
+    Ok(())
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        log::error!("service failed: {e:?}");
+    }
+}
+
+fn run_service() -> Result<()> {
+    let handler = |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            crate::signal::request_shutdown();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let status = service_control_handler::register(SERVICE_NAME, handler)?;
+    let report = |state, controls_accepted, code| {
+        status.set_service_status(ServiceStatus {
+            service_type: SERVICE_TYPE,
+            current_state: state,
+            controls_accepted,
+            exit_code: ServiceExitCode::Win32(code),
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        })
+    };
+    report(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        0,
+    )?;
+
+    let args = ARGS.get().context("service started without arguments")?;
+    let result = crate::run(args);
+    report(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        if result.is_ok() { 0 } else { 1 },
+    )?;
+    result
+}
@@ -0,0 +1,263 @@
+//! 端到端测试：同步 → 下载服务 → 管理接口
+//!
+//! 每个测试启动自己的上游与 relayfetch 进程，覆盖跨模块的行为：
+//! 首次同步与提供下载、304 跳过、上游更新、断点续传、reload_config、清理无用文件、取消分组同步、内部目录与 meta 不对外提供、大文件按范围流式读取。
+
+mod support;
+
+use serde_json::json;
+use support::{Daemon, Origin, content, files_toml};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn syncs_on_startup_and_serves_files() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "hello");
+    origin.put("dir/b.bin", content("b", 64 * 1024));
+
+    let daemon = Daemon::start(&files_toml([
+        ("x/a.txt", origin.url("a.txt")),
+        ("x/b.bin", origin.url("dir/b.bin")),
+    ]))
+    .await;
+
+    let (status, body) = daemon.download("x/a.txt").await;
+    assert_eq!(status, 200);
+    assert_eq!(&body[..], b"hello");
+    let (status, body) = daemon.download("x/b.bin").await;
+    assert_eq!(status, 200);
+    assert_eq!(&body[..], &content("b", 64 * 1024)[..]);
+
+    let (status, _) = daemon.download("x/missing").await;
+    assert_eq!(status, 404);
+
+    let files = daemon.get_json("list_files").await;
+    assert_eq!(files.as_array().map(Vec::len), Some(2), "{}", files);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unchanged_upstream_is_not_downloaded_again() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "hello");
+    let daemon = Daemon::start(&files_toml([("a.txt", origin.url("a.txt"))])).await;
+    origin.clear_hits();
+
+    let job = daemon.sync().await;
+    assert_eq!(job["state"], "finished", "{}", job);
+    assert_eq!(job["failed_files"], 0);
+
+    // 条件请求得到 304，没有再次下载
+    let hits = origin.hits("a.txt");
+    assert!(!hits.is_empty());
+    assert!(hits.iter().all(|h| h.status == 304), "{:?}", hits);
+    assert_eq!(daemon.stored("a.txt").as_deref(), Some(&b"hello"[..]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn updated_upstream_replaces_local_copy() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "version 1");
+    let daemon = Daemon::start(&files_toml([("a.txt", origin.url("a.txt"))])).await;
+    assert_eq!(daemon.download("a.txt").await.1, "version 1");
+
+    origin.put("a.txt", "version 2");
+    let job = daemon.sync().await;
+    assert_eq!(job["state"], "finished", "{}", job);
+
+    assert_eq!(daemon.download("a.txt").await.1, "version 2");
+    assert!(origin.hits("a.txt").iter().any(|h| h.status == 200));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn interrupted_download_resumes_with_range() {
+    let origin = Origin::start().await;
+    let body = content("resume", 512 * 1024);
+    origin.put("big.bin", body.clone());
+    origin.truncate_next("big.bin");
+
+    let daemon = Daemon::start(&files_toml([("big.bin", origin.url("big.bin"))])).await;
+
+    // 第一次响应中途断开，重试时从已写入的位置续传
+    let hits = origin.hits("big.bin");
+    assert!(
+        hits.iter().any(|h| h.status == 206 && h.range.as_deref() == Some("bytes=262144-")),
+        "{:?}\n{}",
+        hits,
+        daemon.log()
+    );
+    assert_eq!(daemon.stored("big.bin").as_deref(), Some(&body[..]));
+    assert_eq!(&daemon.download("big.bin").await.1[..], &body[..]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reload_config_picks_up_new_entries() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "a");
+    origin.put("b.txt", "b");
+    let daemon = Daemon::start(&files_toml([("a.txt", origin.url("a.txt"))])).await;
+    assert!(daemon.stored("b.txt").is_none());
+
+    daemon
+        .reload_files(&files_toml([("a.txt", origin.url("a.txt")), ("b.txt", origin.url("b.txt"))]))
+        .await;
+    let job = daemon.sync().await;
+    assert_eq!(job["state"], "finished", "{}", job);
+
+    assert_eq!(daemon.download("b.txt").await, (200, "b".into()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn removed_entries_are_cleaned_up() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "a");
+    origin.put("b.txt", "b");
+    let daemon = Daemon::start(&files_toml([("a.txt", origin.url("a.txt")), ("b.txt", origin.url("b.txt"))])).await;
+    assert!(daemon.stored("b.txt").is_some());
+
+    daemon.reload_files(&files_toml([("a.txt", origin.url("a.txt"))])).await;
+    let removed = daemon.post("clean_unused_files", json!(null)).await;
+    let removed: Vec<String> = serde_json::from_value(removed["removed"].clone()).unwrap();
+    assert!(removed.iter().any(|f| f.ends_with("b.txt")), "{:?}", removed);
+
+    assert!(daemon.stored("b.txt").is_none());
+    assert_eq!(daemon.download("b.txt").await.0, 404);
+    assert_eq!(daemon.download("a.txt").await, (200, "a".into()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn nested_unused_files_are_cleaned_up() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "a");
+    origin.put("b.txt", "b");
+    let daemon = Daemon::start(&files_toml([
+        ("keep/a.txt", origin.url("a.txt")),
+        ("old/deep/b.txt", origin.url("b.txt")),
+    ]))
+    .await;
+    assert!(daemon.stored("old/deep/b.txt").is_some());
+
+    daemon.reload_files(&files_toml([("keep/a.txt", origin.url("a.txt"))])).await;
+    let removed = daemon.post("clean_unused_files", json!(null)).await;
+    let removed: Vec<String> = serde_json::from_value(removed["removed"].clone()).unwrap();
+    assert!(removed.contains(&"old/deep/b.txt".to_string()), "{:?}", removed);
+    assert!(removed.contains(&"old/deep/b.meta".to_string()), "{:?}", removed);
+    assert!(!removed.iter().any(|f| f.starts_with("keep/") || f.starts_with('.')), "{:?}", removed);
+
+    // 变空的目录一并删除，内部状态目录不受影响
+    assert!(!daemon.storage_dir.join("old").exists());
+    assert!(daemon.storage_dir.join(".relayfetch").exists());
+    assert_eq!(daemon.download("keep/a.txt").await, (200, "a".into()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failing_upstream_is_reported() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "a");
+    let daemon = Daemon::start(&files_toml([
+        ("a.txt", origin.url("a.txt")),
+        ("gone.txt", origin.url("gone.txt")),
+    ]))
+    .await;
+
+    let job = daemon.sync().await;
+    assert_eq!(job["failed_files"], 1, "{}", job);
+    assert_eq!(job["result"], "PartialSuccess", "{}", job);
+
+    let status = daemon.get_json("status").await;
+    assert!(status["files"]["gone.txt"]["error"].is_string(), "{}", status);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn group_sync_can_be_cancelled() {
+    use std::time::{Duration, Instant};
+
+    let origin = Origin::start().await;
+    origin.put("a.bin", content("a", 4096));
+    let files = format!("[files]\n\"a.bin\" = {{ urls = [{:?}], group = \"g\" }}\n\n[groups.g]\n", origin.url("a.bin"));
+    let daemon = std::sync::Arc::new(Daemon::start(&files).await);
+
+    // 上游更新后的下载只发送一半就停住，分组同步一直进行中
+    origin.put("a.bin", content("b", 4096));
+    origin.stall("a.bin");
+    let group = tokio::spawn({
+        let daemon = daemon.clone();
+        async move { daemon.post("sync_group", json!({ "name": "g" })).await }
+    });
+    let deadline = Instant::now() + Duration::from_secs(30);
+    // 启动时的下载、条件请求、停住的下载
+    while origin.hits("a.bin").len() < 3 {
+        assert!(Instant::now() < deadline, "group sync did not start\n{}", daemon.log());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let cancel = daemon.post("cancel_sync", json!(null)).await;
+    assert_eq!(cancel["cancelled"], true, "{}", cancel);
+    let result = tokio::time::timeout(Duration::from_secs(30), group)
+        .await
+        .expect("group sync was not cancelled")
+        .unwrap();
+    assert_eq!(result["failed"], 1, "{}", result);
+    assert_eq!(daemon.stored("a.bin").unwrap(), content("a", 4096));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn internal_directories_are_not_served() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "a");
+    let daemon = Daemon::start(&files_toml([("a.txt", origin.url("a.txt"))])).await;
+
+    // 下载中的 tmp 与代理缓存都在存储目录下以 `.` 开头的目录里
+    for path in [".partial/0123456789abcdef0123456789abcdef.tmp", ".cache/example.com/a.txt"] {
+        let real = daemon.storage_dir.join(path);
+        std::fs::create_dir_all(real.parent().unwrap()).unwrap();
+        std::fs::write(&real, "partial").unwrap();
+        assert_eq!(daemon.download(path).await.0, 404, "{}", path);
+    }
+    assert_eq!(daemon.download("a.txt").await.0, 200);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn meta_sidecars_are_not_served() {
+    let origin = Origin::start().await;
+    origin.put("a.txt", "a");
+    let daemon = Daemon::start(&files_toml([("dir/a.txt", origin.url("a.txt"))])).await;
+
+    // meta 中记录了上游地址
+    let meta = String::from_utf8(daemon.stored("dir/a.meta").expect("meta written")).unwrap();
+    assert!(meta.contains(&origin.url("a.txt")), "{}", meta);
+    assert_eq!(daemon.download("dir/a.meta").await.0, 404);
+
+    std::fs::write(daemon.storage_dir.join("dir/a.txt.tmp"), "partial").unwrap();
+    assert_eq!(daemon.download("dir/a.txt.tmp").await.0, 404);
+    assert_eq!(daemon.download("dir/a.txt").await.0, 200);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ranges_of_large_files_are_streamed() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let origin = Origin::start().await;
+    origin.put("a.txt", "a");
+    let daemon = Daemon::start(&files_toml([("a.txt", origin.url("a.txt"))])).await;
+
+    // 64 GiB 的稀疏文件不占磁盘；整体读入内存时请求会超时或使进程退出
+    let len = 64u64 << 30;
+    let mut file = std::fs::File::create(daemon.storage_dir.join("huge.bin")).unwrap();
+    file.set_len(len).unwrap();
+    file.seek(SeekFrom::Start(len - 5)).unwrap();
+    file.write_all(b"tail!").unwrap();
+    drop(file);
+
+    for (range, expected) in [("bytes=-5", &b"tail!"[..]), ("bytes=1000-1003", &[0u8; 4][..])] {
+        let request = daemon
+            .client
+            .get(format!("http://{}/huge.bin", daemon.download))
+            .header("range", range)
+            .send();
+        let resp = tokio::time::timeout(std::time::Duration::from_secs(10), request)
+            .await
+            .expect("range request timed out")
+            .expect("range request failed");
+        assert_eq!(resp.status(), 206, "{}", range);
+        assert_eq!(&resp.bytes().await.unwrap()[..], expected, "{}", range);
+    }
+}
@@ -0,0 +1,323 @@
+//! 端到端测试环境：内容可变的本地上游 + 独立进程运行的 relayfetch
+//!
+//! 上游按路径提供文件，带强 ETag，支持 If-None-Match（304）与 `Range: bytes=<n>-`（206），
+//! 并可让某个文件的下一次完整响应在中途断开（验证续传），或让它的完整响应发送一半后停住（验证取消）。
+//! 每次请求都会记录下来供断言。
+//! relayfetch 使用临时目录保存配置与存储，端口随机分配，进程随 `Daemon` 一起退出。
+
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, Uri, header};
+use axum::response::{IntoResponse, Response};
+use futures::StreamExt;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// 等待同步、启动等的上限
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 上游收到的一次请求
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub path: String,
+    pub status: u16,
+    pub range: Option<String>,
+}
+
+#[derive(Default)]
+struct OriginState {
+    files: HashMap<String, Bytes>,
+    /// 下一次完整响应只发送前一半后断开的文件
+    truncate: HashSet<String>,
+    /// 完整响应只发送前一半后停住、不再结束的文件
+    stall: HashSet<String>,
+    hits: Vec<Hit>,
+}
+
+#[derive(Clone)]
+pub struct Origin {
+    pub addr: SocketAddr,
+    state: Arc<Mutex<OriginState>>,
+}
+
+impl Origin {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(OriginState::default()));
+        let app = Router::new().fallback(serve).with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        Self { addr, state }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}/{}", self.addr, path)
+    }
+
+    /// 新增或替换文件内容（ETag 随之变化）
+    pub fn put(&self, path: &str, body: impl Into<Bytes>) {
+        self.state.lock().unwrap().files.insert(path.to_string(), body.into());
+    }
+
+    /// 下一次完整响应只发送一半内容后断开连接
+    pub fn truncate_next(&self, path: &str) {
+        self.state.lock().unwrap().truncate.insert(path.to_string());
+    }
+
+    /// 之后的完整响应都只发送一半内容后停住，直到客户端断开
+    pub fn stall(&self, path: &str) {
+        self.state.lock().unwrap().stall.insert(path.to_string());
+    }
+
+    pub fn hits(&self, path: &str) -> Vec<Hit> {
+        let state = self.state.lock().unwrap();
+        state.hits.iter().filter(|h| h.path == path).cloned().collect()
+    }
+
+    pub fn clear_hits(&self) {
+        self.state.lock().unwrap().hits.clear();
+    }
+}
+
+async fn serve(State(state): State<Arc<Mutex<OriginState>>>, uri: Uri, headers: HeaderMap) -> Response {
+    let path = uri.path().trim_start_matches('/').to_string();
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let mut state = state.lock().unwrap();
+    let Some(body) = state.files.get(&path).cloned() else {
+        state.hits.push(Hit { path, status: 404, range });
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let etag = format!("\"{}\"", &hex::encode(Sha256::digest(&body))[..16]);
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|v| v.as_bytes() == etag.as_bytes());
+    let start = range
+        .as_deref()
+        .and_then(|r| r.strip_prefix("bytes="))
+        .and_then(|r| r.strip_suffix('-'))
+        .and_then(|n| n.parse::<usize>().ok());
+
+    let response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else if let Some(start) = start {
+        if start >= body.len() {
+            StatusCode::RANGE_NOT_SATISFIABLE.into_response()
+        } else {
+            let content_range = format!("bytes {}-{}/{}", start, body.len() - 1, body.len());
+            (
+                StatusCode::PARTIAL_CONTENT,
+                [(header::ETAG, etag), (header::CONTENT_RANGE, content_range)],
+                body.slice(start..),
+            )
+                .into_response()
+        }
+    } else if state.truncate.remove(&path) {
+        // 声明完整长度，只发一半就出错，客户端看到的是中途断开的连接
+        let half = body.slice(..body.len() / 2);
+        let chunks = futures::stream::iter([Ok(half)]).chain(futures::stream::once(async {
+            // 先让已发送的一半到达客户端
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Err(std::io::Error::other("connection dropped by test origin"))
+        }));
+        (
+            StatusCode::OK,
+            [(header::ETAG, etag), (header::CONTENT_LENGTH, body.len().to_string())],
+            Body::from_stream(chunks),
+        )
+            .into_response()
+    } else if state.stall.contains(&path) {
+        let half = body.slice(..body.len() / 2);
+        let chunks = futures::stream::iter([Ok::<_, std::io::Error>(half)]).chain(futures::stream::pending());
+        (
+            StatusCode::OK,
+            [(header::ETAG, etag), (header::CONTENT_LENGTH, body.len().to_string())],
+            Body::from_stream(chunks),
+        )
+            .into_response()
+    } else {
+        (StatusCode::OK, [(header::ETAG, etag)], body).into_response()
+    };
+
+    state.hits.push(Hit { path, status: response.status().as_u16(), range });
+    response
+}
+
+pub struct Daemon {
+    pub download: SocketAddr,
+    pub admin: SocketAddr,
+    pub client: reqwest::Client,
+    pub storage_dir: PathBuf,
+    files_toml: PathBuf,
+    child: Child,
+    _dir: tempfile::TempDir,
+}
+
+impl Daemon {
+    /// 以给定的 files.toml 启动 relayfetch，并等待启动时的首次同步完成
+    pub async fn start(files: &str) -> Self {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let storage_dir = dir.path().join("data");
+        let download = free_addr();
+        let admin = free_addr();
+        let grpc = free_addr();
+
+        let config = format!(
+            "interval_secs = 86400\n\
+             storage_dir = {:?}\n\
+             bind = \"{}\"\n\
+             grpc_admin = \"{}\"\n\
+             http_admin = \"{}\"\n\
+             download_retry = 3\n\
+             retry_base_delay_ms = 10\n",
+            storage_dir, download, grpc, admin
+        );
+        let files_toml = dir.path().join("files.toml");
+        std::fs::write(dir.path().join("config.toml"), config).unwrap();
+        std::fs::write(&files_toml, files).unwrap();
+
+        let log = std::fs::File::create(dir.path().join("relayfetch.log")).unwrap();
+        let child = Command::new(env!("CARGO_BIN_EXE_relayfetch"))
+            .arg("--config")
+            .arg(dir.path().join("config.toml"))
+            .arg("--files")
+            .arg(&files_toml)
+            .env("RUST_LOG", "info")
+            .stdout(log.try_clone().unwrap())
+            .stderr(log)
+            .spawn()
+            .expect("failed to start relayfetch");
+
+        let daemon = Self {
+            download,
+            admin,
+            client: reqwest::Client::new(),
+            storage_dir,
+            files_toml,
+            child,
+            _dir: dir,
+        };
+        daemon.wait_idle().await;
+        daemon
+    }
+
+    /// 替换 files.toml 并调用 reload_config
+    pub async fn reload_files(&self, files: &str) {
+        std::fs::write(&self.files_toml, files).unwrap();
+        self.post("reload_config", Value::Null).await;
+    }
+
+    /// 触发一次同步并等待其结束，返回 sync_job 的结果
+    pub async fn sync(&self) -> Value {
+        let job = self.post("trigger_sync", Value::Null).await;
+        let job_id = job["job_id"].as_str().expect("job_id").to_string();
+
+        let deadline = Instant::now() + TIMEOUT;
+        while Instant::now() < deadline {
+            let job = self.get_json(&format!("sync_job?job_id={}", job_id)).await;
+            if job["state"] == "finished" || job["state"] == "failed" {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("sync job {} did not finish\n{}", job_id, self.log());
+    }
+
+    /// 从下载服务获取文件
+    pub async fn download(&self, path: &str) -> (u16, Bytes) {
+        let resp = self
+            .client
+            .get(format!("http://{}/{}", self.download, path))
+            .send()
+            .await
+            .expect("download request failed");
+        let status = resp.status().as_u16();
+        (status, resp.bytes().await.expect("body"))
+    }
+
+    pub async fn get_json(&self, endpoint: &str) -> Value {
+        let resp = self
+            .client
+            .get(format!("http://{}/{}", self.admin, endpoint))
+            .send()
+            .await
+            .expect("management request failed");
+        assert!(resp.status().is_success(), "GET /{}: {}", endpoint, resp.status());
+        resp.json().await.expect("json")
+    }
+
+    pub async fn post(&self, endpoint: &str, body: Value) -> Value {
+        let mut req = self.client.post(format!("http://{}/{}", self.admin, endpoint));
+        if !body.is_null() {
+            req = req.json(&body);
+        }
+        let resp = req.send().await.expect("management request failed");
+        assert!(resp.status().is_success(), "POST /{}: {}\n{}", endpoint, resp.status(), self.log());
+        resp.json().await.expect("json")
+    }
+
+    pub fn stored(&self, path: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.storage_dir.join(path)).ok()
+    }
+
+    /// relayfetch 的日志，断言失败时附上
+    pub fn log(&self) -> String {
+        std::fs::read_to_string(self._dir.path().join("relayfetch.log")).unwrap_or_default()
+    }
+
+    /// 等待管理接口可用且没有进行中的同步
+    async fn wait_idle(&self) {
+        let deadline = Instant::now() + TIMEOUT;
+        while Instant::now() < deadline {
+            if let Ok(resp) = self.client.get(format!("http://{}/status", self.admin)).send().await
+                && let Ok(status) = resp.json::<Value>().await
+                && status["is_running"] == false
+                && !status["last_sync"].is_null()
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("relayfetch did not become ready\n{}", self.log());
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn free_addr() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+}
+
+/// files.toml 内容：（本地路径, 上游 URL）
+pub fn files_toml<'a>(entries: impl IntoIterator<Item = (&'a str, String)>) -> String {
+    let mut out = String::from("[files]\n");
+    for (path, url) in entries {
+        out.push_str(&format!("{:?} = {:?}\n", path, url));
+    }
+    out
+}
+
+/// 便于区分的测试内容
+pub fn content(tag: &str, len: usize) -> Vec<u8> {
+    tag.bytes().cycle().take(len).collect()
+}